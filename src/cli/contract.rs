@@ -41,8 +41,15 @@ fn parse_contract_method(abi: &PathBuf, method: &str) -> Result<ton_abi::Functio
 async fn get_account_stuff(
     node_rpc: &NodeTcpRpc,
     address: &ton_block::MsgAddressInt,
+    block_id: Option<&ton_block::BlockIdExt>,
 ) -> Result<ton_block::AccountStuff> {
-    let state = node_rpc.get_shard_account_state(address).await?;
+    let state = match block_id {
+        Some(block_id) => node_rpc
+            .get_shard_account_state_at(address, block_id)
+            .await
+            .context("failed to get account state at the specified block")?,
+        None => node_rpc.get_shard_account_state(address).await?,
+    };
     match state.read_account()? {
         ton_block::Account::Account(account) => Ok(account),
         ton_block::Account::AccountNone => Err(anyhow::anyhow!("account not deployed")),
@@ -154,26 +161,55 @@ struct CmdCall {
     /// execute method as responsible. (NOTE: requires first argument of type `uint32`)
     #[argh(switch, short = 'r')]
     responsible: bool,
+
+    /// execute method as if the current time is the specified unix timestamp (in seconds)
+    #[argh(option)]
+    now: Option<u32>,
+
+    /// execute method on the account state as of the masterchain block
+    /// `seqno:root_hash:file_hash` (at the block time unless `--now` is specified)
+    #[argh(option, from_str_fn(parse_block_arg))]
+    block: Option<ton_block::BlockIdExt>,
 }
 
 impl CmdCall {
     async fn run(self, config: AppConfig) -> Result<serde_json::Value> {
         let node_rpc = NodeTcpRpc::new(config.control()?).await?;
 
-        let clock = nekoton_utils::SimpleClock;
+        // NOTE: historical states are executed at the block time for reproducible results
+        let now = match (self.now, &self.block) {
+            (None, Some(block_id)) => {
+                let node_udp_rpc = NodeUdpRpc::new(config.adnl()?)
+                    .await
+                    .context("failed to build node UDP client")?;
+                let block = node_udp_rpc.get_block(block_id).await?;
+                let info = block.read_brief_info().context("invalid block")?;
+                Some(info.gen_utime)
+            }
+            (now, _) => now,
+        };
+
+        let const_clock;
+        let clock: &dyn nekoton_utils::Clock = match now {
+            Some(now) => {
+                const_clock = nekoton_utils::ConstClock::from_secs(now as u64);
+                &const_clock
+            }
+            None => &nekoton_utils::SimpleClock,
+        };
 
         let address = parse_address(&self.address)?;
         let method = parse_contract_method(&self.abi, &self.method)?;
         let input = nekoton_abi::parse_abi_tokens(&method.inputs, self.args)?;
 
-        let account_stuff = get_account_stuff(&node_rpc, &address).await?;
+        let account_stuff = get_account_stuff(&node_rpc, &address, self.block.as_ref()).await?;
 
         let nekoton_abi::ExecutionOutput {
             result_code,
             tokens,
         } = match self.responsible {
-            false => method.run_local(&clock, account_stuff, &input)?,
-            true => method.run_local_responsible(&clock, account_stuff, &input)?,
+            false => method.run_local(clock, account_stuff, &input)?,
+            true => method.run_local_responsible(clock, account_stuff, &input)?,
         };
 
         let output = tokens
@@ -244,11 +280,9 @@ impl CmdSend {
         let keys = self.sign.map(StoredKeys::load_as_keypair).transpose()?;
         let state_init = parse_optional_state_init(self.state_init)?;
 
-        // Check whether the node is running
-        node_tcp_rpc.get_stats().await?.try_into_running()?;
-
         // Create subscription
-        let subscription = Subscription::new(node_tcp_rpc, node_udp_rpc);
+        let subscription = Subscription::new(node_tcp_rpc, node_udp_rpc, config.retries.clone());
+        subscription.ensure_ready().await?;
         let signature_id = subscription.get_signature_id().await?;
 
         // Prepare external message
@@ -275,6 +309,19 @@ impl CmdSend {
             message.set_state_init(state_init);
         }
 
+        // NOTE: without node UDP RPC only the transaction hash is known
+        if subscription.is_degraded() {
+            let delivery = subscription
+                .send_message_degraded(&message, expire_at)
+                .await?
+                .context("message expired")?;
+
+            return Ok(serde_json::json!({
+                "tx_hash": delivery.tx_hash.to_hex_string(),
+                "msg_hash": message.serialize()?.repr_hash().to_hex_string(),
+            }));
+        }
+
         // Send external message and wait until it is delivered
         let TransactionWithHash {
             hash: tx_hash,
@@ -378,3 +425,7 @@ impl serde::Serialize for ParsedEvent<'_> {
 fn default_args() -> serde_json::Value {
     serde_json::Value::Object(Default::default())
 }
+
+fn parse_block_arg(value: &str) -> Result<ton_block::BlockIdExt, String> {
+    parse_mc_block_id(value).map_err(|e| e.to_string())
+}
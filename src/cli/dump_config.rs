@@ -0,0 +1,108 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+use argh::FromArgs;
+
+use super::CliContext;
+use crate::util::print_output;
+
+#[derive(FromArgs)]
+/// Prints the effective app config (with secrets redacted) and where each value came from
+#[argh(subcommand, name = "dump-config")]
+pub struct Cmd {
+    /// print only the config without value sources
+    #[argh(switch)]
+    no_sources: bool,
+}
+
+impl Cmd {
+    pub fn run(self, ctx: CliContext) -> Result<()> {
+        let dirs = ctx.dirs();
+        let config = ctx.load_config()?;
+
+        let mut values = serde_json::to_value(&config).context("failed to serialize config")?;
+        for path in SECRETS {
+            redact(&mut values, path);
+        }
+
+        if self.no_sources {
+            print_output(values);
+            return Ok(());
+        }
+
+        // NOTE: the file is read again to distinguish explicit values from defaults
+        let content =
+            std::fs::read_to_string(&dirs.app_config).context("failed to read app config")?;
+        let file = toml::from_str::<toml::Value>(&content).context("failed to parse app config")?;
+
+        let mut sources = BTreeMap::new();
+        let derived_adnl = dirs.adnl_node_config.is_some();
+        collect_sources(&values, &file, derived_adnl, "", &mut sources);
+
+        print_output(serde_json::json!({
+            "path": dirs.app_config,
+            "node_config": dirs.adnl_node_config,
+            "config": values,
+            "sources": sources,
+        }));
+        Ok(())
+    }
+}
+
+/// Replaces the secret at the dotted path (if it exists)
+fn redact(value: &mut serde_json::Value, path: &str) {
+    let mut value = Some(value);
+    for part in path.split('.') {
+        value = value.and_then(|value| value.get_mut(part));
+    }
+    if let Some(value) = value {
+        *value = serde_json::Value::from(REDACTED);
+    }
+}
+
+/// Finds the source of each leaf value of the effective config
+fn collect_sources(
+    value: &serde_json::Value,
+    file: &toml::Value,
+    derived_adnl: bool,
+    path: &str,
+    sources: &mut BTreeMap<String, &'static str>,
+) {
+    if let serde_json::Value::Object(fields) = value {
+        for (name, value) in fields {
+            let path = if path.is_empty() {
+                name.clone()
+            } else {
+                format!("{path}.{name}")
+            };
+            collect_sources(value, file, derived_adnl, &path, sources);
+        }
+        return;
+    }
+
+    let in_file = path
+        .split('.')
+        .try_fold(file, |file, part| file.get(part))
+        .is_some();
+
+    let source = if derived_adnl && DERIVED_ADNL.contains(&path) {
+        "node_config"
+    } else if in_file {
+        "file"
+    } else {
+        "default"
+    };
+    sources.insert(path.to_owned(), source);
+}
+
+const REDACTED: &str = "<redacted>";
+
+/// Secret values which must never be printed
+const SECRETS: &[&str] = &["control.client_secret", "exporter.bearer_token"];
+
+/// ADNL params which are replaced with the ones from `--node-config`
+const DERIVED_ADNL: &[&str] = &[
+    "adnl.server_address",
+    "adnl.server_pubkey",
+    "adnl.zerostate_file_hash",
+];
@@ -0,0 +1,305 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use argh::FromArgs;
+
+use super::CliContext;
+use crate::config::{AppConfig, AppConfigValidator};
+use crate::contracts::{DePool, Elector};
+use crate::network::{ConfigWithId, NodeTcpRpc, NodeUdpRpc, Subscription};
+use crate::util::{human_duration, parse_address, print_output, split_address};
+
+#[derive(FromArgs)]
+/// Elector tools
+#[argh(subcommand, name = "elector")]
+pub struct Cmd {
+    #[argh(subcommand)]
+    subcommand: SubCmd,
+}
+
+impl Cmd {
+    pub async fn run(self, ctx: CliContext) -> Result<()> {
+        match self.subcommand {
+            SubCmd::Config(cmd) => cmd.run(ctx).await,
+            SubCmd::Past(cmd) => cmd.run(ctx).await,
+            SubCmd::Rank(cmd) => cmd.run(ctx).await,
+            SubCmd::Participants(cmd) => cmd.run(ctx).await,
+        }
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum SubCmd {
+    Config(CmdConfig),
+    Past(CmdPast),
+    Rank(CmdRank),
+    Participants(CmdParticipants),
+}
+
+#[derive(FromArgs)]
+/// Shows elector timings and the derived elections schedule
+#[argh(subcommand, name = "config")]
+struct CmdConfig {}
+
+impl CmdConfig {
+    async fn run(self, ctx: CliContext) -> Result<()> {
+        let config = ctx.load_config()?;
+        let node_rpc = NodeTcpRpc::new(config.control()?).await?;
+        let ConfigWithId { config, .. } = node_rpc.get_config_all().await?;
+
+        let timings = config.elector_params().context("invalid elector params")?;
+        let current_vset = config.validator_set().context("invalid validator set")?;
+
+        let round_end = current_vset.utime_until();
+        let elections_start = round_end.saturating_sub(timings.elections_start_before);
+        let elections_end = round_end.saturating_sub(timings.elections_end_before);
+
+        let duration = |secs: u32| {
+            serde_json::json!({
+                "seconds": secs,
+                "human": human_duration(secs),
+            })
+        };
+
+        print_output(serde_json::json!({
+            "validators_elected_for": duration(timings.validators_elected_for),
+            "elections_start_before": duration(timings.elections_start_before),
+            "elections_end_before": duration(timings.elections_end_before),
+            "stake_held_for": duration(timings.stake_held_for),
+            "schedule": [
+                format!("rounds last {}", human_duration(timings.validators_elected_for)),
+                format!(
+                    "elections open {} before the round end",
+                    human_duration(timings.elections_start_before)
+                ),
+                format!(
+                    "elections close {} before the round end",
+                    human_duration(timings.elections_end_before)
+                ),
+                format!(
+                    "elections last {}",
+                    human_duration(
+                        timings
+                            .elections_start_before
+                            .saturating_sub(timings.elections_end_before)
+                    )
+                ),
+                format!(
+                    "stakes are unfrozen {} after the end of the validated round",
+                    human_duration(timings.stake_held_for)
+                ),
+            ],
+            "current_round": {
+                "round_end": round_end,
+                "elections_start": elections_start,
+                "elections_end": elections_end,
+            },
+        }));
+        Ok(())
+    }
+}
+
+#[derive(FromArgs)]
+/// Shows stakes, rewards and unfreeze times in the finished elections
+#[argh(subcommand, name = "past")]
+struct CmdPast {
+    /// participant address (the validator wallet or DePool proxies by default)
+    #[argh(option, short = 'a')]
+    address: Vec<String>,
+
+    /// max number of elections to show
+    #[argh(option, default = "10")]
+    limit: usize,
+}
+
+impl CmdPast {
+    async fn run(self, ctx: CliContext) -> Result<()> {
+        let config = ctx.load_config()?;
+        let node_tcp_rpc = NodeTcpRpc::new(config.control()?).await?;
+        let node_udp_rpc = NodeUdpRpc::new(config.adnl()?).await?;
+        let subscription = Subscription::new(node_tcp_rpc, node_udp_rpc, config.retries.clone());
+        subscription.ensure_ready().await?;
+
+        let participants = resolve_participants(&self.address, &config, &subscription).await?;
+
+        let ConfigWithId { config, .. } = subscription.tcp_rpc().get_config_all().await?;
+        let elector_address = config
+            .elector_address()
+            .context("invalid elector address")?;
+        let elector = Elector::new(elector_address, subscription);
+
+        let mut output = Vec::with_capacity(participants.len());
+        for address in participants {
+            let elections = elector
+                .past_elections_summary(&address, self.limit)
+                .await
+                .context("failed to get past elections")?
+                .into_iter()
+                .map(|summary| {
+                    serde_json::json!({
+                        "election_id": summary.election_id,
+                        "unfreeze_at": summary.unfreeze_at,
+                        "elected": summary.elected,
+                        "stake": summary.stake.to_string(),
+                        "reward": summary.reward.to_string(),
+                        "penalty": summary.penalty().to_string(),
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            output.push(serde_json::json!({
+                "address": address.to_string(),
+                "elections": elections,
+            }));
+        }
+
+        print_output(serde_json::Value::from(output));
+        Ok(())
+    }
+}
+
+#[derive(FromArgs)]
+/// Shows the participant position by stake in the current elections
+#[argh(subcommand, name = "rank")]
+struct CmdRank {
+    /// participant address (the validator wallet or DePool proxies by default)
+    #[argh(option, short = 'a')]
+    address: Vec<String>,
+}
+
+impl CmdRank {
+    async fn run(self, ctx: CliContext) -> Result<()> {
+        let config = ctx.load_config()?;
+        let node_tcp_rpc = NodeTcpRpc::new(config.control()?).await?;
+        let node_udp_rpc = NodeUdpRpc::new(config.adnl()?).await?;
+        let subscription = Subscription::new(node_tcp_rpc, node_udp_rpc, config.retries.clone());
+        subscription.ensure_ready().await?;
+
+        let participants = resolve_participants(&self.address, &config, &subscription).await?;
+
+        let ConfigWithId { config, .. } = subscription.tcp_rpc().get_config_all().await?;
+        let elector_address = config
+            .elector_address()
+            .context("invalid elector address")?;
+        let validators_count = config
+            .validators_count()
+            .context("invalid validators count params")?;
+        let stakes_config = config.stakes_config().context("invalid stakes config")?;
+
+        let elector = Elector::new(elector_address, subscription);
+        let elector_data = elector
+            .get_data()
+            .await
+            .context("failed to get elector data")?;
+        let election_id = elector_data
+            .election_id()
+            .context("no current elections in the elector state")?;
+
+        let mut output = Vec::with_capacity(participants.len());
+        let mut summary = None;
+        for address in participants {
+            let rank = elector_data
+                .participant_rank(&address, &validators_count, &stakes_config)
+                .context("no current elections in the elector state")?;
+            summary = Some((rank.participants, rank.max_validators, rank.cutoff_stake));
+
+            output.push(serde_json::json!({
+                "address": address.to_string(),
+                "rank": rank.rank,
+                "stake": rank.stake.to_string(),
+                "margin": rank.margin().map(|margin| margin.to_string()),
+            }));
+        }
+
+        let (participants, max_validators, cutoff_stake) = summary.unwrap_or_default();
+        print_output(serde_json::json!({
+            "election_id": election_id,
+            "participants": participants,
+            "max_validators": max_validators,
+            "cutoff_stake": cutoff_stake.map(|stake| stake.to_string()),
+            "ranks": output,
+        }));
+        Ok(())
+    }
+}
+
+#[derive(FromArgs)]
+/// Shows all members of the current elections sorted by stake
+#[argh(subcommand, name = "participants")]
+struct CmdParticipants {
+    /// own participant address (the validator wallet or DePool proxies by default)
+    #[argh(option, short = 'a')]
+    address: Vec<String>,
+}
+
+impl CmdParticipants {
+    async fn run(self, ctx: CliContext) -> Result<()> {
+        let config = ctx.load_config()?;
+        let node_tcp_rpc = NodeTcpRpc::new(config.control()?).await?;
+        let node_udp_rpc = NodeUdpRpc::new(config.adnl()?).await?;
+        let subscription = Subscription::new(node_tcp_rpc, node_udp_rpc, config.retries.clone());
+        subscription.ensure_ready().await?;
+
+        // NOTE: only masterchain accounts can participate in elections
+        let own = resolve_participants(&self.address, &config, &subscription)
+            .await?
+            .iter()
+            .filter(|address| address.is_masterchain())
+            .map(|address| split_address(address).map(|(_, account)| account))
+            .collect::<Result<Vec<_>>>()?;
+
+        let ConfigWithId { config, .. } = subscription.tcp_rpc().get_config_all().await?;
+        let elector_address = config
+            .elector_address()
+            .context("invalid elector address")?;
+        let stakes_config = config.stakes_config().context("invalid stakes config")?;
+
+        let elector = Elector::new(elector_address, subscription);
+        let participants = elector
+            .current_election_participants(&stakes_config)
+            .await?
+            .into_iter()
+            .enumerate()
+            .map(|(i, participant)| {
+                serde_json::json!({
+                    "rank": i + 1,
+                    "address": format!("-1:{}", participant.address.to_hex_string()),
+                    "public_key": participant.public_key.to_hex_string(),
+                    "adnl_addr": participant.adnl_addr.to_hex_string(),
+                    "stake": participant.stake.to_string(),
+                    "max_factor": participant.max_factor as f64 / 65536.0,
+                    "created_at": participant.created_at,
+                    "own": own.contains(&participant.address),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        print_output(serde_json::Value::from(participants));
+        Ok(())
+    }
+}
+
+/// Returns the specified addresses or the configured validator wallet or DePool proxies
+async fn resolve_participants(
+    addresses: &[String],
+    config: &AppConfig,
+    subscription: &Arc<Subscription>,
+) -> Result<Vec<ton_block::MsgAddressInt>> {
+    if !addresses.is_empty() {
+        return addresses
+            .iter()
+            .map(|address| parse_address(address))
+            .collect();
+    }
+
+    Ok(match config.validator.as_ref() {
+        Some(AppConfigValidator::Single(single)) => vec![single.address.clone()],
+        Some(AppConfigValidator::DePool(depool)) => {
+            let depool = DePool::from_config(depool, subscription.clone(), config.one_coin())?;
+            let state = depool.get_state().await?;
+            depool.get_info(&state)?.proxies
+        }
+        None => anyhow::bail!("validation is not configured, specify the address"),
+    })
+}
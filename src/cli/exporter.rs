@@ -6,6 +6,7 @@ use anyhow::Result;
 use argh::FromArgs;
 
 use super::CliContext;
+use crate::config::AppConfigExporter;
 use crate::exporter::{
     Exporter, ExporterTarget, FileExporterTarget, HttpExporterTarget, StdoutExporterTarget,
 };
@@ -14,7 +15,7 @@ use crate::exporter::{
 /// Prometheus metrics exporter
 #[argh(subcommand, name = "exporter")]
 pub struct Cmd {
-    /// socket addr to host the exporter
+    /// socket addr to host the exporter. Overrides the `exporter.listen_addr` from the config
     #[argh(option, short = 'a')]
     addr: Option<SocketAddr>,
 
@@ -41,11 +42,25 @@ impl Cmd {
         }
 
         // Add network exporter
-        if let Some(addr) = self.addr {
+        let server_config = match self.addr {
+            // NOTE: TLS and auth params are still taken from the config
+            Some(listen_addr) => Some(AppConfigExporter {
+                listen_addr,
+                ..load_server_config(&ctx).unwrap_or(AppConfigExporter {
+                    listen_addr,
+                    tls: None,
+                    bearer_token: None,
+                })
+            }),
+            // NOTE: configured server is ignored for the single run
+            None if self.once => None,
+            None => load_server_config(&ctx),
+        };
+        if let Some(server_config) = server_config {
             if self.once {
                 return Err(ExporterError::OnceNotSupported.into());
             }
-            targets.push(Box::new(HttpExporterTarget::new(addr).await?));
+            targets.push(Box::new(HttpExporterTarget::new(&server_config).await?));
         }
 
         // Fallback to stdout exporter
@@ -64,6 +79,16 @@ impl Cmd {
     }
 }
 
+fn load_server_config(ctx: &CliContext) -> Option<AppConfigExporter> {
+    match ctx.load_config() {
+        Ok(config) => config.exporter,
+        Err(e) => {
+            tracing::warn!("failed to load exporter config: {e:?}");
+            None
+        }
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
 enum ExporterError {
     #[error("once flag is not supported by http exporter")]
@@ -43,8 +43,9 @@ impl Cmd {
             None => None,
         };
 
-        let mut config = ctx.load_config()?;
         let dirs = ctx.dirs();
+        // NOTE: load config as is, because it will be stored back
+        let mut config = AppConfig::load(&dirs.app_config)?;
 
         // Create keys directory if it doesn't exist
         if !dirs.keys_dir.exists() {
@@ -103,10 +104,11 @@ fn prepare_single_validator(
 ) -> Result<()> {
     use crate::contracts::*;
 
-    const MIN_STAKE: u64 = 10_000 * ONE_EVER as u64;
-    const MAX_STAKE: u64 = 10_000_000 * ONE_EVER as u64;
-
     let currency = &app_config.currency().to_owned();
+    let one_coin = app_config.one_coin();
+
+    let min_stake = coins_to_units(10_000, one_coin)?;
+    let max_stake = coins_to_units(10_000_000, one_coin)?;
 
     let mut steps = Steps::new(2);
 
@@ -129,34 +131,36 @@ fn prepare_single_validator(
     let stake_per_round = match template {
         Some(template) => {
             let stake = template.stake_per_round;
-            if stake > MAX_STAKE {
+            if stake as u128 > max_stake {
                 anyhow::bail!(
                     "Too big stake (max stake is {} {currency})",
-                    Tokens(MAX_STAKE)
+                    Tokens(max_stake)
                 );
-            } else if stake < MIN_STAKE {
+            } else if (stake as u128) < min_stake {
                 anyhow::bail!(
                     "Too small stake (min stake is {} {currency})",
-                    Tokens(MIN_STAKE)
+                    Tokens(min_stake)
                 );
             }
             stake
         }
         None => Input::with_theme(theme)
             .with_prompt(format!("Stake per round ({currency})"))
-            .validate_with(|stake: &u64| match stake.saturating_mul(ONE_EVER as u64) {
-                x if x > MAX_STAKE => Err(format!(
+            .validate_with(|stake: &u64| match (*stake as u128).checked_mul(one_coin) {
+                Some(x) if x > max_stake || x > u64::MAX as u128 => Err(format!(
                     "Too big stake (max stake is {} {currency})",
-                    Tokens(MAX_STAKE)
+                    Tokens(max_stake.min(u64::MAX as u128))
                 )),
-                x if x < MIN_STAKE => Err(format!(
+                None => Err("Too big stake".to_owned()),
+                Some(x) if x < min_stake => Err(format!(
                     "Too small stake (min stake is {} {currency})",
-                    Tokens(MIN_STAKE)
+                    Tokens(min_stake)
                 )),
                 _ => Ok(()),
             })
-            .interact_text()?
-            .saturating_mul(ONE_EVER as u64),
+            .interact_text()
+            .map_err(anyhow::Error::from)
+            .and_then(|stake| coins_to_units_u64(stake, one_coin))?,
     };
 
     // Configure stake factor
@@ -165,15 +169,18 @@ fn prepare_single_validator(
     // Save config
     app_config.validator = Some(AppConfigValidator::Single(AppConfigValidatorSingle {
         address: wallet_address.clone(),
-        stake_per_round,
+        stake_per_round: StakeAmount::Fixed(stake_per_round),
         stake_factor: Some(stake_factor),
+        compound_rewards: false,
+        max_hot_balance: None,
+        sweep_destination: None,
     }));
     dirs.store_app_config(app_config)?;
 
     // Done
     steps.next("Validator configured successfully. Great!");
 
-    let target_balance = stake_per_round as u128 * 2 + Wallet::INITIAL_BALANCE;
+    let target_balance = stake_per_round as u128 * 2 + Wallet::initial_balance(one_coin);
 
     println!(
         "\n{}\n{}\n\n{} {}{}\n\n{}\n{}",
@@ -184,7 +191,7 @@ fn prepare_single_validator(
         style(format!(
             "\n  • {} {currency}, maintenance balance\
              \n  • 2 x {} {currency}, stakes for each round",
-            Tokens(Wallet::INITIAL_BALANCE),
+            Tokens(Wallet::initial_balance(one_coin)),
             Tokens(stake_per_round)
         ))
         .dim(),
@@ -204,16 +211,17 @@ fn prepare_depool_validator(
     use crate::contracts::*;
 
     let currency = &app_config.currency().to_owned();
+    let one_coin = app_config.one_coin();
 
     let (mut steps, params) = match template {
-        Some(template) => prepare_new_depool(theme, dirs, currency, Some(template))?,
+        Some(template) => prepare_new_depool(theme, dirs, currency, one_coin, Some(template))?,
         None => match Select::with_theme(theme)
             .item("Deploy new DePool")
             .item("Use existing DePool")
             .default(0)
             .interact()?
         {
-            0 => prepare_new_depool(theme, dirs, currency, None)?,
+            0 => prepare_new_depool(theme, dirs, currency, one_coin, None)?,
             _ => prepare_existing_depool(theme, dirs, currency)?,
         },
     };
@@ -235,8 +243,8 @@ fn prepare_depool_validator(
 
     if let Some(deployment) = params.deploy {
         let target_balance = deployment.validator_assurance as u128 * 2
-            + Wallet::INITIAL_BALANCE
-            + DePool::INITIAL_BALANCE;
+            + Wallet::initial_balance(one_coin)
+            + DePool::initial_balance(one_coin);
 
         println!(
             "\n{} {}{}",
@@ -246,8 +254,8 @@ fn prepare_depool_validator(
                 "\n  • {} {currency}, maintenance balance\
                  \n  • {} {currency}, DePool deployment fee\
                  \n  • 2 x {} {currency}, stakes for each round",
-                Tokens(Wallet::INITIAL_BALANCE),
-                Tokens(DePool::INITIAL_BALANCE),
+                Tokens(Wallet::initial_balance(one_coin)),
+                Tokens(DePool::initial_balance(one_coin)),
                 Tokens(deployment.validator_assurance),
             ))
             .dim()
@@ -268,6 +276,7 @@ fn prepare_new_depool(
     theme: &dyn Theme,
     dirs: &ProjectDirs,
     currency: &str,
+    one_coin: u128,
     template: Option<&TemplateValidatorDePool>,
 ) -> Result<(Steps, AppConfigValidatorDePool)> {
     let mut steps = Steps::new(2);
@@ -324,7 +333,7 @@ fn prepare_new_depool(
         Some(template) => {
             let stake = template.deploy.min_stake;
             anyhow::ensure!(
-                stake >= 10 * ONE_EVER as u64,
+                stake as u128 >= coins_to_units(10, one_coin)?,
                 "Minimum stake is too small (< 10 {currency})"
             );
             stake
@@ -336,8 +345,9 @@ fn prepare_new_depool(
                 x if x < 10 => Err(format!("Minimum stake is too small (< 10 {currency})")),
                 _ => Ok(()),
             })
-            .interact_text()?
-            .saturating_mul(ONE_EVER as u64),
+            .interact_text()
+            .map_err(anyhow::Error::from)
+            .and_then(|stake| coins_to_units_u64(stake, one_coin))?,
     };
 
     // Configure validator assurance
@@ -345,7 +355,7 @@ fn prepare_new_depool(
         Some(template) => {
             let assurance = template.deploy.validator_assurance;
             anyhow::ensure!(
-                assurance >= 10 * ONE_EVER as u64,
+                assurance as u128 >= coins_to_units(10, one_coin)?,
                 "Too small validator assurance (< 10 {currency})"
             );
             anyhow::ensure!(
@@ -359,13 +369,14 @@ fn prepare_new_depool(
             .default(DEFAULT_VALIDATOR_ASSURANCE)
             .validate_with(|value: &u64| match *value {
                 x if x < 10 => Err(format!("Too small validator assurance (< 10 {currency})")),
-                x if x.saturating_mul(ONE_EVER as u64) < min_stake => {
+                x if (x as u128).saturating_mul(one_coin) < min_stake as u128 => {
                     Err("Validator assurance is less than minimum stake".to_owned())
                 }
                 _ => Ok(()),
             })
-            .interact_text()?
-            .saturating_mul(ONE_EVER as u64),
+            .interact_text()
+            .map_err(anyhow::Error::from)
+            .and_then(|assurance| coins_to_units_u64(assurance, one_coin))?,
     };
 
     // Configure participant reward fraction
@@ -401,6 +412,12 @@ fn prepare_new_depool(
             validator_assurance,
             participant_reward_fraction,
         }),
+        abi_path: None,
+        min_proxy_balance: None,
+        transfer_comment: None,
+        reward_address: None,
+        ticktock_value: None,
+        max_ticktocks: None,
     };
 
     // Configure stEVER strategies stuff
@@ -479,6 +496,12 @@ fn prepare_existing_depool(
         stake_factor: Some(stake_factor),
         cluster: None,
         deploy: None,
+        abi_path: None,
+        min_proxy_balance: None,
+        transfer_comment: None,
+        reward_address: None,
+        ticktock_value: None,
+        max_ticktocks: None,
     };
 
     // Configure stEVER strategies stuff
@@ -507,6 +530,18 @@ impl DePoolType {
     }
 }
 
+/// Converts an amount of whole coins into the smallest units
+fn coins_to_units(coins: u64, one_coin: u128) -> Result<u128> {
+    (coins as u128)
+        .checked_mul(one_coin)
+        .context("token amount is too big")
+}
+
+/// Converts an amount of whole coins into the smallest units which must fit into `u64`
+fn coins_to_units_u64(coins: u64, one_coin: u128) -> Result<u64> {
+    u64::try_from(coins_to_units(coins, one_coin)?).context("token amount is too big")
+}
+
 fn configure_stake_factor(theme: &dyn Theme, template: Option<Option<u32>>) -> Result<u32> {
     const MIN_STAKE_FACTOR: f64 = 1.0;
     const MAX_STAKE_FACTOR: f64 = 3.0;
@@ -568,11 +603,11 @@ impl<P: AsRef<Path>> KeysSelector<'_, P> {
 
         Ok(if exists && !overwrite {
             let keys = StoredKeys::load(path).context("failed to load existing keys")?;
-            keys.as_keypair()
+            keys.as_keypair()?
         } else {
             let keys = StoredKeys::generate()?;
             keys.store(path)?;
-            keys.as_keypair()
+            keys.as_keypair()?
         })
     }
 
@@ -645,7 +680,7 @@ impl<P: AsRef<Path>> KeysSelector<'_, P> {
             }
         };
 
-        Ok(stored_keys.as_keypair())
+        stored_keys.as_keypair()
     }
 }
 
@@ -1,8 +1,9 @@
 use std::borrow::Cow;
-use std::net::{Ipv4Addr, SocketAddrV4};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::str::FromStr;
+use std::time::Duration;
 
 use anyhow::{Context, Result};
 use argh::FromArgs;
@@ -603,7 +604,7 @@ async fn setup_adnl(
 
             // Update client config if it differes from the node config
             let server_pubkey = adnl_node.overlay_pubkey()?;
-            if adnl_client.server_address != adnl_node.ip_address
+            if adnl_client.server_address != SocketAddr::V4(adnl_node.ip_address)
                 || adnl_client.server_pubkey != server_pubkey
                 || adnl_client.zerostate_file_hash != zerostate_file_hash
             {
@@ -613,7 +614,7 @@ async fn setup_adnl(
                     return Ok(false);
                 }
 
-                adnl_client.server_address = adnl_node.ip_address;
+                adnl_client.server_address = adnl_node.ip_address.into();
                 adnl_client.server_pubkey = server_pubkey;
                 adnl_client.zerostate_file_hash = zerostate_file_hash;
 
@@ -629,9 +630,17 @@ async fn setup_adnl(
             // Create client config
             app_config.adnl = Some(AppConfigAdnl {
                 client_port: DEFAULT_LOCAL_ADNL_PORT,
-                server_address: adnl_node.ip_address,
+                server_address: adnl_node.ip_address.into(),
+                fallback_addresses: Vec::new(),
+                address_family: AddressFamily::Any,
                 server_pubkey: adnl_node.overlay_pubkey()?,
                 zerostate_file_hash,
+                refetch_mismatched_blocks: false,
+                block_cache_size: AppConfigAdnl::DEFAULT_BLOCK_CACHE_SIZE,
+                connection_timeout: AppConfigAdnl::DEFAULT_TIMEOUT,
+                query_timeout: AppConfigAdnl::DEFAULT_TIMEOUT,
+                query_retries: AppConfigAdnl::DEFAULT_QUERY_RETRIES,
+                idle_linger: Duration::ZERO,
             });
 
             app_config.store(&dirs.app_config)?;
@@ -672,9 +681,17 @@ async fn setup_adnl(
 
             app_config.adnl = Some(AppConfigAdnl {
                 client_port: DEFAULT_LOCAL_ADNL_PORT,
-                server_address: adnl_node.ip_address,
+                server_address: adnl_node.ip_address.into(),
+                fallback_addresses: Vec::new(),
+                address_family: AddressFamily::Any,
                 server_pubkey: adnl_node.overlay_pubkey()?,
                 zerostate_file_hash,
+                refetch_mismatched_blocks: false,
+                block_cache_size: AppConfigAdnl::DEFAULT_BLOCK_CACHE_SIZE,
+                connection_timeout: AppConfigAdnl::DEFAULT_TIMEOUT,
+                query_timeout: AppConfigAdnl::DEFAULT_TIMEOUT,
+                query_retries: AppConfigAdnl::DEFAULT_QUERY_RETRIES,
+                idle_linger: Duration::ZERO,
             });
 
             dirs.store_app_config(app_config)?;
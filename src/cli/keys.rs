@@ -0,0 +1,126 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use argh::FromArgs;
+
+use super::CliContext;
+use crate::config::{DePoolType, StoredKeys};
+use crate::contracts::wallet;
+use crate::util::*;
+
+#[derive(FromArgs)]
+/// Keys utils
+#[argh(subcommand, name = "keys")]
+pub struct Cmd {
+    #[argh(subcommand)]
+    subcommand: SubCmd,
+}
+
+impl Cmd {
+    pub fn run(self, ctx: CliContext) -> Result<()> {
+        match self.subcommand {
+            SubCmd::Inspect(cmd) => cmd.run(ctx),
+            SubCmd::Migrate(cmd) => cmd.run(ctx),
+        }
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum SubCmd {
+    Inspect(CmdInspect),
+    Migrate(CmdMigrate),
+}
+
+#[derive(Debug, PartialEq, FromArgs)]
+/// Shows addresses of all supported contract types for the keys file
+#[argh(subcommand, name = "inspect")]
+struct CmdInspect {
+    /// path to the keys file (validator keys by default)
+    #[argh(positional)]
+    keys: Option<PathBuf>,
+}
+
+impl CmdInspect {
+    fn run(self, ctx: CliContext) -> Result<()> {
+        let path = self
+            .keys
+            .unwrap_or_else(|| ctx.dirs().validator_keys.clone());
+        let keys = StoredKeys::load(path).context("failed to load keys")?;
+        let public = keys.as_keypair()?.public;
+
+        let wallets = [
+            ton_block::MASTERCHAIN_ID as i8,
+            ton_block::BASE_WORKCHAIN_ID as i8,
+        ]
+        .into_iter()
+        .map(|workchain| {
+            let address = wallet::compute_wallet_address(workchain, &public);
+            let friendly =
+                pack_friendly_address(&address, true).context("failed to pack address")?;
+            Ok(serde_json::json!({
+                "type": "ever_wallet",
+                "workchain": workchain,
+                "raw": address.to_string(),
+                "friendly": friendly,
+            }))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+        // NOTE: DePools can only be deployed to the basechain
+        let depools = [
+            DePoolType::DefaultV3,
+            DePoolType::StEverV1,
+            DePoolType::StEverV2,
+        ]
+        .into_iter()
+        .map(|ty| {
+            let address = ty.compute_depool_address(&public)?;
+            Ok(serde_json::json!({
+                "type": ty,
+                "raw": address.to_string(),
+            }))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+        print_output(serde_json::json!({
+            "public": hex::encode(public.as_bytes()),
+            "wallets": wallets,
+            "depools": depools,
+        }));
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, FromArgs)]
+/// Upgrades the keys file to the latest format version
+#[argh(subcommand, name = "migrate")]
+struct CmdMigrate {
+    /// path to the keys file (validator keys by default)
+    #[argh(positional)]
+    keys: Option<PathBuf>,
+}
+
+impl CmdMigrate {
+    fn run(self, ctx: CliContext) -> Result<()> {
+        let path = self
+            .keys
+            .unwrap_or_else(|| ctx.dirs().validator_keys.clone());
+        let (keys, version) = StoredKeys::load_versioned(&path).context("failed to load keys")?;
+
+        let migrated = version != Some(StoredKeys::VERSION);
+        if migrated {
+            // NOTE: keep the original file in case the new one is not readable by other tools
+            let backup = path.with_extension("json.bak");
+            std::fs::copy(&path, &backup).context("failed to backup keys")?;
+            keys.store(&path)?;
+        }
+
+        print_output(serde_json::json!({
+            "from_version": version,
+            "version": StoredKeys::VERSION,
+            "migrated": migrated,
+        }));
+        Ok(())
+    }
+}
@@ -0,0 +1,165 @@
+use std::str::FromStr;
+
+use anyhow::Result;
+use argh::FromArgs;
+
+use super::CliContext;
+use crate::exporter::Exporter;
+use crate::util::print_output;
+
+#[derive(FromArgs)]
+/// Metrics tools
+#[argh(subcommand, name = "metrics")]
+pub struct Cmd {
+    #[argh(subcommand)]
+    subcommand: SubCmd,
+}
+
+impl Cmd {
+    pub async fn run(self, ctx: CliContext) -> Result<()> {
+        match self.subcommand {
+            SubCmd::Dump(cmd) => cmd.run(ctx).await,
+        }
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum SubCmd {
+    Dump(CmdDump),
+}
+
+#[derive(FromArgs)]
+/// Collects all metrics once and prints them
+#[argh(subcommand, name = "dump")]
+struct CmdDump {
+    /// output format: `prometheus` or `json`. `prometheus` default
+    #[argh(option, default = "DumpFormat::Prometheus")]
+    format: DumpFormat,
+}
+
+impl CmdDump {
+    async fn run(self, ctx: CliContext) -> Result<()> {
+        let metrics = Exporter::new(ctx.dirs, Vec::new()).dump().await?;
+        match self.format {
+            DumpFormat::Prometheus => print_output(metrics),
+            DumpFormat::Json => print_output(metrics_to_json(&metrics)),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone)]
+enum DumpFormat {
+    Prometheus,
+    Json,
+}
+
+impl FromStr for DumpFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "prometheus" => Ok(Self::Prometheus),
+            "json" => Ok(Self::Json),
+            _ => Err(anyhow::Error::msg("unknown format")),
+        }
+    }
+}
+
+/// Converts metrics from the prometheus text format into a JSON array
+fn metrics_to_json(metrics: &str) -> serde_json::Value {
+    let mut result = Vec::new();
+    for line in metrics.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (name, labels, rest) = match line.find(['{', ' ']) {
+            Some(i) if line[i..].starts_with('{') => {
+                let Some((labels, rest)) = parse_labels(&line[i + 1..]) else { continue };
+                (&line[..i], labels, rest)
+            }
+            Some(i) => (&line[..i], Default::default(), &line[i..]),
+            None => continue,
+        };
+
+        // NOTE: the optional timestamp after the value is ignored
+        let Some(value) = rest.split_whitespace().next() else { continue };
+
+        let value = match serde_json::from_str::<serde_json::Number>(value) {
+            Ok(number) => serde_json::Value::Number(number),
+            Err(_) => serde_json::Value::String(value.to_owned()),
+        };
+
+        result.push(serde_json::json!({
+            "name": name,
+            "labels": labels,
+            "value": value,
+        }));
+    }
+    serde_json::Value::Array(result)
+}
+
+/// Parses the label set after the opening brace.
+///
+/// Returns the labels and the rest of the line after the closing brace
+fn parse_labels(mut s: &str) -> Option<(serde_json::Map<String, serde_json::Value>, &str)> {
+    let mut labels = serde_json::Map::new();
+    loop {
+        s = s.trim_start();
+        if let Some(rest) = s.strip_prefix('}') {
+            return Some((labels, rest));
+        }
+
+        let (key, rest) = s.split_once('=')?;
+        let rest = rest.trim_start().strip_prefix('"')?;
+
+        // NOTE: label values can contain any characters, only `\\`, `\"` and `\n` are escaped
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+        let end = loop {
+            match chars.next()? {
+                (i, '"') => break i,
+                (_, '\\') => match chars.next()?.1 {
+                    'n' => value.push('\n'),
+                    c => value.push(c),
+                },
+                (_, c) => value.push(c),
+            }
+        };
+        labels.insert(key.trim().to_owned(), value.into());
+
+        s = rest[end + 1..].trim_start();
+        s = s.strip_prefix(',').unwrap_or(s);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_with_separators_are_parsed() {
+        let metrics = "# TYPE test gauge\n\
+            test{a=\"1,2\",b=\"x=\\\"y\\\" }\"} 5\n\
+            plain 1.5 1700000000\n";
+
+        assert_eq!(
+            metrics_to_json(metrics),
+            serde_json::json!([
+                {
+                    "name": "test",
+                    "labels": { "a": "1,2", "b": "x=\"y\" }" },
+                    "value": 5,
+                },
+                {
+                    "name": "plain",
+                    "labels": {},
+                    "value": 1.5,
+                },
+            ])
+        );
+    }
+}
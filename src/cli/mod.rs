@@ -7,10 +7,16 @@ use crate::config::*;
 use crate::dirs::*;
 
 pub mod contract;
+pub mod dump_config;
+pub mod elector;
 pub mod exporter;
 pub mod init;
+pub mod keys;
+pub mod metrics;
 pub mod node;
 pub mod seed;
+pub mod simulate;
+pub mod tx;
 pub mod validator;
 
 /// All-in-one node management tool
@@ -22,23 +28,36 @@ pub struct App {
     /// path to the root directory
     #[argh(option, default = "ProjectDirs::default_root_dir()")]
     root: PathBuf,
+
+    /// path to the node config to derive ADNL client params from
+    /// (instead of the `adnl` section of the app config)
+    #[argh(option)]
+    node_config: Option<PathBuf>,
 }
 
 impl App {
     pub async fn run(self) -> Result<()> {
         tracing::debug!("root dir {:?}", self.root);
 
-        let ctx = CliContext {
-            dirs: ProjectDirs::new(self.root),
-        };
+        let mut dirs = ProjectDirs::new(self.root);
+        dirs.adnl_node_config = self.node_config;
+
+        let ctx = CliContext { dirs };
 
         match self.command {
             Command::Init(cmd) => cmd.run(ctx).await,
             Command::Validator(cmd) => cmd.run(ctx).await,
+            Command::DrainAndStop(cmd) => cmd.run(ctx).await,
+            Command::DumpConfig(cmd) => cmd.run(ctx),
             Command::Contract(cmd) => cmd.run(ctx).await,
+            Command::Elector(cmd) => cmd.run(ctx).await,
             Command::Exporter(cmd) => cmd.run(ctx).await,
+            Command::Keys(cmd) => cmd.run(ctx),
+            Command::Metrics(cmd) => cmd.run(ctx).await,
             Command::Node(cmd) => cmd.run(ctx).await,
-            Command::Seed(cmd) => cmd.run(),
+            Command::Seed(cmd) => cmd.run(ctx),
+            Command::Simulate(cmd) => cmd.run(ctx).await,
+            Command::Tx(cmd) => cmd.run(ctx).await,
         }
     }
 }
@@ -48,10 +67,17 @@ impl App {
 enum Command {
     Init(init::Cmd),
     Validator(validator::Cmd),
+    DrainAndStop(validator::CmdDrainAndStop),
+    DumpConfig(dump_config::Cmd),
     Contract(contract::Cmd),
+    Elector(elector::Cmd),
     Exporter(exporter::Cmd),
+    Keys(keys::Cmd),
+    Metrics(metrics::Cmd),
     Node(node::Cmd),
     Seed(seed::Cmd),
+    Simulate(simulate::Cmd),
+    Tx(tx::Cmd),
 }
 
 pub struct CliContext {
@@ -60,7 +86,7 @@ pub struct CliContext {
 
 impl CliContext {
     pub fn load_config(&self) -> Result<AppConfig> {
-        AppConfig::load(&self.dirs.app_config)
+        self.dirs.load_app_config()
     }
 
     pub fn dirs(&self) -> &ProjectDirs {
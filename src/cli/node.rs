@@ -1,15 +1,21 @@
 use std::net::Ipv4Addr;
+use std::process::Stdio;
+use std::time::Duration;
 
 use anyhow::{Context, Result};
 use argh::FromArgs;
+use everscale_crypto::ed25519;
 use serde::Serialize;
 use tl_proto::BoxedConstructor;
+use tokio::process::Command;
 use ton_block::Serializable;
 
-use super::CliContext;
-use crate::config::NodeConfig;
-use crate::network::{ConfigParamWithId, ConfigWithId, NodeTcpRpc};
+use super::{CliContext, ProjectDirs};
+use crate::config::{AppConfig, AppConfigControl, NodeConfig};
+use crate::dirs::VALIDATOR_SERVICE;
+use crate::network::{ConfigParamWithId, ConfigWithId, NodeStats, NodeTcpRpc, ValidatorSetEntry};
 use crate::util::*;
+use crate::validator::Timeline;
 
 #[derive(FromArgs)]
 /// Raw node tools operations
@@ -132,7 +138,6 @@ impl Cmd {
                 serde_json::json!({})
             }
             SubCmd::GenDht(cmd) => {
-                use everscale_crypto::ed25519;
                 use everscale_network::proto;
 
                 let now = cmd.time.unwrap_or_else(broxus_util::now);
@@ -194,6 +199,17 @@ impl Cmd {
                     "signature": base64::encode(signature)
                 })
             }
+            SubCmd::RestartSafe(cmd) => {
+                let dirs = ctx.dirs().clone();
+                let rpc_node = ctx.create_rpc_node().await?;
+                cmd.check(&rpc_node, &dirs).await?
+            }
+            SubCmd::RotateControlKeys(cmd) => {
+                let client_pubkey = cmd.rotate(ctx.dirs()).await?;
+                serde_json::json!({
+                    "client_pubkey": hex::encode(client_pubkey.as_bytes()),
+                })
+            }
         };
 
         print_output(response);
@@ -216,6 +232,8 @@ enum SubCmd {
     GetAccount(CmdGetAccount),
     SendMessage(CmdSendMessage),
     GenDht(CmdNodeGenDht),
+    RotateControlKeys(CmdRotateControlKeys),
+    RestartSafe(CmdRestartSafe),
 }
 
 #[derive(FromArgs)]
@@ -315,7 +333,7 @@ struct CmdGetConfigParam {
 /// Gets account state
 #[argh(subcommand, name = "getaccount")]
 struct CmdGetAccount {
-    /// address (raw format)
+    /// address (raw or user-friendly format)
     #[argh(positional)]
     address: String,
 }
@@ -344,6 +362,203 @@ struct CmdNodeGenDht {
     time: Option<u32>,
 }
 
+#[derive(FromArgs)]
+/// Replaces the control client key shared between the node and the keeper
+#[argh(subcommand, name = "rotatekeys")]
+struct CmdRotateControlKeys {
+    /// how long to wait for the restarted node to accept the new key (in seconds)
+    #[argh(option, default = "600")]
+    timeout: u64,
+    /// systemd service of the node which is restarted to apply the keys. `validator` default
+    #[argh(option, default = "VALIDATOR_SERVICE.to_owned()")]
+    service: String,
+    /// don't restart the node, but wait until it is restarted by the operator
+    #[argh(switch)]
+    no_restart: bool,
+}
+
+impl CmdRotateControlKeys {
+    /// Adds the new key to the node config next to the old one, restarts the node
+    /// and switches the app config only after the node accepts the new key.
+    /// Then the old key is removed and the node is restarted again to revoke it.
+    async fn rotate(self, dirs: &ProjectDirs) -> Result<ed25519::PublicKey> {
+        let mut app_config = AppConfig::load(&dirs.app_config)?;
+        let old_control = app_config.control()?.clone();
+        let old_pubkey = ed25519::PublicKey::from(&old_control.client_secret);
+
+        let original_node_config = NodeConfig::load(&dirs.node_config)?;
+        let mut node_control = original_node_config
+            .get_control_server()?
+            .context("node config doesn't have control server entry")?;
+        let Some(clients) = &mut node_control.clients else {
+            anyhow::bail!("control server accepts any client, there is no key to rotate");
+        };
+
+        let client_secret = ed25519::SecretKey::generate(&mut rand::thread_rng());
+        let client_pubkey = ed25519::PublicKey::from(&client_secret);
+        let new_control = AppConfigControl {
+            client_secret,
+            ..old_control
+        };
+
+        // Allow both keys until the new one is verified
+        clients.push(client_pubkey);
+        let mut node_config = original_node_config.clone();
+        node_config.set_control_server(&node_control)?;
+        node_config.store(&dirs.node_config)?;
+
+        let timeout = Duration::from_secs(self.timeout);
+        let res = async {
+            if self.no_restart {
+                eprintln!(
+                    "New control key was added to the node config. Restart the node to apply it"
+                );
+            } else {
+                self.restart_node().await?;
+            }
+            wait_for_control_key(&new_control, timeout).await
+        };
+        if let Err(e) = res.await {
+            original_node_config
+                .store(&dirs.node_config)
+                .context("failed to roll back node config")?;
+            return Err(e.context("new control key was not accepted, node config rolled back"));
+        }
+
+        // NOTE: the node still accepts both keys, so the keeper can't be locked out here
+        app_config.control = Some(new_control.clone());
+        app_config.store(&dirs.app_config)?;
+
+        // Revoke the old key
+        if let Some(clients) = &mut node_control.clients {
+            clients.retain(|key| key.as_bytes() != old_pubkey.as_bytes());
+        }
+        node_config.set_control_server(&node_control)?;
+        node_config.store(&dirs.node_config)?;
+
+        if self.no_restart {
+            eprintln!(
+                "Old control key was removed from the node config. Restart the node to revoke it"
+            );
+        } else {
+            self.restart_node().await?;
+            wait_for_control_key(&new_control, timeout)
+                .await
+                .context("new control key was not accepted after the old one was revoked")?;
+        }
+
+        Ok(client_pubkey)
+    }
+
+    async fn restart_node(&self) -> Result<()> {
+        tracing::info!(service = %self.service, "restarting the node to apply the control keys");
+        exec(
+            Command::new("systemctl")
+                .stdout(Stdio::piped())
+                .arg("restart")
+                .arg(&self.service),
+        )
+        .await
+        .with_context(|| format!("failed to restart service {}", self.service))
+    }
+}
+
+#[derive(FromArgs)]
+/// Checks whether the node can be safely restarted right now
+#[argh(subcommand, name = "restart-safe")]
+struct CmdRestartSafe {
+    /// max timediff (in seconds). 120 seconds default
+    #[argh(option, default = "120")]
+    max_time_diff: i32,
+
+    /// how long the node is expected to be down (in seconds). 600 seconds default
+    #[argh(option, default = "600")]
+    downtime: u32,
+}
+
+impl CmdRestartSafe {
+    /// How long the pending messages sample is considered fresh
+    const PENDING_MESSAGES_TTL: u32 = 60;
+
+    async fn check(self, rpc_node: &NodeTcpRpc, dirs: &ProjectDirs) -> Result<serde_json::Value> {
+        let now = broxus_util::now();
+        let mut reasons = Vec::new();
+
+        match rpc_node.get_stats().await? {
+            NodeStats::Running(stats) => {
+                if stats.mc_time_diff >= self.max_time_diff
+                    || stats.sc_time_diff >= self.max_time_diff
+                {
+                    reasons.push(format!(
+                        "node is not synced (mc_time_diff: {}s, sc_time_diff: {}s)",
+                        stats.mc_time_diff, stats.sc_time_diff
+                    ));
+                }
+                if let ValidatorSetEntry::Validator(_) = stats.in_current_vset {
+                    reasons.push("node is in the current validator set".to_owned());
+                }
+            }
+            NodeStats::NotReady(stats) => {
+                reasons.push(format!("node is not ready ({stats})"));
+            }
+        }
+
+        let ConfigWithId { config, .. } = rpc_node.get_config_all().await?;
+        let timings = config.elector_params().context("invalid elector params")?;
+        let current_vset = config.validator_set().context("invalid validator set")?;
+        let timeline = Timeline::compute(&timings, &current_vset, now);
+        match timeline {
+            Timeline::Elections { .. } => {
+                reasons.push("elections are in progress".to_owned());
+            }
+            Timeline::BeforeElections {
+                until_elections_start,
+            } if until_elections_start <= self.downtime => {
+                reasons.push(format!(
+                    "elections start in {}",
+                    human_duration(until_elections_start)
+                ));
+            }
+            _ => {}
+        }
+
+        match dirs.load_pending_messages()? {
+            Some(pending) if pending.sampled_at + Self::PENDING_MESSAGES_TTL >= now => {
+                let count = pending.masterchain + pending.basechain;
+                if count > 0 {
+                    reasons.push(format!("keeper has {count} pending messages"));
+                }
+            }
+            // NOTE: the keeper is not running or its sample is too old
+            _ => {}
+        }
+
+        Ok(serde_json::json!({
+            "safe": reasons.is_empty(),
+            "timeline": timeline.to_string(),
+            "reasons": reasons,
+        }))
+    }
+}
+
+/// Waits until the node accepts queries signed with the specified control key
+async fn wait_for_control_key(config: &AppConfigControl, timeout: Duration) -> Result<()> {
+    const INTERVAL: Duration = Duration::from_secs(5);
+
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        let res = async { NodeTcpRpc::new(config).await?.get_stats().await }.await;
+        match res {
+            Ok(_) => return Ok(()),
+            Err(e) if tokio::time::Instant::now() + INTERVAL < deadline => {
+                tracing::debug!("new control key is not accepted yet: {e:?}");
+                tokio::time::sleep(INTERVAL).await;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
 impl CliContext {
     async fn create_rpc_node(self) -> Result<NodeTcpRpc> {
         let config = self.load_config()?;
@@ -1,6 +1,11 @@
-use anyhow::Result;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
 use argh::FromArgs;
 
+use super::CliContext;
+use crate::config::{AppConfigValidator, StoredKeys};
+use crate::contracts::wallet;
 use crate::crypto::{self, MnemonicType};
 use crate::util::*;
 
@@ -13,11 +18,12 @@ pub struct Cmd {
 }
 
 impl Cmd {
-    pub fn run(self) -> Result<()> {
+    pub fn run(self, ctx: CliContext) -> Result<()> {
         match self.subcommand {
             SubCmd::Generate(cmd) => cmd.run(),
             SubCmd::Derive(cmd) => cmd.run(),
             SubCmd::Pubkey(cmd) => cmd.run(),
+            SubCmd::Address(cmd) => cmd.run(ctx),
         }
     }
 }
@@ -28,6 +34,7 @@ enum SubCmd {
     Generate(CmdGenerate),
     Derive(CmdDerive),
     Pubkey(CmdPubkey),
+    Address(CmdAddress),
 }
 
 #[derive(Debug, PartialEq, FromArgs)]
@@ -111,6 +118,50 @@ impl CmdPubkey {
     }
 }
 
+#[derive(Debug, PartialEq, FromArgs)]
+/// Computes wallet address from the keys file
+#[argh(subcommand, name = "address")]
+struct CmdAddress {
+    /// path to the keys file (validator keys by default)
+    #[argh(option, short = 'k')]
+    keys: Option<PathBuf>,
+
+    /// wallet workchain (derived from the configured validator type by default)
+    #[argh(option, short = 'w')]
+    workchain: Option<i8>,
+}
+
+impl CmdAddress {
+    fn run(self, ctx: CliContext) -> Result<()> {
+        let workchain = match self.workchain {
+            Some(workchain) => workchain,
+            None => match ctx.load_config()?.validator {
+                Some(AppConfigValidator::Single(_)) => ton_block::MASTERCHAIN_ID as i8,
+                Some(AppConfigValidator::DePool(_)) => ton_block::BASE_WORKCHAIN_ID as i8,
+                None => {
+                    anyhow::bail!("validator is not configured, workchain must be specified")
+                }
+            },
+        };
+
+        let path = self
+            .keys
+            .unwrap_or_else(|| ctx.dirs().validator_keys.clone());
+        let keys = StoredKeys::load(path).context("failed to load keys")?;
+        let keypair = keys.active_keys(broxus_util::now())?.keypair;
+
+        let address = wallet::compute_wallet_address(workchain, &keypair.public);
+        let friendly = pack_friendly_address(&address, true).context("failed to pack address")?;
+
+        print_output(serde_json::json!({
+            "public": hex::encode(keypair.public.as_bytes()),
+            "raw": address.to_string(),
+            "friendly": friendly,
+        }));
+        Ok(())
+    }
+}
+
 fn encode_key_pair(
     secret: ed25519_dalek::SecretKey,
     public: ed25519_dalek::PublicKey,
@@ -0,0 +1,167 @@
+use anyhow::{Context, Result};
+use argh::FromArgs;
+
+use super::CliContext;
+use crate::config::{AppConfigValidator, NodeConfig};
+use crate::contracts::elector::StakeResponse;
+use crate::contracts::{Elector, InternalMessage};
+use crate::network::{ConfigWithId, NodeTcpRpc, NodeUdpRpc, Subscription};
+use crate::util::print_output;
+use crate::validator::DEFAULT_STAKE_FACTOR;
+
+#[derive(FromArgs)]
+/// Local execution of the validator messages
+#[argh(subcommand, name = "simulate")]
+pub struct Cmd {
+    #[argh(subcommand)]
+    subcommand: SubCmd,
+}
+
+impl Cmd {
+    pub async fn run(self, ctx: CliContext) -> Result<()> {
+        match self.subcommand {
+            SubCmd::Elect(cmd) => cmd.run(ctx).await,
+        }
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum SubCmd {
+    Elect(CmdElect),
+}
+
+#[derive(FromArgs)]
+/// Executes the election message on the current elector state without sending it
+#[argh(subcommand, name = "elect")]
+struct CmdElect {
+    /// stake in nano tokens (resolved from the config and the wallet balance by default)
+    #[argh(option)]
+    stake: Option<u128>,
+
+    /// stake factor (from the config by default)
+    #[argh(option)]
+    stake_factor: Option<u32>,
+}
+
+impl CmdElect {
+    async fn run(self, ctx: CliContext) -> Result<()> {
+        let config = ctx.load_config()?;
+        let one_coin = config.one_coin();
+        // NOTE: DePool stakes are sent by proxies, so only the wallet message can be simulated
+        let validator = match &config.validator {
+            Some(AppConfigValidator::Single(single)) => single.clone(),
+            Some(AppConfigValidator::DePool(_)) => {
+                anyhow::bail!("only single validator stakes can be simulated")
+            }
+            None => anyhow::bail!("validation is not configured"),
+        };
+
+        let node_tcp_rpc = NodeTcpRpc::new(config.control()?).await?;
+        let node_udp_rpc = NodeUdpRpc::new(config.adnl()?).await?;
+        let subscription = Subscription::new(node_tcp_rpc, node_udp_rpc, config.retries.clone());
+        subscription.ensure_ready().await?;
+
+        let ConfigWithId {
+            config: blockchain_config,
+            ..
+        } = subscription.tcp_rpc().get_config_all().await?;
+        let elector_address = blockchain_config
+            .elector_address()
+            .context("invalid elector address")?;
+        let stakes_config = blockchain_config
+            .stakes_config()
+            .context("invalid stakes config")?;
+
+        let elector = Elector::new(elector_address, subscription.clone());
+        let elector_data = elector
+            .get_data()
+            .await
+            .context("failed to get elector data")?;
+        let election_id = elector_data
+            .election_id()
+            .context("no current elections in the elector state")?;
+
+        // Resolve the stake the same way as the validator does
+        let stake = match self.stake {
+            Some(stake) => stake,
+            None => {
+                let balance = subscription
+                    .get_account_state(&validator.address)
+                    .await?
+                    .map(|state| state.storage.balance.grams.0)
+                    .unwrap_or_default();
+                std::cmp::min(
+                    validator.stake_per_round.resolve(balance, 2 * one_coin),
+                    stakes_config.max_stake.0,
+                )
+            }
+        };
+        let stake_factor = self
+            .stake_factor
+            .or(validator.stake_factor)
+            .unwrap_or(DEFAULT_STAKE_FACTOR);
+
+        // NOTE: `elect` generates a new key right before sending the stake,
+        // so the latest node key is used until there is one for these elections
+        let node_config = NodeConfig::load(
+            ctx.dirs()
+                .adnl_node_config
+                .as_ref()
+                .unwrap_or(&ctx.dirs().node_config),
+        )?;
+        let mut validator_keys = node_config.get_validator_keys()?;
+        validator_keys.sort_by_key(|keys| (keys.election_id == election_id, keys.election_id));
+        let keys = validator_keys
+            .pop()
+            .context("node has no validator keys yet, send the stake first")?;
+        let adnl_addr = keys
+            .validator_adnl_key_id
+            .context("node validator key has no ADNL address")?;
+
+        let signature_id = subscription.get_signature_id().await?;
+        let payload = elector
+            .participant_payload(
+                election_id,
+                &validator.address,
+                stake_factor,
+                &keys.validator_key_id,
+                &adnl_addr,
+                signature_id,
+            )
+            .await
+            .context("failed to prepare election payload")?;
+
+        let simulation = elector
+            .simulate_stake(
+                &validator.address,
+                InternalMessage {
+                    dst: elector.address().clone(),
+                    amount: stake + one_coin,
+                    payload,
+                },
+                blockchain_config,
+            )
+            .await?;
+
+        let (accepted, reason) = match simulation.response {
+            Some(StakeResponse::Accepted) => (true, None),
+            Some(StakeResponse::Returned(reason)) => (false, Some(reason.to_string())),
+            None => (false, Some("no elector response".to_owned())),
+        };
+
+        print_output(serde_json::json!({
+            "election_id": election_id,
+            "address": validator.address.to_string(),
+            "stake": stake.to_string(),
+            "stake_factor": stake_factor,
+            "validator_key": hex::encode(keys.validator_key_id),
+            "validator_key_election_id": keys.election_id,
+            "exit_code": simulation.exit_code,
+            "aborted": simulation.aborted,
+            "accepted": accepted,
+            "reason": reason,
+        }));
+        Ok(())
+    }
+}
@@ -0,0 +1,166 @@
+use anyhow::{Context, Result};
+use argh::FromArgs;
+
+use super::CliContext;
+use crate::config::AppConfig;
+use crate::network::{CatchUpFrom, NodeTcpRpc, NodeUdpRpc, Subscription, TransactionStatus};
+use crate::util::*;
+
+#[derive(FromArgs)]
+/// Transaction tools
+#[argh(subcommand, name = "tx")]
+pub struct Cmd {
+    #[argh(subcommand)]
+    subcommand: SubCmd,
+}
+
+impl Cmd {
+    pub async fn run(self, ctx: CliContext) -> Result<()> {
+        let response = match self.subcommand {
+            SubCmd::Status(cmd) => cmd.run(ctx.load_config()?).await?,
+            SubCmd::Watch(cmd) => return cmd.run(ctx.load_config()?).await,
+        };
+
+        print_output(response);
+        Ok(())
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum SubCmd {
+    Status(CmdStatus),
+    Watch(CmdWatch),
+}
+
+#[derive(FromArgs)]
+/// Searches the transaction by its hash or by the hash of its inbound message
+#[argh(subcommand, name = "status")]
+struct CmdStatus {
+    /// transaction or message hash (hex encoded string)
+    #[argh(positional)]
+    hash: String,
+
+    /// account address
+    #[argh(option, short = 'd', long = "addr")]
+    address: String,
+
+    /// message expiration timestamp (to distinguish pending messages)
+    #[argh(option, short = 'e')]
+    expire_at: Option<u32>,
+
+    /// number of the latest masterchain blocks to scan. 100 blocks default
+    #[argh(option, default = "100")]
+    depth: u32,
+}
+
+impl CmdStatus {
+    async fn run(self, config: AppConfig) -> Result<serde_json::Value> {
+        let address = parse_address(&self.address)?;
+        let hash = parse_hex_or_base64(&self.hash)
+            .ok()
+            .and_then(|hash| <[u8; 32]>::try_from(hash).ok())
+            .map(ton_types::UInt256::from)
+            .context("invalid hash")?;
+
+        // Prepare RPC clients
+        let node_tcp_rpc = NodeTcpRpc::new(config.control()?)
+            .await
+            .context("failed to build node TCP client")?;
+        let node_udp_rpc = NodeUdpRpc::new(config.adnl()?)
+            .await
+            .context("failed to build node UDP client")?;
+
+        // Create subscription
+        let subscription = Subscription::new(node_tcp_rpc, node_udp_rpc, config.retries.clone());
+        subscription.ensure_ready().await?;
+
+        let status = subscription
+            .find_transaction(&address, &hash, self.expire_at, self.depth)
+            .await?;
+
+        Ok(match status {
+            TransactionStatus::Found { block_id, tx } => {
+                let description = tx
+                    .data
+                    .read_description()
+                    .context("failed to read transaction description")?;
+                let aborted = match description {
+                    ton_block::TransactionDescr::Ordinary(descr) => Some(descr.aborted),
+                    _ => None,
+                };
+
+                serde_json::json!({
+                    "status": "found",
+                    "block_id": block_id.to_string(),
+                    "tx_hash": tx.hash.to_hex_string(),
+                    "lt": tx.data.logical_time(),
+                    "aborted": aborted,
+                })
+            }
+            TransactionStatus::NotFoundYet => serde_json::json!({
+                "status": "pending",
+            }),
+            TransactionStatus::NotFound => serde_json::json!({
+                "status": "not_found",
+            }),
+        })
+    }
+}
+
+#[derive(FromArgs)]
+/// Prints new account transactions as they appear
+#[argh(subcommand, name = "watch")]
+struct CmdWatch {
+    /// account address
+    #[argh(option, short = 'd', long = "addr")]
+    address: String,
+
+    /// max number of not yet printed transactions. 1000 default
+    #[argh(option, default = "1000")]
+    capacity: usize,
+
+    /// start from the past masterchain block: either the number of blocks
+    /// before the latest one or `seqno:root_hash:file_hash`
+    #[argh(option)]
+    from: Option<CatchUpFrom>,
+}
+
+impl CmdWatch {
+    async fn run(self, config: AppConfig) -> Result<()> {
+        let address = parse_address(&self.address)?;
+        anyhow::ensure!(self.capacity > 0, "capacity must be greater than zero");
+
+        // Prepare RPC clients
+        let node_tcp_rpc = NodeTcpRpc::new(config.control()?)
+            .await
+            .context("failed to build node TCP client")?;
+        let node_udp_rpc = NodeUdpRpc::new(config.adnl()?)
+            .await
+            .context("failed to build node UDP client")?;
+
+        // Create subscription
+        let subscription = Subscription::new(node_tcp_rpc, node_udp_rpc, config.retries.clone());
+        subscription.ensure_ready().await?;
+        anyhow::ensure!(
+            !subscription.is_degraded(),
+            "node UDP RPC is required to watch transactions"
+        );
+        if let Some(from) = self.from {
+            subscription.catch_up_from(from)?;
+        }
+
+        let mut transactions = subscription.subscribe_bounded(&address, self.capacity);
+        while let Some(tx) = transactions.recv().await {
+            let output = serde_json::json!({
+                "tx_hash": tx.hash.to_hex_string(),
+                "lt": tx.data.logical_time(),
+                "utime": tx.data.now(),
+                "dropped": subscription.dropped_transactions(),
+            });
+            println!("{output}");
+        }
+
+        Ok(())
+    }
+}
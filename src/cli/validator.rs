@@ -1,20 +1,43 @@
+use std::path::PathBuf;
 use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use argh::FromArgs;
+use tokio::signal::unix::{signal, SignalKind};
 use tokio_util::sync::CancellationToken;
 
 use super::CliContext;
-use crate::validator::{ValidationManager, ValidationParams};
+use crate::config::{ValidationErrors, DEFAULT_UNIT};
+use crate::network::{ConfigWithId, NodeTcpRpc, NodeUdpRpc};
+use crate::util::{parse_timestamp, print_output};
+use crate::validator::{
+    ControlSocket, HistoryEventKind, InstanceLock, ValidationError, ValidationManager,
+    ValidationParams,
+};
 
 #[derive(FromArgs)]
 /// Validation manager service
 #[argh(subcommand, name = "validator")]
 pub struct Cmd {
+    #[argh(subcommand)]
+    subcommand: Option<SubCmd>,
+
     /// max timediff (in seconds). 120 seconds default
     #[argh(option, default = "120")]
     max_time_diff: u16,
 
+    /// max masterchain timediff (in seconds). `--max-time-diff` by default
+    #[argh(option)]
+    max_mc_time_diff: Option<u16>,
+
+    /// max shardchain timediff (in seconds). `--max-time-diff` by default
+    #[argh(option)]
+    max_sc_time_diff: Option<u16>,
+
+    /// how long the node can stay not ready before it is reported (in seconds). 1800 seconds default
+    #[argh(option, default = "1800")]
+    node_not_ready_threshold: u32,
+
     /// offset after stake unfreeze (in seconds). 600 seconds default
     #[argh(option, default = "600")]
     stake_unfreeze_offset: u32,
@@ -27,6 +50,14 @@ pub struct Cmd {
     #[argh(option, default = "120")]
     elections_end_offset: u32,
 
+    /// min time before the elections deadline to start an attempt (in seconds), unless the estimated attempt duration is longer. 60 seconds default
+    #[argh(option, default = "60")]
+    min_time_remaining: u32,
+
+    /// loop iteration duration (in seconds) after which a warning is logged. Disabled by default
+    #[argh(option)]
+    max_iteration_duration: Option<u32>,
+
     /// min retry interval (in seconds). 10 seconds default
     #[argh(option, default = "10")]
     min_retry_interval: u64,
@@ -46,25 +77,129 @@ pub struct Cmd {
     /// ignore contracts deployment
     #[argh(switch)]
     ignore_deploy: bool,
+
+    /// participate only in the currently open elections and exit (with code 19 if none)
+    #[argh(switch)]
+    once_if_elections: bool,
+
+    /// max value attached to any outgoing message (in the smallest units). 20M tokens default
+    #[argh(option)]
+    max_attached_value: Option<u128>,
+
+    /// wallet balance check interval (in seconds). 1 second default
+    #[argh(option, default = "1")]
+    balance_poll_interval: u64,
+
+    /// wallet balance check interval while its transactions are tracked (in seconds).
+    /// 30 seconds default
+    #[argh(option, default = "30")]
+    balance_fallback_poll_interval: u64,
+
+    /// wallet balance wait timeout (in seconds), 0 to wait indefinitely. 3600 seconds default
+    #[argh(option, default = "3600")]
+    balance_wait_timeout: u64,
+
+    /// balance required above the target before sending (in the smallest units).
+    /// 0.1 tokens default
+    #[argh(option)]
+    balance_safety_margin: Option<u128>,
+
+    /// fail on inconsistent options instead of adjusting them with a warning
+    #[argh(switch)]
+    strict: bool,
+
+    /// exit on errors which can't be fixed by retrying (auth, keys, network or config)
+    #[argh(switch)]
+    fail_fast: bool,
+
+    /// path to the file with inputs and decisions of the latest validation loop iteration
+    #[argh(option)]
+    trace: Option<PathBuf>,
+
+    /// start even if another validator instance seems to be running
+    #[argh(switch)]
+    force: bool,
 }
 
 impl Cmd {
     pub async fn run(mut self, ctx: CliContext) -> Result<()> {
+        match self.subcommand.take() {
+            Some(SubCmd::History(cmd)) => return cmd.run(ctx),
+            Some(SubCmd::Fees(cmd)) => return cmd.run(ctx),
+            Some(SubCmd::Blocks(cmd)) => return cmd.run(ctx).await,
+            Some(SubCmd::Plan(_)) => {
+                let plan = crate::validator::plan(ctx.dirs(), &self.params()).await?;
+                for step in plan {
+                    println!("{step}");
+                }
+                return Ok(());
+            }
+            None => {}
+        }
+
+        // Check options before they are adjusted
+        let lints = self.lint();
+        if !lints.is_empty() {
+            if self.strict {
+                return Err(lints.into());
+            }
+            for (option, error) in lints.iter() {
+                tracing::warn!(
+                    option,
+                    "inconsistent option: {error}, the value is adjusted"
+                );
+            }
+        }
+
+        // Make sure that no other instance participates with the same keys
+        let instance_lock = InstanceLock::acquire(&ctx.dirs().validator_lock, self.force)?;
+
         // Start listening termination signals
         let signal_rx = broxus_util::any_signal(broxus_util::TERMINATION_SIGNALS);
 
+        // Make sure that derived ADNL params are correct
+        if ctx.dirs().adnl_node_config.is_some() {
+            let config = ctx.load_config()?;
+            NodeUdpRpc::new(config.adnl()?)
+                .await
+                .context("failed to build node UDP client")?
+                .get_capabilities()
+                .await
+                .context(
+                    "node is not reachable with the ADNL params derived from the node config",
+                )?;
+        }
+
         // Create validation manager
-        let mut manager = ValidationManager::new(
-            ctx.dirs,
-            ValidationParams {
-                max_time_diff: std::cmp::max(self.max_time_diff as i32, 5),
-                stake_unfreeze_offset: self.stake_unfreeze_offset,
-                elections_start_offset: self.elections_start_offset,
-                elections_end_offset: self.elections_end_offset,
-                disable_random_shift: self.disable_random_shift,
-                ignore_deploy: self.ignore_deploy,
-            },
-        );
+        let dirs = ctx.dirs.clone();
+        let control_socket = ctx.load_config()?.control_socket;
+        let mut manager = ValidationManager::new(ctx.dirs, self.params());
+        if let Some(path) = self.trace.clone() {
+            manager.set_trace_path(path);
+        }
+
+        // Serve the control socket while the manager is running
+        let _control_socket = match &control_socket {
+            Some(path) => Some(ControlSocket::bind(path, &manager)?),
+            None => None,
+        };
+
+        // Toggle pause on SIGUSR1
+        let mut pause_signal = signal(SignalKind::user_defined1())?;
+        tokio::spawn({
+            let guard = manager.guard().clone();
+            async move {
+                while pause_signal.recv().await.is_some() {
+                    // NOTE: wait for the in-flight election message
+                    let _guard = guard.lock().await;
+                    let paused = !dirs.is_paused();
+                    match dirs.set_paused(paused) {
+                        Ok(()) => tracing::warn!(paused, "validation pause toggled"),
+                        Err(e) => tracing::error!("failed to toggle validation pause: {e:?}"),
+                    }
+                }
+            }
+        });
 
         // Spawn cancellation future
         let cancellation_token = CancellationToken::new();
@@ -83,6 +218,29 @@ impl Cmd {
             }
         });
 
+        // Stop validation if another instance took over the lock
+        let taken_over = {
+            let guard = manager.guard().clone();
+            let instance_lock = &instance_lock;
+            async move {
+                instance_lock.taken_over().await;
+                // NOTE: wait for the in-flight election message
+                let _guard = guard.lock().await;
+            }
+        };
+
+        // Participate only in the open elections if requested
+        if self.once_if_elections {
+            return tokio::select! {
+                result = manager.try_validate() => result.map_err(|e| {
+                    manager.trace_error(&e);
+                    e
+                }),
+                _ = cancelled => Ok(()),
+                _ = taken_over => Err(anyhow::anyhow!("validator lock was taken over by another instance")),
+            };
+        }
+
         // Prepare validation future
         let validation_fut = async {
             self.min_retry_interval = std::cmp::max(self.min_retry_interval, 1);
@@ -93,7 +251,17 @@ impl Cmd {
             let mut interval = self.min_retry_interval;
             loop {
                 if let Err(e) = manager.try_validate().await {
-                    tracing::error!("error occurred: {e:?}");
+                    manager.trace_error(&e);
+                    match ValidationError::classify(&e) {
+                        // NOTE: let the supervisor restart the process with its own backoff
+                        Some(class) if self.fail_fast && class.is_terminal() => {
+                            return Err::<(), _>(e)
+                        }
+                        Some(class) => {
+                            tracing::error!(category = class.category(), "error occurred: {e:?}")
+                        }
+                        None => tracing::error!("error occurred: {e:?}"),
+                    }
                 }
 
                 tracing::info!("retrying in {interval} seconds");
@@ -108,10 +276,329 @@ impl Cmd {
 
         // Cancellable main loop
         tokio::select! {
-            _ = validation_fut => {},
-            _ = cancelled => {},
-        };
+            result = validation_fut => result,
+            _ = cancelled => Ok(()),
+            _ = taken_over => Err(anyhow::anyhow!("validator lock was taken over by another instance")),
+        }
+    }
+
+    fn max_mc_time_diff(&self) -> u16 {
+        self.max_mc_time_diff.unwrap_or(self.max_time_diff)
+    }
+
+    fn max_sc_time_diff(&self) -> u16 {
+        self.max_sc_time_diff.unwrap_or(self.max_time_diff)
+    }
+
+    fn params(&self) -> ValidationParams {
+        ValidationParams {
+            max_mc_time_diff: std::cmp::max(self.max_mc_time_diff() as i32, 5),
+            max_sc_time_diff: std::cmp::max(self.max_sc_time_diff() as i32, 5),
+            node_not_ready_threshold: self.node_not_ready_threshold,
+            stake_unfreeze_offset: self.stake_unfreeze_offset,
+            elections_start_offset: self.elections_start_offset,
+            elections_end_offset: self.elections_end_offset,
+            min_time_remaining: self.min_time_remaining,
+            max_iteration_duration: self.max_iteration_duration,
+            strict: self.strict,
+            // NOTE: the one-shot run is started by the scheduler at the desired time
+            disable_random_shift: self.disable_random_shift || self.once_if_elections,
+            ignore_deploy: self.ignore_deploy,
+            once_if_elections: self.once_if_elections,
+            max_attached_value: self.max_attached_value,
+            balance_poll_interval: Duration::from_secs(std::cmp::max(
+                self.balance_poll_interval,
+                1,
+            )),
+            balance_fallback_poll_interval: Duration::from_secs(
+                self.balance_fallback_poll_interval,
+            ),
+            balance_wait_timeout: (self.balance_wait_timeout > 0)
+                .then_some(Duration::from_secs(self.balance_wait_timeout)),
+            balance_safety_margin: self.balance_safety_margin,
+        }
+    }
+
+    /// Finds options which would be silently adjusted otherwise.
+    ///
+    /// NOTE: elections offsets are checked against the network params at runtime
+    fn lint(&self) -> ValidationErrors {
+        let mut errors = ValidationErrors::default();
+        errors.ensure(
+            self.max_time_diff >= 5,
+            "--max-time-diff",
+            "must be at least 5 seconds",
+        );
+        errors.ensure(
+            self.max_mc_time_diff() >= 5,
+            "--max-mc-time-diff",
+            "must be at least 5 seconds",
+        );
+        errors.ensure(
+            self.max_sc_time_diff() >= 5,
+            "--max-sc-time-diff",
+            "must be at least 5 seconds",
+        );
+        errors.ensure(
+            self.min_retry_interval > 0,
+            "--min-retry-interval",
+            "must be greater than zero",
+        );
+        errors.ensure(
+            self.max_retry_interval >= self.min_retry_interval,
+            "--max-retry-interval",
+            "must not be less than --min-retry-interval",
+        );
+        errors.ensure(
+            self.retry_interval_multiplier >= 1.0,
+            "--retry-interval-multiplier",
+            "must be at least 1.0",
+        );
+        errors.ensure(
+            self.balance_poll_interval > 0,
+            "--balance-poll-interval",
+            "must be greater than zero",
+        );
+        errors
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum SubCmd {
+    History(CmdHistory),
+    Fees(CmdFees),
+    Plan(CmdPlan),
+    Blocks(CmdBlocks),
+}
+
+#[derive(FromArgs)]
+/// Explains the next validator actions without sending anything
+#[argh(subcommand, name = "plan")]
+struct CmdPlan {}
+
+#[derive(FromArgs)]
+/// Shows recorded validator actions
+#[argh(subcommand, name = "history")]
+struct CmdHistory {
+    /// show actions since this time (unix timestamp, ISO 8601 or relative, e.g. `7d`)
+    #[argh(option, from_str_fn(parse_time_arg))]
+    since: Option<u32>,
+
+    /// show actions until this time (same formats as `--since`)
+    #[argh(option, from_str_fn(parse_time_arg))]
+    until: Option<u32>,
+
+    /// show only actions of this type (stake_sent, stake_recovered, depool_stake_added,
+    /// ticktock_sent, balance_swept, round_finished)
+    #[argh(option)]
+    event: Vec<String>,
+
+    /// validation unit name. `default` by default
+    #[argh(option, default = "DEFAULT_UNIT.to_owned()")]
+    unit: String,
+
+    /// max number of actions per page. 50 default
+    #[argh(option, default = "50")]
+    limit: usize,
+
+    /// page number (starting from 1). 1 default
+    #[argh(option, default = "1")]
+    page: usize,
+}
+
+impl CmdHistory {
+    fn run(self, ctx: CliContext) -> Result<()> {
+        for event in &self.event {
+            anyhow::ensure!(
+                HistoryEventKind::NAMES.contains(&event.as_str()),
+                "unknown event type `{event}` (expected one of: {})",
+                HistoryEventKind::NAMES.join(", ")
+            );
+        }
+        anyhow::ensure!(self.limit > 0, "limit must be greater than zero");
+        anyhow::ensure!(self.page > 0, "page numbers start from 1");
+
+        let events = ctx
+            .dirs()
+            .unit_dirs(&self.unit)
+            .load_validator_history()?
+            .into_iter()
+            .filter(|event| {
+                self.since.map_or(true, |since| event.timestamp >= since)
+                    && self.until.map_or(true, |until| event.timestamp < until)
+                    && (self.event.is_empty() || self.event.iter().any(|e| e == event.kind.name()))
+            })
+            .collect::<Vec<_>>();
+
+        let total = events.len();
+        let pages = (total + self.limit - 1) / self.limit;
+        let events = events
+            .into_iter()
+            .skip((self.page - 1).saturating_mul(self.limit))
+            .take(self.limit)
+            .collect::<Vec<_>>();
+
+        print_output(serde_json::json!({
+            "total": total,
+            "page": self.page,
+            "pages": pages,
+            "events": events,
+        }));
+        Ok(())
+    }
+}
+
+#[derive(FromArgs)]
+/// Shows transaction fees paid by the validator wallet
+#[argh(subcommand, name = "fees")]
+struct CmdFees {
+    /// count fees since this time (unix timestamp, ISO 8601 or relative, e.g. `30d`)
+    #[argh(option, from_str_fn(parse_time_arg))]
+    since: Option<u32>,
+
+    /// count fees until this time (same formats as `--since`)
+    #[argh(option, from_str_fn(parse_time_arg))]
+    until: Option<u32>,
+
+    /// validation unit name. `default` by default
+    #[argh(option, default = "DEFAULT_UNIT.to_owned()")]
+    unit: String,
+}
+
+impl CmdFees {
+    fn run(self, ctx: CliContext) -> Result<()> {
+        let events = ctx.dirs().unit_dirs(&self.unit).load_validator_history()?;
+
+        let mut total = 0u128;
+        let mut by_action = std::collections::BTreeMap::<&str, (usize, u128)>::new();
+        for event in &events {
+            if !(self.since.map_or(true, |since| event.timestamp >= since)
+                && self.until.map_or(true, |until| event.timestamp < until))
+            {
+                continue;
+            }
+            let Some(fees) = event.kind.fees() else {
+                continue;
+            };
+
+            total += fees;
+            let (count, sum) = by_action.entry(event.kind.name()).or_default();
+            *count += 1;
+            *sum += fees;
+        }
+
+        print_output(serde_json::json!({
+            "since": self.since,
+            "until": self.until,
+            "total": total.to_string(),
+            "actions": by_action
+                .into_iter()
+                .map(|(name, (count, fees))| {
+                    (
+                        name.to_owned(),
+                        serde_json::json!({
+                            "count": count,
+                            "fees": fees.to_string(),
+                        }),
+                    )
+                })
+                .collect::<serde_json::Map<_, _>>(),
+        }));
+        Ok(())
+    }
+}
+
+#[derive(FromArgs)]
+/// Shows whether the node produces blocks in the current validation round
+#[argh(subcommand, name = "blocks")]
+struct CmdBlocks {}
+
+impl CmdBlocks {
+    async fn run(self, ctx: CliContext) -> Result<()> {
+        let config = ctx.load_config()?;
+        let node_rpc = NodeTcpRpc::new(config.control()?).await?;
+
+        let stats = node_rpc
+            .get_collation_stats()
+            .await?
+            .context("node doesn't report collation stats")?;
+
+        let ConfigWithId { config, .. } = node_rpc.get_config_all().await?;
+        let current_vset = config.validator_set().context("invalid validator set")?;
+        let round_since = current_vset.utime_since();
+
+        // NOTE: the node reports only the latest block time in each shard
+        let shards = stats
+            .collated
+            .keys()
+            .chain(stats.validated.keys())
+            .collect::<std::collections::BTreeSet<_>>()
+            .into_iter()
+            .map(|shard| {
+                let collated = stats.collated.get(shard).copied();
+                let validated = stats.validated.get(shard).copied();
+                serde_json::json!({
+                    "shard": shard,
+                    "last_collated": collated,
+                    "last_validated": validated,
+                    "collated_in_round": collated.map_or(false, |time| time >= round_since),
+                    "validated_in_round": validated.map_or(false, |time| time >= round_since),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        print_output(serde_json::json!({
+            "round_since": round_since,
+            "last_collated": stats.last_collated(),
+            "last_validated": stats.last_validated(),
+            "producing": stats.last_collated().map_or(false, |time| time >= round_since),
+            "shards": shards,
+        }));
+        Ok(())
+    }
+}
+
+fn parse_time_arg(value: &str) -> Result<u32, String> {
+    parse_timestamp(value, broxus_util::now()).map_err(|e| e.to_string())
+}
+
+#[derive(FromArgs)]
+/// Stops the running validator, recovers unfrozen stakes, skips the nearest elections and exits
+#[argh(subcommand, name = "drain-and-stop")]
+pub struct CmdDrainAndStop {
+    /// how long to wait for the running validator to stop (in seconds). 600 seconds default
+    #[argh(option, default = "600")]
+    stop_timeout: u64,
+
+    /// max value attached to any outgoing message (in the smallest units). 20M tokens default
+    #[argh(option)]
+    max_attached_value: Option<u128>,
+
+    /// balance required above the target before sending (in the smallest units).
+    /// 0.1 tokens default
+    #[argh(option)]
+    balance_safety_margin: Option<u128>,
+}
+
+impl CmdDrainAndStop {
+    pub async fn run(self, ctx: CliContext) -> Result<()> {
+        // NOTE: the lock is held until the end so that no instance elects meanwhile
+        let _instance_lock = InstanceLock::stop_running(
+            &ctx.dirs().validator_lock,
+            Duration::from_secs(self.stop_timeout),
+        )
+        .await?;
 
+        let election_id = crate::validator::drain(
+            ctx.dirs(),
+            self.max_attached_value,
+            self.balance_safety_margin,
+        )
+        .await?;
+        print_output(serde_json::json!({
+            "skipped_election_id": election_id,
+        }));
         Ok(())
     }
 }
@@ -1,39 +1,137 @@
-use std::net::SocketAddrV4;
-use std::path::Path;
+use std::collections::BTreeMap;
+use std::net::{SocketAddr, SocketAddrV4};
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use anyhow::{Context, Result};
-use broxus_util::{
-    const_duration_ms, serde_duration_ms, serde_hex_array, serde_optional_string, serde_string,
-    serde_string_or_number,
-};
+use broxus_util::{const_duration_ms, serde_duration_ms, serde_hex_array, serde_string_or_number};
 use everscale_crypto::ed25519;
 use serde::{Deserialize, Serialize};
 
+use super::validation::ValidationErrors;
+use super::{GlobalConfig, NodeConfig};
+use crate::contracts::{check_comment, DePool, DePoolAbi};
 use crate::currency;
-use crate::util::{serde_mc_address, serde_public_key, serde_secret_key};
+use crate::util::{
+    serde_address, serde_mc_address, serde_optional_address, serde_public_key, serde_secret_key,
+    AddressFamily, RetryPolicy, Tokens,
+};
 
 /// Tool config
 #[derive(Default, Clone, Serialize, Deserialize)]
 #[serde(default, deny_unknown_fields)]
 pub struct AppConfig {
+    /// Number of decimals of the network currency
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decimals: Option<u8>,
+    /// Path of the validator control socket
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub control_socket: Option<PathBuf>,
+    /// Participate only in the elections of rounds with this parity
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub round_parity: Option<RoundParity>,
     /// Control config
     pub control: Option<AppConfigControl>,
     /// ADNL config
     pub adnl: Option<AppConfigAdnl>,
     /// Validation config
     pub validator: Option<AppConfigValidator>,
+    /// Additional independent validators with their own keys
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub units: BTreeMap<String, AppConfigValidator>,
+    /// Metrics exporter HTTP server config
+    pub exporter: Option<AppConfigExporter>,
+    /// External command executed on validator events
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hook: Option<AppConfigHook>,
+    /// Path of the SQLite database with the validator history
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub history_db: Option<PathBuf>,
+    /// Show only the first and the last chars of addresses in logs
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub redact_logs: bool,
+    /// Retry policies for different operations
+    pub retries: AppConfigRetries,
 }
 
 impl AppConfig {
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
         let content = std::fs::read_to_string(path).context("failed to read app config")?;
-        toml::from_str(&content).context("failed to deserialize app config")
+        let config = Self::from_toml(&content).context("failed to deserialize app config")?;
+        currency::set_decimals(config.decimals.unwrap_or(currency::DEFAULT_DECIMALS));
+        Ok(config)
+    }
+
+    /// Parses and validates the config, collecting all errors at once
+    pub fn from_toml(content: &str) -> Result<Self> {
+        let mut errors = ValidationErrors::default();
+
+        let value = toml::from_str::<toml::Value>(content)?;
+
+        // NOTE: each top-level field is deserialized separately with the derived
+        // schema, so that all invalid fields are reported instead of the first one
+        if let toml::Value::Table(table) = &value {
+            for (key, field) in table {
+                let field = toml::value::Table::from_iter([(key.clone(), field.clone())]);
+                errors.deserialize::<Self>("", toml::Value::Table(field));
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors.into());
+        }
+
+        let Some(config) = errors.deserialize::<Self>("", value) else {
+            return Err(errors.into());
+        };
+
+        if let Some(validator) = &config.validator {
+            validator.validate("validator", config.one_coin(), &mut errors);
+        }
+
+        for (name, validator) in &config.units {
+            let path = format!("units.{name}");
+            errors.ensure(
+                name != DEFAULT_UNIT
+                    && !name.is_empty()
+                    && name
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+                &path,
+                "unit name must consist of alphanumeric characters, `-` or `_` \
+                and must not be `default`",
+            );
+            validator.validate(&path, config.one_coin(), &mut errors);
+        }
+
+        if let Some(exporter) = &config.exporter {
+            exporter.validate("exporter", &mut errors);
+        }
+
+        if let Some(hook) = &config.hook {
+            hook.validate("hook", &mut errors);
+        }
+
+        config.retries.validate("retries", &mut errors);
+        if let Some(decimals) = config.decimals {
+            errors.ensure(
+                decimals <= MAX_DECIMALS,
+                "decimals",
+                "too many decimals (at most 18 are supported)",
+            );
+        }
+
+        errors.into_result()?;
+        Ok(config)
     }
 
     pub fn store<P: AsRef<Path>>(&self, path: P) -> Result<()> {
         let data = toml::to_string_pretty(self).context("failed to serialize config")?;
-        std::fs::write(path, data).context("failed to save config")
+
+        // NOTE: write to a temporary file first to never leave a partially written config
+        let path = path.as_ref();
+        let temp_path = path.with_extension("toml.tmp");
+        std::fs::write(&temp_path, data).context("failed to save config")?;
+        std::fs::rename(&temp_path, path).context("failed to save config")
     }
 
     pub fn currency(&self) -> &str {
@@ -50,6 +148,11 @@ impl AppConfig {
         currency::DEFAULT
     }
 
+    /// Returns the amount of the smallest units in one coin of the network currency
+    pub fn one_coin(&self) -> u128 {
+        10u128.pow(self.decimals.unwrap_or(currency::DEFAULT_DECIMALS) as u32)
+    }
+
     pub fn control(&self) -> Result<&AppConfigControl> {
         self.control.as_ref().context("control config is empty")
     }
@@ -57,13 +160,39 @@ impl AppConfig {
     pub fn adnl(&self) -> Result<&AppConfigAdnl> {
         self.adnl.as_ref().context("adnl config is empty")
     }
+
+    /// Returns all configured validators with their unit names
+    pub fn validation_units(&self) -> impl Iterator<Item = (&str, &AppConfigValidator)> {
+        let default = self
+            .validator
+            .iter()
+            .map(|validator| (DEFAULT_UNIT, validator));
+        let units = self
+            .units
+            .iter()
+            .map(|(name, validator)| (name.as_str(), validator));
+        default.chain(units)
+    }
 }
 
+/// Unit name of the main validator
+pub const DEFAULT_UNIT: &str = "default";
+
+const MAX_DECIMALS: u8 = 18;
+
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct AppConfigControl {
-    /// Control server socket address
-    pub server_address: SocketAddrV4,
+    /// Control server socket address (IPv4 or IPv6)
+    pub server_address: SocketAddr,
+
+    /// Other addresses of the control server (e.g. of another IP family)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback_addresses: Vec<SocketAddr>,
+
+    /// IP family of the control server addresses which can be used
+    #[serde(default)]
+    pub address_family: AddressFamily,
 
     /// Control server pubkey
     #[serde(with = "serde_public_key")]
@@ -89,13 +218,21 @@ impl AppConfigControl {
         client_key: ed25519::SecretKey,
     ) -> Self {
         Self {
-            server_address: addr,
+            server_address: addr.into(),
+            fallback_addresses: Vec::new(),
+            address_family: AddressFamily::Any,
             server_pubkey: server_key,
             client_secret: client_key,
             connection_timeout: Duration::from_millis(2000),
             query_timeout: Duration::from_millis(10000),
         }
     }
+
+    /// Control server addresses in the order of the connection attempts
+    pub fn server_candidates(&self) -> Vec<SocketAddr> {
+        let addrs = std::iter::once(self.server_address).chain(self.fallback_addresses.clone());
+        self.address_family.candidates(addrs)
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -104,8 +241,18 @@ pub struct AppConfigAdnl {
     /// Local ADNL port
     pub client_port: u16,
 
-    /// Server ADNL address
-    pub server_address: SocketAddrV4,
+    /// Server ADNL address.
+    ///
+    /// NOTE: IPv6 addresses are reached through a local UDP relay
+    pub server_address: SocketAddr,
+
+    /// Other addresses of the node (e.g. IPv4 address of a dual-stack node)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback_addresses: Vec<SocketAddr>,
+
+    /// IP family of the node addresses which can be used
+    #[serde(default)]
+    pub address_family: AddressFamily,
 
     /// Server overlay pubkey
     #[serde(with = "serde_public_key")]
@@ -114,6 +261,270 @@ pub struct AppConfigAdnl {
     /// Zerostate file hash from the global config
     #[serde(with = "serde_hex_array")]
     pub zerostate_file_hash: [u8; 32],
+
+    /// Re-fetch the downloaded blocks whose data doesn't match the expected ids
+    /// instead of failing the blocks walk.
+    ///
+    /// NOTE: The data is always checked against the ids (shard block ids are taken
+    /// from the masterchain), this option only enables retries
+    #[serde(
+        default,
+        alias = "verify_blocks",
+        skip_serializing_if = "std::ops::Not::not"
+    )]
+    pub refetch_mismatched_blocks: bool,
+
+    /// Max total size of the recently downloaded blocks kept in memory (in bytes).
+    /// Caching is disabled if zero
+    #[serde(default = "AppConfigAdnl::default_block_cache_size")]
+    pub block_cache_size: usize,
+
+    /// Timeout of the initial capabilities query (in milliseconds). 1000 ms default
+    #[serde(with = "serde_duration_ms", default = "const_duration_ms::<1000>")]
+    pub connection_timeout: Duration,
+
+    /// ADNL query timeout (in milliseconds). 1000 ms default.
+    /// Increase it for high-latency links
+    #[serde(with = "serde_duration_ms", default = "const_duration_ms::<1000>")]
+    pub query_timeout: Duration,
+
+    /// How many times a timed out ADNL query is repeated. 2 retries default
+    #[serde(default = "AppConfigAdnl::default_query_retries")]
+    pub query_retries: u32,
+
+    /// How long blocks are still tracked after the last subscription is dropped
+    /// (in milliseconds), so that the next one doesn't start from scratch. Disabled by default
+    #[serde(with = "serde_duration_ms", default = "const_duration_ms::<0>")]
+    pub idle_linger: Duration,
+}
+
+impl AppConfigAdnl {
+    pub const DEFAULT_BLOCK_CACHE_SIZE: usize = 64 << 20;
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(1000);
+    pub const DEFAULT_QUERY_RETRIES: u32 = 2;
+
+    fn default_block_cache_size() -> usize {
+        Self::DEFAULT_BLOCK_CACHE_SIZE
+    }
+
+    fn default_query_retries() -> u32 {
+        Self::DEFAULT_QUERY_RETRIES
+    }
+
+    /// Node addresses in the order of the connection attempts
+    pub fn server_candidates(&self) -> Vec<SocketAddr> {
+        let addrs = std::iter::once(self.server_address).chain(self.fallback_addresses.clone());
+        self.address_family.candidates(addrs)
+    }
+
+    /// Derives ADNL client params from the node config and its global config
+    pub fn from_node_config<P: AsRef<Path>>(path: P, client_port: u16) -> Result<Self> {
+        let path = path.as_ref();
+        let node_config = NodeConfig::load(path)?;
+
+        let adnl_node = node_config
+            .get_adnl_node()?
+            .context("ADNL node config not found")?;
+
+        let global_config_path = node_config
+            .get_global_config_path()?
+            .context("global config path not found in the node config")?;
+        // NOTE: relative paths are resolved from the node config directory
+        let global_config_path = match path.parent() {
+            Some(dir) => dir.join(global_config_path),
+            None => global_config_path,
+        };
+        let global_config = GlobalConfig::load(global_config_path)?;
+
+        Ok(Self {
+            client_port,
+            server_address: adnl_node.ip_address.into(),
+            fallback_addresses: Vec::new(),
+            address_family: AddressFamily::Any,
+            server_pubkey: adnl_node.overlay_pubkey()?,
+            zerostate_file_hash: *global_config.zero_state.file_hash.as_array(),
+            refetch_mismatched_blocks: false,
+            block_cache_size: Self::DEFAULT_BLOCK_CACHE_SIZE,
+            connection_timeout: Self::DEFAULT_TIMEOUT,
+            query_timeout: Self::DEFAULT_TIMEOUT,
+            query_retries: Self::DEFAULT_QUERY_RETRIES,
+            idle_linger: Duration::ZERO,
+        })
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AppConfigExporter {
+    /// Metrics server socket address
+    pub listen_addr: SocketAddr,
+
+    /// TLS params. Plain HTTP is used if empty
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<AppConfigExporterTls>,
+
+    /// Token required in the `Authorization: Bearer` header for `/metrics`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bearer_token: Option<String>,
+}
+
+impl AppConfigExporter {
+    fn validate(&self, path: &str, errors: &mut ValidationErrors) {
+        if let Some(token) = &self.bearer_token {
+            errors.ensure(
+                !token.is_empty() && token.bytes().all(|c| c.is_ascii_graphic()),
+                &format!("{path}.bearer_token"),
+                "token must be a non-empty string of printable ASCII characters",
+            );
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AppConfigHook {
+    /// Path to the executable
+    pub command: PathBuf,
+
+    /// Command arguments, the event name is appended to them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+
+    /// Events on which the command is executed (all events if empty)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<HookEventKind>,
+
+    /// Time after which the command is killed
+    #[serde(with = "serde_duration_ms", default = "const_duration_ms::<30000>")]
+    pub timeout: Duration,
+}
+
+impl AppConfigHook {
+    fn validate(&self, path: &str, errors: &mut ValidationErrors) {
+        errors.ensure(
+            !self.command.as_os_str().is_empty(),
+            &format!("{path}.command"),
+            "command must not be empty",
+        );
+        errors.ensure(
+            !self.timeout.is_zero(),
+            &format!("{path}.timeout"),
+            "timeout must be greater than zero",
+        );
+    }
+
+    /// Whether the command should be executed on this event
+    pub fn is_enabled(&self, kind: HookEventKind) -> bool {
+        self.events.is_empty() || self.events.contains(&kind)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookEventKind {
+    /// Validator keys are in the new validator set
+    Elected,
+    /// Unfrozen stake was requested from the elector
+    Recovered,
+    /// Wallet balance is not enough to participate in the elections
+    Underfunded,
+    /// Node is not ready for too long
+    Desynced,
+}
+
+impl HookEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Elected => "elected",
+            Self::Recovered => "recovered",
+            Self::Underfunded => "underfunded",
+            Self::Desynced => "desynced",
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AppConfigExporterTls {
+    /// Path to the PEM encoded certificate chain
+    pub cert_path: PathBuf,
+
+    /// Path to the PEM encoded private key
+    pub key_path: PathBuf,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AppConfigRetries {
+    /// Resending expired external messages
+    #[serde(default = "AppConfigRetries::default_send_message")]
+    pub send_message: RetryPolicy,
+
+    /// Fetching blocks via ADNL
+    #[serde(default = "AppConfigRetries::default_block_fetch")]
+    pub block_fetch: RetryPolicy,
+
+    /// Updating DePool rounds with ticktocks
+    #[serde(default = "AppConfigRetries::default_depool_update")]
+    pub depool_update: RetryPolicy,
+
+    /// Node queries in the validation loop (blockchain config, elector state)
+    #[serde(default = "AppConfigRetries::default_node_query")]
+    pub node_query: RetryPolicy,
+}
+
+impl AppConfigRetries {
+    fn default_send_message() -> RetryPolicy {
+        RetryPolicy::constant(Duration::ZERO, None)
+    }
+
+    fn default_block_fetch() -> RetryPolicy {
+        RetryPolicy::constant(Duration::from_secs(1), Some(11))
+    }
+
+    fn default_depool_update() -> RetryPolicy {
+        RetryPolicy::constant(Duration::from_secs(60), Some(4))
+    }
+
+    fn default_node_query() -> RetryPolicy {
+        RetryPolicy::constant(Duration::from_secs(2), Some(3))
+    }
+
+    fn validate(&self, path: &str, errors: &mut ValidationErrors) {
+        for (name, policy) in [
+            ("send_message", &self.send_message),
+            ("block_fetch", &self.block_fetch),
+            ("depool_update", &self.depool_update),
+            ("node_query", &self.node_query),
+        ] {
+            errors.ensure(
+                policy.multiplier.is_finite() && policy.multiplier >= 1.0,
+                &format!("{path}.{name}.multiplier"),
+                "multiplier must be a finite number not less than 1.0",
+            );
+            errors.ensure(
+                policy.max_interval >= policy.interval,
+                &format!("{path}.{name}.max_interval"),
+                "max interval must not be less than the initial interval",
+            );
+            errors.ensure(
+                policy.max_attempts != Some(0),
+                &format!("{path}.{name}.max_attempts"),
+                "max attempts must be greater than zero",
+            );
+        }
+    }
+}
+
+impl Default for AppConfigRetries {
+    fn default() -> Self {
+        Self {
+            send_message: Self::default_send_message(),
+            block_fetch: Self::default_block_fetch(),
+            depool_update: Self::default_depool_update(),
+            node_query: Self::default_node_query(),
+        }
+    }
 }
 
 #[derive(Clone, Eq, PartialEq, Serialize, Deserialize)]
@@ -127,6 +538,139 @@ impl AppConfigValidator {
     pub fn is_single(&self) -> bool {
         matches!(self, Self::Single(_))
     }
+
+    /// Returns the address of the wallet which is controlled by the validator keys
+    pub fn wallet_address(&self) -> &ton_block::MsgAddressInt {
+        match self {
+            Self::Single(single) => &single.address,
+            Self::DePool(depool) => &depool.owner,
+        }
+    }
+
+    fn validate(&self, path: &str, one_coin: u128, errors: &mut ValidationErrors) {
+        let stake_factor = match self {
+            Self::Single(single) => {
+                match single.stake_per_round {
+                    StakeAmount::Fixed(amount) => errors.ensure(
+                        amount > 0,
+                        &format!("{path}.stake_per_round"),
+                        "stake must be greater than zero",
+                    ),
+                    StakeAmount::Percent(percent) => errors.ensure(
+                        (1..=100).contains(&percent),
+                        &format!("{path}.stake_per_round"),
+                        "expected a percentage in range 1..=100",
+                    ),
+                }
+                errors.ensure(
+                    !single.compound_rewards
+                        || matches!(single.stake_per_round, StakeAmount::Fixed(_)),
+                    &format!("{path}.compound_rewards"),
+                    "rewards can only be compounded with a fixed stake \
+                    (a percentage of the balance already includes them)",
+                );
+                match (single.max_hot_balance, &single.sweep_destination) {
+                    (Some(max_hot_balance), Some(destination)) => {
+                        errors.ensure(
+                            max_hot_balance > 0,
+                            &format!("{path}.max_hot_balance"),
+                            "max hot balance must be greater than zero",
+                        );
+                        errors.ensure(
+                            destination != &single.address,
+                            &format!("{path}.sweep_destination"),
+                            "sweep destination must differ from the validator wallet",
+                        );
+                    }
+                    (None, None) => {}
+                    (Some(_), None) => errors.push(
+                        format!("{path}.sweep_destination"),
+                        "sweep destination is required with `max_hot_balance`",
+                    ),
+                    (None, Some(_)) => errors.push(
+                        format!("{path}.max_hot_balance"),
+                        "max hot balance is required with `sweep_destination`",
+                    ),
+                }
+                single.stake_factor
+            }
+            Self::DePool(depool) => {
+                errors.ensure(
+                    depool.owner.workchain_id() == ton_block::BASE_WORKCHAIN_ID,
+                    &format!("{path}.owner"),
+                    "expected basechain address",
+                );
+                errors.ensure(
+                    depool.depool.workchain_id() == ton_block::BASE_WORKCHAIN_ID,
+                    &format!("{path}.depool"),
+                    "expected basechain address",
+                );
+                errors.ensure(
+                    !depool.depool_type.is_stever() || depool.cluster.is_some(),
+                    &format!("{path}.cluster"),
+                    "cluster address is required for stEVER DePool",
+                );
+                if let Some(deploy) = &depool.deploy {
+                    errors.ensure(
+                        deploy.min_stake > 0,
+                        &format!("{path}.deploy.min_stake"),
+                        "min stake must be greater than zero",
+                    );
+                    errors.ensure(
+                        (1..=100).contains(&deploy.participant_reward_fraction),
+                        &format!("{path}.deploy.participant_reward_fraction"),
+                        "expected a value in range 1..=100",
+                    );
+                }
+                let abi = match &depool.abi_path {
+                    Some(abi_path) => match DePoolAbi::load(abi_path) {
+                        Ok(abi) => Some(abi),
+                        Err(e) => {
+                            errors.push(format!("{path}.abi_path"), format!("{e:#}"));
+                            None
+                        }
+                    },
+                    None => None,
+                };
+                if depool.reward_address.is_some() {
+                    errors.ensure(
+                        abi.as_ref()
+                            .map(DePoolAbi::supports_reward_address)
+                            .unwrap_or_default(),
+                        &format!("{path}.reward_address"),
+                        "DePool ABI doesn't support a reward address \
+                        (`addOrdinaryStake` must accept an address after the stake)",
+                    );
+                }
+                if let Some(comment) = &depool.transfer_comment {
+                    if let Err(e) = check_comment(comment) {
+                        errors.push(format!("{path}.transfer_comment"), format!("{e:#}"));
+                    }
+                }
+                if let Some(ticktock_value) = depool.ticktock_value {
+                    errors.ensure(
+                        ticktock_value as u128 >= DePool::min_ticktock_value(one_coin),
+                        &format!("{path}.ticktock_value"),
+                        "ticktock value must cover the DePool ticktock fee (at least 1 coin)",
+                    );
+                }
+                errors.ensure(
+                    depool.max_ticktocks != Some(0),
+                    &format!("{path}.max_ticktocks"),
+                    "max ticktocks must be greater than zero",
+                );
+                depool.stake_factor
+            }
+        };
+
+        if let Some(stake_factor) = stake_factor {
+            errors.ensure(
+                stake_factor >= 65536,
+                &format!("{path}.stake_factor"),
+                "stake factor must be at least 65536 (1.0)",
+            );
+        }
+    }
 }
 
 #[derive(Clone, Eq, PartialEq, Serialize, Deserialize)]
@@ -134,30 +678,149 @@ impl AppConfigValidator {
 pub struct AppConfigValidatorSingle {
     #[serde(with = "serde_mc_address")]
     pub address: ton_block::MsgAddressInt,
-    #[serde(with = "serde_string_or_number")]
-    pub stake_per_round: u64,
+    pub stake_per_round: StakeAmount,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub stake_factor: Option<u32>,
+    /// Add the reward of the latest finished round to the fixed stake
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub compound_rewards: bool,
+    /// Max wallet balance (in nano) kept between elections.
+    /// The excess is sent to `sweep_destination`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_hot_balance: Option<u64>,
+    /// Cold wallet which receives the balance above `max_hot_balance`
+    #[serde(
+        default,
+        with = "serde_optional_address",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub sweep_destination: Option<ton_block::MsgAddressInt>,
+}
+
+/// Stake per round: a fixed amount (in nano) or a percentage of the wallet balance, e.g. `"90%"`
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum StakeAmount {
+    Fixed(u64),
+    Percent(u8),
+}
+
+impl StakeAmount {
+    /// Computes the stake from the wallet balance, leaving the `reserve` for fees
+    pub fn resolve(&self, balance: u128, reserve: u128) -> u128 {
+        match *self {
+            Self::Fixed(amount) => amount as u128,
+            Self::Percent(percent) => balance.saturating_sub(reserve) * percent as u128 / 100,
+        }
+    }
+}
+
+impl std::fmt::Display for StakeAmount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Fixed(amount) => write!(f, "{}", Tokens(*amount)),
+            Self::Percent(percent) => write!(f, "{percent}% of the balance"),
+        }
+    }
+}
+
+impl Serialize for StakeAmount {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            Self::Fixed(amount) => serializer.serialize_str(&amount.to_string()),
+            Self::Percent(percent) => serializer.serialize_str(&format!("{percent}%")),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for StakeAmount {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Value {
+            Number(u64),
+            String(String),
+        }
+
+        match Value::deserialize(deserializer)? {
+            Value::Number(amount) => Ok(Self::Fixed(amount)),
+            Value::String(value) => match value.trim().strip_suffix('%') {
+                Some(percent) => percent
+                    .trim()
+                    .parse()
+                    .map(Self::Percent)
+                    .map_err(|_| Error::custom("invalid stake percentage")),
+                None => value
+                    .trim()
+                    .parse()
+                    .map(Self::Fixed)
+                    .map_err(|_| Error::custom("invalid stake amount")),
+            },
+        }
+    }
 }
 
 #[derive(Clone, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct AppConfigValidatorDePool {
-    #[serde(with = "serde_string")]
+    #[serde(with = "serde_address")]
     pub owner: ton_block::MsgAddressInt,
-    #[serde(with = "serde_string")]
+    #[serde(with = "serde_address")]
     pub depool: ton_block::MsgAddressInt,
     pub depool_type: DePoolType,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub stake_factor: Option<u32>,
     #[serde(
         default,
-        with = "serde_optional_string",
+        with = "serde_optional_address",
         skip_serializing_if = "Option::is_none"
     )]
     pub cluster: Option<ton_block::MsgAddressInt>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub deploy: Option<AppConfigDePoolDeploymentParams>,
+    /// Path to the JSON ABI of a modified DePool. The bundled ABI is used by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub abi_path: Option<PathBuf>,
+    /// Proxy balance (in nano) below which the proxy is topped up from the owner wallet
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_proxy_balance: Option<u64>,
+    /// Text comment attached to the top-ups of the DePool and its proxies
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transfer_comment: Option<String>,
+    /// Destination of the rewards and returned stakes of the owner participant.
+    /// Requires a DePool ABI which accepts it in `addOrdinaryStake`
+    #[serde(
+        default,
+        with = "serde_optional_address",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub reward_address: Option<ton_block::MsgAddressInt>,
+    /// Value (in nano) attached to each ticktock, 1 coin by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ticktock_value: Option<u64>,
+    /// Max number of ticktocks sent to update the rounds before the elections.
+    /// Overrides `retries.depool_update.max_attempts` for this DePool
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_ticktocks: Option<u32>,
+}
+
+impl AppConfigValidatorDePool {
+    /// Retry policy of the rounds update with the DePool specific limit
+    pub fn depool_update_retries(&self, retries: &RetryPolicy) -> RetryPolicy {
+        let mut retries = *retries;
+        if let Some(max_ticktocks) = self.max_ticktocks {
+            // NOTE: the rounds are checked once more after the last ticktock
+            retries.max_attempts = Some(max_ticktocks.saturating_add(1));
+        }
+        retries
+    }
 }
 
 #[derive(Clone, Eq, PartialEq, Serialize, Deserialize)]
@@ -169,6 +832,36 @@ pub struct AppConfigDePoolDeploymentParams {
     pub participant_reward_fraction: u8,
 }
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RoundParity {
+    Even,
+    Odd,
+}
+
+impl RoundParity {
+    /// Checks the parity of the round number, computed as `election_id / validators_elected_for`.
+    ///
+    /// NOTE: The round number is the same for all keepers of the network,
+    /// so keepers with different parities participate in alternating rounds.
+    pub fn matches(&self, election_id: u32, validators_elected_for: u32) -> bool {
+        let round = election_id / std::cmp::max(validators_elected_for, 1);
+        match self {
+            Self::Even => round % 2 == 0,
+            Self::Odd => round % 2 == 1,
+        }
+    }
+}
+
+impl std::fmt::Display for RoundParity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Even => "even",
+            Self::Odd => "odd",
+        })
+    }
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub enum DePoolType {
     #[serde(rename = "default_v3")]
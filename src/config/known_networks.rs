@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+
+use once_cell::race::OnceBox;
+
+use crate::util::parse_hex_or_base64;
+
+/// Network which is recognized by its zerostate file hash
+#[derive(Debug, Clone, Copy)]
+pub struct KnownNetwork {
+    pub name: &'static str,
+    /// Global id of the network (if it is known)
+    pub global_id: Option<i32>,
+    /// Currency if it differs from the default one
+    pub currency: Option<&'static str>,
+}
+
+macro_rules! decl_known_networks {
+    ($ident:ident, { $($file_hash:literal => {
+        name: $name:literal,
+        global_id: $global_id:expr,
+        currency: $currency:expr$(,)?
+    }),*$(,)? }) => {
+        pub fn $ident(zerostate_file_hash: &[u8; 32]) -> Option<KnownNetwork> {
+            static KNOWN_NETWORKS: OnceBox<HashMap<[u8; 32], KnownNetwork>> = OnceBox::new();
+            KNOWN_NETWORKS.get_or_init(|| Box::new(HashMap::from([
+                $((parse_hex_or_base64($file_hash).unwrap().try_into().unwrap(), KnownNetwork {
+                    name: $name,
+                    global_id: $global_id,
+                    currency: $currency,
+                })),*
+            ])))
+            .get(zerostate_file_hash)
+            .copied()
+        }
+    }
+}
+
+decl_known_networks! {
+    known_network, {
+        "0nC4eylStbp9qnCq8KjDYb789NjS25L5ZA1UQwcIOOQ=" => {
+            name: "Everscale mainnet",
+            global_id: Some(42),
+            currency: None,
+        },
+        "ywj7H75tJ3PgbEeX+UNP3j0iR1x9imIIJJuQgrlCr8s=" => {
+            name: "Venom mainnet",
+            global_id: None,
+            currency: Some("VENOM"),
+        },
+    }
+}
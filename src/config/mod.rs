@@ -1,12 +1,19 @@
 pub use self::app_config::{
-    AppConfig, AppConfigAdnl, AppConfigControl, AppConfigDePoolDeploymentParams,
-    AppConfigValidator, AppConfigValidatorDePool, AppConfigValidatorSingle, DePoolType,
+    AppConfig, AppConfigAdnl, AppConfigControl, AppConfigDePoolDeploymentParams, AppConfigExporter,
+    AppConfigHook, AppConfigRetries, AppConfigValidator, AppConfigValidatorDePool,
+    AppConfigValidatorSingle, DePoolType, HookEventKind, RoundParity, StakeAmount, DEFAULT_UNIT,
 };
 pub use self::global_config::GlobalConfig;
-pub use self::node_config::{NodeConfig, NodeConfigAdnl, NodeConfigControlServer, NodeLogConfig};
+pub use self::known_networks::{known_network, KnownNetwork};
+pub use self::node_config::{
+    NodeConfig, NodeConfigAdnl, NodeConfigControlServer, NodeConfigValidatorKeys, NodeLogConfig,
+};
 pub use self::stored_keys::StoredKeys;
+pub use self::validation::ValidationErrors;
 
 mod app_config;
 mod global_config;
+mod known_networks;
 mod node_config;
 mod stored_keys;
+mod validation;
@@ -8,6 +8,8 @@ use broxus_util::serde_base64_array;
 use everscale_crypto::ed25519;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
+use super::validation::ValidationErrors;
+
 pub struct NodeLogConfig(&'static str);
 
 impl NodeLogConfig {
@@ -33,6 +35,7 @@ impl NodeConfig {
     const ADNL_NODE: &str = "adnl_node";
     const GLOBAL_CONFIG_PATH: &str = "ton_global_config_name";
     const INTERNAL_DB_PATH: &str = "internal_db_path";
+    const VALIDATOR_KEYS: &str = "validator_keys";
 
     const TEMPLATE: &str = include_str!("default_config.json");
 
@@ -42,16 +45,47 @@ impl NodeConfig {
 
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
         let file = std::fs::File::open(path).context("failed to open node config")?;
-        let config = serde_json::from_reader(std::io::BufReader::new(file))
+        let config: Self = serde_json::from_reader(std::io::BufReader::new(file))
             .context("failed to deserialize node config")?;
+        config.validate().context("invalid node config")?;
         Ok(config)
     }
 
+    /// Checks all known fields, collecting all errors at once
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+
+        let mut check = |field: &str, f: fn(&Self) -> Result<()>| {
+            if let Err(e) = f(self) {
+                errors.push(field, format!("{e:#}"));
+            }
+        };
+
+        check(Self::IP_ADDRESS, |config| {
+            config
+                .get_field::<SocketAddrV4>(Self::IP_ADDRESS)
+                .map(|_| ())
+        });
+        check(Self::ADNL_NODE, |config| config.get_adnl_node().map(|_| ()));
+        check(Self::CONTROL_SERVER, |config| {
+            config.get_control_server().map(|_| ())
+        });
+        check(Self::INTERNAL_DB_PATH, |config| {
+            config.get_internal_db_path().map(|_| ())
+        });
+
+        errors.into_result()
+    }
+
     pub fn store<P: AsRef<Path>>(&self, path: P) -> Result<()> {
         let data = serde_json::to_string_pretty(self).context("failed to serialize node config")?;
         std::fs::write(path, data).context("failed to write node config")
     }
 
+    pub fn get_global_config_path(&self) -> Result<Option<PathBuf>> {
+        self.get_field(Self::GLOBAL_CONFIG_PATH)
+    }
+
     pub fn set_global_config_path<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
         self.set_field(Self::GLOBAL_CONFIG_PATH, path.as_ref())
     }
@@ -91,6 +125,11 @@ impl NodeConfig {
         self.set_field(Self::CONTROL_SERVER, node)
     }
 
+    /// Returns validator keys which were added to the node for the elections
+    pub fn get_validator_keys(&self) -> Result<Vec<NodeConfigValidatorKeys>> {
+        Ok(self.get_field(Self::VALIDATOR_KEYS)?.unwrap_or_default())
+    }
+
     fn get_field<D>(&self, field: &str) -> Result<Option<D>>
     where
         for<'de> D: Deserialize<'de>,
@@ -198,6 +237,18 @@ impl NodeConfigAdnl {
 
 pub type Keys = HashMap<usize, ed25519::SecretKey>;
 
+/// Validator keys of the node for the specific elections
+#[derive(Clone, Deserialize)]
+pub struct NodeConfigValidatorKeys {
+    pub election_id: u32,
+    /// Hash of the validator key
+    #[serde(with = "serde_base64_array")]
+    pub validator_key_id: [u8; 32],
+    /// Hash of the validator ADNL key (ADNL address)
+    #[serde(default, with = "serde_optional_base64_array")]
+    pub validator_adnl_key_id: Option<[u8; 32]>,
+}
+
 mod serde_control_clients {
     use super::*;
 
@@ -256,6 +307,21 @@ mod serde_control_clients {
     }
 }
 
+mod serde_optional_base64_array {
+    use super::*;
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<[u8; 32]>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[repr(transparent)]
+        struct Item(#[serde(with = "serde_base64_array")] [u8; 32]);
+
+        Ok(Option::<Item>::deserialize(deserializer)?.map(|Item(key_hash)| key_hash))
+    }
+}
+
 mod serde_node_public_key {
     use super::*;
 
@@ -4,7 +4,9 @@ use anyhow::{Context, Result};
 use broxus_util::{serde_hex_array, serde_optional_hex_array};
 use serde::{Deserialize, Serialize};
 
+use crate::contracts::wallet;
 use crate::crypto::*;
+use crate::util::Redacted;
 
 #[derive(Serialize)]
 pub struct StoredKeys {
@@ -17,11 +19,19 @@ pub struct StoredKeys {
     pub public: Option<[u8; 32]>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub seed: Option<String>,
+    /// Keys rotation schedule
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub schedule: Vec<ScheduledKeys>,
 }
 
 impl StoredKeys {
     pub const DEFAULT_MNEMONIC_TYPE: MnemonicType = MnemonicType::Bip39;
 
+    /// Format version of the stored keys file.
+    ///
+    /// NOTE: Files without a version were written before it was introduced
+    pub const VERSION: u32 = 1;
+
     pub fn generate() -> Result<Self> {
         Self::from_seed(generate_seed(Self::DEFAULT_MNEMONIC_TYPE))
     }
@@ -35,6 +45,7 @@ impl StoredKeys {
                 secret: keypair.secret.to_bytes(),
                 public: Some(keypair.public.to_bytes()),
                 seed: Some(seed),
+                schedule: Vec::new(),
             })
         }
 
@@ -49,6 +60,7 @@ impl StoredKeys {
                 secret: secret.to_bytes(),
                 public: Some(public.to_bytes()),
                 seed: None,
+                schedule: Vec::new(),
             })
         }
 
@@ -56,56 +68,209 @@ impl StoredKeys {
     }
 
     pub fn load_as_keypair<P: AsRef<Path>>(path: P) -> Result<ed25519_dalek::Keypair> {
-        Ok(Self::load(path)?.as_keypair())
+        Self::load(path)?.as_keypair()
     }
 
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
-        fn inner(path: &Path) -> Result<StoredKeys> {
+        let (keys, version) = Self::load_versioned(path)?;
+        if version.is_none() {
+            tracing::warn!("keys file has no format version, run `nodekeeper keys migrate`");
+        }
+        Ok(keys)
+    }
+
+    /// Loads keys with the file format version (`None` for the files without it)
+    pub fn load_versioned<P: AsRef<Path>>(path: P) -> Result<(Self, Option<u32>)> {
+        fn inner(path: &Path) -> Result<(StoredKeys, Option<u32>)> {
+            #[derive(Deserialize)]
+            pub struct VersionHelper {
+                #[serde(default)]
+                pub version: Option<u32>,
+            }
+
             #[derive(Deserialize)]
             #[serde(deny_unknown_fields)]
             pub struct StoredKeysHelper {
+                #[serde(default)]
+                pub version: Option<u32>,
                 #[serde(with = "serde_optional_hex_array")]
                 pub secret: Option<[u8; 32]>,
                 #[serde(default, with = "serde_optional_hex_array")]
                 pub public: Option<[u8; 32]>,
                 #[serde(default)]
                 pub seed: Option<String>,
+                #[serde(default)]
+                pub schedule: Vec<ScheduledKeysHelper>,
+            }
+
+            #[derive(Deserialize)]
+            #[serde(deny_unknown_fields)]
+            pub struct ScheduledKeysHelper {
+                #[serde(default, with = "serde_optional_hex_array")]
+                pub secret: Option<[u8; 32]>,
+                #[serde(default)]
+                pub seed: Option<String>,
+                pub valid_since: u32,
+                pub valid_until: u32,
             }
 
-            let file = std::fs::File::open(path).context("failed to open keys file")?;
-            let mut deserializer =
-                serde_json::Deserializer::from_reader(std::io::BufReader::new(file));
+            let content = std::fs::read_to_string(path).context("failed to open keys file")?;
+
+            // NOTE: the version is checked first, so that newer files are not misparsed
+            let VersionHelper { version } = serde_json::from_str(&content)
+                .context("failed to parse keys file format version")?;
+            if let Some(version) = version {
+                anyhow::ensure!(
+                    version <= StoredKeys::VERSION,
+                    "unsupported keys file format version {version} \
+                    (at most {} is supported), update nodekeeper",
+                    StoredKeys::VERSION
+                );
+            }
+
+            let mut deserializer = serde_json::Deserializer::from_str(&content);
             let data: StoredKeysHelper = serde_path_to_error::deserialize(&mut deserializer)
                 .context("failed to parse keys")?;
 
-            if let Some(secret) = data.secret {
-                Ok(StoredKeys {
+            let schedule = data
+                .schedule
+                .into_iter()
+                .map(|item| {
+                    let secret = match (item.secret, item.seed) {
+                        (Some(secret), _) => secret,
+                        (None, Some(seed)) => StoredKeys::from_seed(seed)?.secret,
+                        (None, None) => anyhow::bail!("invalid scheduled keys"),
+                    };
+                    anyhow::ensure!(
+                        item.valid_since < item.valid_until,
+                        "invalid scheduled keys validity window"
+                    );
+                    Ok(ScheduledKeys {
+                        secret,
+                        valid_since: item.valid_since,
+                        valid_until: item.valid_until,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            let mut keys = if let Some(secret) = data.secret {
+                StoredKeys {
                     secret,
                     public: data.public,
                     seed: data.seed,
-                })
+                    schedule: Vec::new(),
+                }
             } else if let Some(seed) = data.seed {
-                StoredKeys::from_seed(seed)
+                StoredKeys::from_seed(seed)?
             } else {
                 anyhow::bail!("invalid keys file")
-            }
+            };
+            keys.schedule = schedule;
+
+            Ok((keys, data.version))
         }
 
         inner(path.as_ref())
     }
 
     pub fn store<P: AsRef<Path>>(&self, path: P) -> Result<()> {
-        let data = serde_json::to_string_pretty(self).context("failed to serialize keys")?;
+        #[derive(Serialize)]
+        struct VersionedKeys<'a> {
+            version: u32,
+            #[serde(flatten)]
+            keys: &'a StoredKeys,
+        }
+
+        let data = serde_json::to_string_pretty(&VersionedKeys {
+            version: Self::VERSION,
+            keys: self,
+        })
+        .context("failed to serialize keys")?;
         std::fs::write(path, data).context("failed to save keys")
     }
 
-    pub fn as_secret(&self) -> ed25519_dalek::SecretKey {
-        ed25519_dalek::SecretKey::from_bytes(&self.secret).unwrap()
+    pub fn as_secret(&self) -> Result<ed25519_dalek::SecretKey> {
+        ed25519_dalek::SecretKey::from_bytes(&self.secret).context("invalid secret key")
+    }
+
+    pub fn as_keypair(&self) -> Result<ed25519_dalek::Keypair> {
+        make_keypair(&self.secret)
     }
 
-    pub fn as_keypair(&self) -> ed25519_dalek::Keypair {
-        let secret = self.as_secret();
-        let public = ed25519_dalek::PublicKey::from(&secret);
-        ed25519_dalek::Keypair { secret, public }
+    /// Returns the keys which are active at the specified time.
+    ///
+    /// NOTE: Falls back to the main keys if there is no active scheduled keys.
+    pub fn active_keys(&self, now: u32) -> Result<ActiveKeys> {
+        let scheduled = self
+            .schedule
+            .iter()
+            .filter(|item| item.is_active(now))
+            .max_by_key(|item| item.valid_since);
+
+        Ok(match scheduled {
+            Some(item) => ActiveKeys {
+                keypair: item.as_keypair()?,
+                valid_until: Some(item.valid_until),
+            },
+            None => ActiveKeys {
+                keypair: self.as_keypair()?,
+                valid_until: None,
+            },
+        })
+    }
+
+    /// Returns the keys which are active at the specified time and
+    /// ensures that they sign for the specified wallet.
+    ///
+    /// NOTE: Scheduled keys which are not active yet are not checked,
+    /// they may control another wallet (or DePool owner) which must be
+    /// configured by the time they become active.
+    pub fn active_keys_for_wallet(
+        &self,
+        now: u32,
+        wallet: &ton_block::MsgAddressInt,
+    ) -> Result<ActiveKeys> {
+        let active = self.active_keys(now)?;
+        let workchain = wallet.workchain_id() as i8;
+        let address = wallet::compute_wallet_address(workchain, &active.keypair.public);
+        anyhow::ensure!(
+            &address == wallet,
+            "active keys {} derive wallet {} instead of the configured {}",
+            hex::encode(active.keypair.public.as_bytes()),
+            Redacted(&address),
+            Redacted(wallet),
+        );
+        Ok(active)
     }
 }
+
+/// Keys which are used only during the specified time window
+#[derive(Serialize)]
+pub struct ScheduledKeys {
+    #[serde(with = "serde_hex_array")]
+    pub secret: [u8; 32],
+    pub valid_since: u32,
+    pub valid_until: u32,
+}
+
+impl ScheduledKeys {
+    pub fn is_active(&self, now: u32) -> bool {
+        (self.valid_since..self.valid_until).contains(&now)
+    }
+
+    pub fn as_keypair(&self) -> Result<ed25519_dalek::Keypair> {
+        make_keypair(&self.secret)
+    }
+}
+
+pub struct ActiveKeys {
+    pub keypair: ed25519_dalek::Keypair,
+    /// `None` for the main keys
+    pub valid_until: Option<u32>,
+}
+
+fn make_keypair(secret: &[u8; 32]) -> Result<ed25519_dalek::Keypair> {
+    let secret = ed25519_dalek::SecretKey::from_bytes(secret).context("invalid secret key")?;
+    let public = ed25519_dalek::PublicKey::from(&secret);
+    Ok(ed25519_dalek::Keypair { secret, public })
+}
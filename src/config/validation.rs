@@ -0,0 +1,74 @@
+/// Collects all config errors instead of failing on the first one
+#[derive(Debug, Default)]
+pub struct ValidationErrors {
+    errors: Vec<(String, String)>,
+}
+
+impl ValidationErrors {
+    pub fn push(&mut self, path: impl Into<String>, error: impl std::fmt::Display) {
+        self.errors.push((path.into(), error.to_string()));
+    }
+
+    pub fn ensure(&mut self, condition: bool, path: &str, error: &str) {
+        if !condition {
+            self.push(path, error);
+        }
+    }
+
+    /// Deserializes the value, remembering the full path to the failed field.
+    ///
+    /// NOTE: An empty `path` means the root of the config
+    pub fn deserialize<T>(&mut self, path: &str, value: toml::Value) -> Option<T>
+    where
+        for<'de> T: serde::Deserialize<'de>,
+    {
+        match serde_path_to_error::deserialize(value) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                let inner_path = e.path().to_string();
+                let path = match (path.is_empty(), inner_path == ".") {
+                    (true, true) => ROOT.to_owned(),
+                    (false, true) => path.to_owned(),
+                    (true, false) => inner_path,
+                    (false, false) => format!("{path}.{inner_path}"),
+                };
+                self.push(path, e.into_inner());
+                None
+            }
+        }
+    }
+
+    /// Iterates over `(path, error)` pairs
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.errors
+            .iter()
+            .map(|(path, error)| (path.as_str(), error.as_str()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn into_result(self) -> Result<(), Self> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl std::fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("found {} config error(s):", self.errors.len()))?;
+        for (path, error) in &self.errors {
+            f.write_fmt(format_args!("\n  {path}: {error}"))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+/// Path of the errors which don't belong to any field
+const ROOT: &str = "config";
@@ -1,4 +1,5 @@
 use std::collections::BTreeMap;
+use std::path::Path;
 use std::sync::Arc;
 
 use anyhow::{Context, Result};
@@ -6,15 +7,16 @@ use nekoton_abi::{
     BuildTokenValue, FunctionBuilder, FunctionExt, KnownParamType, KnownParamTypePlain,
     PackAbiPlain, TokenValueExt, UnpackAbi, UnpackAbiPlain, UnpackFirst,
 };
-use nekoton_utils::SimpleClock;
+use nekoton_utils::ConstClock;
 use num::ToPrimitive;
 use ton_abi::contract::ABI_VERSION_2_2;
 use ton_block::{Deserializable, Serializable};
 
-use super::{InternalMessage, ONE_EVER};
-use crate::config::DePoolType;
-use crate::network::Subscription;
-use crate::util::make_default_headers;
+use super::InternalMessage;
+use crate::config::{AppConfigValidatorDePool, DePoolType};
+use crate::crypto::{encode_signed_input, Signer};
+use crate::network::{AccountStatus, Subscription};
+use crate::util::{make_default_headers, parse_contract_abi};
 
 #[derive(Debug, Clone)]
 pub struct DePoolInitParams {
@@ -26,41 +28,93 @@ pub struct DePoolInitParams {
 
 pub struct DePool {
     ty: DePoolType,
-    keypair: Option<ed25519_dalek::Keypair>,
+    abi: Option<DePoolAbi>,
+    signer: Option<Arc<dyn Signer>>,
     address: ton_block::MsgAddressInt,
     subscription: Arc<Subscription>,
+    min_proxy_balance: u128,
+    reward_address: Option<ton_block::MsgAddressInt>,
+    ticktock_value: u128,
+    /// Amount of the smallest units in one coin
+    one_coin: u128,
 }
 
 impl DePool {
-    pub const INITIAL_BALANCE: u128 = 30 * ONE_EVER;
-    pub const CRITICAL_BALANCE: u128 = 20 * ONE_EVER;
-    pub const INITIAL_PROXY_BALANCE: u128 = 3 * ONE_EVER;
-    pub const MIN_PROXY_BALANCE: u128 = 2 * ONE_EVER;
-
     pub fn new(
         ty: DePoolType,
         address: ton_block::MsgAddressInt,
         subscription: Arc<Subscription>,
+        one_coin: u128,
     ) -> Self {
         Self {
             ty,
-            keypair: None,
+            abi: None,
+            signer: None,
             address,
             subscription,
+            min_proxy_balance: 2 * one_coin,
+            reward_address: None,
+            ticktock_value: Self::min_ticktock_value(one_coin),
+            one_coin,
         }
     }
 
+    /// Balance required to deploy the DePool
+    pub fn initial_balance(one_coin: u128) -> u128 {
+        30 * one_coin
+    }
+
+    /// Min value attached to the ticktock which covers its fee
+    pub fn min_ticktock_value(one_coin: u128) -> u128 {
+        one_coin
+    }
+
+    /// Creates a DePool with the ABI from the config (or the bundled one)
+    pub fn from_config(
+        config: &AppConfigValidatorDePool,
+        subscription: Arc<Subscription>,
+        one_coin: u128,
+    ) -> Result<Self> {
+        let mut depool = Self::new(
+            config.depool_type,
+            config.depool.clone(),
+            subscription,
+            one_coin,
+        );
+        if let Some(path) = &config.abi_path {
+            depool.abi = Some(DePoolAbi::load(path)?);
+        }
+        if let Some(min_proxy_balance) = config.min_proxy_balance {
+            depool.min_proxy_balance = min_proxy_balance as u128;
+        }
+        if let Some(ticktock_value) = config.ticktock_value {
+            anyhow::ensure!(
+                ticktock_value as u128 >= Self::min_ticktock_value(one_coin),
+                "ticktock value doesn't cover the DePool ticktock fee"
+            );
+            depool.ticktock_value = ticktock_value as u128;
+        }
+        if let Some(reward_address) = &config.reward_address {
+            anyhow::ensure!(
+                matches!(&depool.abi, Some(abi) if abi.supports_reward_address()),
+                "DePool ABI doesn't support a reward address"
+            );
+            depool.reward_address = Some(reward_address.clone());
+        }
+        Ok(depool)
+    }
+
     pub fn address(&self) -> &ton_block::MsgAddressInt {
         &self.address
     }
 
-    pub fn set_keypair(&mut self, keypair: ed25519_dalek::Keypair) -> Result<()> {
-        let computed_address = self.ty.compute_depool_address(&keypair.public)?;
+    pub fn set_signer(&mut self, signer: Arc<dyn Signer>) -> Result<()> {
+        let computed_address = self.ty.compute_depool_address(&signer.public_key())?;
         anyhow::ensure!(
             computed_address == self.address,
             "wrong DePool address or keys"
         );
-        self.keypair = Some(keypair);
+        self.signer = Some(signer);
         Ok(())
     }
 
@@ -86,7 +140,7 @@ impl DePool {
     }
 
     pub async fn deploy(&self, params: DePoolInitParams) -> Result<()> {
-        let keypair = self.keypair.as_ref().context("DePool keypair not set")?;
+        let signer = self.signer.as_deref().context("DePool signer not set")?;
 
         let inputs = ConstructorInputs {
             min_stake: params.min_stake,
@@ -96,51 +150,61 @@ impl DePool {
             participant_reward_fraction: params.participant_reward_fraction,
         }
         .pack();
+        let inputs = &inputs;
 
-        self.subscription
-            .send_message_with_retires(move |timeout, signature_id| {
+        let tx = self
+            .subscription
+            .send_message_with_retires(move |timeout, signature_id| async move {
                 let (expire_at, header) = make_default_headers(None, timeout);
 
                 let mut message = self.external_message_to_self(
-                    common::constructor()
-                        .encode_input(
-                            &header,
-                            &inputs,
-                            false,
-                            Some((keypair, signature_id)),
-                            Some(self.address.clone()),
-                        )
-                        .context("failed to encode constructor")?,
+                    encode_signed_input(
+                        common::constructor(),
+                        &header,
+                        inputs,
+                        signer,
+                        signature_id,
+                        self.address.clone(),
+                    )
+                    .await
+                    .context("failed to encode constructor")?,
                 );
 
-                message.set_state_init(self.ty.compute_depool_state_init(&keypair.public)?);
+                message.set_state_init(self.ty.compute_depool_state_init(&signer.public_key())?);
 
-                Ok((message, expire_at))
+                Ok::<_, anyhow::Error>((message, expire_at))
             })
             .await
             .context("failed to send constructor message")?;
 
+        tx.ensure_not_aborted()
+            .context("DePool constructor failed")?;
         Ok(())
     }
 
-    pub fn ticktock(&self) -> Result<InternalMessage> {
-        Ok(self.internal_message_to_self(ONE_EVER, common::ticktock().encode_internal_input(&[])?))
+    /// Value attached to each ticktock
+    pub fn ticktock_value(&self) -> u128 {
+        self.ticktock_value
     }
 
-    pub async fn maintain_balances(&self) -> Result<Vec<InternalMessage>> {
-        let account = self
-            .subscription
-            .get_account_state(&self.address)
-            .await?
-            .context("DePool not deployed")?;
+    pub fn ticktock(&self) -> Result<InternalMessage> {
+        let function = self.function(common::ticktock());
+        let payload = function.encode_internal_input(&[])?;
+        Ok(self.internal_message_to_self(self.ticktock_value, payload))
+    }
 
+    /// Computes refill messages for the DePool and its proxies.
+    ///
+    /// NOTE: DePool getters are executed on the provided state snapshot,
+    /// only proxy states are fetched (concurrently).
+    pub async fn maintain_balances(&self, state: &DePoolState) -> Result<Vec<InternalMessage>> {
         let mut messages = Vec::new();
 
-        let depool_info = self.get_info(&account)?;
+        let depool_info = self.get_info(state)?;
 
         // Check depool balance
         let depool_balance = {
-            let tokens = self.run_local(&account, common::get_depool_balance(), &[])?;
+            let tokens = self.run_local(state, common::get_depool_balance(), &[])?;
             match tokens.into_iter().next() {
                 Some(ton_abi::Token {
                     value: ton_abi::TokenValue::Int(ton_abi::Int { number, .. }),
@@ -150,9 +214,10 @@ impl DePool {
             }
         };
 
-        let critical_balance = num::BigInt::from(Self::CRITICAL_BALANCE);
+        let critical_balance = num::BigInt::from(20 * self.one_coin);
         if depool_balance <= critical_balance {
-            let remaining = num::BigInt::from(Self::INITIAL_BALANCE) - depool_balance;
+            let remaining =
+                num::BigInt::from(Self::initial_balance(self.one_coin)) - depool_balance;
             if let Some(remaining) = remaining.to_u128() {
                 messages.push(self.internal_message_to_self(
                     remaining,
@@ -161,15 +226,20 @@ impl DePool {
             }
         }
 
-        // Check proxies
-        for proxy in depool_info.proxies {
-            let account = self
-                .subscription
-                .get_account_state(&proxy)
-                .await
-                .context("failed to get proxy state")?
-                .context("proxy not deployed")?;
+        // Prefetch proxy states
+        let proxy_states = futures_util::future::try_join_all(depool_info.proxies.iter().map(
+            |proxy| async move {
+                self.subscription
+                    .get_account_state(proxy)
+                    .await
+                    .context("failed to get proxy state")?
+                    .context("proxy not deployed")
+            },
+        ))
+        .await?;
 
+        // Check proxies
+        for (proxy, account) in depool_info.proxies.into_iter().zip(proxy_states) {
             let proxy_balance = match account.storage.state {
                 ton_block::AccountState::AccountActive { .. } => account.storage.balance.grams.0,
                 ton_block::AccountState::AccountFrozen { .. } => {
@@ -180,9 +250,12 @@ impl DePool {
                 }
             };
 
-            if proxy_balance <= Self::MIN_PROXY_BALANCE {
+            if proxy_balance <= self.min_proxy_balance {
+                // NOTE: the proxy is refilled above the reserve to not top it up on each round
+                let target =
+                    std::cmp::max(3 * self.one_coin, self.min_proxy_balance + self.one_coin);
                 messages.push(InternalMessage {
-                    amount: Self::INITIAL_PROXY_BALANCE - proxy_balance,
+                    amount: target - proxy_balance,
                     dst: proxy,
                     payload: Default::default(),
                 });
@@ -192,11 +265,73 @@ impl DePool {
         Ok(messages)
     }
 
+    /// Builds the election message route for the round from the DePool state.
+    ///
+    /// NOTE: Only the standard layout is supported: the DePool sends the stake
+    /// of the round through one of its two proxies selected by the round id parity.
+    /// The proxy is checked to be an active contract with the known proxy code.
+    pub async fn election_route(&self, info: &DePoolInfo, round: &Round) -> Result<ElectionRoute> {
+        anyhow::ensure!(
+            info.proxies.len() == 2,
+            "invalid DePool proxies (expected 2, found {})",
+            info.proxies.len()
+        );
+        anyhow::ensure!(
+            round.step == RoundStep::WaitingValidatorRequest,
+            "round {} is not waiting for the validator request",
+            round.id
+        );
+
+        let proxy = &info.proxies[(round.id % 2) as usize];
+        anyhow::ensure!(
+            proxy.is_masterchain(),
+            "DePool proxy {proxy} is not in masterchain"
+        );
+
+        let (depool_state, proxy_state) = futures_util::future::try_join(
+            self.subscription.get_account_state(&self.address),
+            self.subscription.get_account_state(proxy),
+        )
+        .await
+        .context("failed to get election route states")?;
+
+        let depool_status = AccountStatus::from_state(depool_state.as_ref());
+        anyhow::ensure!(
+            depool_status == AccountStatus::Active,
+            "DePool {} is {depool_status}",
+            self.address
+        );
+
+        let proxy_status = AccountStatus::from_state(proxy_state.as_ref());
+        let proxy_code = match proxy_state.map(|state| state.storage.state) {
+            Some(ton_block::AccountState::AccountActive { state_init }) => state_init.code,
+            _ => anyhow::bail!("DePool proxy {proxy} is {proxy_status}"),
+        };
+        let known_code_hash = self.ty.proxy_code().repr_hash();
+        anyhow::ensure!(
+            matches!(&proxy_code, Some(code) if code.repr_hash() == known_code_hash),
+            "DePool proxy {proxy} has unknown code"
+        );
+
+        Ok(ElectionRoute {
+            depool: self.address.clone(),
+            proxy: proxy.clone(),
+        })
+    }
+
     pub fn add_ordinary_stake(&self, amount: u64) -> Result<InternalMessage> {
+        let function = self.function(common::add_ordinary_stake());
+
+        let mut inputs = vec![amount.token_value().named("stake")];
+        if let Some(reward_address) = &self.reward_address {
+            // NOTE: ABI support is checked when the reward address is set
+            let param = &function.inputs[1];
+            inputs.push(reward_address.clone().token_value().named(&param.name));
+        }
+
         Ok(self.internal_message_to_self(
-            (amount as u128) + ONE_EVER / 2,
-            &common::add_ordinary_stake()
-                .encode_internal_input(&[amount.token_value().named("stake")])?,
+            (amount as u128) + self.one_coin / 2,
+            &function.encode_internal_input(&inputs)?,
         ))
     }
 
@@ -206,7 +341,7 @@ impl DePool {
     ) -> Result<InternalMessage> {
         self.ensure_stever()?;
         Ok(self.internal_message_to_self(
-            ONE_EVER,
+            self.one_coin,
             stever::set_allowed_participant()
                 .encode_internal_input(&[address.clone().token_value().named("addr")])?,
         ))
@@ -214,18 +349,18 @@ impl DePool {
 
     pub fn get_participant_info(
         &self,
-        state: &ton_block::AccountStuff,
+        state: &DePoolState,
         addr: &ton_block::MsgAddressInt,
     ) -> Result<Option<ParticipantInfo>> {
         const ERR_NOT_PARTICIPANT: i32 = 116;
 
-        let result = match self.ty {
+        let function = self.function(match self.ty {
             DePoolType::DefaultV3 => common::get_participant_info(),
             DePoolType::StEverV1 | DePoolType::StEverV2 => stever::get_participant_info(),
-        }
-        .run_local(
-            &SimpleClock,
-            state.clone(),
+        });
+        let result = function.run_local(
+            &state.clock(),
+            state.account.clone(),
             &[addr.clone().token_value().named("addr")],
         )?;
 
@@ -236,23 +371,27 @@ impl DePool {
         }
     }
 
-    pub fn get_info(&self, state: &ton_block::AccountStuff) -> Result<DePoolInfo> {
+    pub fn get_info(&self, state: &DePoolState) -> Result<DePoolInfo> {
         let info = self
-            .run_local(state, common::get_depool_info(), &[])?
+            .run_local(state, self.function(common::get_depool_info()), &[])?
             .unpack()?;
         Ok(info)
     }
 
-    pub fn get_rounds(&self, state: &ton_block::AccountStuff) -> Result<RoundsMap> {
+    /// Returns DePool rounds as if the current time is `now` (in seconds)
+    pub async fn get_rounds(&self, now: u32) -> Result<RoundsMap> {
+        let function = self.function(common::get_rounds());
         let rounds = self
-            .run_local(state, common::get_rounds(), &[])?
+            .subscription
+            .run_local_at(&self.address, function, &[], now)
+            .await?
             .unpack_first()?;
         Ok(rounds)
     }
 
     pub fn get_allowed_participants(
         &self,
-        state: &ton_block::AccountStuff,
+        state: &DePoolState,
     ) -> Result<Vec<ton_block::MsgAddressInt>> {
         self.ensure_stever()?;
         let addresses: stever::ParticipantsMap = self
@@ -261,23 +400,36 @@ impl DePool {
         Ok(addresses.into_keys().collect())
     }
 
+    /// Returns the function from the custom ABI or the bundled one
+    fn function<'a>(&'a self, bundled: &'a ton_abi::Function) -> &'a ton_abi::Function {
+        self.abi
+            .as_ref()
+            .and_then(|abi| abi.0.function(&bundled.name).ok())
+            .unwrap_or(bundled)
+    }
+
     fn run_local(
         &self,
-        state: &ton_block::AccountStuff,
+        state: &DePoolState,
         function: &ton_abi::Function,
         inputs: &[ton_abi::Token],
     ) -> Result<Vec<ton_abi::Token>> {
         function
-            .run_local(&SimpleClock, state.clone(), inputs)?
+            .run_local(&state.clock(), state.account.clone(), inputs)?
             .tokens
             .context("no outputs")
     }
 
-    pub async fn get_state(&self) -> Result<ton_block::AccountStuff> {
-        self.subscription
+    pub async fn get_state(&self) -> Result<DePoolState> {
+        let account = self
+            .subscription
             .get_account_state(&self.address)
             .await?
-            .context("DePool not deployed")
+            .context("DePool not deployed")?;
+        Ok(DePoolState {
+            account,
+            now: broxus_util::now(),
+        })
     }
 
     fn ensure_stever(&self) -> Result<()> {
@@ -310,6 +462,73 @@ impl DePool {
     }
 }
 
+/// DePool account state snapshot with the time at which the getters are executed
+#[derive(Clone)]
+pub struct DePoolState {
+    pub account: ton_block::AccountStuff,
+    /// Unix timestamp (in seconds) used as the current time by the getters
+    pub now: u32,
+}
+
+impl DePoolState {
+    fn clock(&self) -> ConstClock {
+        ConstClock::from_secs(self.now as u64)
+    }
+}
+
+/// DePool ABI loaded from a JSON file, used instead of the bundled one
+#[derive(Clone)]
+pub struct DePoolAbi(Arc<ton_abi::Contract>);
+
+impl DePoolAbi {
+    /// Functions which are called by the validator
+    const REQUIRED_FUNCTIONS: [&'static str; 5] = [
+        "getDePoolInfo",
+        "getRounds",
+        "getParticipantInfo",
+        "addOrdinaryStake",
+        "ticktock",
+    ];
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let abi = parse_contract_abi(path)
+            .with_context(|| format!("failed to load DePool ABI from {}", path.display()))?;
+
+        let missing = Self::REQUIRED_FUNCTIONS
+            .into_iter()
+            .filter(|name| abi.function(name).is_err())
+            .collect::<Vec<_>>();
+        anyhow::ensure!(
+            missing.is_empty(),
+            "DePool ABI {} has no required functions: {}",
+            path.display(),
+            missing.join(", ")
+        );
+
+        Ok(Self(Arc::new(abi)))
+    }
+
+    /// Whether `addOrdinaryStake` accepts the reward address after the stake
+    pub fn supports_reward_address(&self) -> bool {
+        match self.0.function("addOrdinaryStake") {
+            Ok(function) => matches!(
+                function.inputs.as_slice(),
+                [_, reward] if reward.kind == ton_abi::ParamType::Address
+            ),
+            Err(_) => false,
+        }
+    }
+}
+
+/// Contracts which forward the election message from the validator wallet to the elector
+#[derive(Debug, Clone)]
+pub struct ElectionRoute {
+    /// Contract which receives the election message from the validator wallet
+    pub depool: ton_block::MsgAddressInt,
+    /// Contract which will be registered in the elector as a participant
+    pub proxy: ton_block::MsgAddressInt,
+}
+
 impl DePoolType {
     pub fn compute_depool_address(
         &self,
@@ -1,4 +1,5 @@
 use std::collections::BTreeMap;
+use std::sync::atomic::AtomicU64;
 use std::sync::Arc;
 
 use anyhow::{Context, Result};
@@ -7,11 +8,14 @@ use nekoton_abi::{
     BuildTokenValue, FunctionBuilder, KnownParamType, KnownParamTypePlain, MaybeRef, PackAbiPlain,
     TokenValueExt, UnpackAbi, UnpackAbiPlain,
 };
+use ton_block::{Deserializable, Serializable};
+use ton_executor::TransactionExecutor;
 
-use super::{InternalMessage, ONE_EVER};
+use super::InternalMessage;
 use crate::network::Subscription;
 use crate::util::split_address;
 
+#[derive(Clone)]
 pub struct Elector {
     address: ton_block::MsgAddressInt,
     subscription: Arc<Subscription>,
@@ -19,26 +23,28 @@ pub struct Elector {
 
 impl Elector {
     pub fn new(address: ton_types::UInt256, subscription: Arc<Subscription>) -> Self {
-        let address = ton_block::MsgAddressInt::AddrStd(ton_block::MsgAddrStd {
-            anycast: None,
-            workchain_id: -1,
-            address: address.into(),
-        });
-
         Self {
-            address,
+            address: Self::make_address(address),
             subscription,
         }
     }
 
+    pub fn make_address(address: ton_types::UInt256) -> ton_block::MsgAddressInt {
+        ton_block::MsgAddressInt::AddrStd(ton_block::MsgAddrStd {
+            anycast: None,
+            workchain_id: -1,
+            address: address.into(),
+        })
+    }
+
     pub fn address(&self) -> &ton_block::MsgAddressInt {
         &self.address
     }
 
-    pub fn recover_stake(&self) -> Result<InternalMessage> {
+    pub fn recover_stake(&self, one_coin: u128) -> Result<InternalMessage> {
         let now = now() as u64;
         Ok(InternalMessage {
-            amount: ONE_EVER,
+            amount: one_coin,
             dst: self.address.clone(),
             payload: methods::recover_stake()
                 .encode_internal_input(&[now.token_value().named("query_id")])
@@ -62,8 +68,6 @@ impl Elector {
             "participant address not in masterchain"
         );
 
-        let (_, address) = split_address(address)?;
-
         let rpc = self.subscription.tcp_rpc();
 
         // Generate new key
@@ -72,12 +76,6 @@ impl Elector {
             .await
             .context("failed to generate validator keys")?;
 
-        // Export its public key
-        let perm_pubkey = rpc
-            .export_public_key(&permanent_key_hash)
-            .await
-            .context("failed to export validator public key")?;
-
         // Add this key as a validator key
         let ttl = election_id
             + timings.validators_elected_for
@@ -101,47 +99,191 @@ impl Elector {
             .await
             .context("failed to add validator adnl address")?;
 
+        self.participant_payload(
+            election_id,
+            address,
+            stake_factor,
+            &permanent_key_hash,
+            &adnl_addr,
+            signature_id,
+        )
+        .await
+    }
+
+    /// Generates elector payload signed by the existing node validator key.
+    pub async fn participant_payload(
+        &self,
+        election_id: u32,
+        address: &ton_block::MsgAddressInt,
+        stake_factor: u32,
+        permanent_key_hash: &[u8; 32],
+        adnl_addr: &[u8; 32],
+        signature_id: Option<i32>,
+    ) -> Result<ton_types::Cell> {
+        anyhow::ensure!(
+            address.is_masterchain(),
+            "participant address not in masterchain"
+        );
+
+        let (_, address) = split_address(address)?;
+
+        let rpc = self.subscription.tcp_rpc();
+
+        // Export its public key
+        let perm_pubkey = rpc
+            .export_public_key(permanent_key_hash)
+            .await
+            .context("failed to export validator public key")?;
+
         // Sign data
         let unsigned = UnsignedParticipantData {
             election_id,
             address,
             max_factor: stake_factor,
             public_key: ton_types::UInt256::from(perm_pubkey.to_bytes()),
-            adnl_addr: ton_types::UInt256::from(adnl_addr),
+            adnl_addr: ton_types::UInt256::from(*adnl_addr),
         };
 
         let data_to_sign = unsigned.build_data_to_sign();
         let data_to_sign = ton_abi::extend_signature_with_id(&data_to_sign, signature_id);
 
         let signature = rpc
-            .sign(&permanent_key_hash, &data_to_sign)
+            .sign(permanent_key_hash, &data_to_sign)
             .await
             .context("failed to sign election data")?;
 
+        // Make sure that the node will be able to validate with these keys.
+        // NOTE: validity periods were already confirmed by the node when adding keys
+        for (key_hash, name) in [(permanent_key_hash, "validator"), (adnl_addr, "ADNL")] {
+            let has_key = rpc
+                .has_key(key_hash)
+                .await
+                .with_context(|| format!("failed to check the {name} key"))?;
+            anyhow::ensure!(
+                has_key,
+                "node doesn't have the {name} key {}, the stake is not sent",
+                hex::encode(key_hash)
+            );
+        }
+
         // Generate internal message payload
         unsigned
             .sign(signature)
             .context("failed to insert signature")
     }
 
+    /// Executes the stake message on the current elector state locally, nothing is sent.
+    pub async fn simulate_stake(
+        &self,
+        src: &ton_block::MsgAddressInt,
+        message: InternalMessage,
+        config: ton_block::ConfigParams,
+    ) -> Result<StakeSimulation> {
+        let account = self.get_state().await?;
+        let lt = account.storage.last_trans_lt + 1;
+        let mut account_root = ton_block::Account::Account(account)
+            .serialize()
+            .context("failed to serialize elector state")?;
+
+        let mut header = ton_block::InternalMessageHeader::with_addresses_and_bounce(
+            src.clone(),
+            message.dst,
+            ton_block::CurrencyCollection::with_grams(message.amount as u64),
+            true,
+        );
+        header.created_at = now().into();
+        header.created_lt = lt;
+
+        let mut in_msg = ton_block::Message::with_int_header(header);
+        in_msg.set_body(message.payload.into());
+
+        let config = ton_executor::BlockchainConfig::with_config(config)
+            .context("invalid blockchain config")?;
+        let executor = ton_executor::OrdinaryTransactionExecutor::new(config);
+        let params = ton_executor::ExecuteParams {
+            block_unixtime: now(),
+            block_lt: lt,
+            last_tr_lt: Arc::new(AtomicU64::new(lt)),
+            ..Default::default()
+        };
+        let tx = executor
+            .execute_with_libs_and_params(Some(&in_msg), &mut account_root, params)
+            .context("failed to execute the stake message")?;
+
+        let (exit_code, aborted) = match tx.read_description()? {
+            ton_block::TransactionDescr::Ordinary(descr) => {
+                let exit_code = match descr.compute_ph {
+                    ton_block::TrComputePhase::Vm(vm) => Some(vm.exit_code),
+                    ton_block::TrComputePhase::Skipped(_) => None,
+                };
+                (exit_code, descr.aborted)
+            }
+            _ => anyhow::bail!("unexpected transaction type"),
+        };
+
+        Ok(StakeSimulation {
+            exit_code,
+            aborted,
+            response: Self::parse_stake_response(&tx),
+        })
+    }
+
+    /// Fetches a new snapshot of the elector state.
+    ///
+    /// NOTE: Each call is a separate request to the node, so the snapshot
+    /// should be passed around instead of fetching it again.
     pub async fn get_data(&self) -> Result<ElectorData> {
         let state = self.get_state().await?;
+        ElectorData::from_state(state)
+    }
 
-        let ton_block::AccountState::AccountActive { state_init } = state.storage.state else {
-            anyhow::bail!("elector account is not active");
-        };
+    /// Fetches the results of the finished elections for the participant, the most recent first.
+    ///
+    /// NOTE: The elector keeps only the elections which stakes are not unfrozen yet.
+    pub async fn past_elections_summary(
+        &self,
+        address: &ton_block::MsgAddressInt,
+        limit: usize,
+    ) -> Result<Vec<PastElectionSummary>> {
+        let data = self.get_data().await?;
+        Ok(data.past_elections_summary(address, limit))
+    }
 
-        let data = state_init.data.context("elector data is empty")?;
-        let inner: data::PartialElectorData = ton_abi::TokenValue::decode_params(
-            data::layout(),
-            data.into(),
-            &ton_abi::contract::ABI_VERSION_2_1,
-            true,
-        )
-        .context("failed to parse elector data")?
-        .unpack()?;
+    /// Fetches all members of the current elections, sorted by stake
+    pub async fn current_election_participants(
+        &self,
+        stakes: &ton_block::ConfigParam17,
+    ) -> Result<Vec<ElectionParticipant>> {
+        let data = self.get_data().await?;
+        data.current_election_participants(stakes)
+            .context("no current elections in the elector state")
+    }
 
-        Ok(ElectorData { inner })
+    /// Finds the elector response to the new stake in its transaction
+    pub fn parse_stake_response(tx: &ton_block::Transaction) -> Option<StakeResponse> {
+        const STAKE_CONFIRMATION: u32 = 0xf374484c;
+        const RETURN_STAKE: u32 = 0xee6f454c;
+
+        let mut response = None;
+        tx.out_msgs
+            .iterate_slices(|msg| {
+                let Some(msg) = msg.reference_opt(0) else { return Ok(true) };
+                let msg = ton_block::Message::construct_from_cell(msg)?;
+                let Some(mut body) = msg.body() else { return Ok(true) };
+
+                response = match body.get_next_u32()? {
+                    STAKE_CONFIRMATION => Some(StakeResponse::Accepted),
+                    RETURN_STAKE => {
+                        let _query_id = body.get_next_u64()?;
+                        let reason = body.get_next_u32()?;
+                        Some(StakeResponse::Returned(StakeReturnReason(reason)))
+                    }
+                    _ => return Ok(true),
+                };
+                Ok(false)
+            })
+            .ok()?;
+        response
     }
 
     async fn get_state(&self) -> Result<ton_block::AccountStuff> {
@@ -149,15 +291,53 @@ impl Elector {
             .get_account_state(&self.address)
             .await
             .context("failed to get elector state")?
-            .context("elector not found")
+            .ok_or_else(|| ElectorStateError::NotDeployed(self.address.clone()).into())
     }
 }
 
+/// Elector account which can't be used at all (as opposed to the elector without elections)
+#[derive(thiserror::Error, Debug)]
+pub enum ElectorStateError {
+    #[error(
+        "elector is not deployed at {0}, check the elector address \
+        (config param 1) and that the node is in the expected network"
+    )]
+    NotDeployed(ton_block::MsgAddressInt),
+    #[error(
+        "elector account {0} is not active, check the elector address \
+        (config param 1) and that the node is in the expected network"
+    )]
+    NotActive(ton_block::MsgAddressInt),
+}
+
+/// Parsed elector state at the moment of fetching.
+///
+/// It is never updated on its own, so all its checks (elected, unfrozen stake, etc.)
+/// reflect the state at the time of [`Elector::get_data`]. Operations that take a long time
+/// (e.g. DePool rounds update) must refresh it before acting on it.
 pub struct ElectorData {
     inner: data::PartialElectorData,
 }
 
 impl ElectorData {
+    pub fn from_state(state: ton_block::AccountStuff) -> Result<Self> {
+        let ton_block::AccountState::AccountActive { state_init } = state.storage.state else {
+            anyhow::bail!(ElectorStateError::NotActive(state.addr));
+        };
+
+        let data = state_init.data.context("elector data is empty")?;
+        let inner: data::PartialElectorData = ton_abi::TokenValue::decode_params(
+            data::layout(),
+            data.into(),
+            &ton_abi::contract::ABI_VERSION_2_1,
+            true,
+        )
+        .context("failed to parse elector data")?
+        .unpack()?;
+
+        Ok(Self { inner })
+    }
+
     pub fn election_id(&self) -> Option<u32> {
         let election_id = self.inner.current_election.0.as_ref()?.elect_at;
         Some(election_id)
@@ -171,6 +351,18 @@ impl ElectorData {
             .find(|&unfreeze_at| unfreeze_at < election_id)
     }
 
+    /// Returns the nearest future time when the frozen stakes of the finished elections
+    /// are unfrozen (the round end plus `stake_held_for` of that round)
+    pub fn next_unfreeze_at(&self) -> Option<u32> {
+        let now = now();
+        self.inner
+            .past_elections
+            .values()
+            .map(|election| election.unfreeze_at)
+            .filter(|&unfreeze_at| unfreeze_at > now)
+            .min()
+    }
+
     pub fn has_unfrozen_stake(
         &self,
         address: &ton_block::MsgAddressInt,
@@ -184,6 +376,278 @@ impl ElectorData {
         self.inner.credits.get(&address).copied()
     }
 
+    /// Models the elector stakes distribution for the current participants
+    /// with an additional stake.
+    ///
+    /// NOTE: It mirrors `try_elect` from the elector contract, so the result
+    /// is only an estimate which can change with the new participants.
+    pub fn compute_effective_stake(
+        &self,
+        stake: u128,
+        stake_factor: u32,
+        validators: &ton_block::ConfigParam16,
+        stakes: &ton_block::ConfigParam17,
+    ) -> Option<EffectiveStake> {
+        let current_election = self.inner.current_election.0.as_ref()?;
+
+        let min_stake = stakes.min_stake.0;
+        let max_stake = stakes.max_stake.0;
+        let max_factor = |factor: u32| std::cmp::min(factor, stakes.max_stake_factor) as u128;
+
+        // (stake, max_factor, is_ours)
+        let mut participants = current_election
+            .members
+            .values()
+            .map(|member| {
+                let stake = std::cmp::min(member.msg_value as u128, max_stake);
+                (stake, max_factor(member.max_factor), false)
+            })
+            .collect::<Vec<_>>();
+        // NOTE: the elector rejects stakes below the minimum
+        if stake >= min_stake {
+            participants.push((
+                std::cmp::min(stake, max_stake),
+                max_factor(stake_factor),
+                true,
+            ));
+        }
+        participants.sort_by(|(left, ..), (right, ..)| right.cmp(left));
+
+        let not_elected = EffectiveStake {
+            stake: 0,
+            validator_count: 0,
+        };
+
+        let max_validators = validators.max_validators.as_u32() as usize;
+        let min_validators = std::cmp::max(validators.min_validators.as_u32() as usize, 1);
+        let count = std::cmp::min(participants.len(), max_validators);
+        if count < min_validators {
+            return Some(not_elected);
+        }
+
+        let compute_stake = |(stake, factor, _): &(u128, u128, bool), min_stake: u128| {
+            std::cmp::min(*stake, (min_stake * factor) >> 16)
+        };
+
+        // Find the number of validators with the max total stake
+        let mut best = None::<(usize, u128)>;
+        for i in min_validators..=count {
+            let min_stake = participants[i - 1].0;
+            let total = participants[..i]
+                .iter()
+                .map(|item| compute_stake(item, min_stake))
+                .sum::<u128>();
+            if !matches!(best, Some((_, best_total)) if best_total >= total) {
+                best = Some((i, total));
+            }
+        }
+
+        let Some((validator_count, total)) = best else { return Some(not_elected) };
+        if total < stakes.min_total_stake.0 {
+            return Some(not_elected);
+        }
+
+        let min_stake = participants[validator_count - 1].0;
+        Some(
+            match participants[..validator_count].iter().find(|item| item.2) {
+                Some(item) => EffectiveStake {
+                    stake: compute_stake(item, min_stake),
+                    validator_count,
+                },
+                None => EffectiveStake {
+                    stake: 0,
+                    validator_count,
+                },
+            },
+        )
+    }
+
+    /// Ranks the participant among the current elections members by stake.
+    ///
+    /// NOTE: Stakes are capped by the network max stake as in the elector,
+    /// but the final distribution also depends on the stake factors.
+    pub fn participant_rank(
+        &self,
+        address: &ton_block::MsgAddressInt,
+        validators: &ton_block::ConfigParam16,
+        stakes: &ton_block::ConfigParam17,
+    ) -> Option<ParticipantRank> {
+        let members = self.current_election_participants(stakes)?;
+
+        let max_validators = validators.max_validators.as_u32() as usize;
+        let cutoff_stake = members
+            .get(max_validators.saturating_sub(1))
+            .map(|member| member.stake);
+
+        // NOTE: only masterchain accounts can participate in elections
+        let address = match split_address(address) {
+            Ok((_, account)) if address.is_masterchain() => Some(account),
+            _ => None,
+        };
+        let position = members
+            .iter()
+            .position(|member| address.as_ref() == Some(&member.address));
+
+        Some(ParticipantRank {
+            participants: members.len(),
+            max_validators,
+            rank: position.map(|position| position + 1),
+            stake: position
+                .map(|position| members[position].stake)
+                .unwrap_or_default(),
+            cutoff_stake,
+        })
+    }
+
+    /// Returns all members of the current elections, the largest stake first.
+    ///
+    /// NOTE: Stakes are capped by the network max stake as in the elector
+    pub fn current_election_participants(
+        &self,
+        stakes: &ton_block::ConfigParam17,
+    ) -> Option<Vec<ElectionParticipant>> {
+        let current_election = self.inner.current_election.0.as_ref()?;
+
+        let mut participants = current_election
+            .members
+            .iter()
+            .map(|(public_key, member)| ElectionParticipant {
+                public_key: *public_key,
+                address: member.src_addr,
+                adnl_addr: member.adnl_addr,
+                stake: std::cmp::min(member.msg_value as u128, stakes.max_stake.0),
+                max_factor: member.max_factor,
+                created_at: member.created_at,
+            })
+            .collect::<Vec<_>>();
+        participants.sort_by(|left, right| right.stake.cmp(&left.stake));
+
+        Some(participants)
+    }
+
+    /// Guesses why the elector could have returned the stake
+    pub fn stake_return_reason(
+        &self,
+        election_id: u32,
+        stake: u128,
+        stake_factor: u32,
+        one_coin: u128,
+    ) -> Option<StakeReturnReason> {
+        let Some(current_election) = &self.inner.current_election.0 else {
+            return Some(StakeReturnReason::NO_ELECTIONS);
+        };
+
+        // NOTE: the same order of checks as in the elector (which deducts one coin for the answer)
+        let stake = stake.saturating_sub(one_coin);
+        Some(if stake_factor < 0x10000 {
+            StakeReturnReason::INVALID_FACTOR
+        } else if (stake << 12) < current_election.total_stake {
+            StakeReturnReason::TOO_SMALL_FRACTION
+        } else if current_election.elect_at != election_id {
+            StakeReturnReason::OTHER_ELECTIONS
+        } else if current_election.finished {
+            StakeReturnReason::NO_ELECTIONS
+        } else if stake < current_election.min_stake {
+            StakeReturnReason::BELOW_MIN_STAKE
+        } else {
+            return None;
+        })
+    }
+
+    /// Returns public keys of the stakes frozen for the specified participant
+    /// in the finished elections.
+    pub fn frozen_keys(
+        &self,
+        election_id: u32,
+        address: &ton_block::MsgAddressInt,
+    ) -> Vec<[u8; 32]> {
+        if !address.is_masterchain() {
+            return Vec::new();
+        }
+
+        let Some(past_election) = self.inner.past_elections.get(&election_id) else {
+            return Vec::new();
+        };
+        let Ok((_, address)) = split_address(address) else { return Vec::new() };
+
+        past_election
+            .frozen_dict
+            .iter()
+            .filter(|(_, frozen)| frozen.addr == address)
+            .map(|(public_key, _)| public_key.inner())
+            .collect()
+    }
+
+    /// Summarizes the participant stakes in the finished elections, the most recent first
+    pub fn past_elections_summary(
+        &self,
+        address: &ton_block::MsgAddressInt,
+        limit: usize,
+    ) -> Vec<PastElectionSummary> {
+        // NOTE: only masterchain accounts can participate in elections
+        let address = match split_address(address) {
+            Ok((_, account)) if address.is_masterchain() => Some(account),
+            _ => None,
+        };
+
+        self.inner
+            .past_elections
+            .iter()
+            .rev()
+            .take(limit)
+            .map(|(&election_id, election)| {
+                let mut summary = PastElectionSummary {
+                    election_id,
+                    unfreeze_at: election.unfreeze_at,
+                    elected: false,
+                    banned: false,
+                    stake: 0,
+                    reward: 0,
+                };
+
+                let frozen_stakes = election
+                    .frozen_dict
+                    .values()
+                    .filter(|frozen| address.as_ref() == Some(&frozen.addr));
+                for frozen in frozen_stakes {
+                    summary.elected = true;
+                    summary.banned |= frozen.banned;
+                    summary.stake += frozen.stake;
+                }
+
+                // NOTE: the same distribution as in the elector `unfreeze_with_bonuses`
+                if !summary.banned && election.total_stake > 0 {
+                    summary.reward = election.bonuses * summary.stake / election.total_stake;
+                }
+
+                summary
+            })
+            .collect()
+    }
+
+    /// Elections in which the participant stake is still frozen at the specified time
+    pub fn frozen_elections(&self, address: &ton_block::MsgAddressInt, now: u32) -> Vec<u32> {
+        self.past_elections_summary(address, usize::MAX)
+            .into_iter()
+            .filter(|summary| summary.elected && summary.unfreeze_at > now)
+            .map(|summary| summary.election_id)
+            .collect()
+    }
+
+    /// Election id and the reward of the latest round in which the participant
+    /// was elected and which stake is already unfrozen, but is not yet returned
+    /// to the credits by the elector.
+    pub fn latest_unfrozen_reward(
+        &self,
+        address: &ton_block::MsgAddressInt,
+        now: u32,
+    ) -> Option<(u32, u128)> {
+        self.past_elections_summary(address, usize::MAX)
+            .into_iter()
+            .find(|summary| summary.elected && summary.unfreeze_at <= now)
+            .map(|summary| (summary.election_id, summary.reward))
+    }
+
     pub fn elected(&self, address: &ton_block::MsgAddressInt) -> bool {
         if !address.is_masterchain() {
             return false;
@@ -199,6 +663,128 @@ impl ElectorData {
     }
 }
 
+/// Estimated elections result for the new stake
+#[derive(Debug, Clone, Copy)]
+pub struct EffectiveStake {
+    /// Stake accounted by the elector (zero if not elected)
+    pub stake: u128,
+    /// Total number of elected validators
+    pub validator_count: usize,
+}
+
+/// Position of the participant among the current elections members
+#[derive(Debug, Clone, Copy)]
+pub struct ParticipantRank {
+    /// Total number of the elections members
+    pub participants: usize,
+    /// Max number of validators in the network
+    pub max_validators: usize,
+    /// Position by stake starting from 1 (`None` if not participating)
+    pub rank: Option<usize>,
+    /// Participant stake (zero if not participating)
+    pub stake: u128,
+    /// Stake of the last member which fits into the validators limit
+    /// (`None` if there are free slots)
+    pub cutoff_stake: Option<u128>,
+}
+
+impl ParticipantRank {
+    /// Difference between the participant stake and the cutoff stake
+    pub fn margin(&self) -> Option<i128> {
+        let cutoff_stake = self.cutoff_stake?;
+        Some(self.stake as i128 - cutoff_stake as i128)
+    }
+}
+
+/// Member of the current elections
+#[derive(Debug, Clone, Copy)]
+pub struct ElectionParticipant {
+    /// Validator public key
+    pub public_key: ton_types::UInt256,
+    /// Masterchain account of the participant
+    pub address: ton_types::UInt256,
+    pub adnl_addr: ton_types::UInt256,
+    /// Stake capped by the network max stake
+    pub stake: u128,
+    /// Max stake factor (fixed point with 16 fractional bits)
+    pub max_factor: u32,
+    /// When the stake was accepted
+    pub created_at: u32,
+}
+
+/// Participant results of the finished elections
+#[derive(Debug, Clone, Copy)]
+pub struct PastElectionSummary {
+    pub election_id: u32,
+    /// When the frozen stake can be recovered
+    pub unfreeze_at: u32,
+    /// Whether the participant is in the validator set of this round
+    pub elected: bool,
+    /// Whether the stake was confiscated by complaints
+    pub banned: bool,
+    /// Frozen stake (without the part returned after elections)
+    pub stake: u128,
+    /// Reward for the round at the moment of fetching (bonuses still
+    /// accumulate until the stake is unfrozen)
+    pub reward: u128,
+}
+
+impl PastElectionSummary {
+    /// Stake lost due to complaints
+    pub fn penalty(&self) -> u128 {
+        if self.banned {
+            self.stake
+        } else {
+            0
+        }
+    }
+}
+
+/// Elector response to the new stake
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum StakeResponse {
+    Accepted,
+    Returned(StakeReturnReason),
+}
+
+/// Result of the local execution of the stake message
+#[derive(Debug, Clone, Copy)]
+pub struct StakeSimulation {
+    /// Compute phase exit code (`None` if the phase was skipped)
+    pub exit_code: Option<i32>,
+    pub aborted: bool,
+    pub response: Option<StakeResponse>,
+}
+
+/// Reason code of the `return_stake` elector message
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct StakeReturnReason(pub u32);
+
+impl StakeReturnReason {
+    pub const NO_ELECTIONS: Self = Self(0);
+    pub const INVALID_SIGNATURE: Self = Self(1);
+    pub const TOO_SMALL_FRACTION: Self = Self(2);
+    pub const OTHER_ELECTIONS: Self = Self(3);
+    pub const KEY_USED_BY_OTHER: Self = Self(4);
+    pub const BELOW_MIN_STAKE: Self = Self(5);
+    pub const INVALID_FACTOR: Self = Self(6);
+}
+
+impl std::fmt::Display for StakeReturnReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match *self {
+            Self::NO_ELECTIONS => "no active elections or elections already finished",
+            Self::INVALID_SIGNATURE => "invalid participant signature",
+            Self::TOO_SMALL_FRACTION => "stake is less than 1/4096 of the total stake",
+            Self::OTHER_ELECTIONS => "stake is for other elections",
+            Self::KEY_USED_BY_OTHER => "validator key is used by another participant",
+            Self::BELOW_MIN_STAKE => "stake is less than the min stake",
+            Self::INVALID_FACTOR => "stake factor is less than 1.0",
+            _ => return f.write_fmt(format_args!("unknown reason {}", self.0)),
+        })
+    }
+}
+
 struct UnsignedParticipantData {
     election_id: u32,
     address: ton_types::UInt256,
@@ -290,6 +876,28 @@ mod data {
     pub struct PastElectionData {
         #[abi(uint32)]
         pub unfreeze_at: u32,
+        #[abi(uint32)]
+        pub stake_held: u32,
+        #[abi(uint256)]
+        pub vset_hash: ton_types::UInt256,
+        #[abi]
+        pub frozen_dict: BTreeMap<ton_types::UInt256, FrozenStake>,
+        #[abi(gram)]
+        pub total_stake: u128,
+        #[abi(gram)]
+        pub bonuses: u128,
+    }
+
+    #[derive(Debug, UnpackAbi, KnownParamType)]
+    pub struct FrozenStake {
+        #[abi(uint256)]
+        pub addr: ton_types::UInt256,
+        #[abi(uint64)]
+        pub weight: u64,
+        #[abi(gram)]
+        pub stake: u128,
+        #[abi(bool)]
+        pub banned: bool,
     }
 }
 
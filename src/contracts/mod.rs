@@ -1,8 +1,10 @@
+use anyhow::Result;
+
 pub use cluster::Cluster;
-pub use depool::DePool;
+pub use depool::{DePool, DePoolAbi};
 pub use elector::Elector;
 pub use strategy::Strategy;
-pub use wallet::Wallet;
+pub use wallet::{Wallet, WalletBalance};
 
 pub mod cluster;
 pub mod depool;
@@ -25,6 +27,38 @@ impl InternalMessage {
             payload: Default::default(),
         }
     }
+
+    /// Replaces the payload with a text comment (`0u32` tag followed by UTF-8 bytes).
+    ///
+    /// NOTE: Only plain transfers can carry a comment, function calls keep their payload.
+    pub fn with_comment(mut self, comment: &str) -> Result<Self> {
+        use ton_types::IBitstring;
+
+        check_comment(comment)?;
+
+        let mut payload = ton_types::BuilderData::new();
+        payload
+            .append_u32(0)?
+            .append_raw(comment.as_bytes(), comment.len() * 8)?;
+        self.payload = payload.into_cell()?;
+        Ok(self)
+    }
+}
+
+/// Checks that the comment fits into a single cell and contains only printable characters
+pub fn check_comment(comment: &str) -> Result<()> {
+    anyhow::ensure!(!comment.is_empty(), "comment is empty");
+    anyhow::ensure!(
+        comment.len() <= MAX_COMMENT_LEN,
+        "comment is too long ({} bytes, max {MAX_COMMENT_LEN})",
+        comment.len()
+    );
+    anyhow::ensure!(
+        !comment.chars().any(char::is_control),
+        "comment contains control characters"
+    );
+    Ok(())
 }
 
-pub const ONE_EVER: u128 = 1_000_000_000;
+/// Max number of UTF-8 bytes in a comment: 1023 cell bits without the 32-bit tag
+pub const MAX_COMMENT_LEN: usize = 123;
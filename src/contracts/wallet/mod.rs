@@ -5,49 +5,138 @@ use nekoton_abi::{FunctionBuilder, KnownParamTypePlain, PackAbiPlain};
 use ton_abi::contract::ABI_VERSION_2_3;
 use ton_block::{Deserializable, GetRepresentationHash};
 
-use super::{InternalMessage, ONE_EVER};
-use crate::network::Subscription;
-use crate::util::{make_default_headers, TransactionWithHash};
+use super::InternalMessage;
+use crate::crypto::{encode_signed_input, Signer};
+use crate::network::{AccountStatus, Subscription, TransactionsRx};
+use crate::util::{make_default_headers, Tokens, TransactionWithHash};
 
 pub struct Wallet {
-    keypair: ed25519_dalek::Keypair,
+    signer: Arc<dyn Signer>,
     address: ton_block::MsgAddressInt,
     subscription: Arc<Subscription>,
+    max_attached_value: u128,
 }
 
 impl Wallet {
-    pub const INITIAL_BALANCE: u128 = 10 * ONE_EVER;
-
+    /// Creates a wallet which refuses to attach more than `max_attached_value`
+    /// to any outgoing message
     pub fn new(
         workchain_id: i8,
-        keypair: ed25519_dalek::Keypair,
+        signer: Arc<dyn Signer>,
         subscription: Arc<Subscription>,
+        max_attached_value: u128,
     ) -> Self {
         Self {
-            address: compute_wallet_address(workchain_id, &keypair.public),
-            keypair,
+            address: compute_wallet_address(workchain_id, &signer.public_key()),
+            signer,
             subscription,
+            max_attached_value,
         }
     }
 
+    /// Balance required to deploy the wallet and keep it running
+    pub fn initial_balance(one_coin: u128) -> u128 {
+        10 * one_coin
+    }
+
+    /// Default upper bound for the value attached to any outgoing message (20M coins)
+    pub fn default_max_attached_value(one_coin: u128) -> u128 {
+        20_000_000 * one_coin
+    }
+
     pub fn address(&self) -> &ton_block::MsgAddressInt {
         &self.address
     }
 
-    pub async fn get_balance(&self) -> Result<Option<u128>> {
+    /// Subscribes to the new wallet transactions
+    pub fn subscribe(&self) -> TransactionsRx {
+        self.subscription.subscribe(&self.address)
+    }
+
+    /// Whether the wallet transactions are delivered to subscribers.
+    ///
+    /// NOTE: Subscriptions receive nothing while blocks can't be downloaded
+    pub fn transactions_tracked(&self) -> bool {
+        !self.subscription.is_degraded()
+    }
+
+    pub async fn get_status(&self) -> Result<AccountStatus> {
         let account = self.get_account_state().await?;
-        Ok(account.map(|state| state.storage.balance.grams.0))
+        Ok(AccountStatus::from_state(account.as_ref()))
     }
 
-    /// Sends the internal message to the recipient, returns the destination transaction
+    /// Fetches the balance and the status from the same account state
+    pub async fn get_balance_with_status(&self) -> Result<WalletBalance> {
+        let account = self.get_account_state().await?;
+        Ok(WalletBalance {
+            status: AccountStatus::from_state(account.as_ref()),
+            balance: account
+                .map(|state| state.storage.balance.grams.0)
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Sends the internal message to the recipient, returns the destination transaction.
+    ///
+    /// Fails with [`MessageBounced`] if the wallet or the recipient has rejected the message.
+    ///
+    /// [`MessageBounced`]: crate::util::MessageBounced
     pub async fn call(&self, internal_message: InternalMessage) -> Result<TransactionWithHash> {
+        self.call_with_fees(internal_message)
+            .await
+            .map(|(tx, _)| tx)
+    }
+
+    /// Same as [`Wallet::call`], but also returns the fees paid by the wallet
+    pub async fn call_with_fees(
+        &self,
+        internal_message: InternalMessage,
+    ) -> Result<(TransactionWithHash, u128)> {
+        self.call_if_with_fees(internal_message, || futures_util::future::ready(Ok(true)))
+            .await?
+            .context("message is no longer needed")
+    }
+
+    /// Same as [`Wallet::call`], but re-sends the expired message only while
+    /// `is_needed` returns `true`. Returns `None` if the message is no longer needed.
+    pub async fn call_if<C, R>(
+        &self,
+        internal_message: InternalMessage,
+        is_needed: C,
+    ) -> Result<Option<TransactionWithHash>>
+    where
+        C: FnMut() -> R,
+        R: std::future::Future<Output = Result<bool>>,
+    {
+        let res = self.call_if_with_fees(internal_message, is_needed).await?;
+        Ok(res.map(|(tx, _)| tx))
+    }
+
+    /// Same as [`Wallet::call_if`], but also returns the fees paid by the wallet.
+    ///
+    /// NOTE: Fees include the forwarding fee of the sent message, but not
+    /// the fees of the destination transaction (they are paid from the attached value).
+    pub async fn call_if_with_fees<C, R>(
+        &self,
+        internal_message: InternalMessage,
+        is_needed: C,
+    ) -> Result<Option<(TransactionWithHash, u128)>>
+    where
+        C: FnMut() -> R,
+        R: std::future::Future<Output = Result<bool>>,
+    {
         let dst = internal_message.dst.clone();
         let mut dst_transactions = self.subscription.subscribe(&dst);
 
-        let src_tx = self.transfer(internal_message).await?;
+        let Some(src_tx) = self.transfer_if(internal_message, is_needed).await? else {
+            return Ok(None);
+        };
         tracing::debug!(source_tx_hash = ?src_tx.hash, "message sent from wallet");
+        src_tx
+            .ensure_not_aborted()
+            .context("wallet failed to send the message")?;
 
-        let mut out_msg_hash = None;
+        let mut out_msg = None;
         src_tx
             .data
             .out_msgs
@@ -59,27 +148,49 @@ impl Wallet {
                 let Some(header) = msg.int_header() else { return Ok(true) };
 
                 if header.dst == dst {
-                    out_msg_hash = Some(msg_hash);
+                    out_msg = Some((msg_hash, header.fwd_fee.0));
                     Ok(false)
                 } else {
                     Ok(true)
                 }
             })
             .context("failed to find outgoing message")?;
-        let out_msg_hash = out_msg_hash.context("outgoing message not found")?;
+        let (out_msg_hash, fwd_fee) = out_msg.context("outgoing message not found")?;
+        let fees = src_tx.total_fees() + fwd_fee;
 
         while let Some(tx) = dst_transactions.recv().await {
             tracing::debug!(source_tx_hash = ?src_tx.hash, tx_hash = ?tx.hash, "new transaction found");
             let Some(msg) = tx.data.in_msg_cell() else { continue; };
             if msg.repr_hash() == out_msg_hash {
-                return Ok(tx);
+                tx.ensure_not_aborted()?;
+                return Ok(Some((tx, fees)));
             }
         }
         anyhow::bail!("destination transaction was not found")
     }
 
     /// Sends the internal message to the recipient, returns the source transaction
-    pub async fn transfer(&self, internal_message: InternalMessage) -> Result<TransactionWithHash> {
+    async fn transfer_if<C, R>(
+        &self,
+        internal_message: InternalMessage,
+        is_needed: C,
+    ) -> Result<Option<TransactionWithHash>>
+    where
+        C: FnMut() -> R,
+        R: std::future::Future<Output = Result<bool>>,
+    {
+        anyhow::ensure!(
+            self.transactions_tracked(),
+            "wallet calls are not available in degraded mode \
+            (node UDP RPC is required to fetch the wallet transactions)"
+        );
+        anyhow::ensure!(
+            internal_message.amount <= self.max_attached_value,
+            "attached value {} exceeds the allowed maximum {}",
+            Tokens(internal_message.amount),
+            Tokens(self.max_attached_value),
+        );
+
         let account = self.get_account_state().await?;
 
         let state_init = match account {
@@ -89,7 +200,8 @@ impl Wallet {
                     anyhow::bail!("account frozen");
                 }
                 ton_block::AccountState::AccountUninit => Some(
-                    make_state_init(&self.keypair.public).context("failed to make state init")?,
+                    make_state_init(&self.signer.public_key())
+                        .context("failed to make state init")?,
                 ),
             },
             None => anyhow::bail!("account not deployed"),
@@ -104,39 +216,43 @@ impl Wallet {
         }
         .pack();
 
-        let tx = self
-            .subscription
-            .send_message_with_retires(|timeout, signature_id| {
-                let (expire_at, headers) = make_default_headers(Some(self.keypair.public), timeout);
-
-                let mut message = ton_block::Message::with_ext_in_header(
-                    ton_block::ExternalInboundMessageHeader {
-                        dst: self.address.clone(),
-                        ..Default::default()
-                    },
-                );
-
-                message.set_body(
-                    ever_wallet::send_transaction()
-                        .encode_input(
+        let inputs = &inputs;
+        let state_init = &state_init;
+        self.subscription
+            .send_message_with_retires_if(
+                move |timeout, signature_id| async move {
+                    let (expire_at, headers) =
+                        make_default_headers(Some(self.signer.public_key()), timeout);
+
+                    let mut message = ton_block::Message::with_ext_in_header(
+                        ton_block::ExternalInboundMessageHeader {
+                            dst: self.address.clone(),
+                            ..Default::default()
+                        },
+                    );
+
+                    message.set_body(
+                        encode_signed_input(
+                            ever_wallet::send_transaction(),
                             &headers,
-                            &inputs,
-                            false,
-                            Some((&self.keypair, signature_id)),
-                            Some(self.address.clone()),
-                        )?
+                            inputs,
+                            self.signer.as_ref(),
+                            signature_id,
+                            self.address.clone(),
+                        )
+                        .await?
                         .into(),
-                );
-
-                if let Some(state_init) = state_init.clone() {
-                    message.set_state_init(state_init);
-                }
+                    );
 
-                Ok((message, expire_at))
-            })
-            .await?;
+                    if let Some(state_init) = state_init.clone() {
+                        message.set_state_init(state_init);
+                    }
 
-        Ok(tx)
+                    Ok::<_, anyhow::Error>((message, expire_at))
+                },
+                is_needed,
+            )
+            .await
     }
 
     async fn get_account_state(&self) -> Result<Option<ton_block::AccountStuff>> {
@@ -147,6 +263,27 @@ impl Wallet {
     }
 }
 
+/// Wallet balance with the account status
+#[derive(Debug, Clone, Copy)]
+pub struct WalletBalance {
+    pub status: AccountStatus,
+    /// Raw account balance (zero if the account doesn't exist)
+    pub balance: u128,
+}
+
+impl WalletBalance {
+    /// Balance which can be sent from the wallet.
+    ///
+    /// NOTE: Uninit wallet is deployed with the first outgoing message,
+    /// but nothing can be sent from the frozen one.
+    pub fn spendable(&self) -> u128 {
+        match self.status {
+            AccountStatus::Active | AccountStatus::Uninit => self.balance,
+            AccountStatus::NotExists | AccountStatus::Frozen => 0,
+        }
+    }
+}
+
 pub fn compute_wallet_address(
     workchain_id: i8,
     pubkey: &ed25519_dalek::PublicKey,
@@ -4,8 +4,11 @@ use anyhow::Result;
 use hmac::digest::Digest;
 use rand::Rng;
 
+pub use self::signer::{encode_signed_input, Signer};
+
 mod bip39;
 mod legacy;
+mod signer;
 
 const LANGUAGE: ::bip39::Language = ::bip39::Language::English;
 
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+
+/// Source of ed25519 signatures for external messages.
+///
+/// The default implementation is an in-memory [`ed25519_dalek::Keypair`],
+/// but it can be implemented for remote signers or HSMs.
+#[async_trait::async_trait]
+pub trait Signer: Send + Sync {
+    fn public_key(&self) -> ed25519_dalek::PublicKey;
+
+    async fn sign(&self, data: &[u8]) -> Result<[u8; 64]>;
+}
+
+#[async_trait::async_trait]
+impl Signer for ed25519_dalek::Keypair {
+    fn public_key(&self) -> ed25519_dalek::PublicKey {
+        self.public
+    }
+
+    async fn sign(&self, data: &[u8]) -> Result<[u8; 64]> {
+        Ok(ed25519_dalek::Signer::sign(self, data).to_bytes())
+    }
+}
+
+/// Encodes the external function call signed by the specified signer
+pub async fn encode_signed_input(
+    function: &ton_abi::Function,
+    header: &HashMap<String, ton_abi::TokenValue>,
+    inputs: &[ton_abi::Token],
+    signer: &dyn Signer,
+    signature_id: Option<i32>,
+    address: ton_block::MsgAddressInt,
+) -> Result<ton_types::BuilderData> {
+    let (builder, hash) = function
+        .create_unsigned_call(header, inputs, false, true, Some(address))
+        .context("failed to encode function call")?;
+
+    let data = ton_abi::extend_signature_with_id(&hash, signature_id);
+    let signature = signer
+        .sign(&data)
+        .await
+        .context("failed to sign function call")?;
+    let public_key = signer.public_key().to_bytes();
+
+    ton_abi::Function::fill_sign(
+        &function.abi_version,
+        Some(signature.as_slice()),
+        Some(&public_key),
+        builder,
+    )
+    .context("failed to insert signature")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Signer which is not an in-memory keypair (like a remote one)
+    struct TestSigner(ed25519_dalek::Keypair);
+
+    #[async_trait::async_trait]
+    impl Signer for TestSigner {
+        fn public_key(&self) -> ed25519_dalek::PublicKey {
+            self.0.public
+        }
+
+        async fn sign(&self, data: &[u8]) -> Result<[u8; 64]> {
+            tokio::task::yield_now().await;
+            Ok(ed25519_dalek::Signer::sign(&self.0, data).to_bytes())
+        }
+    }
+
+    fn keypair() -> ed25519_dalek::Keypair {
+        let secret = ed25519_dalek::SecretKey::from_bytes(&[1; 32]).unwrap();
+        let public = ed25519_dalek::PublicKey::from(&secret);
+        ed25519_dalek::Keypair { secret, public }
+    }
+
+    fn serialize(builder: ton_types::BuilderData) -> Vec<u8> {
+        ton_types::serialize_toc(&builder.into_cell().unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn signed_input_matches_abi_encoding() {
+        let function = nekoton_abi::FunctionBuilder::new("test")
+            .abi_version(ton_abi::contract::ABI_VERSION_2_3)
+            .pubkey_header()
+            .time_header()
+            .expire_header()
+            .input("value", ton_abi::ParamType::Uint(128))
+            .build();
+
+        let keypair = keypair();
+        let (_, header) = crate::util::make_default_headers(Some(keypair.public), 60);
+        let inputs = [ton_abi::Token::new(
+            "value",
+            ton_abi::TokenValue::Uint(ton_abi::Uint::new(123, 128)),
+        )];
+        let address = ton_block::MsgAddressInt::default();
+
+        let expected = function
+            .encode_input(
+                &header,
+                &inputs,
+                false,
+                Some(&keypair),
+                Some(address.clone()),
+            )
+            .unwrap();
+
+        let with_keypair =
+            encode_signed_input(&function, &header, &inputs, &keypair, None, address.clone())
+                .await
+                .unwrap();
+        let test_signer = TestSigner(keypair);
+        let with_test_signer =
+            encode_signed_input(&function, &header, &inputs, &test_signer, None, address)
+                .await
+                .unwrap();
+
+        let expected = serialize(expected);
+        assert_eq!(serialize(with_keypair), expected);
+        assert_eq!(serialize(with_test_signer), expected);
+    }
+}
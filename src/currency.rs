@@ -1,11 +1,13 @@
-use std::collections::HashMap;
+use std::sync::atomic::{AtomicU8, Ordering};
 
 use once_cell::race::OnceBox;
 
-use crate::util::parse_hex_or_base64;
+use crate::config::known_network;
 
 pub const DEFAULT: &str = "EVER";
 
+pub const DEFAULT_DECIMALS: u8 = 9;
+
 const ENV: &str = "NODEKEEPER_CURRENCY";
 
 pub fn from_env() -> Option<&'static str> {
@@ -15,21 +17,18 @@ pub fn from_env() -> Option<&'static str> {
         .as_deref()
 }
 
-macro_rules! decl_known_networks {
-    ($ident:ident, { $($file_hash:literal => $currency:literal),*$(,)? }) => {
-        pub fn $ident(zerostate_file_hash: &[u8; 32]) -> Option<&'static str> {
-            static KNOWN_NETWORKS: OnceBox<HashMap<[u8; 32], &'static str>> = OnceBox::new();
-            KNOWN_NETWORKS.get_or_init(|| Box::new(HashMap::from([
-                $((parse_hex_or_base64($file_hash).unwrap().try_into().unwrap(), $currency)),*
-            ])))
-            .get(zerostate_file_hash)
-            .copied()
-        }
-    }
+static DECIMALS: AtomicU8 = AtomicU8::new(DEFAULT_DECIMALS);
+
+/// Number of decimals used to display token amounts
+pub fn decimals() -> u8 {
+    DECIMALS.load(Ordering::Relaxed)
+}
+
+/// Updates the number of decimals after the app config is loaded
+pub fn set_decimals(decimals: u8) {
+    DECIMALS.store(decimals, Ordering::Relaxed);
 }
 
-decl_known_networks! {
-    detect_custom_currency, {
-        "ywj7H75tJ3PgbEeX+UNP3j0iR1x9imIIJJuQgrlCr8s=" => "VENOM",
-    }
+pub fn detect_custom_currency(zerostate_file_hash: &[u8; 32]) -> Option<&'static str> {
+    known_network(zerostate_file_hash)?.currency
 }
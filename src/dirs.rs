@@ -1,10 +1,15 @@
 use std::path::{Path, PathBuf};
 
+use anyhow::Result;
+
+use crate::config::{AppConfig, AppConfigAdnl, DEFAULT_UNIT};
+
 const ENV: &str = "NODEKEEPER_ROOT";
 
 pub const VALIDATOR_SERVICE: &str = "validator";
 pub const VALIDATOR_MANAGER_SERVICE: &str = "validator-manager";
 
+#[derive(Clone)]
 pub struct ProjectDirs {
     pub app_config: PathBuf,
     pub node_config: PathBuf,
@@ -18,9 +23,25 @@ pub struct ProjectDirs {
     pub keys_dir: PathBuf,
     pub validator_keys: PathBuf,
     pub depool_keys: PathBuf,
+    pub skipped_election: PathBuf,
+    pub elections_deadline: PathBuf,
+    /// Exists while the node is stuck in the not ready state for too long
+    pub node_not_ready: PathBuf,
+    /// Elections are not participated in while this file exists
+    pub pause_file: PathBuf,
+    /// The latest snapshot of messages which are waiting for delivery
+    pub pending_messages: PathBuf,
+    /// Start time of the validator and the latest loop iteration
+    pub validator_heartbeat: PathBuf,
+    /// Lock record of the running validator instance
+    pub validator_lock: PathBuf,
+    pub validator_state: PathBuf,
+    pub validator_history: PathBuf,
     pub root: PathBuf,
     pub validator_service: PathBuf,
     pub validator_manager_service: PathBuf,
+    /// Node config to derive ADNL client params from (instead of the app config)
+    pub adnl_node_config: Option<PathBuf>,
 }
 
 impl ProjectDirs {
@@ -59,12 +80,66 @@ impl ProjectDirs {
             keys_dir,
             validator_keys,
             depool_keys,
+            skipped_election: root.join("skipped_election"),
+            elections_deadline: root.join("elections_deadline"),
+            node_not_ready: root.join("node_not_ready"),
+            pause_file: root.join("paused"),
+            pending_messages: root.join("pending_messages.json"),
+            validator_heartbeat: root.join("validator_heartbeat.json"),
+            validator_lock: root.join("validator.lock"),
+            validator_state: root.join("validator_state.json"),
+            validator_history: root.join("validator_history.jsonl"),
             root,
             validator_service,
             validator_manager_service,
+            adnl_node_config: None,
+        }
+    }
+
+    /// Returns dirs with keys and state of the validation unit.
+    ///
+    /// Additional unit files are stored in `units/<name>`, the rest is shared.
+    pub fn unit_dirs(&self, name: &str) -> Self {
+        if name == DEFAULT_UNIT {
+            return self.clone();
+        }
+
+        let unit_root = self.root.join("units").join(name);
+        let keys_dir = unit_root.join("keys");
+        Self {
+            validator_keys: keys_dir.join("vld.keys.json"),
+            depool_keys: keys_dir.join("depool.keys.json"),
+            keys_dir,
+            validator_state: unit_root.join("validator_state.json"),
+            validator_history: unit_root.join("validator_history.jsonl"),
+            ..self.clone()
         }
     }
 
+    /// Loads the app config, replacing ADNL params with the derived ones if needed
+    pub fn load_app_config(&self) -> Result<AppConfig> {
+        let mut config = AppConfig::load(&self.app_config)?;
+        if let Some(path) = &self.adnl_node_config {
+            let client_port = config.adnl.as_ref().map(|adnl| adnl.client_port);
+            let derived = AppConfigAdnl::from_node_config(path, client_port.unwrap_or_default())?;
+
+            // NOTE: only the server params are derived, local params are preserved
+            config.adnl = Some(match config.adnl.take() {
+                Some(adnl) => AppConfigAdnl {
+                    server_address: derived.server_address,
+                    server_pubkey: derived.server_pubkey,
+                    zerostate_file_hash: derived.zerostate_file_hash,
+                    ..adnl
+                },
+                None => derived,
+            });
+        }
+
+        // NOTE: applied on each load, so the option can be changed without a restart
+        crate::util::set_log_redaction(config.redact_logs);
+        Ok(config)
+    }
+
     pub fn default_root_dir() -> PathBuf {
         if let Ok(path) = std::env::var(ENV) {
             PathBuf::from(path)
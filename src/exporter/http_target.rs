@@ -1,39 +1,98 @@
-use std::net::SocketAddr;
+use std::io::BufReader;
+use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Semaphore;
+use tokio_rustls::rustls;
+use tokio_rustls::TlsAcceptor;
 
 use super::ExporterTarget;
+use crate::config::AppConfigExporter;
 
+/// Serves the latest metrics at `/metrics` and a liveness probe at `/health`.
+///
+/// Until the first metrics are collected `/metrics` responds with `503`,
+/// so that monitoring doesn't treat missing gauges as zeros.
+///
+/// Metrics with exemplars are served in the OpenMetrics format if the client accepts it.
+///
+/// NOTE: At most [`MAX_CONNECTIONS`] connections are served at once, others are closed
+/// right away, and each connection is closed after [`CONNECTION_TIMEOUT`].
 pub struct HttpExporterTarget {
-    state: Arc<parking_lot::RwLock<Option<String>>>,
-    _exporter: Arc<pomfrit::MetricsExporter>,
+    state: Arc<ServerState>,
+    server: tokio::task::JoinHandle<()>,
 }
 
 impl HttpExporterTarget {
-    pub async fn new(addr: SocketAddr) -> Result<Self> {
-        let (exporter, writer) = pomfrit::create_exporter(Some(pomfrit::Config {
-            collection_interval_sec: 1,
-            listen_address: addr,
-            metrics_path: None,
-        }))
-        .await?;
-
-        let state = Arc::new(parking_lot::RwLock::default());
-        writer.spawn({
+    pub async fn new(config: &AppConfigExporter) -> Result<Self> {
+        let acceptor = match &config.tls {
+            Some(tls) => Some(load_tls_acceptor(&tls.cert_path, &tls.key_path)?),
+            None => None,
+        };
+
+        let listener = TcpListener::bind(config.listen_addr)
+            .await
+            .with_context(|| format!("failed to bind exporter to {}", config.listen_addr))?;
+
+        tracing::info!(
+            listen_addr = %config.listen_addr,
+            tls = acceptor.is_some(),
+            auth = config.bearer_token.is_some(),
+            "started metrics server"
+        );
+
+        let state = Arc::new(ServerState {
+            metrics: Default::default(),
+            openmetrics: Default::default(),
+            bearer_token: config.bearer_token.clone(),
+        });
+
+        let server = tokio::spawn({
             let state = state.clone();
-            move |writer| {
-                let metrics = state.read();
-                if let Some(metrics) = &*metrics {
-                    writer.write_str(metrics);
+            let connections = Arc::new(Semaphore::new(MAX_CONNECTIONS));
+            async move {
+                loop {
+                    let (stream, addr) = match listener.accept().await {
+                        Ok(connection) => connection,
+                        Err(e) => {
+                            tracing::warn!("failed to accept exporter connection: {e:?}");
+                            continue;
+                        }
+                    };
+
+                    let Ok(permit) = connections.clone().try_acquire_owned() else {
+                        tracing::debug!(%addr, "too many exporter connections, connection closed");
+                        continue;
+                    };
+
+                    let state = state.clone();
+                    let acceptor = acceptor.clone();
+                    tokio::spawn(async move {
+                        let connection = handle_connection(stream, acceptor, &state);
+                        match tokio::time::timeout(CONNECTION_TIMEOUT, connection).await {
+                            Ok(Ok(())) => {}
+                            Ok(Err(e)) => {
+                                tracing::debug!(%addr, "exporter connection failed: {e:?}")
+                            }
+                            Err(_) => tracing::debug!(%addr, "exporter connection timeout"),
+                        }
+                        drop(permit);
+                    });
                 }
             }
         });
 
-        Ok(Self {
-            state,
-            _exporter: exporter,
-        })
+        Ok(Self { state, server })
+    }
+}
+
+impl Drop for HttpExporterTarget {
+    fn drop(&mut self) {
+        self.server.abort();
     }
 }
 
@@ -43,8 +102,239 @@ impl ExporterTarget for HttpExporterTarget {
     }
 
     fn write(&self, metrics: &dyn std::fmt::Display) -> Result<()> {
-        let mut state = self.state.write();
+        let mut state = self.state.metrics.write();
+        *state = Some(metrics.to_string());
+
+        // NOTE: reset OpenMetrics to not serve stale values after the fallback metrics
+        *self.state.openmetrics.write() = None;
+        Ok(())
+    }
+
+    fn write_openmetrics(&self, metrics: &dyn std::fmt::Display) -> Result<()> {
+        let mut state = self.state.openmetrics.write();
         *state = Some(metrics.to_string());
         Ok(())
     }
 }
+
+struct ServerState {
+    metrics: parking_lot::RwLock<Option<String>>,
+    openmetrics: parking_lot::RwLock<Option<String>>,
+    bearer_token: Option<String>,
+}
+
+impl ServerState {
+    fn is_authorized(&self, authorization: Option<&str>) -> bool {
+        let Some(expected) = &self.bearer_token else {
+            return true;
+        };
+
+        let Some(token) = authorization.and_then(|value| value.strip_prefix("Bearer ")) else {
+            return false;
+        };
+
+        // NOTE: compare all bytes to not leak the matched prefix length via timings
+        let token = token.trim().as_bytes();
+        let expected = expected.as_bytes();
+        token.len() == expected.len()
+            && token
+                .iter()
+                .zip(expected)
+                .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+                == 0
+    }
+}
+
+async fn handle_connection(
+    stream: TcpStream,
+    acceptor: Option<TlsAcceptor>,
+    state: &ServerState,
+) -> Result<()> {
+    match acceptor {
+        Some(acceptor) => {
+            let stream = tokio::time::timeout(REQUEST_TIMEOUT, acceptor.accept(stream))
+                .await
+                .context("TLS handshake timeout")??;
+            handle_request(stream, state).await
+        }
+        None => handle_request(stream, state).await,
+    }
+}
+
+async fn handle_request<S>(mut stream: S, state: &ServerState) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let head = tokio::time::timeout(REQUEST_TIMEOUT, read_request_head(&mut stream))
+        .await
+        .context("request timeout")??;
+
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split(' ');
+    let method = request_line.next().unwrap_or_default();
+    let path = request_line.next().unwrap_or_default();
+    let path = path.split_once('?').map(|(path, _)| path).unwrap_or(path);
+
+    let mut authorization = None;
+    let mut accepts_openmetrics = false;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else { continue };
+        if name.eq_ignore_ascii_case("authorization") {
+            authorization = Some(value.trim());
+        } else if name.eq_ignore_ascii_case("accept") {
+            accepts_openmetrics |= value.contains("application/openmetrics-text");
+        }
+    }
+
+    let openmetrics = match accepts_openmetrics {
+        true => state.openmetrics.read().clone(),
+        false => None,
+    };
+
+    let response = match (method, path) {
+        ("GET", "/health") => Response::text(200, "OK", "OK\n".to_owned()),
+        ("GET", "/metrics") if !state.is_authorized(authorization) => Response {
+            status: 401,
+            reason: "Unauthorized",
+            extra_headers: "WWW-Authenticate: Bearer\r\n",
+            content_type: "text/plain",
+            body: "Unauthorized\n".to_owned(),
+        },
+        ("GET", "/metrics") if openmetrics.is_some() => Response {
+            status: 200,
+            reason: "OK",
+            extra_headers: "",
+            content_type: "application/openmetrics-text; version=1.0.0; charset=utf-8",
+            body: openmetrics.unwrap_or_default(),
+        },
+        ("GET", "/metrics") => match state.metrics.read().clone() {
+            Some(metrics) => Response {
+                status: 200,
+                reason: "OK",
+                extra_headers: "",
+                content_type: "text/plain; version=0.0.4",
+                body: metrics,
+            },
+            None => Response {
+                status: 503,
+                reason: "Service Unavailable",
+                extra_headers: "Retry-After: 5\r\n",
+                content_type: "text/plain",
+                body: "Metrics are not collected yet\n".to_owned(),
+            },
+        },
+        ("GET", _) => Response::text(404, "Not Found", "Not Found\n".to_owned()),
+        _ => Response::text(405, "Method Not Allowed", "Method Not Allowed\n".to_owned()),
+    };
+
+    stream.write_all(response.to_string().as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+async fn read_request_head<S>(stream: &mut S) -> Result<String>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut buffer = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 1024];
+    loop {
+        let n = stream.read(&mut chunk).await?;
+        anyhow::ensure!(n > 0, "connection closed");
+        buffer.extend_from_slice(&chunk[..n]);
+
+        if let Some(end) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            buffer.truncate(end);
+            return String::from_utf8(buffer).context("invalid request head");
+        }
+        anyhow::ensure!(
+            buffer.len() <= MAX_REQUEST_HEAD_LEN,
+            "request head is too long"
+        );
+    }
+}
+
+struct Response {
+    status: u16,
+    reason: &'static str,
+    extra_headers: &'static str,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn text(status: u16, reason: &'static str, body: String) -> Self {
+        Self {
+            status,
+            reason,
+            extra_headers: "",
+            content_type: "text/plain",
+            body,
+        }
+    }
+}
+
+impl std::fmt::Display for Response {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "HTTP/1.1 {} {}\r\n\
+            {}\
+            Content-Type: {}\r\n\
+            Content-Length: {}\r\n\
+            Connection: close\r\n\r\n\
+            {}",
+            self.status,
+            self.reason,
+            self.extra_headers,
+            self.content_type,
+            self.body.len(),
+            self.body
+        )
+    }
+}
+
+fn load_tls_acceptor(cert_path: &Path, key_path: &Path) -> Result<TlsAcceptor> {
+    let certs = read_pem(cert_path)?
+        .into_iter()
+        .filter_map(|item| match item {
+            rustls_pemfile::Item::X509Certificate(cert) => Some(rustls::Certificate(cert)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    anyhow::ensure!(
+        !certs.is_empty(),
+        "no certificates found in the TLS cert file"
+    );
+
+    let key = read_pem(key_path)?
+        .into_iter()
+        .find_map(|item| match item {
+            rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::ECKey(key) => Some(rustls::PrivateKey(key)),
+            _ => None,
+        })
+        .context("no private key found in the TLS key file")?;
+
+    let config = rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .context("invalid TLS certificate or key")?;
+
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+fn read_pem(path: &Path) -> Result<Vec<rustls_pemfile::Item>> {
+    let file =
+        std::fs::File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    rustls_pemfile::read_all(&mut BufReader::new(file))
+        .with_context(|| format!("failed to parse {}", path.display()))
+}
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Max duration of the whole connection (including sending the response)
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_REQUEST_HEAD_LEN: usize = 8192;
+const MAX_CONNECTIONS: usize = 64;
@@ -1,14 +1,22 @@
-use std::time::Duration;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use pomfrit::formatter::DisplayPrometheusExt;
 
 pub use self::file_target::FileExporterTarget;
 pub use self::http_target::HttpExporterTarget;
 pub use self::stdout_target::StdoutExporterTarget;
-use crate::config::{AppConfig, AppConfigValidator, DePoolType};
+use crate::config::{AppConfig, AppConfigValidator, DePoolType, StakeAmount};
+use crate::contracts::{elector::ElectorData, Elector};
 use crate::dirs::ProjectDirs;
-use crate::network::{NodeStats, NodeTcpRpc, ValidatorSetEntry};
+use crate::network::{
+    CollationStats, ConfigWithId, NodeStats, NodeTcpRpc, PendingMessages, ValidatorSetEntry,
+};
+use crate::validator::{
+    HistoryEvent, HistoryEventKind, LoopHeartbeat, Timeline, DEFAULT_STAKE_FACTOR,
+};
 
 mod file_target;
 mod http_target;
@@ -17,17 +25,32 @@ mod stdout_target;
 pub struct Exporter {
     dirs: ProjectDirs,
     targets: Vec<Box<dyn ExporterTarget>>,
+    blocks_produced: parking_lot::Mutex<BlocksProduced>,
+    /// Latest validator status and when it was collected
+    status: parking_lot::Mutex<Option<(Instant, Arc<ValidatorStatus>)>>,
+    /// How long the validator status is reused (the collection interval)
+    status_ttl: Duration,
 }
 
 impl Exporter {
+    /// Default metrics collection interval
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(10);
+
     pub fn new(dirs: ProjectDirs, targets: Vec<Box<dyn ExporterTarget>>) -> Self {
-        Self { dirs, targets }
+        Self {
+            dirs,
+            targets,
+            blocks_produced: Default::default(),
+            status: Default::default(),
+            status_ttl: Self::DEFAULT_INTERVAL,
+        }
     }
 
-    pub async fn serve(self, interval: Duration) {
+    pub async fn serve(mut self, interval: Duration) {
         if self.targets.is_empty() {
             return;
         }
+        self.status_ttl = interval;
 
         let mut interval = tokio::time::interval(interval);
         loop {
@@ -55,20 +78,159 @@ impl Exporter {
         }
     }
 
+    /// Collects all metrics and returns them in the prometheus text format.
+    ///
+    /// NOTE: the validator status is reused if it was collected within the interval
+    pub async fn dump(&self) -> Result<String> {
+        let (config, node_rpc) = self.init_node_rpc().await.map_err(|(e, _)| e)?;
+        let metrics = self.collect_metrics(&config, &node_rpc).await?;
+        Ok(metrics.to_string())
+    }
+
     async fn collect(&self, config: &AppConfig, node_rpc: &NodeTcpRpc) -> Result<()> {
-        let stats = node_rpc.get_stats().await?;
+        let mut metrics = self.collect_metrics(config, node_rpc).await?;
+        self.export(&metrics);
+
+        // NOTE: exemplars are supported only in the OpenMetrics format
+        metrics.openmetrics = true;
+        for target in &self.targets {
+            if let Err(e) = target.write_openmetrics(&metrics) {
+                tracing::warn!(
+                    "failed to write OpenMetrics to the {}: {e:?}",
+                    target.target_name()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    async fn collect_metrics<'a>(
+        &self,
+        config: &'a AppConfig,
+        node_rpc: &NodeTcpRpc,
+    ) -> Result<Metrics<'a>> {
+        let (stats, collation) = node_rpc.get_stats_with_collation().await?;
         let collected_at = broxus_util::now();
 
         tracing::debug!("collected node stats");
 
-        let metrics = Metrics {
+        let status = match &stats {
+            NodeStats::Running(stats) if config.validation_units().next().is_some() => {
+                match self.validator_status(node_rpc, config, stats.mc_time).await {
+                    Ok(status) => Some(status),
+                    Err(e) => {
+                        tracing::warn!("failed to collect validator status: {e:?}");
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+
+        let collation = match (&stats, collation) {
+            (NodeStats::Running(_), Ok(collation)) => collation,
+            (NodeStats::Running(_), Err(e)) => {
+                tracing::warn!("failed to collect collation stats: {e:?}");
+                None
+            }
+            (NodeStats::NotReady(_), _) => None,
+        };
+        let blocks_produced = collation
+            .as_ref()
+            .map(|collation| self.blocks_produced.lock().observe(collation));
+
+        let elections_deadline = match self.dirs.load_elections_deadline() {
+            Ok(deadline) => deadline,
+            Err(e) => {
+                tracing::warn!("failed to load elections deadline: {e:?}");
+                None
+            }
+        };
+
+        // NOTE: the state is reported by the validator only after the threshold
+        let node_not_ready = match &stats {
+            NodeStats::NotReady(_) => match self.dirs.load_node_not_ready() {
+                Ok(since) => since,
+                Err(e) => {
+                    tracing::warn!("failed to load node not ready state: {e:?}");
+                    None
+                }
+            },
+            NodeStats::Running(_) => None,
+        };
+
+        // NOTE: the snapshot is ignored if the validator stopped updating it
+        let pending_messages = match self.dirs.load_pending_messages() {
+            Ok(pending) => pending.filter(|pending| {
+                collected_at.saturating_sub(pending.sampled_at) <= MAX_PENDING_MESSAGES_LAG
+            }),
+            Err(e) => {
+                tracing::warn!("failed to load pending messages: {e:?}");
+                None
+            }
+        };
+
+        let heartbeat = match self.dirs.load_validator_heartbeat() {
+            Ok(heartbeat) => heartbeat,
+            Err(e) => {
+                tracing::warn!("failed to load validator heartbeat: {e:?}");
+                None
+            }
+        };
+
+        let mut missing_keys = Vec::new();
+        let mut events = Vec::new();
+        for (unit, _) in config.validation_units() {
+            let unit_dirs = self.dirs.unit_dirs(unit);
+            match unit_dirs.load_validator_state() {
+                Ok(state) => missing_keys.extend(
+                    state
+                        .missing_keys
+                        .map(|missing| (unit, missing.round_id, missing.public_keys.len())),
+                ),
+                Err(e) => tracing::warn!(unit, "failed to load validator state: {e:?}"),
+            }
+            match unit_dirs.load_validator_history() {
+                Ok(history) => events.push(UnitEvents::from_history(unit, history)),
+                Err(e) => tracing::warn!(unit, "failed to load validator history: {e:?}"),
+            }
+        }
+
+        Ok(Metrics {
             collected_at,
             config,
-            stats: &stats,
-        };
-        self.export(&metrics);
+            stats,
+            status,
+            collation,
+            blocks_produced,
+            elections_deadline,
+            node_not_ready,
+            paused: self.dirs.is_paused(),
+            pending_messages,
+            heartbeat,
+            missing_keys,
+            events,
+            openmetrics: false,
+        })
+    }
 
-        Ok(())
+    /// Returns the validator status, collecting it at most once per interval
+    /// (it requires several node queries)
+    async fn validator_status(
+        &self,
+        node_rpc: &NodeTcpRpc,
+        config: &AppConfig,
+        now: u32,
+    ) -> Result<Arc<ValidatorStatus>> {
+        if let Some((collected_at, status)) = &*self.status.lock() {
+            if collected_at.elapsed() < self.status_ttl {
+                return Ok(status.clone());
+            }
+        }
+
+        let status = Arc::new(ValidatorStatus::collect(node_rpc, config, now).await?);
+        *self.status.lock() = Some((Instant::now(), status.clone()));
+        Ok(status)
     }
 
     fn export(&self, metrics: &dyn std::fmt::Display) {
@@ -89,17 +251,26 @@ impl Exporter {
             (e, MetricsFallback { config_is_valid: V })
         }
 
-        let config = AppConfig::load(&self.dirs.app_config).map_err(fallback::<false>)?;
+        let config = self.dirs.load_app_config().map_err(fallback::<false>)?;
         let control = config.control().map_err(fallback::<true>)?;
         let node_rpc = NodeTcpRpc::new(control).await.map_err(fallback::<true>)?;
         Ok((config, node_rpc))
     }
 }
 
-pub trait ExporterTarget {
+pub trait ExporterTarget: Send + Sync {
     fn target_name(&self) -> &'static str;
 
     fn write(&self, metrics: &dyn std::fmt::Display) -> Result<()>;
+
+    /// Writes metrics in the OpenMetrics format (with exemplars).
+    ///
+    /// NOTE: Called after each successful [`ExporterTarget::write`],
+    /// ignored by the targets which serve only the Prometheus format.
+    fn write_openmetrics(&self, metrics: &dyn std::fmt::Display) -> Result<()> {
+        let _ = metrics;
+        Ok(())
+    }
 }
 
 struct MetricsFallback {
@@ -114,34 +285,339 @@ impl std::fmt::Display for MetricsFallback {
     }
 }
 
-#[derive(Copy, Clone)]
 struct Metrics<'a> {
     collected_at: u32,
     config: &'a AppConfig,
-    stats: &'a NodeStats,
+    stats: NodeStats,
+    status: Option<Arc<ValidatorStatus>>,
+    /// Latest block collation and validation times, if reported by the node
+    collation: Option<CollationStats>,
+    /// Number of blocks collated by the node since the exporter start
+    blocks_produced: Option<u64>,
+    elections_deadline: Option<u32>,
+    /// Since when the node is not ready for longer than the validator threshold
+    node_not_ready: Option<u32>,
+    /// Whether the validation is paused by the operator
+    paused: bool,
+    /// Messages sent by the validator which are waiting for delivery
+    pending_messages: Option<PendingMessages>,
+    /// Start time of the validator and its latest loop iteration
+    heartbeat: Option<LoopHeartbeat>,
+    /// Unit name, round id and the number of elected keys missing in the node
+    missing_keys: Vec<(&'a str, u32, usize)>,
+    events: Vec<UnitEvents<'a>>,
+    /// Whether to write metrics in the OpenMetrics format
+    openmetrics: bool,
+}
+
+impl Metrics<'_> {
+    /// Writes the counter of validator actions for all units.
+    ///
+    /// The latest action is attached as an exemplar in the OpenMetrics format,
+    /// so that the value change can be linked to the transaction.
+    fn write_events_counter<'e, I>(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        name: &str,
+        counters: I,
+    ) -> std::fmt::Result
+    where
+        I: Iterator<Item = (&'e str, &'e EventsCounter)>,
+    {
+        if self.openmetrics {
+            writeln!(f, "# TYPE {name} counter")?;
+        }
+
+        for (unit, counter) in counters {
+            write!(
+                f,
+                "{name}_total{{{UNIT_LABEL}=\"{unit}\"}} {}",
+                counter.count
+            )?;
+
+            if let (true, Some(event)) = (self.openmetrics, &counter.last) {
+                let (tx_hash, election_id) = match &event.kind {
+                    HistoryEventKind::StakeSent {
+                        tx_hash,
+                        election_id,
+                        ..
+                    } => (tx_hash, Some(election_id)),
+                    HistoryEventKind::StakeRecovered { tx_hash, .. }
+                    | HistoryEventKind::DePoolStakeAdded { tx_hash, .. }
+                    | HistoryEventKind::TicktockSent { tx_hash, .. }
+                    | HistoryEventKind::BalanceSwept { tx_hash, .. } => (tx_hash, None),
+                    // NOTE: only events with transactions are counted
+                    HistoryEventKind::RoundFinished { .. } => {
+                        f.write_str("\n")?;
+                        continue;
+                    }
+                };
+
+                write!(f, " # {{tx_hash=\"{tx_hash}\"")?;
+                if let Some(election_id) = election_id {
+                    write!(f, ",election_id=\"{election_id}\"")?;
+                }
+                write!(f, "}} 1 {}", event.timestamp)?;
+            }
+            f.write_str("\n")?;
+        }
+
+        Ok(())
+    }
+
+    fn write_eof(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.openmetrics {
+            f.write_str("# EOF\n")?;
+        }
+        Ok(())
+    }
+}
+
+/// Counter of the blocks collated by the node.
+///
+/// NOTE: The node reports only the time of the latest collated block in each shard,
+/// so a new block is counted when this time changes (i.e. several blocks of the same
+/// shard between two scrapes are counted once). Blocks collated before the exporter
+/// start are not counted.
+#[derive(Default)]
+struct BlocksProduced {
+    last_collated: Option<BTreeMap<String, u32>>,
+    total: u64,
+}
+
+impl BlocksProduced {
+    fn observe(&mut self, collation: &CollationStats) -> u64 {
+        if let Some(last_collated) = &self.last_collated {
+            self.total += collation
+                .collated
+                .iter()
+                .filter(|&(shard, time)| last_collated.get(shard) < Some(time))
+                .count() as u64;
+        }
+        self.last_collated = Some(collation.collated.clone());
+        self.total
+    }
+}
+
+/// Counters of the recorded validator actions
+struct UnitEvents<'a> {
+    unit: &'a str,
+    stakes_sent: EventsCounter,
+    stakes_recovered: EventsCounter,
+    /// Total fees paid by the wallet for all recorded actions
+    fees: u128,
+}
+
+impl<'a> UnitEvents<'a> {
+    fn from_history(unit: &'a str, history: Vec<HistoryEvent>) -> Self {
+        let mut res = Self {
+            unit,
+            stakes_sent: Default::default(),
+            stakes_recovered: Default::default(),
+            fees: 0,
+        };
+
+        for event in history {
+            res.fees += event.kind.fees().unwrap_or_default();
+
+            let counter = match &event.kind {
+                HistoryEventKind::StakeSent { .. } => &mut res.stakes_sent,
+                HistoryEventKind::StakeRecovered { .. } => &mut res.stakes_recovered,
+                HistoryEventKind::DePoolStakeAdded { .. }
+                | HistoryEventKind::TicktockSent { .. }
+                | HistoryEventKind::BalanceSwept { .. }
+                | HistoryEventKind::RoundFinished { .. } => continue,
+            };
+            counter.count += 1;
+            counter.last = Some(event);
+        }
+
+        res
+    }
+}
+
+#[derive(Default)]
+struct EventsCounter {
+    count: usize,
+    /// The latest event (used as an exemplar)
+    last: Option<HistoryEvent>,
+}
+
+/// Read-only validation status
+struct ValidatorStatus {
+    timeline: Timeline,
+    election_id: Option<u32>,
+    /// When the nearest frozen stakes are unfrozen
+    next_unfreeze_at: Option<u32>,
+    units: Vec<UnitStatus>,
+}
+
+/// Read-only status of the validation unit
+struct UnitStatus {
+    name: String,
+    wallet_balance: Option<u128>,
+    elected: Option<bool>,
+    effective_stake: Option<u128>,
+}
+
+impl ValidatorStatus {
+    async fn collect(node_rpc: &NodeTcpRpc, app_config: &AppConfig, now: u32) -> Result<Self> {
+        let ConfigWithId { config, .. } = node_rpc.get_config_all().await?;
+
+        let timings = config.elector_params().context("invalid elector params")?;
+        let current_vset = config.validator_set().context("invalid validator set")?;
+        let timeline = Timeline::compute(&timings, &current_vset, now);
+
+        let elector_address = config
+            .elector_address()
+            .context("invalid elector address")?;
+        let elector = Elector::make_address(elector_address);
+        let elector_data = match get_account_state(node_rpc, &elector).await? {
+            Some(state) => Some(ElectorData::from_state(state)?),
+            None => None,
+        };
+
+        let election_id = elector_data.as_ref().and_then(ElectorData::election_id);
+        let next_unfreeze_at = elector_data
+            .as_ref()
+            .and_then(ElectorData::next_unfreeze_at);
+
+        let mut units = Vec::new();
+        for (name, validator) in app_config.validation_units() {
+            let wallet = match validator {
+                AppConfigValidator::Single(single) => &single.address,
+                AppConfigValidator::DePool(depool) => &depool.owner,
+            };
+            let wallet_balance = get_account_state(node_rpc, wallet)
+                .await?
+                .map(|state| state.storage.balance.grams.0);
+
+            let elected = match validator {
+                AppConfigValidator::Single(single) => elector_data
+                    .as_ref()
+                    .map(|data| data.elected(&single.address)),
+                AppConfigValidator::DePool(_) => None,
+            };
+
+            // Estimate the effective stake for the next elections
+            let effective_stake = match (validator, &elector_data, elected) {
+                (AppConfigValidator::Single(single), Some(elector_data), Some(false)) => {
+                    let validators_count = config
+                        .validators_count()
+                        .context("invalid validators count params")?;
+                    let stakes_config = config.stakes_config().context("invalid stakes config")?;
+                    elector_data
+                        .compute_effective_stake(
+                            single.stake_per_round.resolve(
+                                wallet_balance.unwrap_or_default(),
+                                2 * app_config.one_coin(),
+                            ),
+                            single.stake_factor.unwrap_or(DEFAULT_STAKE_FACTOR),
+                            &validators_count,
+                            &stakes_config,
+                        )
+                        .map(|effective| effective.stake)
+                }
+                _ => None,
+            };
+
+            units.push(UnitStatus {
+                name: name.to_owned(),
+                wallet_balance,
+                elected,
+                effective_stake,
+            });
+        }
+
+        Ok(Self {
+            timeline,
+            election_id,
+            next_unfreeze_at,
+            units,
+        })
+    }
+}
+
+async fn get_account_state(
+    node_rpc: &NodeTcpRpc,
+    address: &ton_block::MsgAddressInt,
+) -> Result<Option<ton_block::AccountStuff>> {
+    let state = node_rpc.get_shard_account_state(address).await?;
+    match state.read_account()? {
+        ton_block::Account::Account(account) => Ok(Some(account)),
+        ton_block::Account::AccountNone => Ok(None),
+    }
 }
 
 impl std::fmt::Display for Metrics<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         const NODE_READY: &str = "node_ready";
         const SYNC_STATUS: &str = "sync_status";
+        const NODE_NOT_READY: &str = "node_not_ready";
 
         f.begin_metric(CONFIG_IS_VALID).value(1)?;
         f.begin_metric(EXPORTER_READY).value(1)?;
 
         f.begin_metric("collected_at").value(self.collected_at)?;
 
-        let stats = match self.stats {
-            NodeStats::NotReady(sync_status) => {
-                return f
-                    .begin_metric(NODE_READY)
-                    .label(SYNC_STATUS, sync_status)
-                    .value(0)
+        if let Some(heartbeat) = &self.heartbeat {
+            f.begin_metric("validator_start_timestamp")
+                .value(heartbeat.started_at)?;
+            f.begin_metric("validator_last_loop_iteration_timestamp")
+                .value(heartbeat.last_iteration_at)?;
+            if let Some(next_iteration_at) = heartbeat.next_iteration_at {
+                f.begin_metric("validator_next_loop_iteration_timestamp")
+                    .value(next_iteration_at)?;
+            }
+            f.begin_metric("validator_elections_skipped_total")
+                .value(heartbeat.elections_skipped)?;
+        }
+
+        if !self.events.is_empty() {
+            let events = &self.events;
+            self.write_events_counter(
+                f,
+                "validator_stakes_sent",
+                events.iter().map(|e| (e.unit, &e.stakes_sent)),
+            )?;
+            self.write_events_counter(
+                f,
+                "validator_stakes_recovered",
+                events.iter().map(|e| (e.unit, &e.stakes_recovered)),
+            )?;
+            for events in events {
+                f.begin_metric("validator_fees_total")
+                    .label(UNIT_LABEL, events.unit)
+                    .value(events.fees)?;
+            }
+        }
+
+        let stats = match &self.stats {
+            NodeStats::NotReady(stats) => {
+                f.begin_metric(NODE_READY)
+                    .label(SYNC_STATUS, stats.sync_status)
+                    .value(0)?;
+                f.begin_metric("node_needs_intervention")
+                    .value(stats.needs_intervention as u8)?;
+                if let Some(mc_time_diff) = stats.mc_time_diff {
+                    f.begin_metric("mc_time_diff").value(mc_time_diff)?;
+                }
+                if let Some(sc_time_diff) = stats.sc_time_diff {
+                    f.begin_metric("sc_time_diff").value(sc_time_diff)?;
+                }
+                f.begin_metric(NODE_NOT_READY)
+                    .value(self.node_not_ready.is_some() as u8)?;
+                if let Some(since) = self.node_not_ready {
+                    f.begin_metric("node_not_ready_seconds")
+                        .value(self.collected_at.saturating_sub(since))?;
+                }
+                return self.write_eof(f);
             }
             NodeStats::Running(stats) => {
                 f.begin_metric(NODE_READY)
                     .label(SYNC_STATUS, stats.sync_status)
                     .value(1)?;
+                f.begin_metric(NODE_NOT_READY).value(0)?;
                 stats
             }
         };
@@ -199,28 +675,142 @@ impl std::fmt::Display for Metrics<'_> {
         const VALIDATION_ENABLED: &str = "validation_enabled";
         const VALIDATOR_TYPE: &str = "validator_type";
 
-        if let Some(validator) = &self.config.validator {
-            f.begin_metric(VALIDATION_ENABLED).value(1)?;
+        let mut validation_enabled = false;
+        for (unit, validator) in self.config.validation_units() {
+            validation_enabled = true;
             match validator {
                 AppConfigValidator::Single(single) => {
-                    f.begin_metric(VALIDATOR_TYPE).value(0)?;
-                    f.begin_metric("validator_single_stake_per_round")
-                        .label("validator", &single.address)
-                        .value(single.stake_per_round)?;
+                    f.begin_metric(VALIDATOR_TYPE)
+                        .label(UNIT_LABEL, unit)
+                        .value(0)?;
+                    match single.stake_per_round {
+                        StakeAmount::Fixed(amount) => {
+                            f.begin_metric("validator_single_stake_per_round")
+                                .label(UNIT_LABEL, unit)
+                                .label("validator", &single.address)
+                                .value(amount)?;
+                        }
+                        StakeAmount::Percent(percent) => {
+                            f.begin_metric("validator_single_stake_percent")
+                                .label(UNIT_LABEL, unit)
+                                .label("validator", &single.address)
+                                .value(percent)?;
+                        }
+                    }
                 }
                 AppConfigValidator::DePool(depool) => {
-                    f.begin_metric(VALIDATOR_TYPE).value(1)?;
+                    f.begin_metric(VALIDATOR_TYPE)
+                        .label(UNIT_LABEL, unit)
+                        .value(1)?;
                     f.begin_metric("validator_depool_type")
+                        .label(UNIT_LABEL, unit)
                         .label("validator", &depool.owner)
                         .label("depool", &depool.depool)
                         .value(depool.depool_type.into_u8())?;
                 }
             }
-        } else {
-            f.begin_metric(VALIDATION_ENABLED).value(0)?;
         }
+        f.begin_metric(VALIDATION_ENABLED)
+            .value(validation_enabled as u8)?;
+        f.begin_metric("validation_paused")
+            .value(self.paused as u8)?;
+
+        if let Some(status) = &self.status {
+            const TIMELINE_PHASE: &str = "timeline_phase";
+            const TIMELINE_REMAINING: &str = "timeline_remaining";
+
+            let (phase, remaining) = match status.timeline {
+                Timeline::BeforeElections {
+                    until_elections_start,
+                } => (0, until_elections_start),
+                Timeline::Elections {
+                    until_elections_end,
+                    ..
+                } => (1, until_elections_end),
+                Timeline::AfterElections { until_round_end } => (2, until_round_end),
+            };
+            f.begin_metric(TIMELINE_PHASE).value(phase)?;
+            f.begin_metric(TIMELINE_REMAINING).value(remaining)?;
 
-        Ok(())
+            if let Some(election_id) = status.election_id {
+                f.begin_metric("elector_election_id").value(election_id)?;
+            }
+
+            if let Some(unfreeze_at) = status.next_unfreeze_at {
+                f.begin_metric("next_unfreeze_timestamp")
+                    .value(unfreeze_at)?;
+            }
+
+            for unit in &status.units {
+                if let Some(balance) = unit.wallet_balance {
+                    f.begin_metric("validator_wallet_balance")
+                        .label(UNIT_LABEL, &unit.name)
+                        .value(balance)?;
+                }
+                if let Some(elected) = unit.elected {
+                    f.begin_metric("validator_elected")
+                        .label(UNIT_LABEL, &unit.name)
+                        .value(elected as u8)?;
+                }
+                if let Some(stake) = unit.effective_stake {
+                    f.begin_metric("validator_effective_stake")
+                        .label(UNIT_LABEL, &unit.name)
+                        .value(stake)?;
+                }
+            }
+        }
+
+        if let Some(deadline) = self.elections_deadline {
+            f.begin_metric("elections_deadline_timestamp")
+                .value(deadline)?;
+        }
+
+        if let Some(pending) = &self.pending_messages {
+            const PENDING_MESSAGES: &str = "pending_messages";
+            f.begin_metric(PENDING_MESSAGES)
+                .label("workchain", ton_block::MASTERCHAIN_ID)
+                .value(pending.masterchain)?;
+            f.begin_metric(PENDING_MESSAGES)
+                .label("workchain", ton_block::BASE_WORKCHAIN_ID)
+                .value(pending.basechain)?;
+
+            if let Some(sent_at) = pending.oldest_sent_at {
+                f.begin_metric("oldest_pending_message_age_seconds")
+                    .value(self.collected_at.saturating_sub(sent_at))?;
+            }
+
+            f.begin_metric("dropped_transactions_total")
+                .value(pending.dropped_transactions)?;
+        }
+
+        if let Some(collation) = &self.collation {
+            const SHARD_LABEL: &str = "shard";
+
+            for (shard, time) in &collation.collated {
+                f.begin_metric("validator_last_collated_timestamp")
+                    .label(SHARD_LABEL, shard)
+                    .value(*time)?;
+            }
+            for (shard, time) in &collation.validated {
+                f.begin_metric("validator_last_validated_timestamp")
+                    .label(SHARD_LABEL, shard)
+                    .value(*time)?;
+            }
+        }
+
+        if let Some(blocks_produced) = self.blocks_produced {
+            f.begin_metric("blocks_produced_total")
+                .value(blocks_produced)?;
+        }
+
+        for &(unit, round_id, count) in &self.missing_keys {
+            f.begin_metric("validator_keys_missing")
+                .label(UNIT_LABEL, unit)
+                .label("round_id", round_id)
+                .value(count)?;
+        }
+
+        self.write_eof(f)
     }
 }
 
@@ -236,3 +826,7 @@ impl DePoolType {
 
 const CONFIG_IS_VALID: &str = "config_is_valid";
 const EXPORTER_READY: &str = "exporter_ready";
+const UNIT_LABEL: &str = "unit";
+
+/// Max age of the pending messages snapshot in seconds
+const MAX_PENDING_MESSAGES_LAG: u32 = 60;
@@ -1,5 +1,3 @@
-use anyhow::Result;
-
 #[macro_export]
 macro_rules! once {
     ($ty:path, || $expr:expr) => {{
@@ -44,14 +42,23 @@ mod util;
 mod validator;
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() {
     if console::user_attended() {
         tracing_subscriber::fmt::init();
     } else {
         tracing_subscriber::fmt::fmt().without_time().init();
     }
 
-    argh::from_env::<ArgsOrVersion<cli::App>>().0.run().await
+    if let Err(e) = argh::from_env::<ArgsOrVersion<cli::App>>().0.run().await {
+        eprintln!("Error: {e:?}");
+
+        // Use distinct exit codes for known failures to simplify automation
+        let exit_code = match validator::ValidationError::classify(&e) {
+            Some(class) => class.exit_code(),
+            None => 1,
+        };
+        std::process::exit(exit_code);
+    }
 }
 
 struct ArgsOrVersion<T: argh::FromArgs>(T);
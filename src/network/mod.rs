@@ -1,6 +1,8 @@
 pub use self::node_tcp_rpc::*;
-pub use self::node_udp_rpc::NodeUdpRpc;
-pub use self::subscription::Subscription;
+pub use self::node_udp_rpc::{NodeUdpRpc, NodeUdpRpcError};
+pub use self::subscription::{
+    AccountStatus, CatchUpFrom, PendingMessages, Subscription, TransactionStatus, TransactionsRx,
+};
 
 mod node_tcp_rpc;
 mod node_udp_rpc;
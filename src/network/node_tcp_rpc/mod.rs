@@ -6,9 +6,12 @@ use tl_proto::{IntermediateBytes, TlRead, TlWrite};
 use ton_block::Deserializable;
 
 use self::stats::StatsError;
-pub use self::stats::{NodeStats, RunningStats, ValidatorSetEntry};
+pub use self::stats::{
+    CollationStats, NodeStats, NotReadyStats, RunningStats, SyncStatus, ValidatorSetEntry,
+};
 use self::tcp_adnl::{TcpAdnl, TcpAdnlConfig, TcpAdnlError};
 use crate::config::AppConfigControl;
+use crate::util::happy_eyeballs;
 
 mod proto;
 mod stats;
@@ -22,11 +25,21 @@ pub struct NodeTcpRpc {
 
 impl NodeTcpRpc {
     pub async fn new(config: &AppConfigControl) -> Result<Self> {
-        let tcp_adnl = TcpAdnl::connect(TcpAdnlConfig {
-            server_address: config.server_address.into(),
-            server_pubkey: config.server_pubkey,
-            client_secret: config.client_secret,
-            connection_timeout: config.connection_timeout,
+        let candidates = config.server_candidates();
+        anyhow::ensure!(
+            !candidates.is_empty(),
+            "control server has no {} addresses",
+            config.address_family
+        );
+
+        // NOTE: the first responding address is used if there are several of them
+        let tcp_adnl = happy_eyeballs(&candidates, |server_address| {
+            TcpAdnl::connect(TcpAdnlConfig {
+                server_address,
+                server_pubkey: config.server_pubkey,
+                client_secret: config.client_secret,
+                connection_timeout: config.connection_timeout,
+            })
         })
         .await
         .map_err(NodeRpcError::ConnectionFailed)?;
@@ -51,6 +64,21 @@ impl NodeTcpRpc {
             .ok_or_else(|| NodeRpcError::InvalidPubkey.into())
     }
 
+    /// Checks whether the node keystore contains the specified key
+    pub async fn has_key(&self, key_hash: &[u8; 32]) -> Result<bool> {
+        match self.export_public_key(key_hash).await {
+            Ok(_) => Ok(true),
+            // NOTE: node responds with an error object for unknown keys
+            Err(e)
+                if e.downcast_ref::<NodeRpcError>()
+                    .map_or(false, NodeRpcError::is_key_not_found) =>
+            {
+                Ok(false)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
     pub async fn sign(&self, key_hash: &[u8; 32], data: &[u8]) -> Result<[u8; 64]> {
         let proto::Signature { signature } = self.query(proto::Sign { key_hash, data }).await?;
         signature
@@ -93,6 +121,28 @@ impl NodeTcpRpc {
         NodeStats::try_from(stats).map_err(|e| NodeRpcError::InvalidStats(e).into())
     }
 
+    /// Returns the latest block collation and validation times of the node.
+    ///
+    /// NOTE: The node reports only the time of the latest block in each shard,
+    /// `None` is returned if it doesn't report them at all.
+    pub async fn get_collation_stats(&self) -> Result<Option<CollationStats>> {
+        let stats = self.query::<_, proto::Stats>(proto::GetStats).await?;
+        CollationStats::try_from_stats(&stats).map_err(|e| NodeRpcError::InvalidStats(e).into())
+    }
+
+    /// Returns the node stats and the collation stats parsed from the same response.
+    ///
+    /// NOTE: Invalid collation stats don't fail the node stats
+    pub async fn get_stats_with_collation(
+        &self,
+    ) -> Result<(NodeStats, Result<Option<CollationStats>>)> {
+        let stats = self.query::<_, proto::Stats>(proto::GetStats).await?;
+        let collation = CollationStats::try_from_stats(&stats)
+            .map_err(|e| NodeRpcError::InvalidStats(e).into());
+        let stats = NodeStats::try_from(stats).map_err(NodeRpcError::InvalidStats)?;
+        Ok((stats, collation))
+    }
+
     pub async fn set_states_gc_interval(&self, interval_ms: u32) -> Result<()> {
         self.query(proto::SetStatesGcInterval { interval_ms })
             .await
@@ -125,6 +175,15 @@ impl NodeTcpRpc {
         })
     }
 
+    /// Returns the blockchain config with all params required for the elections.
+    ///
+    /// NOTE: The node can fail to assemble a complete config, the missing param
+    /// is reported as [`NodeRpcError::InvalidConfigParam`].
+    pub async fn get_elections_config(&self) -> Result<ElectionsConfig> {
+        let config = self.get_config_all().await?;
+        ElectionsConfig::try_from(config).map_err(Into::into)
+    }
+
     pub async fn get_config_param(&self, param: u32) -> Result<ConfigParamWithId> {
         let proto::ConfigInfo {
             id, config_proof, ..
@@ -152,13 +211,31 @@ impl NodeTcpRpc {
             })
             .await?;
 
-        match shard_account {
-            proto::ShardAccount::State(data) => {
-                ton_block::ShardAccount::construct_from_bytes(&data)
-                    .map_err(|_| NodeRpcError::InvalidAccountState.into())
-            }
-            proto::ShardAccount::Empty => Ok(ton_block::ShardAccount::default()),
-        }
+        parse_shard_account(shard_account)
+    }
+
+    /// Returns the account state as of the specified block.
+    ///
+    /// NOTE: The node must support `raw.getAccountByBlock` and still have the block state.
+    pub async fn get_shard_account_state_at(
+        &self,
+        address: &ton_block::MsgAddressInt,
+        block_id: &ton_block::BlockIdExt,
+    ) -> Result<ton_block::ShardAccount> {
+        let shard_account = self
+            .query::<_, proto::ShardAccount>(proto::GetAccountByBlock {
+                block: proto::BlockIdExt {
+                    workchain: block_id.shard_id.workchain_id(),
+                    shard: block_id.shard_id.shard_prefix_with_tag(),
+                    seqno: block_id.seq_no,
+                    root_hash: block_id.root_hash.as_slice(),
+                    file_hash: block_id.file_hash.as_slice(),
+                },
+                address: address.to_string().as_bytes(),
+            })
+            .await?;
+
+        parse_shard_account(shard_account)
     }
 
     async fn query<Q, R>(&self, query: Q) -> Result<R>
@@ -174,13 +251,26 @@ impl NodeTcpRpc {
             )
             .await
         {
-            Ok(Some(data)) => Ok(data),
+            Ok(Some(proto::ControlAnswer::Ok(data))) => Ok(data),
+            Ok(Some(proto::ControlAnswer::Error(e))) => Err(NodeRpcError::QueryRejected {
+                code: e.code,
+                message: String::from_utf8_lossy(&e.message).into_owned(),
+            }
+            .into()),
             Ok(None) => Err(NodeRpcError::QueryTimeout.into()),
             Err(e) => Err(NodeRpcError::QueryFailed(e).into()),
         }
     }
 }
 
+fn parse_shard_account(shard_account: proto::ShardAccount) -> Result<ton_block::ShardAccount> {
+    match shard_account {
+        proto::ShardAccount::State(data) => ton_block::ShardAccount::construct_from_bytes(&data)
+            .map_err(|_| NodeRpcError::InvalidAccountState.into()),
+        proto::ShardAccount::Empty => Ok(ton_block::ShardAccount::default()),
+    }
+}
+
 fn convert_proto_to_block_id(
     id: proto::BlockIdExtOwned,
 ) -> Result<ton_block::BlockIdExt, NodeRpcError> {
@@ -198,6 +288,54 @@ pub struct ConfigWithId {
     pub config: ton_block::ConfigParams,
 }
 
+/// Blockchain config with the params required for the elections
+pub struct ElectionsConfig {
+    pub block_id: ton_block::BlockIdExt,
+    pub config: ton_block::ConfigParams,
+    pub elector_address: ton_types::UInt256,
+    pub timings: ton_block::ConfigParam15,
+    pub validators_count: ton_block::ConfigParam16,
+    pub stakes_config: ton_block::ConfigParam17,
+    pub current_vset: ton_block::ValidatorSet,
+}
+
+impl TryFrom<ConfigWithId> for ElectionsConfig {
+    type Error = NodeRpcError;
+
+    fn try_from(ConfigWithId { block_id, config }: ConfigWithId) -> Result<Self, Self::Error> {
+        fn invalid<E: std::fmt::Display>(
+            param: u32,
+            name: &'static str,
+        ) -> impl FnOnce(E) -> NodeRpcError {
+            move |e| NodeRpcError::InvalidConfigParam {
+                param,
+                name,
+                reason: e.to_string(),
+            }
+        }
+
+        Ok(Self {
+            elector_address: config
+                .elector_address()
+                .map_err(invalid(1, "elector address"))?,
+            timings: config
+                .elector_params()
+                .map_err(invalid(15, "elector params"))?,
+            validators_count: config
+                .validators_count()
+                .map_err(invalid(16, "validators count"))?,
+            stakes_config: config
+                .stakes_config()
+                .map_err(invalid(17, "stakes config"))?,
+            current_vset: config
+                .validator_set()
+                .map_err(invalid(34, "current validator set"))?,
+            block_id,
+            config,
+        })
+    }
+}
+
 pub struct ConfigParamWithId {
     pub block_id: ton_block::BlockIdExt,
     pub param: String,
@@ -205,12 +343,17 @@ pub struct ConfigParamWithId {
 
 fn expect_success(_: proto::Success) {}
 
+/// Error message of the node keystore for unknown key hashes
+const KEY_NOT_FOUND_ERROR: &str = "key not found";
+
 #[derive(thiserror::Error, Debug)]
 pub enum NodeRpcError {
     #[error("connection failed")]
     ConnectionFailed(#[source] TcpAdnlError),
     #[error("query failed")]
     QueryFailed(#[source] TcpAdnlError),
+    #[error("query rejected by the node (code {code}): {message}")]
+    QueryRejected { code: i32, message: String },
     #[error("query timeout")]
     QueryTimeout,
     #[error("invalid stats")]
@@ -227,4 +370,24 @@ pub enum NodeRpcError {
     InvalidBlockId,
     #[error("invalid blockchain config")]
     InvalidBlockchainConfig,
+    #[error("config param {param} ({name}) is missing or invalid: {reason}")]
+    InvalidConfigParam {
+        param: u32,
+        name: &'static str,
+        reason: String,
+    },
+}
+
+impl NodeRpcError {
+    pub fn is_socket_closed(&self) -> bool {
+        matches!(self, Self::QueryFailed(TcpAdnlError::SocketClosed))
+    }
+
+    /// Whether the node rejected the query because the requested key is not in its keystore
+    pub fn is_key_not_found(&self) -> bool {
+        match self {
+            Self::QueryRejected { message, .. } => message.contains(KEY_NOT_FOUND_ERROR),
+            _ => false,
+        }
+    }
 }
@@ -1,4 +1,4 @@
-use tl_proto::{IntermediateBytes, TlRead, TlWrite};
+use tl_proto::{IntermediateBytes, TlRead, TlResult, TlWrite};
 
 #[derive(TlWrite)]
 #[tl(boxed, id = "engine.validator.controlQuery", scheme = "proto.tl")]
@@ -94,6 +94,13 @@ pub struct GetShardAccountState<'tl> {
     pub address: &'tl [u8],
 }
 
+#[derive(Copy, Clone, TlWrite)]
+#[tl(boxed, id = "raw.getAccountByBlock", scheme = "proto.tl")]
+pub struct GetAccountByBlock<'tl> {
+    pub block: BlockIdExt<'tl>,
+    pub address: &'tl [u8],
+}
+
 #[derive(Copy, Clone, TlRead, TlWrite)]
 #[tl(size_hint = 80)]
 pub struct BlockIdExt<'tl> {
@@ -172,4 +179,33 @@ pub enum ShardAccount {
     Empty,
 }
 
+/// Control query answer which can be replaced with an error object by the node
+pub enum ControlAnswer<T> {
+    Ok(T),
+    Error(ControlQueryError),
+}
+
+impl<'tl, T> TlRead<'tl> for ControlAnswer<T>
+where
+    T: TlRead<'tl>,
+{
+    type Repr = tl_proto::Boxed;
+
+    fn read_from(packet: &'tl [u8], offset: &mut usize) -> TlResult<Self> {
+        let mut peek_offset = *offset;
+        if u32::read_from(packet, &mut peek_offset)? == ControlQueryError::TL_ID {
+            ControlQueryError::read_from(packet, offset).map(Self::Error)
+        } else {
+            T::read_from(packet, offset).map(Self::Ok)
+        }
+    }
+}
+
+#[derive(Clone, TlRead)]
+#[tl(boxed, id = "engine.validator.controlQueryError", scheme = "proto.tl")]
+pub struct ControlQueryError {
+    pub code: i32,
+    pub message: Vec<u8>,
+}
+
 pub type HashRef<'tl> = &'tl [u8; 32];
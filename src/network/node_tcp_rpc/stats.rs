@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::str::FromStr;
 
 use broxus_util::{serde_base64_array, serde_hex_array};
@@ -11,18 +12,63 @@ use crate::util::serde_block_id;
 #[serde(rename_all = "snake_case", tag = "state")]
 pub enum NodeStats {
     Running(RunningStats),
-    NotReady(SyncStatus),
+    NotReady(NotReadyStats),
 }
 
 impl NodeStats {
     pub fn try_into_running(self) -> Result<RunningStats, StatsError> {
         match self {
             Self::Running(stats) => Ok(stats),
-            Self::NotReady(_) => Err(StatsError::NotReady),
+            Self::NotReady(stats) => Err(StatsError::NotReady(stats.sync_status)),
         }
     }
 }
 
+/// Partial stats of the node which is not synced yet
+#[derive(Clone, Debug, Serialize)]
+pub struct NotReadyStats {
+    pub sync_status: SyncStatus,
+    /// Human-readable explanation of the sync status
+    pub reason: &'static str,
+    /// Whether the node can't become ready without the operator
+    pub needs_intervention: bool,
+    pub node_version: Option<NodeVersion>,
+    /// Present only when the node already applies blocks
+    pub mc_time_diff: Option<i32>,
+    pub sc_time_diff: Option<i32>,
+}
+
+impl NotReadyStats {
+    fn new(
+        sync_status: SyncStatus,
+        node_version: Option<NodeVersion>,
+        mc_time_diff: Option<i32>,
+        sc_time_diff: Option<i32>,
+    ) -> Self {
+        Self {
+            sync_status,
+            reason: sync_status.reason(),
+            needs_intervention: sync_status.needs_intervention(),
+            node_version,
+            mc_time_diff,
+            sc_time_diff,
+        }
+    }
+}
+
+impl std::fmt::Display for NotReadyStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.sync_status, self.reason)?;
+        if let Some(mc_time_diff) = self.mc_time_diff {
+            write!(f, ", mc_time_diff: {mc_time_diff}s")?;
+        }
+        if let Some(sc_time_diff) = self.sc_time_diff {
+            write!(f, ", sc_time_diff: {sc_time_diff}s")?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone, Debug, Serialize)]
 pub struct RunningStats {
     pub sync_status: SyncStatus,
@@ -142,7 +188,12 @@ impl TryFrom<proto::Stats> for NodeStats {
 
         let sync_status = sync_status.unwrap_or(SyncStatus::NoSetStatus);
         if sync_status != SyncStatus::SynchronizationFinished {
-            return Ok(Self::NotReady(sync_status));
+            return Ok(Self::NotReady(NotReadyStats::new(
+                sync_status,
+                node_version,
+                mc_time_diff,
+                sc_time_diff,
+            )));
         }
 
         match (
@@ -190,6 +241,52 @@ impl TryFrom<proto::Stats> for NodeStats {
     }
 }
 
+/// Latest block collation and validation times of the node (per shard)
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct CollationStats {
+    pub collated: BTreeMap<String, u32>,
+    pub validated: BTreeMap<String, u32>,
+}
+
+impl CollationStats {
+    /// Extracts collation stats from the node stats.
+    ///
+    /// Returns `None` if the node doesn't report them
+    pub fn try_from_stats(stats: &proto::Stats) -> Result<Option<Self>, StatsError> {
+        let mut collated = None;
+        let mut validated = None;
+        for item in &stats.items {
+            let target = match item.key.as_slice() {
+                STATS_COLLATION_STATS => &mut collated,
+                STATS_VALIDATION_STATS => &mut validated,
+                _ => continue,
+            };
+            *target = Some(
+                serde_json::from_slice::<BTreeMap<String, u32>>(&item.value)
+                    .map_err(|_| StatsError::InvalidValue)?,
+            );
+        }
+
+        Ok(match (collated, validated) {
+            (None, None) => None,
+            (collated, validated) => Some(Self {
+                collated: collated.unwrap_or_default(),
+                validated: validated.unwrap_or_default(),
+            }),
+        })
+    }
+
+    /// Latest collation time in any shard
+    pub fn last_collated(&self) -> Option<u32> {
+        self.collated.values().copied().max()
+    }
+
+    /// Latest validation time in any shard
+    pub fn last_validated(&self) -> Option<u32> {
+        self.validated.values().copied().max()
+    }
+}
+
 #[derive(Copy, Clone, Debug, Serialize)]
 pub struct NodeVersion {
     pub major: u32,
@@ -247,6 +344,28 @@ pub enum SyncStatus {
     NoSetStatus,
 }
 
+impl SyncStatus {
+    /// Explains what the node is doing in this state
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::StartBoot => "node is starting up",
+            Self::LoadMasterState => "loading masterchain state",
+            Self::LoadShardStates => "loading shard states",
+            Self::FinishBoot => "finishing boot",
+            Self::SynchronizationByBlocks => "catching up with the network",
+            Self::SynchronizationFinished => "synced",
+            Self::CheckingDb => "checking database",
+            Self::DbBroken => "database is broken",
+            Self::NoSetStatus => "sync status is not reported yet",
+        }
+    }
+
+    /// Whether the node can't leave this state by itself
+    pub fn needs_intervention(&self) -> bool {
+        matches!(self, Self::DbBroken)
+    }
+}
+
 impl std::fmt::Display for SyncStatus {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         std::fmt::Debug::fmt(self, f)
@@ -255,8 +374,8 @@ impl std::fmt::Display for SyncStatus {
 
 #[derive(thiserror::Error, Debug)]
 pub enum StatsError {
-    #[error("node is not ready")]
-    NotReady,
+    #[error("node is not ready ({0}: {})", .0.reason())]
+    NotReady(SyncStatus),
     #[error("invalid value")]
     InvalidValue,
     #[error("fields missing")]
@@ -274,3 +393,5 @@ const STATS_CURRENT_VSET_ADNL: &[u8] = b"current_vset_p34_adnl_id";
 const STATS_IN_NEXT_VSET: &[u8] = b"in_next_vset_p36";
 const STATS_NEXT_VSET_ADNL: &[u8] = b"next_vset_p36_adnl_id";
 const STATS_LAST_APPLIED_MC_BLOCK: &[u8] = b"last_applied_masterchain_block_id";
+const STATS_COLLATION_STATS: &[u8] = b"collation_stats";
+const STATS_VALIDATION_STATS: &[u8] = b"validation_stats";
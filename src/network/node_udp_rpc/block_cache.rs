@@ -0,0 +1,87 @@
+use std::collections::BTreeMap;
+
+use rustc_hash::FxHashMap;
+
+use crate::util::BlockStuff;
+
+/// LRU cache of the downloaded blocks, bounded by the total size of their data
+pub struct BlockCache {
+    /// Max total size of the cached blocks in bytes
+    capacity: usize,
+    /// Total size of the cached blocks in bytes
+    used: usize,
+    /// Monotonic counter of cache accesses
+    tick: u64,
+    entries: FxHashMap<ton_block::BlockIdExt, CacheEntry>,
+    /// Block ids ordered by the last access
+    lru: BTreeMap<u64, ton_block::BlockIdExt>,
+}
+
+struct CacheEntry {
+    block: BlockStuff,
+    size: usize,
+    last_used: u64,
+}
+
+impl BlockCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            used: 0,
+            tick: 0,
+            entries: Default::default(),
+            lru: Default::default(),
+        }
+    }
+
+    pub fn get(&mut self, id: &ton_block::BlockIdExt) -> Option<BlockStuff> {
+        let tick = self.next_tick();
+        let entry = self.entries.get_mut(id)?;
+
+        self.lru.remove(&entry.last_used);
+        self.lru.insert(tick, id.clone());
+        entry.last_used = tick;
+
+        Some(entry.block.clone())
+    }
+
+    /// Inserts the block with its data size, evicting the least recently used ones
+    pub fn insert(&mut self, block: &BlockStuff, size: usize) {
+        // NOTE: blocks larger than the whole cache are not cached at all
+        if size > self.capacity {
+            return;
+        }
+
+        self.remove(block.id());
+        while self.used + size > self.capacity {
+            let Some((_, id)) = self.lru.pop_first() else { break };
+            if let Some(entry) = self.entries.remove(&id) {
+                self.used -= entry.size;
+            }
+        }
+
+        let tick = self.next_tick();
+        self.lru.insert(tick, block.id().clone());
+        self.entries.insert(
+            block.id().clone(),
+            CacheEntry {
+                block: block.clone(),
+                size,
+                last_used: tick,
+            },
+        );
+        self.used += size;
+    }
+
+    pub fn remove(&mut self, id: &ton_block::BlockIdExt) {
+        if let Some(entry) = self.entries.remove(id) {
+            self.lru.remove(&entry.last_used);
+            self.used -= entry.size;
+        }
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
@@ -1,4 +1,4 @@
-use std::net::SocketAddrV4;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -8,21 +8,44 @@ use parking_lot::Mutex;
 use rand::Rng;
 use tl_proto::{TlRead, TlWrite};
 
+use self::block_cache::BlockCache;
+use self::udp_relay::UdpRelay;
 use crate::config::AppConfigAdnl;
 use crate::util::BlockStuff;
 
+mod block_cache;
 mod proto;
+mod udp_relay;
+
 #[derive(Clone)]
 pub struct NodeUdpRpc {
     inner: Arc<NodeInner>,
+    refetch_mismatched_blocks: bool,
+    idle_linger: Duration,
 }
 
 impl NodeUdpRpc {
     pub async fn new(config: &AppConfigAdnl) -> Result<Self> {
+        // Select the node address
+        let server_address = select_server_address(config)?;
+
+        // Reach IPv6 nodes through the local relay
+        let (peer_address, relay) = match server_address {
+            SocketAddr::V4(addr) => (addr, None),
+            SocketAddr::V6(addr) => {
+                let relay = UdpRelay::bind(addr).await?;
+                (relay.local_addr(), Some(relay))
+            }
+        };
+
         // Resolve public ip
-        let ip_addr = public_ip::addr_v4()
-            .await
-            .context("failed to resolve public ip")?;
+        let ip_addr = match (public_ip::addr_v4().await, &relay) {
+            (Some(ip_addr), _) => ip_addr,
+            // NOTE: hosts without a public IPv4 address can't advertise it,
+            // the node answers to the relay from which it receives the queries
+            (None, Some(_)) => Ipv4Addr::LOCALHOST,
+            (None, None) => anyhow::bail!("failed to resolve public ip"),
+        };
 
         // Build keystore
         let keystore = adnl::Keystore::builder()
@@ -67,7 +90,7 @@ impl NodeUdpRpc {
             adnl::NewPeerContext::Dht,
             &local_id,
             &peer_id,
-            config.server_address,
+            peer_address,
             peer_id_full,
         )
         .context("failed to add server as a peer")?;
@@ -81,12 +104,39 @@ impl NodeUdpRpc {
                 adnl,
                 rldp,
                 roundtrip: Default::default(),
+                block_cache: Mutex::new(BlockCache::new(config.block_cache_size)),
+                connection_timeout: config.connection_timeout,
+                query_timeout: config.query_timeout,
+                query_retries: config.query_retries,
+                _relay: relay,
             }),
+            refetch_mismatched_blocks: config.refetch_mismatched_blocks,
+            idle_linger: config.idle_linger,
         })
     }
 
+    /// Whether the downloaded blocks must be checked against the expected ids
+    pub fn refetch_mismatched_blocks(&self) -> bool {
+        self.refetch_mismatched_blocks
+    }
+
+    /// How long blocks are still tracked without subscriptions
+    pub fn idle_linger(&self) -> Duration {
+        self.idle_linger
+    }
+
+    /// Removes the block from the cache, so that it will be downloaded again
+    pub fn forget_block(&self, block_id: &ton_block::BlockIdExt) {
+        self.inner.block_cache.lock().remove(block_id);
+    }
+
     pub async fn get_capabilities(&self) -> Result<proto::Capabilities> {
-        self.inner.adnl_query(proto::GetCapabilities, 1000).await
+        let timeout = self.inner.connection_timeout;
+        let capabilities = self
+            .inner
+            .adnl_query(proto::GetCapabilities, timeout)
+            .await?;
+        Ok(capabilities)
     }
 
     /// Waits for the next block
@@ -108,7 +158,12 @@ impl NodeUdpRpc {
                 // Received valid block
                 Some(Ok(proto::DataFull::Found {
                     block_id, block, ..
-                })) => break BlockStuff::new(block, block_id),
+                })) => {
+                    let size = block.len();
+                    let block = BlockStuff::new(block, block_id)?;
+                    self.inner.block_cache.lock().insert(&block, size);
+                    break Ok(block);
+                }
                 // Received invalid response
                 Some(Err(e)) => break Err(e.into()),
                 // Received empty response or nothing (due to timeout)
@@ -121,13 +176,17 @@ impl NodeUdpRpc {
         }
     }
 
-    /// Polls the server for the specified block
+    /// Polls the server for the specified block (or takes it from the cache)
     pub async fn get_block(&self, block_id: &ton_block::BlockIdExt) -> Result<BlockStuff> {
+        if let Some(block) = self.inner.block_cache.lock().get(block_id) {
+            return Ok(block);
+        }
+
         let mut timeouts = BLOCK_TIMEOUTS;
         loop {
             match self
                 .inner
-                .adnl_query(proto::PrepareBlock { block_id }, 1000)
+                .adnl_query(proto::PrepareBlock { block_id }, self.inner.query_timeout)
                 .await?
             {
                 proto::Prepared::Found => break,
@@ -147,7 +206,11 @@ impl NodeUdpRpc {
                 .await?;
 
             match data {
-                Some(block) => break BlockStuff::new(&block, block_id.clone()),
+                Some(data) => {
+                    let block = BlockStuff::new(&data, block_id.clone())?;
+                    self.inner.block_cache.lock().insert(&block, data.len());
+                    break Ok(block);
+                }
                 None => {
                     tracing::debug!("block receiver timeout");
                     timeouts.sleep_and_update().await;
@@ -165,24 +228,42 @@ struct NodeInner {
     adnl: Arc<adnl::Node>,
     rldp: Arc<rldp::Node>,
     roundtrip: Mutex<u64>,
+    block_cache: Mutex<BlockCache>,
+    connection_timeout: Duration,
+    query_timeout: Duration,
+    query_retries: u32,
+    _relay: Option<UdpRelay>,
 }
 
 impl NodeInner {
-    async fn adnl_query<Q, R>(&self, query: Q, timeout: u64) -> Result<R>
+    /// Sends the query, repeating it on timeouts
+    async fn adnl_query<Q, R>(&self, query: Q, timeout: Duration) -> Result<R, NodeUdpRpcError>
     where
-        Q: TlWrite,
+        Q: TlWrite + Copy,
         for<'a> R: TlRead<'a, Repr = tl_proto::Boxed> + 'static,
     {
-        self.adnl
-            .query_with_prefix(
-                &self.local_id,
-                &self.peer_id,
-                &self.query_prefix,
-                query,
-                Some(timeout),
-            )
-            .await?
-            .context("timeout")
+        let timeout = timeout.as_millis() as u64;
+        for attempt in 0..=self.query_retries {
+            if attempt > 0 {
+                tracing::debug!(attempt, "retrying timed out ADNL query");
+            }
+
+            let answer = self
+                .adnl
+                .query_with_prefix(
+                    &self.local_id,
+                    &self.peer_id,
+                    &self.query_prefix,
+                    query,
+                    Some(timeout),
+                )
+                .await
+                .map_err(NodeUdpRpcError::QueryFailed)?;
+            if let Some(answer) = answer {
+                return Ok(answer);
+            }
+        }
+        Err(NodeUdpRpcError::QueryTimeout)
     }
 
     async fn rldp_query<Q>(&self, query: Q, attempt: u64) -> Result<Option<Vec<u8>>>
@@ -229,6 +310,24 @@ impl Drop for NodeInner {
     }
 }
 
+#[derive(thiserror::Error, Debug)]
+pub enum NodeUdpRpcError {
+    #[error("ADNL query timeout")]
+    QueryTimeout,
+    #[error("ADNL query failed")]
+    QueryFailed(#[source] anyhow::Error),
+}
+
+impl NodeUdpRpcError {
+    /// Whether the error was caused by a query timeout (e.g. due to a slow link)
+    /// rather than by a rejected or invalid query
+    pub fn is_timeout(error: &anyhow::Error) -> bool {
+        error
+            .chain()
+            .any(|e| matches!(e.downcast_ref::<Self>(), Some(Self::QueryTimeout)))
+    }
+}
+
 const BLOCK_TIMEOUTS: DownloaderTimeouts = DownloaderTimeouts {
     initial: 200,
     max: 1000,
@@ -257,6 +356,22 @@ impl DownloaderTimeouts {
     }
 }
 
+/// Selects the first node address which can be used by the UDP network stack.
+///
+/// NOTE: ADNL peers of the network stack have only IPv4 addresses,
+/// so IPv6 addresses are skipped
+/// Selects the node address, IPv4 addresses are preferred since IPv6 ones
+/// are reached through the relay
+fn select_server_address(config: &AppConfigAdnl) -> Result<SocketAddr> {
+    let candidates = config.server_candidates();
+    candidates
+        .iter()
+        .find(|addr| addr.is_ipv4())
+        .or_else(|| candidates.first())
+        .copied()
+        .with_context(|| format!("node has no {} addresses", config.address_family))
+}
+
 fn session_keys() -> &'static [u8; 32] {
     use once_cell::sync::OnceCell;
 
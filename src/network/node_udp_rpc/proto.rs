@@ -45,7 +45,7 @@ pub enum Prepared {
     Found,
 }
 
-#[derive(TlWrite, TlRead)]
+#[derive(Copy, Clone, TlWrite, TlRead)]
 #[tl(boxed, id = "tonNode.getCapabilities", scheme = "proto.tl")]
 pub struct GetCapabilities;
 
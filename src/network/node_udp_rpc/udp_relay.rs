@@ -0,0 +1,82 @@
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+
+use anyhow::{Context, Result};
+use tokio::net::UdpSocket;
+
+/// Forwards UDP datagrams between the local IPv4 ADNL socket and an IPv6 node address.
+///
+/// NOTE: The UDP network stack supports only IPv4 peers, so an IPv6 node is added
+/// as a peer with the loopback address of the relay instead.
+pub struct UdpRelay {
+    local_addr: SocketAddrV4,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl UdpRelay {
+    /// Max size of the forwarded datagram
+    const MAX_DATAGRAM_LEN: usize = 65536;
+
+    pub async fn bind(remote_addr: SocketAddrV6) -> Result<Self> {
+        let local = UdpSocket::bind(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0))
+            .await
+            .context("failed to bind local relay socket")?;
+        let local_addr = match local.local_addr()? {
+            SocketAddr::V4(addr) => addr,
+            SocketAddr::V6(_) => anyhow::bail!("local relay socket is not IPv4"),
+        };
+
+        let remote = UdpSocket::bind(SocketAddrV6::new(Ipv6Addr::UNSPECIFIED, 0, 0, 0))
+            .await
+            .context("failed to bind remote relay socket")?;
+        remote
+            .connect(remote_addr)
+            .await
+            .context("failed to connect remote relay socket")?;
+
+        let task = tokio::spawn(async move {
+            let mut local_buffer = vec![0; Self::MAX_DATAGRAM_LEN];
+            let mut remote_buffer = vec![0; Self::MAX_DATAGRAM_LEN];
+
+            // NOTE: the address of the ADNL socket is known only after the first datagram
+            let mut client_addr = None;
+            loop {
+                tokio::select! {
+                    received = local.recv_from(&mut local_buffer) => match received {
+                        Ok((len, addr)) => {
+                            client_addr = Some(addr);
+                            if let Err(e) = remote.send(&local_buffer[..len]).await {
+                                tracing::debug!(%remote_addr, "failed to forward datagram: {e:?}");
+                            }
+                        }
+                        Err(e) => tracing::debug!("failed to receive local datagram: {e:?}"),
+                    },
+                    received = remote.recv(&mut remote_buffer) => match (received, client_addr) {
+                        (Ok(len), Some(addr)) => {
+                            if let Err(e) = local.send_to(&remote_buffer[..len], addr).await {
+                                tracing::debug!(%addr, "failed to forward datagram: {e:?}");
+                            }
+                        }
+                        (Ok(_), None) => {}
+                        (Err(e), _) => {
+                            tracing::debug!(%remote_addr, "failed to receive datagram: {e:?}");
+                        }
+                    },
+                }
+            }
+        });
+
+        tracing::info!(%local_addr, %remote_addr, "started UDP relay to the IPv6 node address");
+        Ok(Self { local_addr, task })
+    }
+
+    /// Address which is used as the node address by the network stack
+    pub fn local_addr(&self) -> SocketAddrV4 {
+        self.local_addr
+    }
+}
+
+impl Drop for UdpRelay {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
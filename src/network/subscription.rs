@@ -1,48 +1,66 @@
 use std::collections::hash_map;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, Weak};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
 use arc_swap::ArcSwapOption;
 use nekoton_abi::FunctionExt;
-use nekoton_utils::SimpleClock;
+use nekoton_utils::ConstClock;
 use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
 use tokio::sync::{mpsc, oneshot, Notify};
 use tokio_util::sync::{CancellationToken, DropGuard};
 use ton_block::{Deserializable, Serializable};
 
 use super::node_tcp_rpc::{ConfigWithId, NodeTcpRpc};
-use super::node_udp_rpc::NodeUdpRpc;
-use crate::util::{split_address, BlockStuff, FxDashMap, TransactionWithHash};
-
-pub struct Subscription {
-    node_tcp_rpc: NodeTcpRpc,
-    node_udp_rpc: NodeUdpRpc,
+use super::node_udp_rpc::{NodeUdpRpc, NodeUdpRpcError};
+use crate::config::AppConfigRetries;
+use crate::util::{
+    parse_mc_block_id, split_address, BlockStuff, BlockStuffError, FxDashMap, Redacted,
+    RetryPolicy, TransactionWithHash,
+};
+
+pub struct Subscription<R = NodeRpc> {
+    rpc: Arc<R>,
     last_mc_block: ArcSwapOption<StoredMcBlock>,
     subscription_count: AtomicUsize,
+    dropped_transactions: AtomicU64,
+    /// When the latest dropped transaction warning was logged
+    dropped_warned_at: AtomicU32,
     subscriptions_changed: Arc<Notify>,
     subscription_loop_step: Arc<Notify>,
     mc_subscriptions: AccountSubscriptions,
     sc_subscriptions: AccountSubscriptions,
     global_id: tokio::sync::Mutex<Option<i32>>,
+    catch_up: parking_lot::Mutex<Option<CatchUpFrom>>,
+    degraded: AtomicBool,
+    degraded_sends: FxDashMap<ton_block::MsgAddressInt, Arc<tokio::sync::Mutex<()>>>,
+    retries: AppConfigRetries,
     _cancellation: DropGuard,
 }
 
-impl Subscription {
-    pub fn new(node_tcp_rpc: NodeTcpRpc, node_udp_rpc: NodeUdpRpc) -> Arc<Self> {
+impl<R: SubscriptionRpc> Subscription<R> {
+    /// Creates a subscription over the node RPC and starts the blocks walk
+    fn with_rpc(rpc: R, retries: AppConfigRetries) -> Arc<Self> {
         let cancellation = CancellationToken::new();
 
         let subscription = Arc::new(Self {
-            node_tcp_rpc,
-            node_udp_rpc,
+            rpc: Arc::new(rpc),
             last_mc_block: Default::default(),
             subscription_count: Default::default(),
+            dropped_transactions: Default::default(),
+            dropped_warned_at: Default::default(),
             subscriptions_changed: Default::default(),
             subscription_loop_step: Default::default(),
             mc_subscriptions: Default::default(),
             sc_subscriptions: Default::default(),
             global_id: Default::default(),
+            catch_up: Default::default(),
+            degraded: Default::default(),
+            degraded_sends: Default::default(),
+            retries,
             _cancellation: cancellation.clone().drop_guard(),
         });
 
@@ -57,28 +75,86 @@ impl Subscription {
 
         subscription
     }
+}
+
+impl Subscription {
+    /// Lifetime (in seconds) of each external message sent with retries
+    pub const MESSAGE_TIMEOUT: u32 = 60;
+
+    pub fn new(
+        node_tcp_rpc: NodeTcpRpc,
+        node_udp_rpc: NodeUdpRpc,
+        retries: AppConfigRetries,
+    ) -> Arc<Self> {
+        let rpc = NodeRpc {
+            tcp: node_tcp_rpc,
+            udp: node_udp_rpc,
+        };
+        Self::with_rpc(rpc, retries)
+    }
 
     pub async fn ensure_ready(&self) -> Result<()> {
-        let (stats, capabilities) = futures_util::future::join(
-            self.node_tcp_rpc.get_stats(),
-            self.node_udp_rpc.get_capabilities(),
-        )
-        .await;
+        let (stats, capabilities) =
+            futures_util::future::join(self.rpc.tcp.get_stats(), self.rpc.udp.get_capabilities())
+                .await;
 
         stats
             .context("failed to get node stats")?
             .try_into_running()?;
-        capabilities.context("failed to get node capabilities")?;
+
+        // NOTE: message delivery can still be confirmed via the control interface without UDP RPC
+        match capabilities {
+            Ok(_) => {
+                if self.degraded.swap(false, Ordering::AcqRel) {
+                    tracing::info!("node UDP RPC is available again, leaving degraded mode");
+                }
+            }
+            Err(e) => {
+                if !self.degraded.swap(true, Ordering::AcqRel) {
+                    tracing::warn!(
+                        "node UDP RPC is unavailable, transactions can't be fetched \
+                        and wallet calls are disabled: {e:?}"
+                    );
+                }
+            }
+        }
 
         Ok(())
     }
+}
+
+impl<R: SubscriptionRpc> Subscription<R> {
+    /// Whether blocks can't be downloaded, so transactions can't be fetched.
+    ///
+    /// NOTE: Transaction subscriptions receive nothing in this mode,
+    /// use [`Subscription::send_message_degraded`] to confirm the message delivery.
+    pub fn is_degraded(&self) -> bool {
+        self.degraded.load(Ordering::Acquire)
+    }
 
+    /// Makes the blocks walk start from the past masterchain block instead of the latest one,
+    /// so that the transactions since that block are also processed.
+    ///
+    /// NOTE: Applied only once, so it must be called before the first subscription.
+    pub fn catch_up_from(&self, from: CatchUpFrom) -> Result<()> {
+        if let CatchUpFrom::BlocksBack(count) = &from {
+            anyhow::ensure!(
+                *count <= MAX_CATCH_UP_BLOCKS,
+                "can't catch up more than {MAX_CATCH_UP_BLOCKS} blocks"
+            );
+        }
+        *self.catch_up.lock() = Some(from);
+        Ok(())
+    }
+}
+
+impl Subscription {
     pub fn tcp_rpc(&self) -> &NodeTcpRpc {
-        &self.node_tcp_rpc
+        &self.rpc.tcp
     }
 
     pub fn udp_rpc(&self) -> &NodeUdpRpc {
-        &self.node_udp_rpc
+        &self.rpc.udp
     }
 
     pub async fn get_account_state(
@@ -86,7 +162,8 @@ impl Subscription {
         address: &ton_block::MsgAddressInt,
     ) -> Result<Option<ton_block::AccountStuff>> {
         let state = self
-            .node_tcp_rpc
+            .rpc
+            .tcp
             .get_shard_account_state(address)
             .await
             .context("failed to get shard account state")?;
@@ -99,38 +176,91 @@ impl Subscription {
         }
     }
 
+    pub async fn get_account_status(
+        &self,
+        address: &ton_block::MsgAddressInt,
+    ) -> Result<AccountStatus> {
+        let state = self.get_account_state(address).await?;
+        Ok(AccountStatus::from_state(state.as_ref()))
+    }
+
     pub async fn run_local(
         &self,
         address: &ton_block::MsgAddressInt,
         function: &ton_abi::Function,
         inputs: &[ton_abi::Token],
+    ) -> Result<Vec<ton_abi::Token>> {
+        self.run_local_at(address, function, inputs, broxus_util::now())
+            .await
+    }
+
+    /// Executes the getter as if the current time is `now` (in seconds)
+    pub async fn run_local_at(
+        &self,
+        address: &ton_block::MsgAddressInt,
+        function: &ton_abi::Function,
+        inputs: &[ton_abi::Token],
+        now: u32,
     ) -> Result<Vec<ton_abi::Token>> {
         let account = self
             .get_account_state(address)
             .await?
             .context("account not deployed")?;
-        let output = function.run_local(&SimpleClock, account, inputs)?;
+        let clock = ConstClock::from_secs(now as u64);
+        let output = function.run_local(&clock, account, inputs)?;
         match output.tokens {
             Some(tokens) => Ok(tokens),
             None => anyhow::bail!("getter failed (exit code: {})", output.result_code),
         }
     }
 
-    pub async fn send_message_with_retires<F>(&self, mut f: F) -> Result<TransactionWithHash>
+    pub async fn send_message_with_retires<F, M>(&self, f: F) -> Result<TransactionWithHash>
+    where
+        F: FnMut(u32, Option<i32>) -> M,
+        M: std::future::Future<Output = Result<(ton_block::Message, u32)>>,
+    {
+        self.send_message_with_retires_if(f, || futures_util::future::ready(Ok(true)))
+            .await?
+            .context("message is no longer needed")
+    }
+
+    /// Re-sends expired messages while `is_needed` returns `true`.
+    ///
+    /// Returns `None` if the message was expired and is no longer needed.
+    pub async fn send_message_with_retires_if<F, M, C, R>(
+        &self,
+        mut f: F,
+        mut is_needed: C,
+    ) -> Result<Option<TransactionWithHash>>
     where
-        F: FnMut(u32, Option<i32>) -> Result<(ton_block::Message, u32)>,
+        F: FnMut(u32, Option<i32>) -> M,
+        M: std::future::Future<Output = Result<(ton_block::Message, u32)>>,
+        C: FnMut() -> R,
+        R: std::future::Future<Output = Result<bool>>,
     {
         let signature_id = self.get_signature_id().await?;
 
-        let timeout = 60;
+        let mut backoff = self.retries.send_message.backoff();
         loop {
-            let (message, expire_at) = f(timeout, signature_id)?;
+            let (message, expire_at) = f(Self::MESSAGE_TIMEOUT, signature_id).await?;
             if let Some(tx) = self.send_message(&message, expire_at).await? {
-                break Ok(tx);
+                break Ok(Some(tx));
+            }
+
+            match backoff.next_delay() {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => anyhow::bail!("message expired after {} attempts", backoff.attempts()),
+            }
+
+            if !is_needed().await? {
+                tracing::info!("expired message is no longer needed");
+                break Ok(None);
             }
         }
     }
+}
 
+impl<R: SubscriptionRpc> Subscription<R> {
     pub async fn send_message(
         &self,
         message: &ton_block::Message,
@@ -148,6 +278,12 @@ impl Subscription {
         let msg_hash = msg_cell.repr_hash();
         let data = ton_types::serialize_toc(&msg_cell)?;
 
+        // NOTE: transactions can only be fetched from the downloaded blocks
+        anyhow::ensure!(
+            !self.is_degraded(),
+            "transactions can't be fetched in degraded mode (node UDP RPC is unavailable)"
+        );
+
         // Find pending messages map
         let subscriptions = match workchain {
             ton_block::MASTERCHAIN_ID => &self.mc_subscriptions,
@@ -163,6 +299,7 @@ impl Subscription {
                 hash_map::Entry::Vacant(entry) => {
                     let (tx, rx) = oneshot::channel();
                     entry.insert(PendingMessage {
+                        sent_at: broxus_util::now(),
                         expire_at,
                         tx: Some(tx),
                     });
@@ -186,15 +323,17 @@ impl Subscription {
         subscription_loop_works.await;
 
         // Send the message
-        if let Err(e) = self.node_tcp_rpc.send_message(data).await {
+        if let Err(e) = self.rpc.send_message(data).await {
             // Remove pending message from the map before returning an error
             match subscriptions.entry(dst) {
                 dashmap::mapref::entry::Entry::Occupied(mut entry) => {
                     let should_remove = {
                         let subscription = entry.get_mut();
-                        subscription.pending_messages.remove(&msg_hash);
-                        self.subscription_count.fetch_sub(1, Ordering::Release);
-                        self.subscriptions_changed.notify_waiters();
+                        // NOTE: message could have already been removed by the gc
+                        if subscription.pending_messages.remove(&msg_hash).is_some() {
+                            self.subscription_count.fetch_sub(1, Ordering::Release);
+                            self.subscriptions_changed.notify_waiters();
+                        }
                         subscription.is_empty()
                     };
 
@@ -208,14 +347,14 @@ impl Subscription {
             };
             return Err(e);
         }
-        tracing::debug!(dst = %raw_dst, ?msg_hash, "external message broadcasted");
+        tracing::debug!(dst = %Redacted(&raw_dst), ?msg_hash, "external message broadcasted");
 
         // Wait for the message execution
         let tx = rx.await?;
         match &tx {
             Some(tx) => {
                 tracing::debug!(
-                    dst = %raw_dst,
+                    dst = %Redacted(&raw_dst),
                     ?msg_hash,
                     tx_hash = ?tx.hash,
                     "external message delivered"
@@ -223,7 +362,7 @@ impl Subscription {
             }
             None => {
                 tracing::warn!(
-                    dst = %raw_dst,
+                    dst = %Redacted(&raw_dst),
                     ?msg_hash,
                     "external message expired"
                 );
@@ -232,9 +371,171 @@ impl Subscription {
 
         Ok(tx)
     }
+}
+
+impl Subscription {
+    /// Sends the message and polls the destination account state until
+    /// its last transaction changes. Works without node UDP RPC.
+    ///
+    /// NOTE: Any new transaction of the account is considered as the message execution.
+    /// Only the hash and the logical time of the transaction are returned,
+    /// because transactions can't be fetched via the control interface.
+    /// Messages to the same account are sent one by one, otherwise all of them
+    /// would be resolved by the first transaction.
+    pub async fn send_message_degraded(
+        &self,
+        message: &ton_block::Message,
+        expire_at: u32,
+    ) -> Result<Option<MessageDelivery>> {
+        let dst = match message.ext_in_header() {
+            Some(header) => &header.dst,
+            None => anyhow::bail!("expected external message"),
+        };
+        let data = ton_types::serialize_toc(&message.serialize()?)?;
+
+        let lock = self.degraded_sends.entry(dst.clone()).or_default().clone();
+        let res = {
+            let _guard = lock.lock().await;
+            self.send_message_degraded_impl(dst, data, expire_at).await
+        };
+
+        // Remove the lock if there are no other messages to this account
+        drop(lock);
+        self.degraded_sends
+            .remove_if(dst, |_, lock| Arc::strong_count(lock) == 1);
+
+        res
+    }
+
+    async fn send_message_degraded_impl(
+        &self,
+        dst: &ton_block::MsgAddressInt,
+        data: Vec<u8>,
+        expire_at: u32,
+    ) -> Result<Option<MessageDelivery>> {
+        const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+        if broxus_util::now() > expire_at {
+            tracing::warn!(dst = %Redacted(dst), "external message expired in queue (degraded mode)");
+            return Ok(None);
+        }
+
+        let initial_lt = self
+            .rpc
+            .tcp
+            .get_shard_account_state(dst)
+            .await?
+            .last_trans_lt();
+
+        self.rpc.tcp.send_message(data).await?;
+        tracing::debug!(dst = %Redacted(dst), "external message broadcasted (degraded mode)");
+
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+
+            let state = self.rpc.tcp.get_shard_account_state(dst).await?;
+            if state.last_trans_lt() > initial_lt {
+                let tx_hash = state.last_trans_hash().clone();
+                tracing::debug!(dst = %Redacted(dst), ?tx_hash, "external message delivered (degraded mode)");
+                return Ok(Some(MessageDelivery {
+                    tx_hash,
+                    lt: state.last_trans_lt(),
+                }));
+            }
+
+            if broxus_util::now() > expire_at {
+                tracing::warn!(dst = %Redacted(dst), "external message expired (degraded mode)");
+                return Ok(None);
+            }
+        }
+    }
+}
 
+impl<R: SubscriptionRpc> Subscription<R> {
     pub fn subscribe(&self, address: &ton_block::MsgAddressInt) -> TransactionsRx {
         let (tx, rx) = mpsc::unbounded_channel();
+        self.add_transactions_subscription(address, TransactionsTx::Unbounded(tx));
+        rx
+    }
+
+    /// Subscribes to the account transactions with the limited queue.
+    ///
+    /// NOTE: New transactions are dropped while the queue is full.
+    pub fn subscribe_bounded(
+        &self,
+        address: &ton_block::MsgAddressInt,
+        capacity: usize,
+    ) -> BoundedTransactionsRx {
+        let (tx, rx) = mpsc::channel(capacity);
+        self.add_transactions_subscription(address, TransactionsTx::Bounded(tx));
+        rx
+    }
+
+    /// Collects messages which were broadcast but not found in blocks yet
+    pub fn pending_messages(&self) -> PendingMessages {
+        fn collect(subscriptions: &AccountSubscriptions, oldest: &mut Option<u32>) -> usize {
+            let mut count = 0;
+            for item in subscriptions.iter() {
+                for message in item.pending_messages.values() {
+                    count += 1;
+                    *oldest = Some(match *oldest {
+                        Some(oldest) => std::cmp::min(oldest, message.sent_at),
+                        None => message.sent_at,
+                    });
+                }
+            }
+            count
+        }
+
+        let mut oldest_sent_at = None;
+        PendingMessages {
+            sampled_at: broxus_util::now(),
+            masterchain: collect(&self.mc_subscriptions, &mut oldest_sent_at),
+            basechain: collect(&self.sc_subscriptions, &mut oldest_sent_at),
+            oldest_sent_at,
+            dropped_transactions: self.dropped_transactions(),
+        }
+    }
+
+    /// Total number of transactions dropped due to the lagging consumers
+    pub fn dropped_transactions(&self) -> u64 {
+        self.dropped_transactions.load(Ordering::Acquire)
+    }
+
+    /// Counts the dropped transaction.
+    ///
+    /// NOTE: A lagging consumer drops transactions in bursts, so the warning
+    /// is logged at most once per interval (the counter is exported as a metric)
+    fn on_transaction_dropped(&self, address: &ton_types::UInt256, tx_hash: &ton_types::UInt256) {
+        const WARNING_INTERVAL: u32 = 60;
+
+        let dropped = self.dropped_transactions.fetch_add(1, Ordering::AcqRel) + 1;
+
+        let now = broxus_util::now();
+        let warned_at = self.dropped_warned_at.load(Ordering::Acquire);
+        if now < warned_at.saturating_add(WARNING_INTERVAL)
+            || self
+                .dropped_warned_at
+                .compare_exchange(warned_at, now, Ordering::AcqRel, Ordering::Acquire)
+                .is_err()
+        {
+            return;
+        }
+
+        tracing::warn!(
+            account = %Redacted(&address.to_hex_string()),
+            ?tx_hash,
+            total_dropped = dropped,
+            "transactions consumer lags, transaction dropped \
+            (further drops are not logged for {WARNING_INTERVAL} seconds)"
+        );
+    }
+
+    fn add_transactions_subscription(
+        &self,
+        address: &ton_block::MsgAddressInt,
+        tx: TransactionsTx,
+    ) {
         let subscriptions = if address.workchain_id() == ton_block::MASTERCHAIN_ID {
             &self.mc_subscriptions
         } else {
@@ -244,20 +545,20 @@ impl Subscription {
         let address =
             ton_types::UInt256::from_le_bytes(&address.address().get_bytestring_on_stack(0));
 
-        subscriptions
-            .entry(address)
-            .or_default()
-            .transactions
-            .push(tx);
+        let mut subscription = subscriptions.entry(address).or_default();
+        subscription.transactions.push(tx);
 
+        // Update counter while the entry is still acquired
         self.subscription_count.fetch_add(1, Ordering::Release);
         self.subscriptions_changed.notify_waiters();
-        rx
     }
+}
 
+impl Subscription {
     pub async fn get_signature_id(&self) -> Result<Option<i32>> {
         let ConfigWithId { block_id, config } = self
-            .node_tcp_rpc
+            .rpc
+            .tcp
             .get_config_all()
             .await
             .context("failed to get blockchain config")?;
@@ -265,36 +566,105 @@ impl Subscription {
             return Ok(None);
         }
 
-        let global_id = {
-            let mut global_id = self.global_id.lock().await;
-            match *global_id {
-                // Once received, it will never change
-                Some(global_id) => global_id,
-                // Try to get the known masterchain block
-                None => {
-                    // TODO: replace with `global_id` from `getstats` when it will be available.
-                    const RETRIES: usize = 10;
-                    const INTERVAL: Duration = Duration::from_secs(1);
-
-                    let mut retries = 0;
-                    let block = loop {
-                        match self.node_udp_rpc.get_block(&block_id).await {
-                            Ok(block) => break block,
-                            Err(e) if retries < RETRIES => {
-                                tracing::error!("failed to get the latest mc block: {e:?}");
-                                tokio::time::sleep(INTERVAL).await;
-                                retries += 1;
+        let global_id = self.get_global_id(&block_id).await?;
+        Ok(Some(global_id))
+    }
+
+    /// Returns the global id of the network the node is in.
+    ///
+    /// NOTE: `block_id` is used only for the first call, the result is cached.
+    pub async fn get_global_id(&self, block_id: &ton_block::BlockIdExt) -> Result<i32> {
+        let mut global_id = self.global_id.lock().await;
+        match *global_id {
+            // Once received, it will never change
+            Some(global_id) => Ok(global_id),
+            // Try to get the known masterchain block
+            None => {
+                // TODO: replace with `global_id` from `getstats` when it will be available.
+                let mut backoff = self.retries.block_fetch.backoff();
+                let block = loop {
+                    match self.rpc.udp.get_block(block_id).await {
+                        Ok(block) => break block,
+                        Err(e) => match backoff.next_delay() {
+                            Some(delay) => {
+                                if NodeUdpRpcError::is_timeout(&e) {
+                                    tracing::warn!("timed out getting the latest mc block");
+                                } else {
+                                    tracing::error!("failed to get the latest mc block: {e:?}");
+                                }
+                                tokio::time::sleep(delay).await;
                             }
-                            Err(e) => return Err(e),
-                        }
-                    };
+                            None => return Err(e),
+                        },
+                    }
+                };
 
-                    *global_id.insert(block.block().global_id)
+                Ok(*global_id.insert(block.block().global_id))
+            }
+        }
+    }
+}
+
+impl<R: SubscriptionRpc> Subscription<R> {
+    /// Searches the transaction by its hash (or by the hash of its inbound message)
+    /// in the last `depth` masterchain blocks and all shard blocks between them.
+    ///
+    /// NOTE: `expire_at` is used to distinguish pending messages from the expired ones.
+    pub async fn find_transaction(
+        &self,
+        address: &ton_block::MsgAddressInt,
+        hash: &ton_types::UInt256,
+        expire_at: Option<u32>,
+        depth: u32,
+    ) -> Result<TransactionStatus> {
+        let (workchain, account) = split_address(address)?;
+
+        let last_mc_block = self.rpc.get_last_mc_block_id().await?;
+
+        let mut mc_block = self.rpc.get_block(&last_mc_block).await?;
+        let mc_utime = mc_block.read_brief_info()?.gen_utime;
+
+        for _ in 0..depth {
+            let prev_mc_block_id = mc_block.read_brief_info()?.prev1;
+            if prev_mc_block_id.seq_no == 0 {
+                break;
+            }
+            let prev_mc_block = self.rpc.get_block(&prev_mc_block_id).await?;
+
+            let blocks = if workchain == ton_block::MASTERCHAIN_ID {
+                vec![mc_block]
+            } else {
+                let edge = Edge(prev_mc_block.shard_blocks_seq_no()?);
+
+                let mut blocks = Vec::new();
+                for (shard, id) in mc_block.shard_blocks()? {
+                    if shard.workchain_id() != workchain {
+                        continue;
+                    }
+                    let shard_blocks =
+                        walk_shard_blocks(self.rpc.as_ref(), &self.retries.block_fetch, &edge, id)
+                            .await?;
+                    blocks.extend(shard_blocks.into_iter().map(|(_, block)| block));
+                }
+                blocks
+            };
+
+            for block in &blocks {
+                if let Some(tx) = find_transaction_in_block(block.block(), &account, hash)? {
+                    return Ok(TransactionStatus::Found {
+                        block_id: block.id().clone(),
+                        tx,
+                    });
                 }
             }
-        };
 
-        Ok(Some(global_id))
+            mc_block = prev_mc_block;
+        }
+
+        Ok(match expire_at {
+            Some(expire_at) if expire_at >= mc_utime => TransactionStatus::NotFoundYet,
+            _ => TransactionStatus::NotFound,
+        })
     }
 
     async fn make_blocks_step(&self) -> Result<()> {
@@ -307,11 +677,18 @@ impl Subscription {
         self.subscription_loop_step.notify_waiters(); // messages barrier
 
         // Get next masterchain block
-        let next_mc_block = self
-            .node_udp_rpc
-            .get_next_block(last_mc_block.data.id())
+        let Some(next_mc_block) = self
+            .get_next_mc_block(last_mc_block.data.id())
             .await
-            .context("failed to get next block")?;
+            .context("failed to get next block")?
+        else {
+            // NOTE: Transactions in the skipped blocks are not delivered,
+            // so the pending messages there are resolved by expiration
+            self.update_last_mc_block()
+                .await
+                .context("failed to resync last mc block")?;
+            return Ok(());
+        };
         let next_shard_block_ids = next_mc_block.shard_blocks()?;
         let next_mc_utime = {
             let info = next_mc_block.block().read_info()?;
@@ -322,44 +699,39 @@ impl Subscription {
 
         tracing::debug!("next shard blocks: {next_shard_block_ids:#?}");
 
+        // NOTE: Shard blocks are not needed when only masterchain accounts are tracked
+        // (e.g. a single validator), so lagging shards don't delay the confirmation.
+        // Messages registered after this check are sent after the next barrier,
+        // so they can't be in the skipped blocks.
+        let shard_block_ids = if self.sc_subscriptions.is_empty() {
+            tracing::debug!("no shardchain subscriptions, skipping shard blocks");
+            Vec::new()
+        } else {
+            next_shard_block_ids.values().cloned().collect()
+        };
+
         // Get all shard blocks between these masterchain blocks
-        let mut tasks = Vec::with_capacity(next_shard_block_ids.len());
-        for id in next_shard_block_ids.values().cloned() {
+        let mut tasks = Vec::with_capacity(shard_block_ids.len());
+        for id in shard_block_ids {
             let last_mc_block = last_mc_block.clone();
-            let rpc = self.node_udp_rpc.clone();
+            let rpc = self.rpc.clone();
+            let retries = self.retries.block_fetch;
             tasks.push(tokio::spawn(async move {
-                let edge = &last_mc_block.shards_edge;
-                let mut blocks = Vec::new();
-
-                let mut stack = Vec::from([id]);
-                while let Some(id) = stack.pop() {
-                    let block = rpc.get_block(&id).await?;
-                    let info = block.read_brief_info()?;
-                    blocks.push((info.gen_utime, block));
-
-                    if edge.is_before(&info.prev1) {
-                        stack.push(info.prev1);
-                    }
-                    if let Some(prev_id2) = info.prev2 {
-                        if edge.is_before(&prev_id2) {
-                            stack.push(prev_id2);
-                        }
-                    }
-                }
-
-                // Sort blocks by time (to increase processing locality) and seqno
-                blocks.sort_unstable_by_key(|(info, block_data)| (*info, block_data.id().seq_no));
-
-                Ok::<_, anyhow::Error>(blocks)
+                walk_shard_blocks(rpc.as_ref(), &retries, &last_mc_block.shards_edge, id).await
             }));
         }
 
-        // Wait and process all shard blocks
+        // Wait for all shard blocks
+        let mut blocks = Vec::new();
         for task in tasks {
-            let blocks = task.await??;
-            for (_, item) in blocks {
-                self.process_block(item.block(), &self.sc_subscriptions)?;
-            }
+            blocks.extend(task.await??);
+        }
+
+        sort_shard_blocks(&mut blocks);
+
+        // Process all shard blocks
+        for (_, item) in blocks {
+            self.process_block(item.block(), &self.sc_subscriptions)?;
         }
         self.process_block(next_mc_block.block(), &self.mc_subscriptions)?;
 
@@ -384,6 +756,48 @@ impl Subscription {
         Ok(())
     }
 
+    /// Downloads the next masterchain block, re-fetching it if its data doesn't
+    /// match its id (only when re-fetching is enabled).
+    ///
+    /// Returns `None` if the block doesn't reference the previous one (e.g. the node
+    /// switched to another branch), so the walk must be restarted from the latest block.
+    async fn get_next_mc_block(
+        &self,
+        prev_id: &ton_block::BlockIdExt,
+    ) -> Result<Option<BlockStuff>> {
+        let rpc = self.rpc.as_ref();
+        let mut backoff = self.retries.block_fetch.backoff();
+        let block = loop {
+            match rpc.get_next_block(prev_id).await {
+                Ok(block) => break block,
+                Err(e) if rpc.refetch_mismatched_blocks() && is_block_hash_mismatch(&e) => {
+                    match backoff.next_delay() {
+                        Some(delay) => {
+                            tracing::warn!(%prev_id, "rejected next block: {e:?}");
+                            tokio::time::sleep(delay).await;
+                        }
+                        None => return Err(e),
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        };
+
+        let info = block.read_brief_info()?;
+        if info.prev1 != *prev_id || info.prev2.is_some() {
+            tracing::warn!(
+                block_id = %block.id(),
+                expected_prev = %prev_id,
+                actual_prev = %info.prev1,
+                "next masterchain block doesn't continue the walked chain, resyncing",
+            );
+            rpc.forget_block(block.id());
+            return Ok(None);
+        }
+
+        Ok(Some(block))
+    }
+
     async fn get_last_mc_block(&self) -> Result<Arc<StoredMcBlock>> {
         // Always try to use the cached one
         if let Some(last_mc_block) = &*self.last_mc_block.load() {
@@ -393,9 +807,22 @@ impl Subscription {
     }
 
     async fn update_last_mc_block(&self) -> Result<Arc<StoredMcBlock>> {
-        let stats = self.node_tcp_rpc.get_stats().await?;
-        let last_mc_block = stats.try_into_running()?.last_mc_block;
-        let data = self.node_udp_rpc.get_block(&last_mc_block).await?;
+        let last_mc_block = self.rpc.get_last_mc_block_id().await?;
+
+        let catch_up = self.catch_up.lock().clone();
+        let data = match catch_up {
+            None => self.rpc.get_block(&last_mc_block).await?,
+            Some(from) => {
+                let data = self
+                    .find_catch_up_block(&last_mc_block, from)
+                    .await
+                    .context("failed to find catch-up block")?;
+                *self.catch_up.lock() = None;
+
+                tracing::info!(block_id = %data.id(), "catching up from the past block");
+                data
+            }
+        };
 
         let shards_edge = Edge(data.shard_blocks_seq_no()?);
 
@@ -404,6 +831,41 @@ impl Subscription {
         Ok(block)
     }
 
+    async fn find_catch_up_block(
+        &self,
+        last_mc_block: &ton_block::BlockIdExt,
+        from: CatchUpFrom,
+    ) -> Result<BlockStuff> {
+        match from {
+            CatchUpFrom::Block(id) => {
+                anyhow::ensure!(
+                    id.shard().is_masterchain(),
+                    "catch-up block is not a masterchain block"
+                );
+                anyhow::ensure!(
+                    id.seq_no <= last_mc_block.seq_no,
+                    "catch-up block is ahead of the node"
+                );
+                anyhow::ensure!(
+                    last_mc_block.seq_no - id.seq_no <= MAX_CATCH_UP_BLOCKS,
+                    "can't catch up more than {MAX_CATCH_UP_BLOCKS} blocks"
+                );
+                self.rpc.get_block(&id).await
+            }
+            CatchUpFrom::BlocksBack(count) => {
+                let mut block = self.rpc.get_block(last_mc_block).await?;
+                for _ in 0..count {
+                    let prev_id = block.read_brief_info()?.prev1;
+                    if prev_id.seq_no == 0 {
+                        break;
+                    }
+                    block = self.rpc.get_block(&prev_id).await?;
+                }
+                Ok(block)
+            }
+        }
+    }
+
     fn process_block(
         &self,
         block: &ton_block::Block,
@@ -430,7 +892,9 @@ impl Subscription {
                     let tx = TransactionWithHash { hash, data };
 
                     for channel in &subscription.transactions {
-                        channel.send(tx.clone()).ok();
+                        if !channel.send(tx.clone()) {
+                            self.on_transaction_dropped(&address, &tx.hash);
+                        }
                     }
 
                     let in_msg_hash = match &tx.data.in_msg {
@@ -486,6 +950,91 @@ impl Subscription {
     fn has_subscriptions(&self) -> bool {
         self.subscription_count.load(Ordering::Acquire) > 0
     }
+
+    /// Recomputes the number of subscriptions from the maps and compares it with the counter.
+    ///
+    /// NOTE: The check is skipped if the counter was changed during the computation.
+    fn audit_subscription_count(&self) {
+        fn count(subscriptions: &AccountSubscriptions) -> usize {
+            subscriptions
+                .iter()
+                .map(|item| item.pending_messages.len() + item.transactions.len())
+                .sum()
+        }
+
+        let before = self.subscription_count.load(Ordering::Acquire);
+        let actual = count(&self.mc_subscriptions) + count(&self.sc_subscriptions);
+        let after = self.subscription_count.load(Ordering::Acquire);
+
+        if before == after && actual != after {
+            tracing::error!(counter = after, actual, "subscription count mismatch");
+        }
+    }
+}
+
+/// Node RPC methods used by the subscription and its blocks walk.
+///
+/// NOTE: The walk doesn't depend on the concrete node clients,
+/// so that recorded blocks can be replayed through it in tests.
+#[async_trait::async_trait]
+pub trait SubscriptionRpc: Send + Sync + 'static {
+    /// Returns the id of the latest masterchain block of the running node
+    async fn get_last_mc_block_id(&self) -> Result<ton_block::BlockIdExt>;
+
+    /// Broadcasts the serialized external message
+    async fn send_message(&self, data: Vec<u8>) -> Result<()>;
+
+    async fn get_block(&self, block_id: &ton_block::BlockIdExt) -> Result<BlockStuff>;
+
+    /// Waits for the next block
+    async fn get_next_block(&self, prev_block_id: &ton_block::BlockIdExt) -> Result<BlockStuff>;
+
+    /// Removes the block from the cache, so that it will be downloaded again
+    fn forget_block(&self, block_id: &ton_block::BlockIdExt);
+
+    /// Whether the downloaded blocks which don't match the expected ids are re-fetched
+    fn refetch_mismatched_blocks(&self) -> bool;
+
+    /// How long blocks are still tracked without subscriptions
+    fn idle_linger(&self) -> Duration;
+}
+
+/// Control (TCP) and blocks (UDP) clients of the node
+pub struct NodeRpc {
+    tcp: NodeTcpRpc,
+    udp: NodeUdpRpc,
+}
+
+#[async_trait::async_trait]
+impl SubscriptionRpc for NodeRpc {
+    async fn get_last_mc_block_id(&self) -> Result<ton_block::BlockIdExt> {
+        let stats = self.tcp.get_stats().await?;
+        Ok(stats.try_into_running()?.last_mc_block)
+    }
+
+    async fn send_message(&self, data: Vec<u8>) -> Result<()> {
+        self.tcp.send_message(data).await
+    }
+
+    async fn get_block(&self, block_id: &ton_block::BlockIdExt) -> Result<BlockStuff> {
+        self.udp.get_block(block_id).await
+    }
+
+    async fn get_next_block(&self, prev_block_id: &ton_block::BlockIdExt) -> Result<BlockStuff> {
+        self.udp.get_next_block(prev_block_id).await
+    }
+
+    fn forget_block(&self, block_id: &ton_block::BlockIdExt) {
+        self.udp.forget_block(block_id);
+    }
+
+    fn refetch_mismatched_blocks(&self) -> bool {
+        self.udp.refetch_mismatched_blocks()
+    }
+
+    fn idle_linger(&self) -> Duration {
+        self.udp.idle_linger()
+    }
 }
 
 #[derive(Default)]
@@ -502,10 +1051,129 @@ impl AccountSubscription {
 
 type AccountSubscriptions = FxDashMap<ton_types::UInt256, AccountSubscription>;
 
-pub type TransactionsTx = mpsc::UnboundedSender<TransactionWithHash>;
+/// Snapshot of the messages which are waiting for delivery
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct PendingMessages {
+    pub sampled_at: u32,
+    pub masterchain: usize,
+    pub basechain: usize,
+    /// Broadcast time of the oldest pending message
+    pub oldest_sent_at: Option<u32>,
+    /// Total number of transactions dropped due to the lagging consumers
+    #[serde(default)]
+    pub dropped_transactions: u64,
+}
+
+/// Transaction which executed the message, confirmed without fetching it
+#[derive(Debug, Clone)]
+pub struct MessageDelivery {
+    pub tx_hash: ton_types::UInt256,
+    pub lt: u64,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum AccountStatus {
+    NotExists,
+    Uninit,
+    Active,
+    Frozen,
+}
+
+impl AccountStatus {
+    pub fn from_state(state: Option<&ton_block::AccountStuff>) -> Self {
+        match state.map(|state| &state.storage.state) {
+            None => Self::NotExists,
+            Some(ton_block::AccountState::AccountUninit) => Self::Uninit,
+            Some(ton_block::AccountState::AccountActive { .. }) => Self::Active,
+            Some(ton_block::AccountState::AccountFrozen { .. }) => Self::Frozen,
+        }
+    }
+}
+
+impl std::fmt::Display for AccountStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::NotExists => "not exists",
+            Self::Uninit => "uninit",
+            Self::Active => "active",
+            Self::Frozen => "frozen",
+        })
+    }
+}
+
+/// Masterchain block from which the blocks walk starts
+#[derive(Debug, Clone)]
+pub enum CatchUpFrom {
+    /// The specified masterchain block
+    Block(ton_block::BlockIdExt),
+    /// The specified number of masterchain blocks before the latest one
+    BlocksBack(u32),
+}
+
+impl FromStr for CatchUpFrom {
+    type Err = anyhow::Error;
+
+    /// Parses either the number of blocks or `seqno:root_hash:file_hash` of the masterchain block
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(count) = u32::from_str(s) {
+            return Ok(Self::BlocksBack(count));
+        }
+
+        parse_mc_block_id(s)
+            .map(Self::Block)
+            .context("expected the number of blocks or `seqno:root_hash:file_hash`")
+    }
+}
+
+pub enum TransactionStatus {
+    /// Transaction was found in the specified block
+    Found {
+        block_id: ton_block::BlockIdExt,
+        tx: TransactionWithHash,
+    },
+    /// Transaction was not found, but the message has not expired yet
+    NotFoundYet,
+    /// Transaction was not found in the scanned blocks
+    NotFound,
+}
+
 pub type TransactionsRx = mpsc::UnboundedReceiver<TransactionWithHash>;
+pub type BoundedTransactionsRx = mpsc::Receiver<TransactionWithHash>;
+
+enum TransactionsTx {
+    Unbounded(mpsc::UnboundedSender<TransactionWithHash>),
+    Bounded(mpsc::Sender<TransactionWithHash>),
+}
+
+impl TransactionsTx {
+    /// Returns `false` if the transaction was dropped due to the full queue
+    fn send(&self, tx: TransactionWithHash) -> bool {
+        match self {
+            Self::Unbounded(channel) => {
+                channel.send(tx).ok();
+                true
+            }
+            Self::Bounded(channel) => !matches!(
+                channel.try_send(tx),
+                Err(mpsc::error::TrySendError::Full(_))
+            ),
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        match self {
+            Self::Unbounded(channel) => channel.is_closed(),
+            Self::Bounded(channel) => channel.is_closed(),
+        }
+    }
+}
+
+/// Max number of masterchain blocks to process when catching up
+const MAX_CATCH_UP_BLOCKS: u32 = 10000;
+
+async fn walk_blocks<R: SubscriptionRpc>(subscription: Weak<Subscription<R>>) {
+    const AUDIT_INTERVAL: usize = 100; // steps
 
-async fn walk_blocks(subscription: Weak<Subscription>) {
     loop {
         let subscription = match subscription.upgrade() {
             Some(subscription) => subscription,
@@ -515,19 +1183,38 @@ async fn walk_blocks(subscription: Weak<Subscription>) {
         let pending_messages_changed = subscription.subscriptions_changed.clone();
         let signal = pending_messages_changed.notified();
 
-        if subscription.has_subscriptions() {
+        // NOTE: blocks can't be downloaded in degraded mode
+        if subscription.has_subscriptions() && !subscription.is_degraded() {
             // Update the latest masterchain block before starting the blocks loop.
             // All message senders will wait until `subscription_loop_step` is triggered.
             if let Err(e) = subscription.update_last_mc_block().await {
                 tracing::error!("failed to update last mc block: {e:?}");
             }
 
-            while subscription.has_subscriptions() {
+            // NOTE: blocks are still tracked for a while without subscriptions,
+            // so that the rapidly resubscribing consumers don't restart the loop
+            let linger = subscription.rpc.idle_linger();
+            let mut idle_since = None;
+
+            let mut steps = 0usize;
+            loop {
+                if subscription.has_subscriptions() {
+                    idle_since = None;
+                } else if idle_since.get_or_insert_with(Instant::now).elapsed() >= linger {
+                    break;
+                }
+
                 if let Err(e) = subscription.make_blocks_step().await {
                     tracing::error!("failed to make blocks step: {e:?}");
                 }
+
+                steps += 1;
+                if steps % AUDIT_INTERVAL == 0 {
+                    subscription.audit_subscription_count();
+                }
             }
         }
+        subscription.audit_subscription_count();
         drop(subscription);
 
         tracing::debug!("waiting for new messages");
@@ -535,6 +1222,113 @@ async fn walk_blocks(subscription: Weak<Subscription>) {
     }
 }
 
+/// Collects all shard blocks starting from the specified block down to the edge
+/// Sorts blocks of all shards by time (to increase processing locality),
+/// shard and seqno, so that the processing order doesn't depend on tasks
+fn sort_shard_blocks(blocks: &mut [(u32, BlockStuff)]) {
+    blocks.sort_unstable_by_key(|(gen_utime, block)| {
+        let id = block.id();
+        (
+            *gen_utime,
+            id.shard().workchain_id(),
+            id.shard().shard_prefix_with_tag(),
+            id.seq_no,
+        )
+    });
+}
+
+async fn walk_shard_blocks<R: SubscriptionRpc + ?Sized>(
+    rpc: &R,
+    retries: &RetryPolicy,
+    edge: &Edge,
+    id: ton_block::BlockIdExt,
+) -> Result<Vec<(u32, BlockStuff)>> {
+    let mut blocks = Vec::new();
+
+    let mut stack = Vec::from([id]);
+    while let Some(id) = stack.pop() {
+        let block = get_shard_block(rpc, retries, &id).await?;
+        let info = block.read_brief_info()?;
+        blocks.push((info.gen_utime, block));
+
+        if edge.is_before(&info.prev1) {
+            stack.push(info.prev1);
+        }
+        if let Some(prev_id2) = info.prev2 {
+            if edge.is_before(&prev_id2) {
+                stack.push(prev_id2);
+            }
+        }
+    }
+
+    Ok(blocks)
+}
+
+/// Downloads the shard block, re-fetching it if its data doesn't match
+/// the id from the masterchain (only when re-fetching is enabled)
+async fn get_shard_block<R: SubscriptionRpc + ?Sized>(
+    rpc: &R,
+    retries: &RetryPolicy,
+    id: &ton_block::BlockIdExt,
+) -> Result<BlockStuff> {
+    let mut backoff = retries.backoff();
+    loop {
+        match rpc.get_block(id).await {
+            Ok(block) => return Ok(block),
+            Err(e) if rpc.refetch_mismatched_blocks() && is_block_hash_mismatch(&e) => {
+                match backoff.next_delay() {
+                    Some(delay) => {
+                        tracing::warn!(block_id = %id, "rejected block: {e:?}");
+                        tokio::time::sleep(delay).await;
+                    }
+                    None => return Err(e),
+                }
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Whether the downloaded block data doesn't match the requested id
+fn is_block_hash_mismatch(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<BlockStuffError>().is_some()
+}
+
+fn find_transaction_in_block(
+    block: &ton_block::Block,
+    account: &ton_types::UInt256,
+    hash: &ton_types::UInt256,
+) -> Result<Option<TransactionWithHash>> {
+    use ton_block::HashmapAugType;
+
+    let extra = block.read_extra()?;
+    let account_blocks = extra.read_account_blocks()?;
+    let Some(account_block) = account_blocks.get(account)? else {
+        return Ok(None);
+    };
+
+    let mut result = None;
+    account_block
+        .transactions()
+        .iterate_slices_with_keys(|_, tx| {
+            let cell = tx.reference(0)?;
+            let tx_hash = cell.repr_hash();
+            let data = ton_block::Transaction::construct_from_cell(cell)?;
+
+            let matches =
+                &tx_hash == hash || matches!(&data.in_msg, Some(in_msg) if &in_msg.hash() == hash);
+            if matches {
+                result = Some(TransactionWithHash {
+                    hash: tx_hash,
+                    data,
+                });
+            }
+            Ok(!matches)
+        })?;
+
+    Ok(result)
+}
+
 struct StoredMcBlock {
     data: BlockStuff,
     shards_edge: Edge,
@@ -557,6 +1351,7 @@ impl Edge {
 }
 
 struct PendingMessage {
+    sent_at: u32,
     expire_at: u32,
     tx: Option<oneshot::Sender<Option<TransactionWithHash>>>,
 }
@@ -574,3 +1369,607 @@ fn requires_signature_id(capabilities: u64) -> bool {
 
     capabilities & CAP_WITH_SIGNATURE_ID != 0
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Replays the prepared blocks instead of downloading them from the node
+    struct FixtureRpc {
+        last_mc_block_id: ton_block::BlockIdExt,
+        blocks: FxHashMap<ton_block::BlockIdExt, BlockStuff>,
+        next_blocks: FxHashMap<ton_block::BlockIdExt, ton_block::BlockIdExt>,
+        sent_messages: parking_lot::Mutex<Vec<Vec<u8>>>,
+    }
+
+    impl FixtureRpc {
+        /// Creates a masterchain with the specified blocks (the first one is the latest known)
+        fn with_mc_blocks(mc_blocks: Vec<BlockStuff>) -> Self {
+            let mut rpc = Self {
+                last_mc_block_id: mc_blocks[0].id().clone(),
+                blocks: Default::default(),
+                next_blocks: Default::default(),
+                sent_messages: Default::default(),
+            };
+            for pair in mc_blocks.windows(2) {
+                rpc.next_blocks
+                    .insert(pair[0].id().clone(), pair[1].id().clone());
+            }
+            for block in mc_blocks {
+                rpc.add_block(block);
+            }
+            rpc
+        }
+
+        fn add_block(&mut self, block: BlockStuff) {
+            self.blocks.insert(block.id().clone(), block);
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl SubscriptionRpc for FixtureRpc {
+        async fn get_last_mc_block_id(&self) -> Result<ton_block::BlockIdExt> {
+            Ok(self.last_mc_block_id.clone())
+        }
+
+        async fn send_message(&self, data: Vec<u8>) -> Result<()> {
+            self.sent_messages.lock().push(data);
+            Ok(())
+        }
+
+        async fn get_block(&self, block_id: &ton_block::BlockIdExt) -> Result<BlockStuff> {
+            self.blocks
+                .get(block_id)
+                .cloned()
+                .with_context(|| format!("unknown block {block_id}"))
+        }
+
+        async fn get_next_block(
+            &self,
+            prev_block_id: &ton_block::BlockIdExt,
+        ) -> Result<BlockStuff> {
+            match self.next_blocks.get(prev_block_id) {
+                Some(id) => self.get_block(id).await,
+                // NOTE: the node waits for the next block, which never comes
+                None => futures_util::future::pending().await,
+            }
+        }
+
+        fn forget_block(&self, _: &ton_block::BlockIdExt) {}
+
+        fn refetch_mismatched_blocks(&self) -> bool {
+            true
+        }
+
+        fn idle_linger(&self) -> Duration {
+            Duration::ZERO
+        }
+    }
+
+    struct BlockParams<'a> {
+        shard: ton_block::ShardIdent,
+        seq_no: u32,
+        gen_utime: u32,
+        prev: &'a [&'a BlockStuff],
+        after_split: bool,
+        transactions: Vec<ton_block::Transaction>,
+    }
+
+    impl<'a> BlockParams<'a> {
+        fn new(shard: ton_block::ShardIdent, seq_no: u32, prev: &'a [&'a BlockStuff]) -> Self {
+            Self {
+                shard,
+                seq_no,
+                gen_utime: seq_no,
+                prev,
+                after_split: false,
+                transactions: Vec::new(),
+            }
+        }
+
+        fn build(self) -> BlockStuff {
+            fn block_ref(block: &BlockStuff) -> ton_block::ExtBlkRef {
+                let id = block.id();
+                ton_block::ExtBlkRef {
+                    end_lt: 0,
+                    seq_no: id.seq_no,
+                    root_hash: id.root_hash.clone(),
+                    file_hash: id.file_hash.clone(),
+                }
+            }
+
+            let prev = match self.prev {
+                [] => ton_block::BlkPrevInfo::Block {
+                    prev: Default::default(),
+                },
+                [prev] => ton_block::BlkPrevInfo::Block {
+                    prev: block_ref(prev),
+                },
+                [prev1, prev2] => ton_block::BlkPrevInfo::Blocks {
+                    prev1: ton_block::ChildCell::with_struct(&block_ref(prev1)).unwrap(),
+                    prev2: ton_block::ChildCell::with_struct(&block_ref(prev2)).unwrap(),
+                },
+                _ => panic!("too many prev blocks"),
+            };
+
+            let mut info = ton_block::BlockInfo::default();
+            info.set_shard(self.shard);
+            info.set_seq_no(self.seq_no).unwrap();
+            info.set_gen_utime(ton_block::UnixTime32(self.gen_utime));
+            info.set_after_split(self.after_split);
+            info.set_prev_stuff(self.prev.len() == 2, &prev).unwrap();
+
+            let mut account_blocks = ton_block::ShardAccountBlocks::default();
+            for tx in &self.transactions {
+                account_blocks
+                    .add_serialized_transaction(tx, &tx.serialize().unwrap())
+                    .unwrap();
+            }
+
+            let mut extra = ton_block::BlockExtra::default();
+            extra.write_account_blocks(&account_blocks).unwrap();
+            if self.shard.is_masterchain() {
+                extra
+                    .write_custom(Some(&ton_block::McBlockExtra::default()))
+                    .unwrap();
+            }
+
+            let block = ton_block::Block::with_params(
+                0,
+                info,
+                Default::default(),
+                Default::default(),
+                extra,
+            )
+            .unwrap();
+
+            let cell = block.serialize().unwrap();
+            let data = ton_types::serialize_toc(&cell).unwrap();
+            let id = ton_block::BlockIdExt {
+                shard_id: self.shard,
+                seq_no: self.seq_no,
+                root_hash: cell.repr_hash(),
+                file_hash: ton_types::UInt256::calc_file_hash(&data),
+            };
+            BlockStuff::new(&data, id).unwrap()
+        }
+    }
+
+    fn mc_account(byte: u8) -> (ton_types::UInt256, ton_block::MsgAddressInt) {
+        let account = ton_types::UInt256::from([byte; 32]);
+        let address = ton_block::MsgAddressInt::with_standart(
+            None,
+            ton_block::MASTERCHAIN_ID as i8,
+            ton_types::SliceData::from_raw(account.as_slice().to_vec(), 256),
+        )
+        .unwrap();
+        (account, address)
+    }
+
+    fn external_message(dst: &ton_block::MsgAddressInt, nonce: u32) -> ton_block::Message {
+        let mut body = ton_types::BuilderData::new();
+        body.append_u32(nonce).unwrap();
+
+        let mut message =
+            ton_block::Message::with_ext_in_header(ton_block::ExternalInboundMessageHeader {
+                dst: dst.clone(),
+                ..Default::default()
+            });
+        message.set_body(body.into());
+        message
+    }
+
+    fn transaction(
+        account: &ton_types::UInt256,
+        in_msg: &ton_block::Message,
+        lt: u64,
+    ) -> ton_block::Transaction {
+        let mut tx = ton_block::Transaction::with_address_and_status(
+            ton_types::SliceData::from_raw(account.as_slice().to_vec(), 256),
+            ton_block::AccountStatus::AccStateActive,
+        );
+        tx.set_logical_time(lt);
+        tx.write_in_msg(Some(in_msg)).unwrap();
+        tx
+    }
+
+    fn shard(prefix: u64) -> ton_block::ShardIdent {
+        ton_block::ShardIdent::with_tagged_prefix(ton_block::BASE_WORKCHAIN_ID, prefix).unwrap()
+    }
+
+    const FULL_SHARD: u64 = 0x8000_0000_0000_0000;
+    const LEFT_SHARD: u64 = 0x4000_0000_0000_0000;
+    const RIGHT_SHARD: u64 = 0xc000_0000_0000_0000;
+
+    const RETRIES: RetryPolicy = RetryPolicy::constant(Duration::ZERO, Some(1));
+
+    fn block_id(shard: ton_block::ShardIdent, seq_no: u32) -> ton_block::BlockIdExt {
+        ton_block::BlockIdExt {
+            shard_id: shard,
+            seq_no,
+            root_hash: Default::default(),
+            file_hash: Default::default(),
+        }
+    }
+
+    async fn walk(
+        rpc: &FixtureRpc,
+        edge: &[(ton_block::ShardIdent, u32)],
+        top: &BlockStuff,
+    ) -> Vec<(ton_block::ShardIdent, u32)> {
+        let edge = Edge(edge.iter().copied().collect());
+        let blocks = walk_shard_blocks(rpc, &RETRIES, &edge, top.id().clone())
+            .await
+            .unwrap();
+
+        let mut blocks = blocks
+            .into_iter()
+            .map(|(_, block)| (*block.id().shard(), block.id().seq_no))
+            .collect::<Vec<_>>();
+        blocks.sort_unstable_by_key(|(shard, seq_no)| (shard.shard_prefix_with_tag(), *seq_no));
+        blocks
+    }
+
+    #[test]
+    fn edge_same_shard() {
+        let edge = Edge([(shard(FULL_SHARD), 10)].into_iter().collect());
+        assert!(edge.is_before(&block_id(shard(FULL_SHARD), 11)));
+        assert!(!edge.is_before(&block_id(shard(FULL_SHARD), 10)));
+        assert!(!edge.is_before(&block_id(shard(FULL_SHARD), 9)));
+    }
+
+    #[test]
+    fn edge_after_split() {
+        // The edge still has the parent shard
+        let edge = Edge([(shard(FULL_SHARD), 10)].into_iter().collect());
+        assert!(edge.is_before(&block_id(shard(LEFT_SHARD), 11)));
+        assert!(edge.is_before(&block_id(shard(RIGHT_SHARD), 11)));
+        assert!(!edge.is_before(&block_id(shard(LEFT_SHARD), 10)));
+    }
+
+    #[test]
+    fn edge_after_merge() {
+        // The edge still has both child shards
+        let edge = Edge(
+            [(shard(LEFT_SHARD), 10), (shard(RIGHT_SHARD), 12)]
+                .into_iter()
+                .collect(),
+        );
+        assert!(edge.is_before(&block_id(shard(FULL_SHARD), 13)));
+        assert!(!edge.is_before(&block_id(shard(FULL_SHARD), 10)));
+    }
+
+    #[test]
+    fn edge_unknown_shard() {
+        let edge = Edge([(shard(FULL_SHARD), 10)].into_iter().collect());
+        let mc_block = block_id(ton_block::ShardIdent::masterchain(), 100);
+        assert!(!edge.is_before(&mc_block));
+    }
+
+    #[tokio::test]
+    async fn shards_edge_stops_at_known_block() {
+        let mut rpc = FixtureRpc::with_mc_blocks(vec![BlockParams::new(
+            ton_block::ShardIdent::masterchain(),
+            1,
+            &[],
+        )
+        .build()]);
+
+        let mut prev = BlockParams::new(shard(FULL_SHARD), 5, &[]).build();
+        rpc.add_block(prev.clone());
+        for seq_no in 6..=8 {
+            let block = BlockParams::new(shard(FULL_SHARD), seq_no, &[&prev]).build();
+            rpc.add_block(block.clone());
+            prev = block;
+        }
+
+        let blocks = walk(&rpc, &[(shard(FULL_SHARD), 5)], &prev).await;
+        assert_eq!(blocks, [6, 7, 8].map(|seq_no| (shard(FULL_SHARD), seq_no)));
+
+        // Only the new top block is walked when the previous one is on the edge
+        let blocks = walk(&rpc, &[(shard(FULL_SHARD), 7)], &prev).await;
+        assert_eq!(blocks, [(shard(FULL_SHARD), 8)]);
+    }
+
+    #[tokio::test]
+    async fn shards_edge_after_split() {
+        let mut rpc = FixtureRpc::with_mc_blocks(vec![BlockParams::new(
+            ton_block::ShardIdent::masterchain(),
+            1,
+            &[],
+        )
+        .build()]);
+
+        let parent = BlockParams::new(shard(FULL_SHARD), 5, &[]).build();
+        rpc.add_block(parent.clone());
+
+        let left = BlockParams {
+            after_split: true,
+            ..BlockParams::new(shard(LEFT_SHARD), 6, &[&parent])
+        }
+        .build();
+        rpc.add_block(left.clone());
+
+        let top = BlockParams::new(shard(LEFT_SHARD), 7, &[&left]).build();
+        rpc.add_block(top.clone());
+
+        // The walk must stop at the parent shard block from the edge
+        let blocks = walk(&rpc, &[(shard(FULL_SHARD), 5)], &top).await;
+        assert_eq!(blocks, [(shard(LEFT_SHARD), 6), (shard(LEFT_SHARD), 7)]);
+    }
+
+    #[tokio::test]
+    async fn shards_edge_after_merge() {
+        let mut rpc = FixtureRpc::with_mc_blocks(vec![BlockParams::new(
+            ton_block::ShardIdent::masterchain(),
+            1,
+            &[],
+        )
+        .build()]);
+
+        let left_prev = BlockParams::new(shard(LEFT_SHARD), 5, &[]).build();
+        let left = BlockParams::new(shard(LEFT_SHARD), 6, &[&left_prev]).build();
+        let right = BlockParams::new(shard(RIGHT_SHARD), 6, &[]).build();
+        let merged = BlockParams::new(shard(FULL_SHARD), 7, &[&left, &right]).build();
+        for block in [&left_prev, &left, &right, &merged] {
+            rpc.add_block(block.clone());
+        }
+
+        // Both children are already processed
+        let blocks = walk(
+            &rpc,
+            &[(shard(LEFT_SHARD), 6), (shard(RIGHT_SHARD), 6)],
+            &merged,
+        )
+        .await;
+        assert_eq!(blocks, [(shard(FULL_SHARD), 7)]);
+
+        // Only the right child is processed
+        let blocks = walk(
+            &rpc,
+            &[(shard(LEFT_SHARD), 5), (shard(RIGHT_SHARD), 6)],
+            &merged,
+        )
+        .await;
+        assert_eq!(blocks, [(shard(LEFT_SHARD), 6), (shard(FULL_SHARD), 7)]);
+    }
+
+    #[tokio::test]
+    async fn sent_message_is_matched_to_transaction() {
+        let (account, address) = mc_account(0x11);
+        let message = external_message(&address, 1);
+        let tx = transaction(&account, &message, 1000);
+        let tx_hash = tx.serialize().unwrap().repr_hash();
+
+        let mc1 = BlockParams::new(ton_block::ShardIdent::masterchain(), 1, &[]).build();
+        let mc2 = BlockParams {
+            transactions: vec![tx],
+            ..BlockParams::new(ton_block::ShardIdent::masterchain(), 2, &[&mc1])
+        }
+        .build();
+
+        let subscription = Subscription::with_rpc(
+            FixtureRpc::with_mc_blocks(vec![mc1, mc2]),
+            Default::default(),
+        );
+
+        let found = tokio::time::timeout(
+            Duration::from_secs(10),
+            subscription.send_message(&message, u32::MAX),
+        )
+        .await
+        .unwrap()
+        .unwrap()
+        .expect("message expired");
+
+        assert_eq!(found.hash, tx_hash);
+        assert_eq!(subscription.rpc.sent_messages.lock().len(), 1);
+        assert!(!subscription.has_subscriptions());
+    }
+
+    #[tokio::test]
+    async fn gc_reaps_expired_messages() {
+        let (_, address) = mc_account(0x22);
+        let message = external_message(&address, 1);
+
+        let mc1 = BlockParams::new(ton_block::ShardIdent::masterchain(), 1, &[]).build();
+        let mc2 = BlockParams {
+            gen_utime: 1000,
+            ..BlockParams::new(ton_block::ShardIdent::masterchain(), 2, &[&mc1])
+        }
+        .build();
+
+        let subscription = Subscription::with_rpc(
+            FixtureRpc::with_mc_blocks(vec![mc1, mc2]),
+            Default::default(),
+        );
+
+        let found = tokio::time::timeout(
+            Duration::from_secs(10),
+            subscription.send_message(&message, 999),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        assert!(found.is_none());
+        assert_eq!(subscription.pending_messages().masterchain, 0);
+        assert!(!subscription.has_subscriptions());
+    }
+
+    #[tokio::test]
+    async fn gc_keeps_other_messages_of_the_account() {
+        let (account, address) = mc_account(0x33);
+        let expired = external_message(&address, 1);
+        let delivered = external_message(&address, 2);
+        let pending = external_message(&address, 3);
+        let tx = transaction(&account, &delivered, 1000);
+        let tx_hash = tx.serialize().unwrap().repr_hash();
+
+        let mc1 = BlockParams::new(ton_block::ShardIdent::masterchain(), 1, &[]).build();
+        let mc2 = BlockParams {
+            gen_utime: 1000,
+            transactions: vec![tx],
+            ..BlockParams::new(ton_block::ShardIdent::masterchain(), 2, &[&mc1])
+        }
+        .build();
+
+        let subscription = Subscription::with_rpc(
+            FixtureRpc::with_mc_blocks(vec![mc1, mc2]),
+            Default::default(),
+        );
+
+        // NOTE: all messages are registered on the first poll, before the walk starts
+        let resolved = futures_util::future::join(
+            subscription.send_message(&expired, 999),
+            subscription.send_message(&delivered, u32::MAX),
+        );
+        let pending = subscription.send_message(&pending, u32::MAX);
+        futures_util::pin_mut!(pending);
+
+        let (expired, delivered) = tokio::time::timeout(Duration::from_secs(10), async {
+            tokio::select! {
+                res = resolved => res,
+                _ = &mut pending => panic!("message without a transaction was resolved"),
+            }
+        })
+        .await
+        .unwrap();
+
+        assert!(expired.unwrap().is_none());
+        assert_eq!(delivered.unwrap().expect("message expired").hash, tx_hash);
+
+        // Only the message without a transaction is left
+        assert_eq!(subscription.pending_messages().masterchain, 1);
+        assert_eq!(subscription.subscription_count.load(Ordering::Acquire), 1);
+        assert_eq!(
+            subscription
+                .mc_subscriptions
+                .get(&account)
+                .map(|item| item.pending_messages.len()),
+            Some(1)
+        );
+    }
+
+    #[tokio::test]
+    async fn subscription_count_follows_subscriptions() {
+        let (account, address) = mc_account(0x44);
+        let mc1 = BlockParams::new(ton_block::ShardIdent::masterchain(), 1, &[]).build();
+
+        let subscription =
+            Subscription::with_rpc(FixtureRpc::with_mc_blocks(vec![mc1]), Default::default());
+        let count = || subscription.subscription_count.load(Ordering::Acquire);
+
+        let unbounded = subscription.subscribe(&address);
+        let bounded = subscription.subscribe_bounded(&address, 1);
+        assert_eq!(count(), 2);
+        assert!(subscription.has_subscriptions());
+
+        // Open channels are kept
+        subscription.subscriptions_gc(&subscription.mc_subscriptions, u32::MAX);
+        assert_eq!(count(), 2);
+
+        // Closed channels are removed
+        drop(unbounded);
+        subscription.subscriptions_gc(&subscription.mc_subscriptions, u32::MAX);
+        assert_eq!(count(), 1);
+        assert!(subscription.mc_subscriptions.contains_key(&account));
+
+        // Empty subscriptions are removed
+        drop(bounded);
+        subscription.subscriptions_gc(&subscription.mc_subscriptions, u32::MAX);
+        assert_eq!(count(), 0);
+        assert!(subscription.mc_subscriptions.is_empty());
+        assert!(!subscription.has_subscriptions());
+    }
+
+    #[test]
+    fn shard_blocks_order_is_deterministic() {
+        let block = |shard_prefix: u64, seq_no: u32, gen_utime: u32| {
+            let block = BlockParams {
+                gen_utime,
+                ..BlockParams::new(shard(shard_prefix), seq_no, &[])
+            }
+            .build();
+            (gen_utime, block)
+        };
+
+        // NOTE: blocks of different shards can have the same time
+        let blocks = vec![
+            block(RIGHT_SHARD, 11, 100),
+            block(LEFT_SHARD, 20, 100),
+            block(LEFT_SHARD, 21, 101),
+            block(RIGHT_SHARD, 12, 101),
+            block(RIGHT_SHARD, 13, 102),
+        ];
+        let expected = [
+            (LEFT_SHARD, 20),
+            (RIGHT_SHARD, 11),
+            (LEFT_SHARD, 21),
+            (RIGHT_SHARD, 12),
+            (RIGHT_SHARD, 13),
+        ]
+        .map(|(prefix, seq_no)| (shard(prefix), seq_no));
+
+        // The order must not depend on the order in which the shard tasks finish
+        for shift in 0..blocks.len() {
+            for reversed in [false, true] {
+                let mut blocks = blocks.clone();
+                blocks.rotate_left(shift);
+                if reversed {
+                    blocks.reverse();
+                }
+
+                sort_shard_blocks(&mut blocks);
+                let order = blocks
+                    .iter()
+                    .map(|(_, block)| (*block.id().shard(), block.id().seq_no))
+                    .collect::<Vec<_>>();
+                assert_eq!(order, expected);
+            }
+        }
+    }
+
+    fn account_with_state(state: ton_block::AccountState) -> ton_block::AccountStuff {
+        let (_, address) = mc_account(1);
+        let account = ton_block::Account::uninit(
+            address,
+            1,
+            0,
+            ton_block::CurrencyCollection::with_grams(1_000_000_000),
+        );
+        let ton_block::Account::Account(mut account) = account else {
+            panic!("uninit account must exist");
+        };
+        account.storage.state = state;
+        account
+    }
+
+    #[test]
+    fn account_status_from_state() {
+        let cases = [
+            (None, AccountStatus::NotExists),
+            (
+                Some(ton_block::AccountState::AccountUninit),
+                AccountStatus::Uninit,
+            ),
+            (
+                Some(ton_block::AccountState::AccountActive {
+                    state_init: Default::default(),
+                }),
+                AccountStatus::Active,
+            ),
+            (
+                Some(ton_block::AccountState::AccountFrozen {
+                    state_init_hash: Default::default(),
+                }),
+                AccountStatus::Frozen,
+            ),
+        ];
+
+        for (state, expected) in cases {
+            let account = state.map(account_with_state);
+            assert_eq!(AccountStatus::from_state(account.as_ref()), expected);
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+
+use super::split_address;
+
+const BOUNCEABLE_TAG: u8 = 0x11;
+const NON_BOUNCEABLE_TAG: u8 = 0x51;
+const TESTNET_FLAG: u8 = 0x80;
+
+const PACKED_LEN: usize = 36;
+
+/// Parses the address either in raw (`wc:hex`) or in user-friendly (base64) format
+pub fn parse_any_address(address: &str) -> Result<ton_block::MsgAddressInt> {
+    let address = address.trim();
+    if address.contains(':') {
+        return ton_block::MsgAddressInt::from_str(address).map_err(From::from);
+    }
+    unpack_friendly_address(address).context("invalid address")
+}
+
+/// Packs the standard address into the url-safe user-friendly format
+pub fn pack_friendly_address(
+    address: &ton_block::MsgAddressInt,
+    bounceable: bool,
+) -> Result<String> {
+    if let ton_block::MsgAddressInt::AddrStd(address) = address {
+        anyhow::ensure!(
+            address.anycast.is_none(),
+            "anycast addresses can't be packed"
+        );
+    }
+    let (workchain, address) = split_address(address)?;
+
+    let mut data = Vec::with_capacity(PACKED_LEN);
+    data.push(if bounceable {
+        BOUNCEABLE_TAG
+    } else {
+        NON_BOUNCEABLE_TAG
+    });
+    data.push(workchain as u8);
+    data.extend_from_slice(address.as_slice());
+    data.extend_from_slice(&crc16(&data).to_be_bytes());
+
+    Ok(base64::encode_config(data, base64::URL_SAFE))
+}
+
+/// Unpacks the user-friendly address in both standard and url-safe base64.
+///
+/// NOTE: Bounceable and testnet flags are accepted, but don't affect the address
+fn unpack_friendly_address(address: &str) -> Result<ton_block::MsgAddressInt> {
+    let data = base64::decode_config(address, base64::URL_SAFE)
+        .or_else(|_| base64::decode(address))
+        .context("invalid base64")?;
+    anyhow::ensure!(
+        data.len() == PACKED_LEN,
+        "expected {PACKED_LEN} bytes, got {}",
+        data.len()
+    );
+
+    let (data, checksum) = data.split_at(PACKED_LEN - 2);
+    anyhow::ensure!(crc16(data).to_be_bytes() == checksum, "checksum mismatch");
+
+    let tag = data[0] & !TESTNET_FLAG;
+    anyhow::ensure!(
+        tag == BOUNCEABLE_TAG || tag == NON_BOUNCEABLE_TAG,
+        "unknown address tag 0x{tag:02x}"
+    );
+
+    let workchain = data[1] as i8;
+    let address = ton_types::UInt256::from_slice(&data[2..]);
+    Ok(ton_block::MsgAddressInt::AddrStd(
+        ton_block::MsgAddrStd::with_address(None, workchain, address.into()),
+    ))
+}
+
+/// CRC16-XMODEM
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for byte in data {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ZERO_ADDRESS: &str = "0:0000000000000000000000000000000000000000000000000000000000000000";
+
+    fn addr(raw: &str) -> ton_block::MsgAddressInt {
+        ton_block::MsgAddressInt::from_str(raw).unwrap()
+    }
+
+    #[test]
+    fn pack_unpack_round_trip() {
+        let raw = "-1:3333333333333333333333333333333333333333333333333333333333333333";
+        let packed = pack_friendly_address(&addr(raw), true).unwrap();
+        assert_eq!(packed, "Ef8zMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzM0vF");
+        assert_eq!(parse_any_address(&packed).unwrap(), addr(raw));
+
+        // Raw addresses are parsed as is
+        assert_eq!(parse_any_address(&format!(" {raw} ")).unwrap(), addr(raw));
+    }
+
+    #[test]
+    fn bounceable_address() {
+        let packed = pack_friendly_address(&addr(ZERO_ADDRESS), true).unwrap();
+        assert_eq!(packed, "EQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAM9c");
+        assert_eq!(parse_any_address(&packed).unwrap(), addr(ZERO_ADDRESS));
+    }
+
+    #[test]
+    fn non_bounceable_address() {
+        let packed = pack_friendly_address(&addr(ZERO_ADDRESS), false).unwrap();
+        assert_eq!(packed, "UQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAJKZ");
+        assert_eq!(parse_any_address(&packed).unwrap(), addr(ZERO_ADDRESS));
+    }
+
+    #[test]
+    fn testnet_address() {
+        for packed in [
+            "kQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAHTW",
+            "0QAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAACkT",
+        ] {
+            assert_eq!(parse_any_address(packed).unwrap(), addr(ZERO_ADDRESS));
+        }
+    }
+
+    #[test]
+    fn bad_checksum() {
+        let err = unpack_friendly_address("EQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAM9d")
+            .unwrap_err();
+        assert_eq!(err.to_string(), "checksum mismatch");
+        assert!(parse_any_address("EQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAM9d").is_err());
+    }
+}
@@ -2,6 +2,7 @@ use anyhow::{Context, Result};
 use rustc_hash::FxHashMap;
 use ton_block::Deserializable;
 
+#[derive(Clone)]
 pub struct BlockStuff {
     id: ton_block::BlockIdExt,
     block: ton_block::Block,
@@ -10,13 +11,14 @@ pub struct BlockStuff {
 impl BlockStuff {
     pub fn new(mut data: &[u8], id: ton_block::BlockIdExt) -> Result<Self> {
         let file_hash = ton_types::UInt256::calc_file_hash(data);
-        anyhow::ensure!(id.file_hash() == file_hash, "wrong file_hash for {id}");
+        if id.file_hash() != file_hash {
+            return Err(BlockStuffError::WrongFileHash(id).into());
+        }
 
         let root = ton_types::deserialize_tree_of_cells(&mut data)?;
-        anyhow::ensure!(
-            id.root_hash() == root.repr_hash(),
-            "wrong root hash for {id}"
-        );
+        if id.root_hash() != root.repr_hash() {
+            return Err(BlockStuffError::WrongRootHash(id).into());
+        }
 
         let block = ton_block::Block::construct_from(&mut root.into())?;
         Ok(Self { id, block })
@@ -125,3 +127,11 @@ pub struct BriefBlockInfo {
     pub prev1: ton_block::BlockIdExt,
     pub prev2: Option<ton_block::BlockIdExt>,
 }
+
+#[derive(thiserror::Error, Debug)]
+pub enum BlockStuffError {
+    #[error("wrong file_hash for {0}")]
+    WrongFileHash(ton_block::BlockIdExt),
+    #[error("wrong root hash for {0}")]
+    WrongRootHash(ton_block::BlockIdExt),
+}
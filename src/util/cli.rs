@@ -26,16 +26,17 @@ pub struct Tokens<T>(pub T);
 
 impl<T: Into<u128> + Copy> std::fmt::Display for Tokens<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let decimals = crate::currency::decimals() as usize;
+        let one_coin = 10u128.pow(decimals as u32);
+
         let num: u128 = self.0.into();
-        let int = num / 1000000000;
-        let mut frac = num % 1000000000;
+        let int = num / one_coin;
+        let frac = num % one_coin;
 
         int.fmt(f)?;
         if frac > 0 {
-            while frac % 10 == 0 && frac > 0 {
-                frac /= 10;
-            }
-            f.write_fmt(format_args!(".{frac}"))?;
+            let frac = format!("{frac:0decimals$}");
+            f.write_fmt(format_args!(".{}", frac.trim_end_matches('0')))?;
         }
         Ok(())
     }
@@ -54,7 +55,7 @@ impl FromStr for AddressInput {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        ton_block::MsgAddressInt::from_str(s.trim())
+        parse_any_address(s)
             .map(Self)
             .map_err(|_| anyhow::Error::msg("invalid address"))
     }
@@ -96,8 +97,30 @@ where
     ton_abi::Contract::try_from(contract)
 }
 
+/// Parses `seqno:root_hash:file_hash` of the masterchain block
+pub fn parse_mc_block_id(s: &str) -> Result<ton_block::BlockIdExt> {
+    let parse_hash = |hash: &str| -> Result<ton_types::UInt256> {
+        let hash = <[u8; 32]>::try_from(hex::decode(hash)?.as_slice())?;
+        Ok(ton_types::UInt256::from(hash))
+    };
+
+    let mut parts = s.split(':');
+    let (Some(seq_no), Some(root_hash), Some(file_hash), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        anyhow::bail!("expected `seqno:root_hash:file_hash`");
+    };
+
+    Ok(ton_block::BlockIdExt {
+        shard_id: ton_block::ShardIdent::masterchain(),
+        seq_no: seq_no.parse().context("invalid seqno")?,
+        root_hash: parse_hash(root_hash).context("invalid root hash")?,
+        file_hash: parse_hash(file_hash).context("invalid file hash")?,
+    })
+}
+
 pub fn parse_address(address: &str) -> Result<ton_block::MsgAddressInt> {
-    ton_block::MsgAddressInt::from_str(address).map_err(From::from)
+    parse_any_address(address)
 }
 
 pub fn parse_optional_pubkey(pubkey: Option<String>) -> Result<Option<ed25519_dalek::PublicKey>> {
@@ -152,6 +175,53 @@ pub fn parse_hex_or_base64(data: &str) -> Result<Vec<u8>> {
     }
 }
 
+/// Parses unix timestamp, ISO 8601 date or datetime (UTC by default)
+/// or relative time in the past (e.g. `30m`, `12h`, `7d`, `2w`)
+pub fn parse_timestamp(value: &str, now: u32) -> Result<u32> {
+    use time::format_description::well_known::Rfc3339;
+
+    const DATETIME_FORMAT: &str = "[year]-[month]-[day]T[hour]:[minute]:[second]";
+    const DATE_FORMAT: &str = "[year]-[month]-[day]";
+
+    let value = value.trim();
+    if let Ok(timestamp) = u32::from_str(value) {
+        return Ok(timestamp);
+    }
+
+    if let Some((amount, unit)) = value
+        .find(|c: char| !c.is_ascii_digit())
+        .map(|i| value.split_at(i))
+    {
+        let multiplier = match unit {
+            "s" => Some(1),
+            "m" => Some(60),
+            "h" => Some(3600),
+            "d" => Some(86400),
+            "w" => Some(604800),
+            _ => None,
+        };
+        if let (Some(multiplier), Ok(amount)) = (multiplier, u32::from_str(amount)) {
+            return Ok(now.saturating_sub(amount.saturating_mul(multiplier)));
+        }
+    }
+
+    let datetime = match time::OffsetDateTime::parse(value, &Rfc3339) {
+        Ok(datetime) => datetime,
+        Err(_) => {
+            let datetime_format = time::format_description::parse(DATETIME_FORMAT)?;
+            let date_format = time::format_description::parse(DATE_FORMAT)?;
+            match time::PrimitiveDateTime::parse(value, &datetime_format) {
+                Ok(datetime) => datetime.assume_utc(),
+                Err(_) => time::Date::parse(value, &date_format)
+                    .with_context(|| format!("invalid timestamp `{value}`"))?
+                    .midnight()
+                    .assume_utc(),
+            }
+        }
+    };
+    u32::try_from(datetime.unix_timestamp()).context("timestamp is out of range")
+}
+
 pub fn confirm<T>(theme: &dyn Theme, default: bool, text: T) -> std::io::Result<bool>
 where
     T: Into<String>,
@@ -162,6 +232,26 @@ where
         .interact()
 }
 
+/// Formats seconds as e.g. `18h 12m 16s`
+pub fn human_duration(secs: u32) -> String {
+    const UNITS: [(u32, &str); 4] = [(86400, "d"), (3600, "h"), (60, "m"), (1, "s")];
+
+    if secs == 0 {
+        return "0s".to_owned();
+    }
+
+    let mut remaining = secs;
+    let mut parts = Vec::new();
+    for (unit, suffix) in UNITS {
+        let value = remaining / unit;
+        if value > 0 {
+            parts.push(format!("{value}{suffix}"));
+            remaining %= unit;
+        }
+    }
+    parts.join(" ")
+}
+
 pub fn print_output<T: std::fmt::Display>(arg: T) {
     if console::user_attended() {
         writeln!(std::io::stdout(), "{arg:#}")
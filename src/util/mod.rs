@@ -2,13 +2,21 @@ use std::hash::BuildHasherDefault;
 
 use dashmap::DashMap;
 
+pub use self::address::*;
 pub use self::block_stuff::*;
 pub use self::cli::*;
+pub use self::net::*;
+pub use self::redact::*;
+pub use self::retry::*;
 pub use self::serde::*;
 pub use self::transaction::*;
 
+mod address;
 mod block_stuff;
 mod cli;
+mod net;
+mod redact;
+mod retry;
 mod serde;
 pub mod system;
 mod transaction;
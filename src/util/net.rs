@@ -0,0 +1,111 @@
+use std::future::Future;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use futures_util::stream::{FuturesUnordered, StreamExt};
+use serde::{Deserialize, Serialize};
+
+/// IP family of the node endpoints which can be used
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressFamily {
+    /// Both families, IPv6 is preferred
+    #[default]
+    Any,
+    Ipv4,
+    Ipv6,
+}
+
+impl AddressFamily {
+    pub fn allows(&self, addr: &SocketAddr) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Ipv4 => addr.is_ipv4(),
+            Self::Ipv6 => addr.is_ipv6(),
+        }
+    }
+
+    /// Filters the addresses and orders them for the connection attempts.
+    ///
+    /// NOTE: Families alternate starting from IPv6 (RFC 8305),
+    /// the order within each family is preserved
+    pub fn candidates<I>(&self, addrs: I) -> Vec<SocketAddr>
+    where
+        I: IntoIterator<Item = SocketAddr>,
+    {
+        let (v6, v4): (Vec<_>, Vec<_>) = addrs
+            .into_iter()
+            .filter(|addr| self.allows(addr))
+            .partition(SocketAddr::is_ipv6);
+
+        let mut result = Vec::with_capacity(v6.len() + v4.len());
+        let (mut v6, mut v4) = (v6.into_iter(), v4.into_iter());
+        loop {
+            match (v6.next(), v4.next()) {
+                (None, None) => break,
+                (v6, v4) => result.extend(v6.into_iter().chain(v4)),
+            }
+        }
+        result
+    }
+}
+
+impl std::fmt::Display for AddressFamily {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Any => "any",
+            Self::Ipv4 => "ipv4",
+            Self::Ipv6 => "ipv6",
+        })
+    }
+}
+
+/// Delay before the next connection attempt is started (RFC 8305)
+pub const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Connects to the first responding candidate ("happy eyeballs").
+///
+/// Attempts are started one after another with a short delay (or right after
+/// the previous one fails) and run concurrently, the first successful one wins.
+/// Returns the error of the last failed attempt if all of them fail.
+///
+/// NOTE: `candidates` must not be empty
+pub async fn happy_eyeballs<T, E, F, R>(candidates: &[SocketAddr], mut connect: F) -> Result<T, E>
+where
+    F: FnMut(SocketAddr) -> R,
+    R: Future<Output = Result<T, E>>,
+{
+    assert!(!candidates.is_empty(), "no candidates to connect to");
+
+    let mut candidates = candidates.iter().copied();
+    let mut attempts = FuturesUnordered::new();
+    let mut last_error = None;
+
+    if let Some(addr) = candidates.next() {
+        attempts.push(connect(addr));
+    }
+
+    loop {
+        tokio::select! {
+            Some(res) = attempts.next() => match res {
+                Ok(value) => return Ok(value),
+                Err(e) => {
+                    last_error = Some(e);
+                    match candidates.next() {
+                        Some(addr) => attempts.push(connect(addr)),
+                        None if attempts.is_empty() => break,
+                        None => {}
+                    }
+                }
+            },
+            _ = tokio::time::sleep(CONNECTION_ATTEMPT_DELAY) => {
+                if let Some(addr) = candidates.next() {
+                    attempts.push(connect(addr));
+                }
+            }
+        }
+    }
+
+    // NOTE: the loop is left only after a failed attempt
+    Err(last_error.expect("no attempts were made"))
+}
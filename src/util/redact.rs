@@ -0,0 +1,33 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static REDACT_LOGS: AtomicBool = AtomicBool::new(false);
+
+/// Number of chars kept at each side of the redacted value
+const VISIBLE_CHARS: usize = 6;
+
+/// Enables or disables truncation of the addresses in logs
+pub fn set_log_redaction(enabled: bool) {
+    REDACT_LOGS.store(enabled, Ordering::Relaxed);
+}
+
+/// Display wrapper for the log fields, which shows only the first and the last
+/// chars of the value if the log redaction is enabled.
+pub struct Redacted<'a, T>(pub &'a T);
+
+impl<T: std::fmt::Display> std::fmt::Display for Redacted<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !REDACT_LOGS.load(Ordering::Relaxed) {
+            return self.0.fmt(f);
+        }
+
+        let value = self.0.to_string();
+        let len = value.chars().count();
+        if len <= VISIBLE_CHARS * 2 {
+            return f.write_str(&value);
+        }
+
+        let head = value.chars().take(VISIBLE_CHARS).collect::<String>();
+        let tail = value.chars().skip(len - VISIBLE_CHARS).collect::<String>();
+        write!(f, "{head}…{tail}")
+    }
+}
@@ -0,0 +1,105 @@
+use std::time::Duration;
+
+use broxus_util::serde_duration_ms;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Exponential backoff params
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetryPolicy {
+    /// Delay before the first retry
+    #[serde(with = "serde_duration_ms")]
+    pub interval: Duration,
+
+    /// Delay multiplier for each next retry
+    #[serde(default = "default_multiplier")]
+    pub multiplier: f64,
+
+    /// Upper bound of the delay
+    #[serde(with = "serde_duration_ms")]
+    pub max_interval: Duration,
+
+    /// Max random delay added to each computed delay
+    #[serde(with = "serde_duration_ms", default)]
+    pub jitter: Duration,
+
+    /// Max number of attempts (including the first one). Unlimited if empty
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_attempts: Option<u32>,
+}
+
+impl RetryPolicy {
+    /// Policy with a constant delay between attempts
+    pub const fn constant(interval: Duration, max_attempts: Option<u32>) -> Self {
+        Self {
+            interval,
+            multiplier: 1.0,
+            max_interval: interval,
+            jitter: Duration::ZERO,
+            max_attempts,
+        }
+    }
+
+    /// Max duration of the operation if each attempt takes `attempt_duration`.
+    ///
+    /// NOTE: only the first attempt is counted if attempts are unlimited
+    pub fn max_duration(&self, attempt_duration: Duration) -> Duration {
+        let attempts = self.max_attempts.unwrap_or(1).max(1);
+
+        let mut total = attempt_duration;
+        let mut delay = self.interval.as_secs_f64();
+        for _ in 1..attempts {
+            let max_delay = Duration::from_secs_f64(delay.min(self.max_interval.as_secs_f64()));
+            total = total
+                .saturating_add(max_delay)
+                .saturating_add(self.jitter)
+                .saturating_add(attempt_duration);
+            delay *= self.multiplier;
+        }
+        total
+    }
+
+    pub fn backoff(&self) -> Backoff {
+        Backoff {
+            policy: *self,
+            attempt: 0,
+        }
+    }
+}
+
+/// Retry state for the single operation
+pub struct Backoff {
+    policy: RetryPolicy,
+    attempt: u32,
+}
+
+impl Backoff {
+    /// Number of the already finished attempts
+    pub fn attempts(&self) -> u32 {
+        self.attempt + 1
+    }
+
+    /// Returns the delay before the next attempt or `None` if attempts are exhausted
+    pub fn next_delay(&mut self) -> Option<Duration> {
+        if matches!(self.policy.max_attempts, Some(max) if self.attempt + 1 >= max) {
+            return None;
+        }
+
+        let policy = &self.policy;
+        let delay = policy.interval.as_secs_f64() * policy.multiplier.powi(self.attempt as i32);
+        let mut delay = Duration::from_secs_f64(delay.min(policy.max_interval.as_secs_f64()));
+
+        let jitter = policy.jitter.as_millis() as u64;
+        if jitter > 0 {
+            delay += Duration::from_millis(rand::thread_rng().gen_range(0..=jitter));
+        }
+
+        self.attempt += 1;
+        Some(delay)
+    }
+}
+
+fn default_multiplier() -> f64 {
+    1.0
+}
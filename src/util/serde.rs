@@ -1,7 +1,8 @@
 use everscale_crypto::ed25519;
 use serde::{Deserialize, Deserializer, Serializer};
 
-pub mod serde_mc_address {
+/// Address in raw format, which can also be parsed from the user-friendly format
+pub mod serde_address {
     use broxus_util::serde_string;
     use ton_block::MsgAddressInt;
 
@@ -14,12 +15,63 @@ pub mod serde_mc_address {
     ) -> Result<MsgAddressInt, D::Error> {
         use serde::de::Error;
 
-        match serde_string::deserialize(deserializer)? {
+        let str = String::deserialize(deserializer)?;
+        crate::util::parse_any_address(&str).map_err(Error::custom)
+    }
+}
+
+pub mod serde_optional_address {
+    use ton_block::MsgAddressInt;
+
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        address: &Option<MsgAddressInt>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match address {
+            Some(address) => serde_address::serialize(address, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<MsgAddressInt>, D::Error> {
+        use serde::de::Error;
+
+        match Option::<String>::deserialize(deserializer)? {
+            Some(str) => crate::util::parse_any_address(&str)
+                .map(Some)
+                .map_err(Error::custom),
+            None => Ok(None),
+        }
+    }
+}
+
+pub mod serde_mc_address {
+    use ton_block::MsgAddressInt;
+
+    use super::*;
+
+    pub use super::serde_address::serialize;
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<MsgAddressInt, D::Error> {
+        use serde::de::Error;
+
+        match serde_address::deserialize(deserializer)? {
             MsgAddressInt::AddrStd(addr) => {
                 if addr.workchain_id as i32 == ton_block::MASTERCHAIN_ID {
                     Ok(MsgAddressInt::AddrStd(addr))
                 } else {
-                    Err(Error::custom("expected masterchain address"))
+                    Err(Error::custom(format!(
+                        "expected masterchain address, got workchain {} (stakes can only be \
+                        sent from the masterchain, use the workchain -1 address from \
+                        `nodekeeper keys inspect`)",
+                        addr.workchain_id
+                    )))
                 }
             }
             MsgAddressInt::AddrVar(_) => Err(Error::custom("unsupported address")),
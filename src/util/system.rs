@@ -44,6 +44,18 @@ pub fn home_dir(uid: u32) -> Option<PathBuf> {
     }
 }
 
+pub fn host_name() -> Option<String> {
+    let mut buf = [0u8; 256];
+    // SAFETY: `buf` is valid for `buf.len()` bytes
+    match unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) } {
+        0 => {
+            let len = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+            Some(String::from_utf8_lossy(&buf[..len]).into_owned())
+        }
+        _ => None,
+    }
+}
+
 unsafe fn get_passwd(uid: u32, buf: &mut Buffer) -> Option<libc::passwd> {
     let mut pwd: MaybeUninit<libc::passwd> = MaybeUninit::uninit();
     let mut pwdp = ptr::null_mut();
@@ -69,3 +81,36 @@ fn make_buffer() -> Buffer {
 }
 
 type Buffer = Vec<libc::c_char>;
+
+/// Asks the process to stop with `SIGTERM`
+pub fn terminate(pid: u32) -> std::io::Result<()> {
+    let pid = match libc::pid_t::try_from(pid) {
+        Ok(pid) if pid > 0 => pid,
+        _ => return Err(std::io::ErrorKind::InvalidInput.into()),
+    };
+
+    // SAFETY: no pointers are passed
+    match unsafe { libc::kill(pid, libc::SIGTERM) } {
+        0 => Ok(()),
+        _ => Err(std::io::Error::last_os_error()),
+    }
+}
+
+/// Tries to take an exclusive advisory lock of the file (released when the file is closed).
+///
+/// Returns `false` if the lock is held by another process
+pub fn try_lock_exclusive(file: &std::fs::File) -> std::io::Result<bool> {
+    use std::os::unix::io::AsRawFd;
+
+    // SAFETY: the descriptor is valid while `file` is alive
+    match unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } {
+        0 => Ok(true),
+        _ => {
+            let e = std::io::Error::last_os_error();
+            match e.raw_os_error() {
+                Some(libc::EWOULDBLOCK) => Ok(false),
+                _ => Err(e),
+            }
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 
 pub fn split_address(address: &ton_block::MsgAddressInt) -> Result<(i32, ton_types::UInt256)> {
     match address {
@@ -37,3 +37,107 @@ pub struct TransactionWithHash {
     pub hash: ton_types::UInt256,
     pub data: ton_block::Transaction,
 }
+
+impl TransactionWithHash {
+    /// Fees paid in the transaction (storage, compute, action and import fees)
+    pub fn total_fees(&self) -> u128 {
+        self.data.total_fees().grams.0
+    }
+
+    /// Fails if the inbound message was rejected by the recipient contract
+    /// (its compute phase failed or it was bounced back).
+    ///
+    /// NOTE: A transaction with a skipped compute phase is also aborted, but
+    /// a non-bounceable message still credits the account (e.g. an uninit one)
+    pub fn ensure_not_aborted(&self) -> Result<()> {
+        let description = self
+            .data
+            .read_description()
+            .context("failed to read transaction description")?;
+        let ton_block::TransactionDescr::Ordinary(description) = description else {
+            return Ok(());
+        };
+
+        let compute_failed = matches!(
+            &description.compute_ph,
+            ton_block::TrComputePhase::Vm(phase) if !phase.success
+        );
+        if !description.aborted || (!compute_failed && description.bounce.is_none()) {
+            return Ok(());
+        }
+
+        let compute_phase = match &description.compute_ph {
+            ton_block::TrComputePhase::Skipped(phase) => format!("skipped ({:?})", phase.reason),
+            ton_block::TrComputePhase::Vm(phase) => format!("exit code {}", phase.exit_code),
+        };
+        let action_phase = match &description.action {
+            Some(phase) => format!("result code {}", phase.result_code),
+            None => "skipped".to_owned(),
+        };
+
+        Err(MessageBounced {
+            tx_hash: self.hash.to_hex_string(),
+            compute_phase,
+            action_phase,
+        }
+        .into())
+    }
+}
+
+/// The message was rejected by the recipient contract
+#[derive(thiserror::Error, Debug)]
+#[error(
+    "message bounced: transaction {tx_hash} was aborted \
+    (compute phase: {compute_phase}, action phase: {action_phase})"
+)]
+pub struct MessageBounced {
+    tx_hash: String,
+    compute_phase: String,
+    action_phase: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transaction(description: ton_block::TransactionDescrOrdinary) -> TransactionWithHash {
+        let mut data = ton_block::Transaction::default();
+        data.write_description(&ton_block::TransactionDescr::Ordinary(description))
+            .unwrap();
+        TransactionWithHash {
+            hash: Default::default(),
+            data,
+        }
+    }
+
+    #[test]
+    fn credit_only_transaction_is_not_bounced() {
+        let tx = transaction(ton_block::TransactionDescrOrdinary {
+            credit_ph: Some(ton_block::TrCreditPhase {
+                due_fees_collected: None,
+                credit: ton_block::CurrencyCollection::with_grams(1_000_000_000),
+            }),
+            compute_ph: ton_block::TrComputePhase::Skipped(ton_block::TrComputePhaseSkipped {
+                reason: ton_block::ComputeSkipReason::NoState,
+            }),
+            aborted: true,
+            ..Default::default()
+        });
+        tx.ensure_not_aborted().unwrap();
+    }
+
+    #[test]
+    fn failed_compute_phase_is_bounced() {
+        let tx = transaction(ton_block::TransactionDescrOrdinary {
+            compute_ph: ton_block::TrComputePhase::Vm(ton_block::TrComputePhaseVm {
+                success: false,
+                exit_code: 100,
+                ..Default::default()
+            }),
+            aborted: true,
+            bounce: Some(ton_block::TrBouncePhase::Negfunds),
+            ..Default::default()
+        });
+        assert!(tx.ensure_not_aborted().is_err());
+    }
+}
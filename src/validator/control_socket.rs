@@ -0,0 +1,210 @@
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{Mutex, Notify};
+
+use super::{recover_stakes, ValidationManager, ValidationParams};
+use crate::dirs::ProjectDirs;
+use crate::exporter::Exporter;
+
+/// Local control socket of the running validator.
+///
+/// Accepts newline-delimited JSON-RPC 2.0 requests, e.g.
+/// `{"jsonrpc":"2.0","id":1,"method":"pause"}`, and writes one response line per request.
+///
+/// Methods:
+/// - `status` — pause state, skipped elections and the collected metrics;
+/// - `pause` / `resume` — toggles the pause file after the in-flight election message;
+/// - `recover` — recovers unfrozen stakes of the main validator;
+/// - `reload` — wakes the validation loop up to apply the current config.
+pub struct ControlSocket {
+    path: PathBuf,
+    server: tokio::task::JoinHandle<()>,
+}
+
+impl ControlSocket {
+    pub fn bind(path: &Path, manager: &ValidationManager) -> Result<Self> {
+        // NOTE: the socket file is left after an unclean shutdown
+        if path.exists() {
+            std::fs::remove_file(path).context("failed to remove stale control socket")?;
+        }
+
+        let listener = UnixListener::bind(path)
+            .with_context(|| format!("failed to bind control socket to {}", path.display()))?;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+            .context("failed to restrict control socket permissions")?;
+
+        tracing::info!(path = %path.display(), "started control socket");
+
+        let state = Arc::new(ControlState {
+            dirs: manager.dirs.clone(),
+            params: manager.params.clone(),
+            guard: manager.guard.clone(),
+            wakeup: manager.wakeup.clone(),
+            exporter: Exporter::new(manager.dirs.clone(), Vec::new()),
+        });
+
+        let server = tokio::spawn(async move {
+            loop {
+                let stream = match listener.accept().await {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        tracing::warn!("failed to accept control connection: {e:?}");
+                        continue;
+                    }
+                };
+
+                let state = state.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, &state).await {
+                        tracing::debug!("control connection failed: {e:?}");
+                    }
+                });
+            }
+        });
+
+        Ok(Self {
+            path: path.to_owned(),
+            server,
+        })
+    }
+}
+
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        self.server.abort();
+        std::fs::remove_file(&self.path).ok();
+    }
+}
+
+struct ControlState {
+    dirs: ProjectDirs,
+    params: ValidationParams,
+    guard: Arc<Mutex<()>>,
+    wakeup: Arc<Notify>,
+    /// Reused between requests to not query the validator status each time
+    exporter: Exporter,
+}
+
+impl ControlState {
+    async fn call(&self, method: &str) -> Result<serde_json::Value, RpcError> {
+        match method {
+            "status" => {
+                // NOTE: metrics are collected separately to not depend on the exporter process
+                let metrics = self.exporter.dump().await.map_err(RpcError::failed)?;
+                let skipped_election = self
+                    .dirs
+                    .load_skipped_election()
+                    .map_err(RpcError::failed)?;
+
+                Ok(serde_json::json!({
+                    "paused": self.dirs.is_paused(),
+                    "skipped_election_id": skipped_election,
+                    "metrics": metrics,
+                }))
+            }
+            "pause" | "resume" => {
+                let paused = method == "pause";
+
+                // NOTE: wait for the in-flight election message
+                let _guard = self.guard.lock().await;
+                self.dirs.set_paused(paused).map_err(RpcError::failed)?;
+                tracing::warn!(paused, "validation pause changed via the control socket");
+
+                if !paused {
+                    self.wakeup.notify_one();
+                }
+                Ok(serde_json::json!({ "paused": paused }))
+            }
+            "recover" => {
+                tracing::info!("recovering stakes via the control socket");
+                recover_stakes(&self.dirs, &self.params, &self.guard)
+                    .await
+                    .map_err(RpcError::failed)?;
+                Ok(serde_json::json!({}))
+            }
+            "reload" => {
+                self.wakeup.notify_one();
+                Ok(serde_json::json!({}))
+            }
+            _ => Err(RpcError {
+                code: METHOD_NOT_FOUND,
+                message: format!("unknown method `{method}`"),
+            }),
+        }
+    }
+}
+
+async fn handle_connection(stream: UnixStream, state: &ControlState) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => match state.call(&request.method).await {
+                Ok(result) => serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": request.id,
+                    "result": result,
+                }),
+                Err(e) => e.into_response(request.id),
+            },
+            Err(e) => RpcError {
+                code: PARSE_ERROR,
+                message: e.to_string(),
+            }
+            .into_response(serde_json::Value::Null),
+        };
+
+        let mut response = response.to_string();
+        response.push('\n');
+        writer.write_all(response.as_bytes()).await?;
+    }
+
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: serde_json::Value,
+    method: String,
+}
+
+struct RpcError {
+    code: i32,
+    message: String,
+}
+
+impl RpcError {
+    fn failed(e: anyhow::Error) -> Self {
+        Self {
+            code: SERVER_ERROR,
+            message: format!("{e:?}"),
+        }
+    }
+
+    fn into_response(self, id: serde_json::Value) -> serde_json::Value {
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": {
+                "code": self.code,
+                "message": self.message,
+            },
+        })
+    }
+}
+
+const PARSE_ERROR: i32 = -32700;
+const METHOD_NOT_FOUND: i32 = -32601;
+const SERVER_ERROR: i32 = -32000;
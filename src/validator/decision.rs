@@ -0,0 +1,190 @@
+use crate::config::*;
+
+/// Inputs of the decision whether to participate in the current elections
+pub struct ElectionsInput {
+    pub election_id: u32,
+    /// Elections marked as skipped by the operator
+    pub skipped_election: Option<u32>,
+    pub paused: bool,
+    pub round_parity: Option<RoundParity>,
+    pub validators_elected_for: u32,
+    /// Time after which no attempts are made
+    pub deadline_at: u32,
+    /// Time when the attempt will start (e.g. after the previous stakes unfreeze)
+    pub start_at: u32,
+    pub min_time_remaining: u32,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ElectionsDecision {
+    /// Stakes will be sent
+    Participate,
+    /// Elections were marked as skipped by the operator
+    Skipped,
+    /// Validation is paused, no messages are sent
+    Paused,
+    /// Elections round doesn't match the configured parity
+    OtherParity(RoundParity),
+    /// Attempt can't finish before the deadline
+    InsufficientTime { time_remaining: u32 },
+}
+
+impl ElectionsInput {
+    /// Returns when the attempt starts.
+    ///
+    /// NOTE: stakes are sent after the previous stakes unfreeze,
+    /// unless they unfreeze only after the deadline
+    pub fn attempt_start_at(now: u32, unfreeze_at: Option<u32>, deadline_at: u32) -> u32 {
+        match unfreeze_at {
+            Some(unfreeze_at) if unfreeze_at <= deadline_at => std::cmp::max(now, unfreeze_at),
+            _ => now,
+        }
+    }
+
+    pub fn decide(&self) -> ElectionsDecision {
+        if self.skipped_election == Some(self.election_id) {
+            return ElectionsDecision::Skipped;
+        }
+        if self.paused {
+            return ElectionsDecision::Paused;
+        }
+        if let Some(parity) = self.round_parity {
+            if !parity.matches(self.election_id, self.validators_elected_for) {
+                return ElectionsDecision::OtherParity(parity);
+            }
+        }
+
+        let time_remaining = self.deadline_at.saturating_sub(self.start_at);
+        if time_remaining < self.min_time_remaining {
+            return ElectionsDecision::InsufficientTime { time_remaining };
+        }
+
+        ElectionsDecision::Participate
+    }
+}
+
+/// Stake of a single validator for the current elections
+#[derive(Debug, Clone, Copy)]
+pub enum SingleStake {
+    Fixed {
+        stake: u128,
+    },
+    /// Fixed stake with the latest reward compounded into it
+    Compounded {
+        base: u128,
+        reward: u128,
+        compounded: u128,
+    },
+    /// Stake resolved from the wallet balance
+    Percent {
+        percent: u8,
+        balance: u128,
+        stake: u128,
+    },
+    /// Stake resolved from the wallet balance is below the min stake
+    BelowMinStake {
+        percent: u8,
+        stake: u128,
+        min_stake: u128,
+    },
+}
+
+impl SingleStake {
+    /// Returns whether the stake depends on the wallet balance
+    pub fn requires_balance(single: &AppConfigValidatorSingle) -> bool {
+        !matches!(single.stake_per_round, StakeAmount::Fixed(_) if !single.compound_rewards)
+    }
+
+    /// Resolves the stake for these elections.
+    ///
+    /// NOTE: `reserve` is kept on the wallet for fees and is never staked
+    pub fn resolve(
+        single: &AppConfigValidatorSingle,
+        balance: u128,
+        reward: u128,
+        reserve: u128,
+        stakes_config: &ton_block::ConfigParam17,
+    ) -> Self {
+        match single.stake_per_round {
+            StakeAmount::Fixed(amount) if single.compound_rewards => {
+                let base = amount as u128;
+
+                // Only the reward part is clamped, the base stake is always required
+                let compounded = reward
+                    .min(balance.saturating_sub(reserve).saturating_sub(base))
+                    .min(stakes_config.max_stake.0.saturating_sub(base));
+                Self::Compounded {
+                    base,
+                    reward,
+                    compounded,
+                }
+            }
+            StakeAmount::Fixed(amount) => Self::Fixed {
+                stake: amount as u128,
+            },
+            StakeAmount::Percent(percent) => {
+                let stake = std::cmp::min(
+                    single.stake_per_round.resolve(balance, reserve),
+                    stakes_config.max_stake.0,
+                );
+                let min_stake = stakes_config.min_stake.0;
+                if stake < min_stake {
+                    Self::BelowMinStake {
+                        percent,
+                        stake,
+                        min_stake,
+                    }
+                } else {
+                    Self::Percent {
+                        percent,
+                        balance,
+                        stake,
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the stake which will be sent (if any)
+    pub fn stake(&self) -> Option<u128> {
+        match self {
+            Self::Fixed { stake } | Self::Percent { stake, .. } => Some(*stake),
+            Self::Compounded {
+                base, compounded, ..
+            } => Some(base + compounded),
+            Self::BelowMinStake { .. } => None,
+        }
+    }
+}
+
+/// Computes the sweep of a single validator wallet (if the sweep is configured
+/// and the excess is large enough).
+pub fn sweep_excess(
+    single: &AppConfigValidatorSingle,
+    balance: u128,
+    reward: u128,
+    one_coin: u128,
+    safety_margin: u128,
+    max_attached_value: u128,
+) -> Option<SweepExcess> {
+    let max_hot_balance = single.max_hot_balance?;
+    single.sweep_destination.as_ref()?;
+
+    let next_stake = match single.stake_per_round {
+        StakeAmount::Fixed(amount) if single.compound_rewards => amount as u128 + reward,
+        StakeAmount::Fixed(amount) => amount as u128,
+        StakeAmount::Percent(_) => 0,
+    };
+    let reserve = 3 * one_coin + safety_margin;
+    let keep = std::cmp::max(max_hot_balance as u128, next_stake + reserve);
+
+    let amount = std::cmp::min(balance.saturating_sub(keep), max_attached_value);
+    // NOTE: small amounts are not worth the fees
+    (amount >= one_coin).then_some(SweepExcess { keep, amount })
+}
+
+pub struct SweepExcess {
+    /// Balance which is kept on the wallet
+    pub keep: u128,
+    pub amount: u128,
+}
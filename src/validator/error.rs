@@ -0,0 +1,86 @@
+/// Major classes of the validation failures.
+///
+/// Errors are still propagated as [`anyhow::Error`], these variants are attached
+/// as a context to make them distinguishable at the process boundary.
+#[derive(thiserror::Error, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ValidationError {
+    #[error("node is unavailable")]
+    NodeUnavailable,
+    #[error("control server rejected the client key")]
+    AuthRejected,
+    #[error("node is on a different network")]
+    NetworkMismatch,
+    #[error("not enough funds")]
+    Underfunded,
+    #[error("elections are closed")]
+    ElectionsClosed,
+    #[error("DePool rounds are out of sync with the elections")]
+    DePoolPhaseSkew,
+    #[error("keys mismatch")]
+    KeyMismatch,
+    #[error("stake returned by elector")]
+    StakeReturned,
+    #[error("node has no keys for the elected validator")]
+    ValidatorKeyMissing,
+    #[error("no open elections")]
+    NoOpenElections,
+    #[error("invalid config")]
+    ConfigInvalid,
+}
+
+impl ValidationError {
+    /// Finds the validation error class in the error chain
+    pub fn classify(error: &anyhow::Error) -> Option<Self> {
+        error.downcast_ref::<Self>().copied()
+    }
+
+    /// Wraps the detailed reason into the classified error
+    pub fn with_reason<R>(self, reason: R) -> anyhow::Error
+    where
+        R: std::fmt::Display + std::fmt::Debug + Send + Sync + 'static,
+    {
+        anyhow::Error::msg(reason).context(self)
+    }
+
+    /// Process exit code for this class of errors
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::NodeUnavailable => 10,
+            Self::AuthRejected => 11,
+            Self::NetworkMismatch => 12,
+            Self::Underfunded => 13,
+            Self::ElectionsClosed => 14,
+            Self::DePoolPhaseSkew => 15,
+            Self::KeyMismatch => 16,
+            Self::StakeReturned => 17,
+            Self::ValidatorKeyMissing => 18,
+            Self::NoOpenElections => 19,
+            Self::ConfigInvalid => 20,
+        }
+    }
+
+    /// Whether retrying can't help without the operator intervention
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            Self::AuthRejected | Self::NetworkMismatch | Self::KeyMismatch | Self::ConfigInvalid
+        )
+    }
+
+    /// Stable category name for logs and notifications
+    pub fn category(&self) -> &'static str {
+        match self {
+            Self::NodeUnavailable => "node_unavailable",
+            Self::AuthRejected => "auth_rejected",
+            Self::NetworkMismatch => "network_mismatch",
+            Self::Underfunded => "underfunded",
+            Self::ElectionsClosed => "elections_closed",
+            Self::DePoolPhaseSkew => "depool_phase_skew",
+            Self::KeyMismatch => "key_mismatch",
+            Self::StakeReturned => "stake_returned",
+            Self::ValidatorKeyMissing => "validator_key_missing",
+            Self::NoOpenElections => "no_open_elections",
+            Self::ConfigInvalid => "config_invalid",
+        }
+    }
+}
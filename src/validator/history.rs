@@ -0,0 +1,111 @@
+use broxus_util::serde_string;
+use serde::{Deserialize, Serialize};
+
+/// Validator action, stored as a line in the history file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEvent {
+    /// Unix timestamp of the action
+    pub timestamp: u32,
+    #[serde(flatten)]
+    pub kind: HistoryEventKind,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum HistoryEventKind {
+    /// Stake was sent to the elector (or to the DePool proxy)
+    StakeSent {
+        election_id: u32,
+        participant: String,
+        #[serde(with = "serde_string")]
+        stake: u128,
+        /// Fees paid by the wallet (zero for the actions recorded before fees were tracked)
+        #[serde(with = "serde_string", default)]
+        fees: u128,
+        tx_hash: String,
+    },
+    /// Unfrozen stake was requested from the elector
+    StakeRecovered {
+        wallet: String,
+        #[serde(with = "serde_string")]
+        stake: u128,
+        #[serde(with = "serde_string", default)]
+        fees: u128,
+        tx_hash: String,
+    },
+    /// Ordinary stake was added to the DePool round
+    DePoolStakeAdded {
+        round_id: u64,
+        #[serde(with = "serde_string")]
+        amount: u128,
+        #[serde(with = "serde_string", default)]
+        fees: u128,
+        tx_hash: String,
+    },
+    /// Ticktock was sent to the DePool to update its rounds
+    TicktockSent {
+        depool: String,
+        #[serde(with = "serde_string")]
+        fees: u128,
+        tx_hash: String,
+    },
+    /// Balance above the max hot balance was sent to the cold wallet
+    BalanceSwept {
+        destination: String,
+        #[serde(with = "serde_string")]
+        amount: u128,
+        #[serde(with = "serde_string")]
+        fees: u128,
+        tx_hash: String,
+    },
+    /// Summary of the finished validation round
+    RoundFinished {
+        round_id: u32,
+        /// Whether the stake was accepted by the elector
+        elected: bool,
+        /// Whether the validator keys were in the round validator set
+        in_validator_set: bool,
+        #[serde(with = "serde_string")]
+        stake: u128,
+        /// Estimated reward at the end of the round
+        #[serde(with = "serde_string")]
+        reward: u128,
+        /// Stake confiscated by complaints
+        #[serde(with = "serde_string")]
+        penalty: u128,
+    },
+}
+
+impl HistoryEventKind {
+    pub const NAMES: [&'static str; 6] = [
+        "stake_sent",
+        "stake_recovered",
+        "depool_stake_added",
+        "round_finished",
+        "ticktock_sent",
+        "balance_swept",
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::StakeSent { .. } => Self::NAMES[0],
+            Self::StakeRecovered { .. } => Self::NAMES[1],
+            Self::DePoolStakeAdded { .. } => Self::NAMES[2],
+            Self::RoundFinished { .. } => Self::NAMES[3],
+            Self::TicktockSent { .. } => Self::NAMES[4],
+            Self::BalanceSwept { .. } => Self::NAMES[5],
+        }
+    }
+
+    /// Fees paid by the wallet for the action (`None` for actions without transactions)
+    pub fn fees(&self) -> Option<u128> {
+        match self {
+            Self::StakeSent { fees, .. }
+            | Self::StakeRecovered { fees, .. }
+            | Self::DePoolStakeAdded { fees, .. }
+            | Self::TicktockSent { fees, .. }
+            | Self::BalanceSwept { fees, .. } => Some(*fees),
+            Self::RoundFinished { .. } => None,
+        }
+    }
+}
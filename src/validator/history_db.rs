@@ -0,0 +1,409 @@
+use std::io::{BufRead, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+use anyhow::{Context, Result};
+use broxus_util::now;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::history::{HistoryEvent, HistoryEventKind};
+
+/// Schema migrations, the number of the applied ones is stored as `user_version`.
+///
+/// NOTE: Never modify the applied migrations, add new ones instead
+const MIGRATIONS: &[&str] = &[
+    // 1: initial schema
+    "CREATE TABLE rounds (
+        unit TEXT NOT NULL,
+        round_id INTEGER NOT NULL,
+        elected INTEGER NOT NULL,
+        in_validator_set INTEGER NOT NULL,
+        stake TEXT NOT NULL,
+        reward TEXT NOT NULL,
+        penalty TEXT NOT NULL,
+        reported_at INTEGER NOT NULL,
+        PRIMARY KEY (unit, round_id)
+    );
+    CREATE TABLE stakes (
+        tx_hash TEXT PRIMARY KEY,
+        unit TEXT NOT NULL,
+        election_id INTEGER NOT NULL,
+        participant TEXT NOT NULL,
+        stake TEXT NOT NULL,
+        sent_at INTEGER NOT NULL
+    );
+    CREATE TABLE recoveries (
+        tx_hash TEXT PRIMARY KEY,
+        unit TEXT NOT NULL,
+        wallet TEXT NOT NULL,
+        stake TEXT NOT NULL,
+        recovered_at INTEGER NOT NULL
+    );
+    CREATE TABLE depool_stakes (
+        tx_hash TEXT PRIMARY KEY,
+        unit TEXT NOT NULL,
+        round_id INTEGER NOT NULL,
+        amount TEXT NOT NULL,
+        added_at INTEGER NOT NULL
+    );
+    CREATE TABLE outcomes (
+        unit TEXT NOT NULL,
+        election_id INTEGER NOT NULL,
+        outcome TEXT NOT NULL,
+        recorded_at INTEGER NOT NULL
+    );
+    CREATE TABLE imported_history (
+        unit TEXT PRIMARY KEY,
+        byte_offset INTEGER NOT NULL
+    );",
+    // 2: lookups by elections
+    "CREATE INDEX stakes_election_id ON stakes (election_id);
+    CREATE INDEX outcomes_election_id ON outcomes (election_id);",
+    // 3: transaction fees
+    "ALTER TABLE stakes ADD COLUMN fees TEXT NOT NULL DEFAULT '0';
+    ALTER TABLE recoveries ADD COLUMN fees TEXT NOT NULL DEFAULT '0';
+    ALTER TABLE depool_stakes ADD COLUMN fees TEXT NOT NULL DEFAULT '0';
+    CREATE TABLE ticktocks (
+        tx_hash TEXT PRIMARY KEY,
+        unit TEXT NOT NULL,
+        depool TEXT NOT NULL,
+        fees TEXT NOT NULL,
+        sent_at INTEGER NOT NULL
+    );",
+    // 4: balance sweeps
+    "CREATE TABLE sweeps (
+        tx_hash TEXT PRIMARY KEY,
+        unit TEXT NOT NULL,
+        destination TEXT NOT NULL,
+        amount TEXT NOT NULL,
+        fees TEXT NOT NULL,
+        swept_at INTEGER NOT NULL
+    );",
+];
+
+/// SQLite copy of the validator history.
+///
+/// All writes are done by a separate thread, so the validation loop never waits for them.
+/// Actions are imported from the JSON-lines history of each unit, which stays the source
+/// of truth, so nothing is lost if the database was unavailable for some time.
+pub struct HistoryDb {
+    path: PathBuf,
+    tx: mpsc::Sender<Command>,
+}
+
+impl HistoryDb {
+    pub fn open(path: &Path) -> Self {
+        let (tx, rx) = mpsc::channel();
+
+        let path = path.to_owned();
+        std::thread::spawn({
+            let path = path.clone();
+            move || {
+                let mut connection = match open_connection(&path) {
+                    Ok(connection) => connection,
+                    Err(e) => {
+                        tracing::error!(?path, "failed to open history database: {e:?}");
+                        return;
+                    }
+                };
+
+                // NOTE: the thread stops when the sender is dropped
+                while let Ok(command) = rx.recv() {
+                    if let Err(e) = command.execute(&mut connection) {
+                        tracing::warn!(?path, "failed to write history database: {e:?}");
+                    }
+                }
+            }
+        });
+
+        Self { path, tx }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Imports the new actions from the unit history file
+    pub fn import_history(&self, unit: &str, history_path: &Path) {
+        self.send(Command::ImportHistory {
+            unit: unit.to_owned(),
+            history_path: history_path.to_owned(),
+        });
+    }
+
+    /// Records the result of the elections attempt
+    pub fn record_outcome(&self, unit: &str, election_id: u32, outcome: String) {
+        self.send(Command::RecordOutcome {
+            unit: unit.to_owned(),
+            election_id,
+            outcome,
+            recorded_at: now(),
+        });
+    }
+
+    fn send(&self, command: Command) {
+        if self.tx.send(command).is_err() {
+            tracing::warn!(path = ?self.path, "history database is not available");
+        }
+    }
+}
+
+enum Command {
+    ImportHistory {
+        unit: String,
+        history_path: PathBuf,
+    },
+    RecordOutcome {
+        unit: String,
+        election_id: u32,
+        outcome: String,
+        recorded_at: u32,
+    },
+}
+
+impl Command {
+    fn execute(self, connection: &mut Connection) -> Result<()> {
+        match self {
+            Self::ImportHistory { unit, history_path } => {
+                import_history(connection, &unit, &history_path)
+            }
+            Self::RecordOutcome {
+                unit,
+                election_id,
+                outcome,
+                recorded_at,
+            } => {
+                connection.execute(
+                    "INSERT INTO outcomes (unit, election_id, outcome, recorded_at)
+                    VALUES (?1, ?2, ?3, ?4)",
+                    params![unit, election_id, outcome, recorded_at],
+                )?;
+                Ok(())
+            }
+        }
+    }
+}
+
+fn open_connection(path: &Path) -> Result<Connection> {
+    let mut connection = Connection::open(path).context("failed to open database")?;
+
+    let version: usize = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    anyhow::ensure!(
+        version <= MIGRATIONS.len(),
+        "database schema version {version} is newer than supported {}, update nodekeeper",
+        MIGRATIONS.len()
+    );
+
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let transaction = connection.transaction()?;
+        transaction
+            .execute_batch(migration)
+            .with_context(|| format!("failed to apply migration {}", i + 1))?;
+        transaction.pragma_update(None, "user_version", i + 1)?;
+        transaction.commit()?;
+        tracing::info!(version = i + 1, "applied history database migration");
+    }
+
+    Ok(connection)
+}
+
+/// Inserts the history entries after the last imported offset.
+///
+/// NOTE: The file is read from the start if it was truncated,
+/// duplicates are ignored by the primary keys.
+fn import_history(connection: &mut Connection, unit: &str, history_path: &Path) -> Result<()> {
+    if !history_path.exists() {
+        return Ok(());
+    }
+
+    let transaction = connection.transaction()?;
+    let mut offset: u64 = transaction
+        .query_row(
+            "SELECT byte_offset FROM imported_history WHERE unit = ?1",
+            params![unit],
+            |row| row.get(0),
+        )
+        .optional()?
+        .unwrap_or_default();
+
+    let mut file = std::fs::File::open(history_path).context("failed to open history")?;
+    if file.metadata()?.len() < offset {
+        offset = 0;
+    }
+    file.seek(SeekFrom::Start(offset))?;
+
+    let mut reader = std::io::BufReader::new(file);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let read = reader.read_line(&mut line)?;
+        // NOTE: the last line could still be written
+        if read == 0 || !line.ends_with('\n') {
+            break;
+        }
+        offset += read as u64;
+
+        match serde_json::from_str::<HistoryEvent>(&line) {
+            Ok(event) => insert_event(&transaction, unit, event)?,
+            Err(e) => tracing::warn!(unit, "skipping invalid history entry: {e:?}"),
+        }
+    }
+
+    transaction.execute(
+        "INSERT OR REPLACE INTO imported_history (unit, byte_offset) VALUES (?1, ?2)",
+        params![unit, offset],
+    )?;
+    transaction.commit()?;
+    Ok(())
+}
+
+fn insert_event(connection: &Connection, unit: &str, event: HistoryEvent) -> Result<()> {
+    let timestamp = event.timestamp;
+    match event.kind {
+        HistoryEventKind::StakeSent {
+            election_id,
+            participant,
+            stake,
+            fees,
+            tx_hash,
+        } => connection.execute(
+            "INSERT OR IGNORE INTO stakes
+            (tx_hash, unit, election_id, participant, stake, fees, sent_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                tx_hash,
+                unit,
+                election_id,
+                participant,
+                stake.to_string(),
+                fees.to_string(),
+                timestamp
+            ],
+        )?,
+        HistoryEventKind::StakeRecovered {
+            wallet,
+            stake,
+            fees,
+            tx_hash,
+        } => connection.execute(
+            "INSERT OR IGNORE INTO recoveries
+            (tx_hash, unit, wallet, stake, fees, recovered_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                tx_hash,
+                unit,
+                wallet,
+                stake.to_string(),
+                fees.to_string(),
+                timestamp
+            ],
+        )?,
+        HistoryEventKind::DePoolStakeAdded {
+            round_id,
+            amount,
+            fees,
+            tx_hash,
+        } => connection.execute(
+            "INSERT OR IGNORE INTO depool_stakes
+            (tx_hash, unit, round_id, amount, fees, added_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                tx_hash,
+                unit,
+                round_id,
+                amount.to_string(),
+                fees.to_string(),
+                timestamp
+            ],
+        )?,
+        HistoryEventKind::TicktockSent {
+            depool,
+            fees,
+            tx_hash,
+        } => connection.execute(
+            "INSERT OR IGNORE INTO ticktocks (tx_hash, unit, depool, fees, sent_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![tx_hash, unit, depool, fees.to_string(), timestamp],
+        )?,
+        HistoryEventKind::BalanceSwept {
+            destination,
+            amount,
+            fees,
+            tx_hash,
+        } => connection.execute(
+            "INSERT OR IGNORE INTO sweeps (tx_hash, unit, destination, amount, fees, swept_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                tx_hash,
+                unit,
+                destination,
+                amount.to_string(),
+                fees.to_string(),
+                timestamp
+            ],
+        )?,
+        HistoryEventKind::RoundFinished {
+            round_id,
+            elected,
+            in_validator_set,
+            stake,
+            reward,
+            penalty,
+        } => connection.execute(
+            "INSERT OR IGNORE INTO rounds
+            (unit, round_id, elected, in_validator_set, stake, reward, penalty, reported_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                unit,
+                round_id,
+                elected,
+                in_validator_set,
+                stake.to_string(),
+                reward.to_string(),
+                penalty.to_string(),
+                timestamp
+            ],
+        )?,
+    };
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user_version(connection: &Connection) -> usize {
+        connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn open_connection_applies_migrations() {
+        let path =
+            std::env::temp_dir().join(format!("nodekeeper-history-{}.sqlite3", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        // Database with only the initial schema
+        {
+            let connection = Connection::open(&path).unwrap();
+            connection.execute_batch(MIGRATIONS[0]).unwrap();
+            connection.pragma_update(None, "user_version", 1).unwrap();
+        }
+
+        let connection = open_connection(&path).unwrap();
+        assert_eq!(user_version(&connection), MIGRATIONS.len());
+
+        // Tables and columns from the later migrations exist
+        connection.prepare("SELECT fees FROM stakes").unwrap();
+        connection.prepare("SELECT tx_hash FROM sweeps").unwrap();
+        drop(connection);
+
+        // Reopening doesn't apply the migrations again
+        let connection = open_connection(&path).unwrap();
+        assert_eq!(user_version(&connection), MIGRATIONS.len());
+        drop(connection);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,107 @@
+use std::process::Stdio;
+
+use anyhow::{Context, Result};
+use broxus_util::now;
+use tokio::process::Command;
+
+use crate::config::{AppConfigHook, HookEventKind};
+
+/// Validator event passed to the external hook command
+#[derive(Debug, Clone)]
+pub enum HookEvent {
+    Elected { unit: String, round_id: u32 },
+    Recovered { wallet: String, stake: u128 },
+    Underfunded { unit: String, reason: String },
+    Desynced { since: u32, sync_status: String },
+}
+
+impl HookEvent {
+    pub fn kind(&self) -> HookEventKind {
+        match self {
+            Self::Elected { .. } => HookEventKind::Elected,
+            Self::Recovered { .. } => HookEventKind::Recovered,
+            Self::Underfunded { .. } => HookEventKind::Underfunded,
+            Self::Desynced { .. } => HookEventKind::Desynced,
+        }
+    }
+
+    /// Event details, passed as `NODEKEEPER_*` environment variables
+    fn env(&self) -> Vec<(&'static str, String)> {
+        match self {
+            Self::Elected { unit, round_id } => vec![
+                ("NODEKEEPER_UNIT", unit.clone()),
+                ("NODEKEEPER_ROUND_ID", round_id.to_string()),
+            ],
+            Self::Recovered { wallet, stake } => vec![
+                ("NODEKEEPER_WALLET", wallet.clone()),
+                ("NODEKEEPER_STAKE", stake.to_string()),
+            ],
+            Self::Underfunded { unit, reason } => vec![
+                ("NODEKEEPER_UNIT", unit.clone()),
+                ("NODEKEEPER_REASON", reason.clone()),
+            ],
+            Self::Desynced { since, sync_status } => vec![
+                ("NODEKEEPER_SINCE", since.to_string()),
+                ("NODEKEEPER_SYNC_STATUS", sync_status.clone()),
+            ],
+        }
+    }
+}
+
+/// Executes the hook command in background.
+///
+/// NOTE: The command is killed after the timeout, so it never blocks the validation
+pub fn run_hook(hook: Option<&AppConfigHook>, event: HookEvent) {
+    let Some(hook) = hook else {
+        return;
+    };
+    if !hook.is_enabled(event.kind()) {
+        return;
+    }
+
+    let hook = hook.clone();
+    tokio::spawn(async move {
+        let event_name = event.kind().as_str();
+        if let Err(e) = execute(&hook, &event).await {
+            tracing::error!(event = event_name, "hook command failed: {e:?}");
+        }
+    });
+}
+
+async fn execute(hook: &AppConfigHook, event: &HookEvent) -> Result<()> {
+    let event_name = event.kind().as_str();
+
+    let mut command = Command::new(&hook.command);
+    command
+        .args(&hook.args)
+        .arg(event_name)
+        .env("NODEKEEPER_EVENT", event_name)
+        .env("NODEKEEPER_TIMESTAMP", now().to_string())
+        .envs(event.env())
+        .stdin(Stdio::null())
+        .kill_on_drop(true);
+
+    let output = tokio::time::timeout(hook.timeout, command.output())
+        .await
+        .map_err(|_| anyhow::anyhow!("timed out after {}s", hook.timeout.as_secs()))?
+        .context("failed to execute")?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if output.status.success() {
+        tracing::info!(
+            event = event_name,
+            stdout = stdout.trim(),
+            stderr = stderr.trim(),
+            "hook command finished"
+        );
+        Ok(())
+    } else {
+        anyhow::bail!(
+            "{}, stdout: {:?}, stderr: {:?}",
+            output.status,
+            stdout.trim(),
+            stderr.trim()
+        )
+    }
+}
@@ -0,0 +1,253 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use broxus_util::now;
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
+
+use crate::util::system;
+
+/// Lock record of the running validator instance.
+///
+/// Prevents two keepers with the same root (and therefore the same keys)
+/// from participating in the same elections. A separate file next to the record
+/// is held with `flock` while the instance is running (the record itself is replaced
+/// on each write), and the record is refreshed periodically for the instances which
+/// can't see the `flock` (e.g. on another host), so a lock which was left after
+/// an unclean shutdown expires on its own.
+pub struct InstanceLock {
+    path: PathBuf,
+    record: LockRecord,
+    taken_over: CancellationToken,
+    refresh: tokio::task::JoinHandle<()>,
+    _file: std::fs::File,
+}
+
+impl InstanceLock {
+    const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+    /// Lock is considered stale if it was not refreshed for this number of seconds
+    const STALE_AFTER: u32 = 180;
+
+    /// Locks the file and writes the lock record, failing if another instance holds
+    /// the `flock` or has a fresh record.
+    ///
+    /// NOTE: With `force` the existing lock is only reported
+    pub fn acquire(path: &Path, force: bool) -> Result<Self> {
+        let record = LockRecord::current();
+        let (file, locked) = Self::lock_file(path)?;
+
+        // NOTE: the record is checked only after the `flock` to not race with
+        // the instances on the same host
+        let existing = LockRecord::load(path)?;
+        let conflict = match &existing {
+            _ if !locked => true,
+            Some(existing) => !existing.is_same_instance(&record) && existing.is_fresh(),
+            None => false,
+        };
+        if conflict {
+            let existing = match existing {
+                Some(existing) => existing.to_string(),
+                None => "no lock record".to_owned(),
+            };
+            if !force {
+                anyhow::bail!(
+                    "another validator instance is running ({existing}), \
+                    use `--force` if it is not the case"
+                );
+            }
+            tracing::error!(%existing, "another validator instance is running, lock overwritten");
+        }
+
+        Self::start(path, record, file)
+    }
+
+    /// Asks the validator instance which holds the lock to stop (with `SIGTERM`,
+    /// so that it finishes the in-flight election message first), waits until it exits
+    /// and acquires the lock.
+    ///
+    /// NOTE: only the instances on the same host can be stopped
+    pub async fn stop_running(path: &Path, timeout: Duration) -> Result<Self> {
+        const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+        let record = LockRecord::current();
+        let started_at = std::time::Instant::now();
+        let mut stopping = None;
+        loop {
+            let (file, locked) = Self::lock_file(path)?;
+            let existing = LockRecord::load(path)?;
+            if locked {
+                // NOTE: the `flock` is not visible to the instances on other hosts
+                if let Some(existing) = existing {
+                    anyhow::ensure!(
+                        existing.hostname == record.hostname || !existing.is_fresh(),
+                        "validator instance is running on another host ({existing})"
+                    );
+                }
+                if let Some(stopped) = stopping {
+                    tracing::info!(%stopped, "running validator instance stopped");
+                }
+                return Self::start(path, record, file);
+            }
+            drop(file);
+
+            let existing = match stopping.take() {
+                Some(existing) => existing,
+                None => {
+                    let existing =
+                        existing.context("validator lock is held by an unknown process")?;
+                    anyhow::ensure!(
+                        existing.hostname == record.hostname,
+                        "validator instance is running on another host ({existing})"
+                    );
+                    system::terminate(existing.pid).with_context(|| {
+                        format!("failed to stop the running validator instance ({existing})")
+                    })?;
+                    tracing::warn!(%existing, "asked the running validator instance to stop");
+                    existing
+                }
+            };
+
+            if started_at.elapsed() >= timeout {
+                anyhow::bail!(
+                    "running validator instance ({existing}) didn't stop in {} seconds",
+                    timeout.as_secs()
+                );
+            }
+            stopping = Some(existing);
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// Opens the `flock` file and tries to lock it.
+    ///
+    /// Returns `false` if the lock is held by another process
+    fn lock_file(path: &Path) -> Result<(std::fs::File, bool)> {
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(path.with_extension("flock"))
+            .context("failed to open validator lock")?;
+        let locked = system::try_lock_exclusive(&file).context("failed to lock validator lock")?;
+        Ok((file, locked))
+    }
+
+    /// Writes the lock record and starts refreshing it
+    fn start(path: &Path, record: LockRecord, file: std::fs::File) -> Result<Self> {
+        record.store(path)?;
+
+        let taken_over = CancellationToken::new();
+        let refresh = tokio::spawn({
+            let path = path.to_owned();
+            let mut record = record.clone();
+            let taken_over = taken_over.clone();
+            async move {
+                let mut interval = tokio::time::interval(Self::REFRESH_INTERVAL);
+                interval.tick().await;
+                loop {
+                    interval.tick().await;
+
+                    match LockRecord::load(&path) {
+                        Ok(Some(existing)) if !existing.is_same_instance(&record) => {
+                            tracing::error!(
+                                %existing,
+                                "validator lock was taken over by another instance"
+                            );
+                            taken_over.cancel();
+                            break;
+                        }
+                        Err(e) => tracing::warn!("failed to read validator lock: {e:?}"),
+                        _ => {}
+                    }
+
+                    record.updated_at = now();
+                    if let Err(e) = record.store(&path) {
+                        tracing::warn!("failed to refresh validator lock: {e:?}");
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            path: path.to_owned(),
+            record,
+            taken_over,
+            refresh,
+            _file: file,
+        })
+    }
+
+    /// Waits until another instance takes over the lock.
+    ///
+    /// NOTE: validation must be stopped after that to not send the stakes twice
+    pub async fn taken_over(&self) {
+        self.taken_over.cancelled().await
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        self.refresh.abort();
+
+        // NOTE: the lock could have been taken over by another instance
+        if let Ok(Some(existing)) = LockRecord::load(&self.path) {
+            if existing.is_same_instance(&self.record) {
+                std::fs::remove_file(&self.path).ok();
+            }
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct LockRecord {
+    hostname: String,
+    pid: u32,
+    updated_at: u32,
+}
+
+impl LockRecord {
+    fn current() -> Self {
+        Self {
+            hostname: system::host_name().unwrap_or_default(),
+            pid: std::process::id(),
+            updated_at: now(),
+        }
+    }
+
+    fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(path).context("failed to read validator lock")?;
+        // NOTE: a broken record is treated as a stale lock
+        Ok(serde_json::from_str(&content).ok())
+    }
+
+    fn store(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string(self)?;
+
+        // NOTE: write to a temporary file first to never leave a partially written record
+        let temp_path = path.with_extension("lock.tmp");
+        std::fs::write(&temp_path, content).context("failed to write validator lock")?;
+        std::fs::rename(&temp_path, path).context("failed to write validator lock")
+    }
+
+    fn is_same_instance(&self, other: &Self) -> bool {
+        self.hostname == other.hostname && self.pid == other.pid
+    }
+
+    fn is_fresh(&self) -> bool {
+        now() < self.updated_at.saturating_add(InstanceLock::STALE_AFTER)
+    }
+}
+
+impl std::fmt::Display for LockRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "host `{}`, pid {}, updated at {}",
+            self.hostname, self.pid, self.updated_at
+        ))
+    }
+}
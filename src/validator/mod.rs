@@ -1,3 +1,6 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -5,19 +8,62 @@ use anyhow::{Context, Result};
 use broxus_util::now;
 use futures_util::FutureExt;
 use rand::Rng;
-use tokio::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, MutexGuard, Notify};
+use tokio_util::sync::{CancellationToken, DropGuard};
+use tracing::Instrument;
 
 use crate::config::*;
 use crate::contracts::*;
+use crate::crypto::Signer;
 use crate::dirs::ProjectDirs;
-use crate::network::{ConfigWithId, NodeStats, NodeTcpRpc, NodeUdpRpc, Subscription};
-use crate::util::Tokens;
+use crate::network::{
+    AccountStatus, ConfigWithId, ElectionsConfig, NodeRpcError, NodeStats, NodeTcpRpc, NodeUdpRpc,
+    NotReadyStats, PendingMessages, Subscription,
+};
+use crate::util::{human_duration, Redacted, RetryPolicy, Tokens};
+
+pub use self::control_socket::ControlSocket;
+use self::decision::{sweep_excess, ElectionsDecision, ElectionsInput, SingleStake, SweepExcess};
+pub use self::error::ValidationError;
+pub use self::history::{HistoryEvent, HistoryEventKind};
+use self::history_db::HistoryDb;
+use self::hook::{run_hook, HookEvent};
+pub use self::instance_lock::InstanceLock;
+pub use self::plan::plan;
+use self::state::{DePoolTopUp, MissingKeys, ValidatorState};
+use self::trace::DecisionTrace;
+
+mod control_socket;
+mod decision;
+mod error;
+mod history;
+mod history_db;
+mod hook;
+mod instance_lock;
+mod plan;
+mod state;
+mod trace;
 
 pub struct ValidationManager {
     dirs: ProjectDirs,
     params: ValidationParams,
-    last_params: parking_lot::Mutex<Option<AppConfigValidator>>,
+    last_params: parking_lot::Mutex<HashMap<String, AppConfigValidator>>,
     guard: Arc<Mutex<()>>,
+    wakeup: Arc<Notify>,
+    network_checked: bool,
+    keys_checked_rounds: HashMap<String, u32>,
+    /// When the node was first seen in the not ready state and when it was last reported
+    node_not_ready: Option<(u32, Option<u32>)>,
+    trace: Option<DecisionTrace>,
+    /// External command from the latest loaded config
+    hook: Option<AppConfigHook>,
+    history_db: Option<HistoryDb>,
+    /// When the next recovery attempt between the elections is scheduled
+    next_recovery_at: Option<u32>,
+    heartbeat: LoopHeartbeat,
+    /// Start time of the current loop iteration (zero while sleeping)
+    iteration_started_at: Arc<AtomicU32>,
 }
 
 impl ValidationManager {
@@ -27,71 +73,230 @@ impl ValidationManager {
             params,
             last_params: Default::default(),
             guard: Default::default(),
+            wakeup: Default::default(),
+            network_checked: false,
+            keys_checked_rounds: Default::default(),
+            node_not_ready: None,
+            trace: None,
+            hook: None,
+            history_db: None,
+            next_recovery_at: None,
+            heartbeat: LoopHeartbeat {
+                started_at: now(),
+                last_iteration_at: 0,
+                next_iteration_at: None,
+                elections_skipped: 0,
+            },
+            iteration_started_at: Default::default(),
         }
     }
 
+    /// Writes inputs and decisions of the latest loop iteration to the file
+    pub fn set_trace_path(&mut self, path: PathBuf) {
+        self.trace = Some(DecisionTrace::new(path));
+    }
+
     pub fn guard(&self) -> &Arc<Mutex<()>> {
         &self.guard
     }
 
     pub async fn try_validate(&mut self) -> Result<()> {
         const SYNC_CHECK_INTERVAL: u32 = 10;
+        const PAUSE_CHECK_INTERVAL: u32 = 30;
 
         tracing::info!("started validation loop");
 
+        let _watchdog = self.spawn_watchdog();
         let mut random_shift = None;
 
         let mut interval = 0u32;
         loop {
             // Sleep with the requested interval
             if interval > 0 {
+                // Wake up to recover stakes right after they are unfrozen
+                let until_recovery = self
+                    .next_recovery_at
+                    .take()
+                    .and_then(|at| at.checked_sub(now()));
+                if let Some(until_recovery) = until_recovery {
+                    interval = std::cmp::min(interval, until_recovery);
+                }
                 interval = std::cmp::max(interval, 10);
-                tokio::time::sleep(Duration::from_secs(interval as u64)).await;
+                self.iteration_started_at.store(0, Ordering::Release);
+                self.heartbeat.next_iteration_at = Some(now() + interval);
+                self.store_heartbeat();
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_secs(interval as u64)) => {}
+                    _ = self.wakeup.notified() => tracing::info!("woken up to reload the config"),
+                }
+            }
+
+            self.heartbeat.last_iteration_at = now();
+            self.heartbeat.next_iteration_at = None;
+            self.iteration_started_at
+                .store(self.heartbeat.last_iteration_at, Ordering::Release);
+            self.store_heartbeat();
+
+            if let Some(trace) = &mut self.trace {
+                trace.reset();
             }
 
             // Read config
-            let mut config = AppConfig::load(&self.dirs.app_config)?;
-            let validator = match config.validator.take() {
-                Some(validator) => validator,
-                None => {
-                    interval = SYNC_CHECK_INTERVAL;
-                    continue;
+            let config = self
+                .dirs
+                .load_app_config()
+                .context(ValidationError::ConfigInvalid)?;
+            self.hook = config.hook.clone();
+            let units = config
+                .validation_units()
+                .map(|(name, validator)| ValidationUnit::new(&self.dirs, name, validator))
+                .collect::<Vec<_>>();
+            if units.is_empty() {
+                interval = SYNC_CHECK_INTERVAL;
+                self.trace_action("validation is not configured", interval);
+                continue;
+            }
+
+            // Import actions made since the last iteration (e.g. by the CLI)
+            self.update_history_db(config.history_db.as_deref());
+            if let Some(history_db) = &self.history_db {
+                for unit in &units {
+                    history_db.import_history(&unit.name, &unit.dirs.validator_history);
                 }
-            };
+            }
 
             // Create tcp rpc and wait until node is synced
-            let node_tcp_rpc = NodeTcpRpc::new(config.control()?).await?;
-            if !self.is_synced(&node_tcp_rpc, validator.is_single()).await? {
+            let node_tcp_rpc = NodeTcpRpc::new(config.control()?)
+                .await
+                .context(ValidationError::NodeUnavailable)?;
+            let only_mc = units.iter().all(|unit| unit.validator.is_single());
+            if !self.is_synced(&node_tcp_rpc, only_mc).await? {
                 interval = SYNC_CHECK_INTERVAL;
+                self.trace_action("node is not synced", interval);
                 continue;
             }
-            let node_udp_rpc = NodeUdpRpc::new(config.adnl()?).await?;
+            let node_udp_rpc = NodeUdpRpc::new(config.adnl()?)
+                .await
+                .context(ValidationError::NodeUnavailable)?;
 
             // Create subscription
-            let subscription = Subscription::new(node_tcp_rpc, node_udp_rpc);
+            let subscription =
+                Subscription::new(node_tcp_rpc, node_udp_rpc, config.retries.clone());
             subscription.ensure_ready().await?;
 
+            // Export pending messages while the subscription is alive
+            let _pending_messages = PendingMessagesSampler::spawn(&self.dirs, &subscription);
+
             // Get current network config params
-            let ConfigWithId {
+            // NOTE: partially assembled configs are retried too
+            let ElectionsConfig {
                 block_id: target_block,
                 config: blockchain_config,
-            } = subscription.tcp_rpc().get_config_all().await?;
+                elector_address,
+                timings: mut timings,
+                validators_count,
+                stakes_config,
+                current_vset,
+            } = retry_node_query(&config.retries.node_query, "get blockchain config", || {
+                subscription.tcp_rpc().get_elections_config()
+            })
+            .await?;
 
-            if !self.params.ignore_deploy && self.ensure_deployed(&validator, &subscription).await?
-            {
-                // Proceed to the next iteration after contracts deployment
-                continue;
+            // Make sure that the node is in the expected network (only once)
+            if !self.network_checked {
+                self.check_network(&subscription, &target_block).await?;
+                self.network_checked = true;
+            }
+
+            // NOTE: only read-only checks are performed while paused
+            let paused = self.dirs.is_paused();
+            if paused {
+                tracing::warn!("validation is paused");
             }
 
-            let elector_address = blockchain_config
-                .elector_address()
-                .context("invalid elector address")?;
-            let timings = blockchain_config
-                .elector_params()
-                .context("invalid elector params")?;
-            let current_vset = blockchain_config
-                .validator_set()
-                .context("invalid validator set")?;
+            if !self.params.ignore_deploy && !paused {
+                let mut deployed = false;
+                for unit in &units {
+                    deployed |= self
+                        .ensure_deployed(unit, &subscription, config.one_coin())
+                        .await
+                        .with_context(|| format!("failed to deploy unit `{}`", unit.name))?;
+                }
+
+                if deployed {
+                    // Proceed to the next iteration after contracts deployment
+                    self.trace_action("contracts deployed", 0);
+                    continue;
+                }
+            }
+
+            self.params.check_elections_offsets(&timings)?;
+            self.trace("config", || {
+                serde_json::json!({
+                    "block_id": target_block.to_string(),
+                    "elector_address": elector_address.to_hex_string(),
+                    "timings": {
+                        "validators_elected_for": timings.validators_elected_for,
+                        "elections_start_before": timings.elections_start_before,
+                        "elections_end_before": timings.elections_end_before,
+                        "stake_held_for": timings.stake_held_for,
+                    },
+                    "max_validators": validators_count.max_validators.as_u32(),
+                    "stakes": {
+                        "min_stake": stakes_config.min_stake.0.to_string(),
+                        "max_stake": stakes_config.max_stake.0.to_string(),
+                        "min_total_stake": stakes_config.min_total_stake.0.to_string(),
+                        "max_stake_factor": stakes_config.max_stake_factor,
+                    },
+                    "current_vset": {
+                        "utime_since": current_vset.utime_since(),
+                        "utime_until": current_vset.utime_until(),
+                        "total": current_vset.total(),
+                    },
+                    "paused": paused,
+                })
+            });
+
+            // Make sure that the node still has keys for the current round (once per round)
+            let elector = Elector::new(elector_address, subscription.clone());
+            for unit in &units {
+                if let Err(e) = self
+                    .check_elected_keys(
+                        unit,
+                        &subscription,
+                        &elector,
+                        &current_vset,
+                        config.one_coin(),
+                    )
+                    .await
+                {
+                    tracing::warn!(
+                        unit = %unit.name,
+                        "failed to check elected validator keys: {e:?}"
+                    );
+                }
+            }
+
+            // Summarize the previous round (once per round)
+            if let Ok(prev_vset) = blockchain_config.prev_validator_set() {
+                for unit in &units {
+                    if let Err(e) = self
+                        .report_finished_round(
+                            unit,
+                            &subscription,
+                            &elector,
+                            &prev_vset,
+                            config.one_coin(),
+                        )
+                        .await
+                    {
+                        tracing::warn!(
+                            unit = %unit.name,
+                            "failed to report the finished round: {e:?}"
+                        );
+                    }
+                }
+            }
 
             // Get block with the config
             tracing::info!("target block id: {target_block}");
@@ -103,15 +308,39 @@ impl ValidationManager {
             // Compute where are we on the validation timeline
             let timeline = Timeline::compute(&timings, &current_vset, target_block_info.gen_utime);
             tracing::info!("timeline: {timeline}");
+            self.trace("timeline", || {
+                serde_json::json!({
+                    "gen_utime": target_block_info.gen_utime,
+                    "timeline": timeline.to_string(),
+                })
+            });
+
+            // NOTE: stakes are recovered during the elections attempts otherwise
+            self.next_recovery_at = None;
+            let waiting = !matches!(timeline, Timeline::Elections { .. });
+            if waiting && !paused && !self.params.once_if_elections {
+                match self
+                    .recover_between_elections(&units, &config, &subscription, &elector)
+                    .await
+                {
+                    Ok(next_unfreeze_at) => {
+                        self.next_recovery_at = next_unfreeze_at
+                            .map(|unfreeze_at| unfreeze_at + self.params.stake_unfreeze_offset);
+                    }
+                    Err(e) => tracing::error!("failed to recover stakes: {e:?}"),
+                }
+            }
 
-            let elections_end = match timeline {
+            let mut elections_end = match timeline {
                 // If elections were not started yet, wait for the start (with an additional offset)
                 Timeline::BeforeElections {
                     until_elections_start,
                 } => {
+                    self.ensure_can_wait("elections are not started yet")?;
                     random_shift = None; // reset random shift before each elections
                     tracing::info!("waiting for the elections to start");
                     interval = until_elections_start + self.params.elections_start_offset;
+                    self.trace_action("waiting for the elections to start", interval);
                     continue;
                 }
                 // If elections started
@@ -129,7 +358,8 @@ impl ValidationManager {
                                 .saturating_sub(self.params.elections_end_offset)
                                 .saturating_sub(self.params.elections_start_offset)
                                 / 4;
-                            *random_shift.insert(rand::thread_rng().gen_range(0..range))
+                            // NOTE: the range is empty if offsets exceed the elections
+                            *random_shift.insert(rand::thread_rng().gen_range(0..range.max(1)))
                         }
                     };
 
@@ -139,6 +369,7 @@ impl ValidationManager {
                         if offset > 0 {
                             // Wait a bit after elections start
                             interval = offset;
+                            self.trace_action("waiting for the start offset", interval);
                             continue;
                         }
                     } else if let Some(offset) = self
@@ -147,7 +378,9 @@ impl ValidationManager {
                         .checked_sub(until_elections_end)
                     {
                         // Elections will end soon, attempts are doomed
+                        self.ensure_can_wait("elections will end soon")?;
                         interval = offset;
+                        self.trace_action("elections will end soon", interval);
                         continue;
                     }
 
@@ -156,30 +389,115 @@ impl ValidationManager {
                 }
                 // Elections were already finished, wait for the new round
                 Timeline::AfterElections { until_round_end } => {
+                    self.ensure_can_wait("elections are already finished")?;
                     tracing::info!("waiting for the new round to start");
                     interval = until_round_end;
+                    self.trace_action("waiting for the new round to start", interval);
                     continue;
                 }
             };
 
             // Participate in elections
-            let elector = Elector::new(elector_address, subscription.clone());
-            let elector_data = elector
-                .get_data()
-                .await
-                .context("failed to get elector data")?;
+            let retries = &config.retries.node_query;
+            let elector_data =
+                retry_node_query(retries, "get elector data", || elector.get_data()).await;
+            let elector_data = elector_data.map_err(|e| {
+                // NOTE: the elector must always exist in the expected network
+                if e.is::<elector::ElectorStateError>() {
+                    e.context(ValidationError::NetworkMismatch)
+                } else {
+                    e.context("failed to get elector data")
+                }
+            })?;
 
             // Get current election id
             let Some(election_id) = elector_data.election_id() else {
                 tracing::info!("no current elections in the elector state");
                 interval = 1; // retry nearly immediate
+                self.trace_action("no current elections in the elector state", interval);
                 continue;
             };
+            self.trace("elector", || {
+                serde_json::json!({
+                    "election_id": election_id,
+                    "nearest_unfreeze_at": elector_data.nearest_unfreeze_at(election_id),
+                })
+            });
+
+            // Decide whether to participate in these elections
+            let unfreeze_at = elector_data
+                .nearest_unfreeze_at(election_id)
+                .map(|unfreeze_at| unfreeze_at + self.params.stake_unfreeze_offset);
+            let deadline_at = elections_end.saturating_sub(self.params.elections_end_offset);
+            let mut elections = ElectionsInput {
+                election_id,
+                skipped_election: self.dirs.load_skipped_election()?,
+                paused,
+                round_parity: config.round_parity,
+                validators_elected_for: timings.validators_elected_for,
+                deadline_at,
+                start_at: ElectionsInput::attempt_start_at(now(), unfreeze_at, deadline_at),
+                min_time_remaining: self
+                    .params
+                    .required_time_remaining(&config.retries.send_message),
+            };
+            match elections.decide() {
+                ElectionsDecision::Participate => {}
+                // Skip elections marked by the operator
+                ElectionsDecision::Skipped => {
+                    tracing::warn!(election_id, "elections were marked as skipped");
+                    self.ensure_can_wait("elections are marked as skipped")?;
+                    interval = elections_end.saturating_sub(now());
+                    self.trace_action("elections are marked as skipped", interval);
+                    continue;
+                }
+                // Wait until the validation is resumed
+                ElectionsDecision::Paused => {
+                    tracing::warn!(election_id, "waiting for the validation to be resumed");
+                    self.ensure_can_wait("validation is paused")?;
+                    interval = PAUSE_CHECK_INTERVAL;
+                    self.trace_action("validation is paused", interval);
+                    continue;
+                }
+                // Skip rounds of the other parity, but still recover unfrozen stakes
+                ElectionsDecision::OtherParity(parity) => {
+                    tracing::info!(election_id, %parity, "skipping elections of the other round");
+                    for unit in &units {
+                        let res = recover_unfrozen_stakes(
+                            unit,
+                            &config,
+                            subscription.clone(),
+                            &elector,
+                            &elector_data,
+                            &self.params.wallet_limits(config.one_coin()),
+                            &self.guard,
+                        )
+                        .await;
+                        if let Err(e) = res {
+                            tracing::error!(unit = %unit.name, "failed to recover stakes: {e:?}");
+                        }
+                    }
+
+                    self.ensure_can_wait("elections round doesn't match the parity")?;
+                    interval = elections_end.saturating_sub(now());
+                    self.trace_action("elections round doesn't match the parity", interval);
+                    continue;
+                }
+                // Skip attempts which can't finish before the deadline
+                ElectionsDecision::InsufficientTime { time_remaining } => {
+                    interval = self.skip_insufficient_time(
+                        election_id,
+                        time_remaining,
+                        elections.min_time_remaining,
+                        elections_end,
+                    )?;
+                    continue;
+                }
+            }
 
             // Wait until stakes are unfrozen
-            if let Some(mut unfreeze_at) = elector_data.nearest_unfreeze_at(election_id) {
-                unfreeze_at += self.params.stake_unfreeze_offset;
-                if unfreeze_at > elections_end.saturating_sub(self.params.elections_end_offset) {
+            if let Some(unfreeze_at) = unfreeze_at {
+                if unfreeze_at > deadline_at {
                     tracing::warn!(
                         unfreeze_at,
                         elections_end,
@@ -193,76 +511,648 @@ impl ValidationManager {
                 }
             }
 
-            // Prepare context
-            let keypair = self.dirs.load_validator_keys()?;
-            let ctx = ElectionsContext {
-                subscription,
-                elector,
-                elector_data,
-                election_id,
-                timings,
-                guard: &self.guard,
-            };
+            // NOTE: elector timings could have been changed since the target block
+            match self.refresh_timings(&subscription, &mut timings).await? {
+                Some(fresh_elections_end) => elections_end = fresh_elections_end,
+                None => {
+                    interval = 1; // recompute the timeline nearly immediate
+                    self.trace_action("elections are no longer open", interval);
+                    continue;
+                }
+            }
 
-            // Prepare election future
-            let validation = match validator {
-                AppConfigValidator::Single(validation) => validation.elect(keypair, ctx).boxed(),
-                AppConfigValidator::DePool(validation) => validation.elect(keypair, ctx).boxed(),
-            };
+            // NOTE: the deadline could have been moved by the fresh timings
+            let deadline_at = elections_end.saturating_sub(self.params.elections_end_offset);
+            elections.deadline_at = deadline_at;
+            elections.start_at = now();
+            if let ElectionsDecision::InsufficientTime { time_remaining } = elections.decide() {
+                interval =
+                    self.skip_insufficient_time(election_id, time_remaining, elections_end)?;
+                continue;
+            }
+
+            if self.trace.is_some() {
+                let balances = collect_balances(&units, &subscription).await;
+                self.trace("balances", || balances);
+            }
+
+            // Prepare election futures for all units
+            let stages = units
+                .iter()
+                .map(|_| parking_lot::Mutex::new("started"))
+                .collect::<Vec<_>>();
+            let mut elector_data = Some(elector_data);
+            let mut validations = Vec::with_capacity(units.len());
+            for (unit, stage) in units.iter().zip(&stages) {
+                // NOTE: each unit modifies its own elector state snapshot
+                let elector_data = match elector_data.take() {
+                    Some(elector_data) => elector_data,
+                    None => elector
+                        .get_data()
+                        .await
+                        .context("failed to get elector data")?,
+                };
+
+                let signer: Arc<dyn Signer> = Arc::new(
+                    unit.dirs
+                        .load_validator_keys(unit.validator.wallet_address())
+                        .with_context(|| format!("failed to load keys of unit `{}`", unit.name))?,
+                );
+                let ctx = ElectionsContext {
+                    dirs: &unit.dirs,
+                    subscription: subscription.clone(),
+                    elector: elector.clone(),
+                    elector_data,
+                    election_id,
+                    deadline_at,
+                    timings: timings.clone(),
+                    validators_count: validators_count.clone(),
+                    stakes_config: stakes_config.clone(),
+                    guard: &self.guard,
+                    max_attached_value: self.params.max_attached_value(config.one_coin()),
+                    balance_wait: self.params.balance_wait(config.one_coin()),
+                    depool_update_retries: config.retries.depool_update,
+                    one_coin: config.one_coin(),
+                    stage,
+                    hook: config.hook.as_ref(),
+                };
+
+                let validation = match &unit.validator {
+                    AppConfigValidator::Single(validation) => {
+                        validation.clone().elect(signer, ctx).boxed()
+                    }
+                    AppConfigValidator::DePool(validation) => {
+                        validation.clone().elect(signer, ctx).boxed()
+                    }
+                };
+
+                let name = unit.name.as_str();
+                validations.push(
+                    validation
+                        .map(move |res| res.with_context(|| format!("unit `{name}` failed")))
+                        .instrument(tracing::info_span!("unit", unit = name)),
+                );
+            }
 
             // Try elect
-            let deadline = Duration::from_secs(
-                elections_end
-                    .saturating_sub(self.params.elections_end_offset)
-                    .saturating_sub(now()) as u64,
+            let deadline = Duration::from_secs(deadline_at.saturating_sub(now()) as u64);
+            tracing::info!(
+                elections_end,
+                elections_end_offset = self.params.elections_end_offset,
+                deadline_at,
+                deadline = deadline.as_secs(),
+                "participating in elections until the deadline"
             );
-            match tokio::time::timeout(deadline, validation).await {
-                Ok(Ok(())) => tracing::info!("elections successful"),
-                Ok(Err(e)) => return Err(e),
-                Err(_) => tracing::warn!("elections deadline reached"),
+            if let Err(e) = self.dirs.store_elections_deadline(deadline_at) {
+                tracing::warn!("failed to store elections deadline: {e:?}");
+            }
+
+            let started_at = std::time::Instant::now();
+            let validations = futures_util::future::join_all(validations);
+            let mut outcomes = serde_json::Map::new();
+            let mut paused = false;
+            match tokio::time::timeout(deadline, validations).await {
+                Ok(results) => {
+                    // NOTE: the first error is returned, the rest are only logged
+                    let mut first_error = None;
+                    for (unit, result) in units.iter().zip(results) {
+                        let outcome = match &result {
+                            Ok(ElectOutcome::Done) => "ok".to_owned(),
+                            Ok(ElectOutcome::Paused) => "paused".to_owned(),
+                            Err(e) => format!("{e:?}"),
+                        };
+                        outcomes.insert(unit.name.clone(), outcome.into());
+
+                        if let Err(e) = &result {
+                            if ValidationError::classify(e) == Some(ValidationError::Underfunded) {
+                                run_hook(
+                                    config.hook.as_ref(),
+                                    HookEvent::Underfunded {
+                                        unit: unit.name.clone(),
+                                        reason: format!("{e:#}"),
+                                    },
+                                );
+                            }
+                        }
+
+                        match result {
+                            Ok(ElectOutcome::Done) => {
+                                tracing::info!(unit = %unit.name, "elections successful")
+                            }
+                            Ok(ElectOutcome::Paused) => {
+                                tracing::warn!(unit = %unit.name, "elections were paused");
+                                paused = true;
+                            }
+                            Err(e) if first_error.is_none() => first_error = Some(e),
+                            Err(e) => tracing::error!(
+                                unit = %unit.name,
+                                category = ValidationError::classify(&e).map(|e| e.category()),
+                                "elections failed: {e:?}"
+                            ),
+                        }
+                    }
+                    if let Some(e) = first_error {
+                        self.record_outcomes(&units, election_id, outcomes);
+                        return Err(e);
+                    }
+                }
+                Err(_) => {
+                    for (unit, stage) in units.iter().zip(&stages) {
+                        let outcome = format!("deadline reached at stage `{}`", stage.lock());
+                        outcomes.insert(unit.name.clone(), outcome.into());
+
+                        tracing::warn!(
+                            unit = %unit.name,
+                            elapsed = started_at.elapsed().as_secs(),
+                            stage = *stage.lock(),
+                            "elections deadline reached"
+                        );
+                    }
+                    if self.params.once_if_elections {
+                        self.record_outcomes(&units, election_id, outcomes);
+                        return Err(ValidationError::ElectionsClosed
+                            .with_reason("elections deadline reached"));
+                    }
+                }
+            }
+            self.record_outcomes(&units, election_id, outcomes);
+
+            // Wait until the validation is resumed to finish the attempt
+            if paused {
+                self.ensure_can_wait("validation is paused")?;
+                interval = PAUSE_CHECK_INTERVAL;
+                self.trace_action("validation is paused", interval);
+                continue;
             }
 
+            if self.params.once_if_elections {
+                return Ok(());
+            }
             interval = elections_end.saturating_sub(now());
         }
     }
 
+    /// Records the trace entry (if enabled), the value is computed only when needed
+    fn trace<F>(&mut self, key: &str, f: F)
+    where
+        F: FnOnce() -> serde_json::Value,
+    {
+        if let Some(trace) = &mut self.trace {
+            trace.record(key, f());
+        }
+    }
+
+    fn trace_action(&mut self, action: &str, next_check_in: u32) {
+        self.trace("action", || {
+            serde_json::json!({
+                "action": action,
+                "next_check_in": next_check_in,
+            })
+        });
+    }
+
+    /// Reopens the history database if its path was changed
+    fn update_history_db(&mut self, path: Option<&std::path::Path>) {
+        match (path, &self.history_db) {
+            (Some(path), Some(history_db)) if history_db.path() == path => {}
+            (Some(path), _) => self.history_db = Some(HistoryDb::open(path)),
+            (None, _) => self.history_db = None,
+        }
+    }
+
+    /// Records the results of the elections attempt with all actions made during it
+    /// (to the decision trace and the history database)
+    fn record_outcomes(
+        &mut self,
+        units: &[ValidationUnit],
+        election_id: u32,
+        outcomes: serde_json::Map<String, serde_json::Value>,
+    ) {
+        if let Some(history_db) = &self.history_db {
+            for unit in units {
+                history_db.import_history(&unit.name, &unit.dirs.validator_history);
+                if let Some(outcome) = outcomes.get(&unit.name).and_then(|value| value.as_str()) {
+                    history_db.record_outcome(&unit.name, election_id, outcome.to_owned());
+                }
+            }
+        }
+
+        let Some(started_at) = self.trace.as_ref().map(DecisionTrace::started_at) else {
+            return;
+        };
+
+        let mut actions = serde_json::Map::new();
+        for unit in units {
+            let events = match unit.dirs.load_validator_history() {
+                Ok(history) => history
+                    .into_iter()
+                    .filter(|event| event.timestamp >= started_at)
+                    .collect::<Vec<_>>(),
+                Err(e) => {
+                    tracing::warn!(unit = %unit.name, "failed to load validator history: {e:?}");
+                    Vec::new()
+                }
+            };
+            actions.insert(
+                unit.name.clone(),
+                serde_json::to_value(events).unwrap_or_default(),
+            );
+        }
+
+        self.trace_action("participated in elections", 0);
+        self.trace("outcomes", || outcomes.into());
+        self.trace("actions", || actions.into());
+    }
+
+    /// Records the error which interrupted the validation loop (if the trace is enabled)
+    pub fn trace_error(&mut self, error: &anyhow::Error) {
+        self.trace("error", || {
+            serde_json::json!({
+                "category": ValidationError::classify(error).map(|e| e.category()),
+                "message": format!("{error:?}"),
+            })
+        });
+    }
+
+    /// Skips the elections attempt which can't finish before the deadline.
+    ///
+    /// Returns the interval until the end of the elections.
+    fn skip_insufficient_time(
+        &mut self,
+        election_id: u32,
+        time_remaining: u32,
+        required_time_remaining: u32,
+        elections_end: u32,
+    ) -> Result<u32> {
+        tracing::warn!(
+            election_id,
+            time_remaining,
+            required_time_remaining,
+            "insufficient time remaining, skipping the elections attempt"
+        );
+        self.heartbeat.elections_skipped += 1;
+        self.ensure_can_wait("insufficient time remaining")?;
+        let interval = elections_end.saturating_sub(now());
+        self.trace_action("insufficient time remaining", interval);
+        Ok(interval)
+    }
+
+    fn ensure_can_wait(&self, reason: &'static str) -> Result<()> {
+        if self.params.once_if_elections {
+            return Err(ValidationError::NoOpenElections.with_reason(reason));
+        }
+        Ok(())
+    }
+
     async fn ensure_deployed(
         &self,
-        validator: &AppConfigValidator,
+        unit: &ValidationUnit,
         subscription: &Arc<Subscription>,
+        one_coin: u128,
     ) -> Result<bool> {
-        if matches!(&*self.last_params.lock(), Some(last_params) if last_params == validator) {
+        if self.last_params.lock().get(&unit.name) == Some(&unit.validator) {
             return Ok(false);
         }
 
         let ctx = DeploymentContext {
             subscription,
-            dirs: &self.dirs,
+            dirs: &unit.dirs,
             guard: &self.guard,
+            max_attached_value: self.params.max_attached_value(one_coin),
+            balance_wait: self.params.balance_wait(one_coin),
+            one_coin,
         };
 
-        match validator {
+        match &unit.validator {
             AppConfigValidator::Single(validator) => validator.deploy(ctx).await?,
             AppConfigValidator::DePool(validator) => validator.deploy(ctx).await?,
         }
-        *self.last_params.lock() = Some(validator.clone());
+        self.last_params
+            .lock()
+            .insert(unit.name.clone(), unit.validator.clone());
         Ok(true)
     }
 
-    async fn is_synced(&self, node_rpc: &NodeTcpRpc, only_mc: bool) -> Result<bool> {
+    async fn check_network(
+        &self,
+        subscription: &Subscription,
+        block_id: &ton_block::BlockIdExt,
+    ) -> Result<()> {
+        if !self.dirs.global_config.exists() {
+            return Ok(());
+        }
+
+        let global_config = GlobalConfig::load(&self.dirs.global_config)?;
+        let file_hash = global_config.zero_state.file_hash.as_array();
+        let Some(expected) = crate::config::known_network(file_hash) else {
+            tracing::warn!(
+                zerostate_file_hash = %hex::encode(file_hash),
+                "global config is for an unknown network, node network is not checked"
+            );
+            return Ok(());
+        };
+        let Some(expected_global_id) = expected.global_id else {
+            tracing::warn!(
+                network = expected.name,
+                "global id of the network is unknown, node network is not checked"
+            );
+            return Ok(());
+        };
+
+        let global_id = subscription
+            .get_global_id(block_id)
+            .await
+            .context("failed to get node global id")?;
+        if global_id != expected_global_id {
+            return Err(ValidationError::NetworkMismatch.with_reason(format!(
+                "node is on network with global id {global_id} but global config is for {} \
+                (global id {})",
+                expected.name, expected_global_id,
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn check_elected_keys(
+        &mut self,
+        unit: &ValidationUnit,
+        subscription: &Arc<Subscription>,
+        elector: &Elector,
+        current_vset: &ton_block::ValidatorSet,
+        one_coin: u128,
+    ) -> Result<()> {
+        let round_id = current_vset.utime_since();
+        if self.keys_checked_rounds.get(&unit.name) == Some(&round_id) {
+            return Ok(());
+        }
+
+        let participants = unit.participants(subscription, one_coin).await?;
+
+        // NOTE: election id of the finished elections is equal to the round start
+        let elector_data = elector
+            .get_data()
+            .await
+            .context("failed to get elector data")?;
+
+        let node_rpc = subscription.tcp_rpc();
+        let mut elected = false;
+        let mut missing = Vec::new();
+        for participant in &participants {
+            for public_key in elector_data.frozen_keys(round_id, participant) {
+                elected = true;
+                let key_hash =
+                    tl_proto::hash(everscale_crypto::tl::PublicKey::Ed25519 { key: &public_key });
+                if !node_rpc.has_key(&key_hash).await? {
+                    missing.push(hex::encode(public_key));
+                }
+            }
+        }
+
+        // NOTE: the event is repeated after restart, since checked rounds are not persisted
+        if elected {
+            run_hook(
+                self.hook.as_ref(),
+                HookEvent::Elected {
+                    unit: unit.name.clone(),
+                    round_id,
+                },
+            );
+        }
+
+        let mut state = unit.dirs.load_validator_state()?;
+        if missing.is_empty() {
+            tracing::info!(unit = %unit.name, round_id, "node has keys for all elected stakes");
+            if state.missing_keys.take().is_some() {
+                unit.dirs.store_validator_state(&state)?;
+            }
+        } else {
+            // NOTE: the stake is still frozen, but the node will not sign any blocks
+            tracing::error!(
+                unit = %unit.name,
+                category = ValidationError::ValidatorKeyMissing.category(),
+                round_id,
+                public_keys = ?missing,
+                "node has no keys for the elected validator, \
+                stake is at risk of being slashed for the whole round"
+            );
+            state.missing_keys = Some(MissingKeys {
+                round_id,
+                public_keys: missing,
+            });
+            unit.dirs.store_validator_state(&state)?;
+        }
+
+        self.keys_checked_rounds.insert(unit.name.clone(), round_id);
+        Ok(())
+    }
+
+    /// Recovers stakes of all units which were unfrozen since the last elections.
+    ///
+    /// Returns the nearest time when the next frozen stakes are unfrozen.
+    async fn recover_between_elections(
+        &self,
+        units: &[ValidationUnit],
+        config: &AppConfig,
+        subscription: &Arc<Subscription>,
+        elector: &Elector,
+    ) -> Result<Option<u32>> {
+        let elector_data = retry_node_query(&config.retries.node_query, "get elector data", || {
+            elector.get_data()
+        })
+        .await?;
+
+        let limits = self.params.wallet_limits(config.one_coin());
+        for unit in units {
+            let res = recover_unfrozen_stakes(
+                unit,
+                config,
+                subscription.clone(),
+                elector,
+                &elector_data,
+                &limits,
+                &self.guard,
+            )
+            .await;
+            if let Err(e) = res {
+                tracing::error!(unit = %unit.name, "failed to recover stakes: {e:?}");
+            }
+
+            if let AppConfigValidator::Single(single) = &unit.validator {
+                let res = sweep_excess_balance(
+                    &unit.dirs,
+                    config,
+                    single,
+                    subscription.clone(),
+                    &elector_data,
+                    &limits,
+                    &self.guard,
+                )
+                .await;
+                if let Err(e) = res {
+                    tracing::error!(unit = %unit.name, "failed to sweep excess balance: {e:?}");
+                }
+            }
+        }
+
+        // NOTE: stakes which are already unfrozen by time, but not yet by the elector
+        // are recovered on the next iterations without a precise schedule
+        let next_unfreeze_at = elector_data.next_unfreeze_at();
+        if let Some(unfreeze_at) = next_unfreeze_at {
+            tracing::info!(unfreeze_at, "waiting for the next stakes to unfreeze");
+        }
+        Ok(next_unfreeze_at)
+    }
+
+    /// Logs the consolidated summary of the finished round and records it into the history.
+    ///
+    /// NOTE: block production stats are not available through the control server,
+    /// and the round is not reported if its stake was already recovered.
+    async fn report_finished_round(
+        &self,
+        unit: &ValidationUnit,
+        subscription: &Arc<Subscription>,
+        elector: &Elector,
+        prev_vset: &ton_block::ValidatorSet,
+        one_coin: u128,
+    ) -> Result<()> {
+        let round_id = prev_vset.utime_since();
+
+        let mut state = unit.dirs.load_validator_state()?;
+        if matches!(state.last_round_report, Some(last) if last >= round_id) {
+            return Ok(());
+        }
+
+        let participants = unit.participants(subscription, one_coin).await?;
+        let elector_data = elector
+            .get_data()
+            .await
+            .context("failed to get elector data")?;
+
+        let (mut elected, mut in_validator_set) = (false, false);
+        let (mut stake, mut reward, mut penalty) = (0, 0, 0);
+        for participant in &participants {
+            // NOTE: election id of the finished elections is equal to the round start
+            let summary = elector_data
+                .past_elections_summary(participant, usize::MAX)
+                .into_iter()
+                .find(|summary| summary.election_id == round_id);
+            if let Some(summary) = summary {
+                elected |= summary.elected;
+                stake += summary.stake;
+                reward += summary.reward;
+                penalty += summary.penalty();
+            }
+
+            in_validator_set |= elector_data
+                .frozen_keys(round_id, participant)
+                .iter()
+                .any(|key| {
+                    prev_vset
+                        .list()
+                        .iter()
+                        .any(|descr| descr.public_key.as_slice() == key)
+                });
+        }
+
+        tracing::info!(
+            unit = %unit.name,
+            round_id,
+            elected,
+            in_validator_set,
+            stake = %Tokens(stake),
+            reward = %Tokens(reward),
+            penalty = %Tokens(penalty),
+            "round report"
+        );
+        unit.dirs
+            .record_validator_history(HistoryEventKind::RoundFinished {
+                round_id,
+                elected,
+                in_validator_set,
+                stake,
+                reward,
+                penalty,
+            });
+
+        state.last_round_report = Some(round_id);
+        unit.dirs.store_validator_state(&state)
+    }
+
+    /// Re-reads elector timings right before participating in elections.
+    ///
+    /// Returns the elections end computed from the fresh timings,
+    /// or `None` if the elections are no longer open with them.
+    async fn refresh_timings(
+        &self,
+        subscription: &Subscription,
+        timings: &mut ton_block::ConfigParam15,
+    ) -> Result<Option<u32>> {
+        fn summary(timings: &ton_block::ConfigParam15) -> [u32; 4] {
+            [
+                timings.validators_elected_for,
+                timings.elections_start_before,
+                timings.elections_end_before,
+                timings.stake_held_for,
+            ]
+        }
+
+        let ElectionsConfig {
+            timings: fresh_timings,
+            current_vset,
+            ..
+        } = subscription.tcp_rpc().get_elections_config().await?;
+
+        if summary(&fresh_timings) != summary(timings) {
+            tracing::warn!(
+                old = ?summary(timings),
+                new = ?summary(&fresh_timings),
+                "elector timings changed during the round \
+                (elected_for, start_before, end_before, held_for)"
+            );
+            self.params.check_elections_offsets(&fresh_timings)?;
+            *timings = fresh_timings;
+        }
+
+        Ok(match Timeline::compute(timings, &current_vset, now()) {
+            Timeline::Elections { elections_end, .. } => Some(elections_end),
+            timeline => {
+                tracing::warn!(%timeline, "elections are no longer open with the fresh timings");
+                None
+            }
+        })
+    }
+
+    async fn is_synced(&mut self, node_rpc: &NodeTcpRpc, only_mc: bool) -> Result<bool> {
         let interval = Duration::from_secs(10);
         let mut attempts = 6;
         loop {
-            match node_rpc.get_stats().await? {
+            let stats = node_rpc.get_stats().await.map_err(|e| {
+                // NOTE: control server silently closes connections of unknown clients
+                let class = match e.downcast_ref::<NodeRpcError>() {
+                    Some(e) if e.is_socket_closed() => ValidationError::AuthRejected,
+                    _ => ValidationError::NodeUnavailable,
+                };
+                e.context(class)
+            })?;
+
+            match stats {
                 NodeStats::Running(stats) => {
-                    if stats.mc_time_diff < self.params.max_time_diff
-                        && (only_mc || stats.sc_time_diff < self.params.max_time_diff)
+                    self.track_node_not_ready(None);
+                    if stats.mc_time_diff < self.params.max_mc_time_diff
+                        && (only_mc || stats.sc_time_diff < self.params.max_sc_time_diff)
                     {
                         break Ok(true);
                     }
+                    tracing::debug!(
+                        mc_time_diff = stats.mc_time_diff,
+                        sc_time_diff = stats.sc_time_diff,
+                        only_mc,
+                        "node is behind the network"
+                    );
                 }
-                NodeStats::NotReady(sync_status) => {
-                    tracing::trace!(%sync_status, "node not synced");
+                NodeStats::NotReady(stats) => {
+                    self.track_node_not_ready(Some(&stats));
                 }
             }
 
@@ -274,16 +1164,317 @@ impl ValidationManager {
             }
         }
     }
+
+    fn store_heartbeat(&self) {
+        if let Err(e) = self.dirs.store_validator_heartbeat(&self.heartbeat) {
+            tracing::warn!("failed to store validator heartbeat: {e:?}");
+        }
+    }
+
+    /// Warns about loop iterations which take longer than the configured max duration.
+    ///
+    /// NOTE: The watchdog is stopped when the returned guard is dropped
+    fn spawn_watchdog(&self) -> Option<DropGuard> {
+        const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+        let max_duration = self.params.max_iteration_duration?;
+        let iteration_started_at = self.iteration_started_at.clone();
+
+        let watchdog = async move {
+            let mut interval = tokio::time::interval(CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                let started_at = iteration_started_at.load(Ordering::Acquire);
+                let duration = now().saturating_sub(started_at);
+                if started_at > 0 && duration > max_duration {
+                    tracing::warn!(
+                        started_at,
+                        duration,
+                        max_duration,
+                        "validation loop iteration takes too long"
+                    );
+                }
+            }
+        };
+
+        let cancellation = CancellationToken::new();
+        let guard = cancellation.clone().drop_guard();
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = watchdog => {},
+                _ = cancellation.cancelled() => {}
+            }
+        });
+
+        Some(guard)
+    }
+
+    /// Escalates the node being not ready for longer than the threshold.
+    ///
+    /// States which can't be left without the operator (e.g. broken database)
+    /// are escalated immediately.
+    ///
+    /// NOTE: the node is still polled, the escalation is only visible in logs and metrics
+    fn track_node_not_ready(&mut self, stats: Option<&NotReadyStats>) {
+        const REPORT_INTERVAL: u32 = 600;
+
+        let Some(stats) = stats else {
+            if let Some((since, _)) = self.node_not_ready.take() {
+                tracing::info!(
+                    not_ready_for = %human_duration(now().saturating_sub(since)),
+                    "node is ready again"
+                );
+                if let Err(e) = self.dirs.set_node_not_ready(None) {
+                    tracing::warn!("failed to reset node not ready state: {e:?}");
+                }
+            }
+            return;
+        };
+
+        let now = now();
+        let (since, reported_at) = self.node_not_ready.get_or_insert((now, None));
+        let duration = now.saturating_sub(*since);
+        if (duration < self.params.node_not_ready_threshold && !stats.needs_intervention)
+            || matches!(reported_at, Some(reported_at) if now < *reported_at + REPORT_INTERVAL)
+        {
+            tracing::trace!(%stats, "node not synced");
+            return;
+        }
+        let first_report = reported_at.replace(now).is_none();
+        let since = *since;
+
+        tracing::error!(
+            sync_status = %stats.sync_status,
+            reason = stats.reason,
+            mc_time_diff = ?stats.mc_time_diff,
+            not_ready_for = %human_duration(duration),
+            "node is not ready for too long and probably needs intervention"
+        );
+        if let Err(e) = self.dirs.set_node_not_ready(Some(since)) {
+            tracing::warn!("failed to store node not ready state: {e:?}");
+        }
+        if first_report {
+            run_hook(
+                self.hook.as_ref(),
+                HookEvent::Desynced {
+                    since,
+                    sync_status: stats.sync_status.to_string(),
+                },
+            );
+        }
+    }
+}
+
+/// Collects balances of the contracts of all units (for the decision trace)
+async fn collect_balances(
+    units: &[ValidationUnit],
+    subscription: &Subscription,
+) -> serde_json::Value {
+    let mut balances = serde_json::Map::new();
+    for unit in units {
+        let addresses = match &unit.validator {
+            AppConfigValidator::Single(single) => vec![single.address.clone()],
+            AppConfigValidator::DePool(depool) => vec![depool.owner.clone(), depool.depool.clone()],
+        };
+        for address in addresses {
+            let balance = match subscription.get_account_state(&address).await {
+                Ok(state) => serde_json::Value::from(
+                    state.map(|state| state.storage.balance.grams.0.to_string()),
+                ),
+                Err(e) => format!("failed to get balance: {e:?}").into(),
+            };
+            balances.insert(address.to_string(), balance);
+        }
+    }
+    balances.into()
+}
+
+/// Independent validator with its own keys and state
+struct ValidationUnit {
+    name: String,
+    dirs: ProjectDirs,
+    validator: AppConfigValidator,
+}
+
+impl ValidationUnit {
+    fn new(dirs: &ProjectDirs, name: &str, validator: &AppConfigValidator) -> Self {
+        Self {
+            name: name.to_owned(),
+            dirs: dirs.unit_dirs(name),
+            validator: validator.clone(),
+        }
+    }
+
+    /// Collects all contracts which could have participated in the elections
+    async fn participants(
+        &self,
+        subscription: &Arc<Subscription>,
+        one_coin: u128,
+    ) -> Result<Vec<ton_block::MsgAddressInt>> {
+        Ok(match &self.validator {
+            AppConfigValidator::Single(single) => vec![single.address.clone()],
+            AppConfigValidator::DePool(depool) => {
+                let depool = DePool::from_config(depool, subscription.clone(), one_coin)?;
+                let state = depool.get_state().await?;
+                depool.get_info(&state)?.proxies
+            }
+        })
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct ValidationParams {
-    pub max_time_diff: i32,
+    /// Max masterchain time diff (in seconds) of a synced node
+    pub max_mc_time_diff: i32,
+    /// Max shardchain time diff (in seconds) of a synced node
+    pub max_sc_time_diff: i32,
+    /// How long (in seconds) the node can stay not ready before it is reported
+    pub node_not_ready_threshold: u32,
     pub stake_unfreeze_offset: u32,
     pub elections_start_offset: u32,
     pub elections_end_offset: u32,
+    /// Min time (in seconds) before the deadline to start the elections attempt
+    pub min_time_remaining: u32,
+    /// Loop iteration duration (in seconds) after which a warning is logged
+    pub max_iteration_duration: Option<u32>,
+    /// Whether inconsistent params are errors instead of warnings
+    pub strict: bool,
     pub disable_random_shift: bool,
     pub ignore_deploy: bool,
+    /// Whether to participate only in the currently open elections and stop
+    pub once_if_elections: bool,
+    /// Max value attached to any outgoing message (20M coins if not specified)
+    pub max_attached_value: Option<u128>,
+    /// Interval between balance checks
+    pub balance_poll_interval: Duration,
+    /// Interval between balance checks while the wallet transactions are tracked
+    pub balance_fallback_poll_interval: Duration,
+    /// Max balance waiting duration. Waits indefinitely if empty
+    pub balance_wait_timeout: Option<Duration>,
+    /// Amount required above the balance target (0.1 coin if not specified)
+    pub balance_safety_margin: Option<u128>,
+}
+
+/// Params of waiting until the wallet balance is enough
+#[derive(Debug, Clone, Copy)]
+pub struct BalanceWait {
+    /// Interval between balance checks
+    pub poll_interval: Duration,
+    /// Interval between balance checks while the wallet transactions are tracked
+    /// (each incoming transaction triggers a check, so polling is only a safety net)
+    pub fallback_poll_interval: Duration,
+    /// Max waiting duration. Waits indefinitely if empty
+    pub timeout: Option<Duration>,
+    /// Amount (in the smallest units) required above the target to cover fees
+    /// charged before sending
+    pub safety_margin: u128,
+}
+
+impl BalanceWait {
+    /// Default params for the network with the specified coin size
+    pub fn new(one_coin: u128) -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            fallback_poll_interval: Duration::from_secs(30),
+            timeout: Some(Duration::from_secs(3600)),
+            safety_margin: Self::default_safety_margin(one_coin),
+        }
+    }
+
+    /// Default amount required above the target (0.1 coin)
+    pub fn default_safety_margin(one_coin: u128) -> u128 {
+        one_coin / 10
+    }
+}
+
+/// Outgoing messages limits for the network with the specified coin size
+#[derive(Debug, Clone, Copy)]
+struct WalletLimits {
+    max_attached_value: u128,
+    balance_wait: BalanceWait,
+}
+
+impl ValidationParams {
+    fn wallet_limits(&self, one_coin: u128) -> WalletLimits {
+        WalletLimits {
+            max_attached_value: self.max_attached_value(one_coin),
+            balance_wait: self.balance_wait(one_coin),
+        }
+    }
+
+    /// Returns the max attached value in the smallest units of the network currency
+    fn max_attached_value(&self, one_coin: u128) -> u128 {
+        self.max_attached_value
+            .unwrap_or_else(|| Wallet::default_max_attached_value(one_coin))
+    }
+
+    /// Estimated time (in seconds) needed for the elections attempt, but not less
+    /// than `min_time_remaining`.
+    ///
+    /// NOTE: the balance wait is estimated as a single balance check, because
+    /// waiting for the funds is not limited by anything but the deadline
+    fn required_time_remaining(&self, send_message: &RetryPolicy) -> u32 {
+        let message_timeout = Duration::from_secs(Subscription::MESSAGE_TIMEOUT as u64);
+        let estimate = self
+            .balance_poll_interval
+            .saturating_add(send_message.max_duration(message_timeout));
+        let estimate = u32::try_from(estimate.as_secs()).unwrap_or(u32::MAX);
+        std::cmp::max(self.min_time_remaining, estimate)
+    }
+
+    /// Returns the balance waiting params for the network with the specified coin size
+    fn balance_wait(&self, one_coin: u128) -> BalanceWait {
+        BalanceWait {
+            poll_interval: self.balance_poll_interval,
+            fallback_poll_interval: self.balance_fallback_poll_interval,
+            timeout: self.balance_wait_timeout,
+            safety_margin: self
+                .balance_safety_margin
+                .unwrap_or_else(|| BalanceWait::default_safety_margin(one_coin)),
+        }
+    }
+
+    /// Checks that the elections offsets leave some time to participate.
+    ///
+    /// NOTE: otherwise it is always too early and then too late to send the stake,
+    /// so the validator would never participate without any error
+    fn check_elections_offsets(&self, timings: &ton_block::ConfigParam15) -> Result<()> {
+        let Some(message) = self.elections_offsets_issue(timings) else {
+            return Ok(());
+        };
+        anyhow::ensure!(!self.strict, message);
+        tracing::error!("inconsistent options: {message}");
+        Ok(())
+    }
+
+    /// Explains why the elections offsets leave no time to participate (if they do)
+    fn elections_offsets_issue(&self, timings: &ton_block::ConfigParam15) -> Option<String> {
+        let elections_duration = timings
+            .elections_start_before
+            .saturating_sub(timings.elections_end_before);
+
+        // At least one second must remain for the attempt even without `--min-time-remaining`
+        let required = self.min_time_remaining.max(1);
+        let offsets = self
+            .elections_start_offset
+            .saturating_add(self.elections_end_offset);
+        if offsets.saturating_add(required) <= elections_duration {
+            return None;
+        }
+
+        let available = elections_duration.saturating_sub(required);
+        Some(format!(
+            "--elections-start-offset ({}) and --elections-end-offset ({}) leave no time \
+            to participate in the elections ({elections_duration} seconds, \
+            at least {required} seconds must remain for the attempt). \
+            Max --elections-start-offset is {} with the current end offset, \
+            max --elections-end-offset is {} with the current start offset",
+            self.elections_start_offset,
+            self.elections_end_offset,
+            available.saturating_sub(self.elections_end_offset),
+            available.saturating_sub(self.elections_start_offset),
+        ))
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -291,15 +1482,145 @@ struct DeploymentContext<'a> {
     subscription: &'a Arc<Subscription>,
     dirs: &'a ProjectDirs,
     guard: &'a Mutex<()>,
+    max_attached_value: u128,
+    balance_wait: BalanceWait,
+    /// Amount of the smallest units in one coin
+    one_coin: u128,
+}
+
+/// Result of the elections attempt of a single unit
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum ElectOutcome {
+    /// Stake was sent or there was nothing to do
+    Done,
+    /// Validation was paused before sending the stake
+    Paused,
+}
+
+struct ElectionsContext<'a> {
+    dirs: &'a ProjectDirs,
+    subscription: Arc<Subscription>,
+    elector: Elector,
+    /// Elector state snapshot, fetched once per `try_validate` iteration.
+    /// Use [`ElectionsContext::refresh_elector_data`] to get a fresh one.
+    elector_data: elector::ElectorData,
+    election_id: u32,
+    /// Timestamp after which the elections attempt is abandoned
+    deadline_at: u32,
+    timings: ton_block::ConfigParam15,
+    validators_count: ton_block::ConfigParam16,
+    stakes_config: ton_block::ConfigParam17,
+    guard: &'a Mutex<()>,
+    max_attached_value: u128,
+    balance_wait: BalanceWait,
+    depool_update_retries: RetryPolicy,
+    /// Amount of the smallest units in one coin
+    one_coin: u128,
+    /// Current elections step, reported when the deadline is reached
+    stage: &'a parking_lot::Mutex<&'static str>,
+    hook: Option<&'a AppConfigHook>,
 }
 
-struct ElectionsContext<'a> {
-    subscription: Arc<Subscription>,
-    elector: Elector,
-    elector_data: elector::ElectorData,
-    election_id: u32,
-    timings: ton_block::ConfigParam15,
-    guard: &'a Mutex<()>,
+impl ElectionsContext<'_> {
+    fn set_stage(&self, stage: &'static str) {
+        *self.stage.lock() = stage;
+    }
+
+    /// Replaces the elector state snapshot with the new one
+    async fn refresh_elector_data(&mut self) -> Result<()> {
+        self.elector_data = self
+            .elector
+            .get_data()
+            .await
+            .context("failed to refresh elector data")?;
+        Ok(())
+    }
+
+    /// Makes sure that the elector has accepted the stake instead of returning it
+    async fn verify_stake_accepted(
+        &mut self,
+        participant: &ton_block::MsgAddressInt,
+        stake: u128,
+        stake_factor: u32,
+        response: Option<elector::StakeResponse>,
+    ) -> Result<()> {
+        const ATTEMPTS: usize = 12;
+        const INTERVAL: Duration = Duration::from_secs(5);
+
+        let stake_returned = |reason: elector::StakeReturnReason| {
+            ValidationError::StakeReturned.with_reason(format!(
+                "elector returned the stake of {participant}: {reason}"
+            ))
+        };
+
+        if let Some(elector::StakeResponse::Returned(reason)) = response {
+            return Err(stake_returned(reason));
+        }
+
+        // NOTE: the stake could still be on its way to the elector (e.g. through the DePool)
+        for attempt in 0..ATTEMPTS {
+            if attempt > 0 {
+                tokio::time::sleep(INTERVAL).await;
+            }
+
+            self.refresh_elector_data().await?;
+            if self.elector_data.elected(participant) {
+                tracing::info!(%participant, "stake accepted by elector");
+                return Ok(());
+            }
+        }
+
+        match self.elector_data.stake_return_reason(
+            self.election_id,
+            stake,
+            stake_factor,
+            self.one_coin,
+        ) {
+            Some(reason) => Err(stake_returned(reason)),
+            None => Err(ValidationError::StakeReturned.with_reason(format!(
+                "{participant} is not among the elections participants"
+            ))),
+        }
+    }
+
+    /// Estimates the stake which will be accounted by the elector
+    /// with the current participants and warns if it is too low
+    fn check_effective_stake(&self, stake: u128, stake_factor: u32) {
+        let Some(effective) = self.elector_data.compute_effective_stake(
+            stake,
+            stake_factor,
+            &self.validators_count,
+            &self.stakes_config,
+        ) else {
+            return;
+        };
+
+        let min_stake = self.stakes_config.min_stake.0;
+        if effective.stake == 0 {
+            tracing::warn!(
+                stake = %Tokens(stake),
+                stake_factor,
+                validator_count = effective.validator_count,
+                "stake will not be elected with the current participants"
+            );
+        } else if effective.stake < min_stake {
+            tracing::warn!(
+                stake = %Tokens(stake),
+                stake_factor,
+                effective_stake = %Tokens(effective.stake),
+                min_stake = %Tokens(min_stake),
+                "effective stake is below the min stake"
+            );
+        } else {
+            tracing::info!(
+                stake = %Tokens(stake),
+                stake_factor,
+                effective_stake = %Tokens(effective.stake),
+                validator_count = effective.validator_count,
+                "computed effective stake"
+            );
+        }
+    }
 }
 
 impl AppConfigValidatorSingle {
@@ -308,57 +1629,156 @@ impl AppConfigValidatorSingle {
         Ok(())
     }
 
-    async fn elect(self, keypair: ed25519_dalek::Keypair, ctx: ElectionsContext<'_>) -> Result<()> {
+    async fn elect(
+        self,
+        signer: Arc<dyn Signer>,
+        mut ctx: ElectionsContext<'_>,
+    ) -> Result<ElectOutcome> {
         tracing::info!(
             election_id = ctx.election_id,
-            address = %self.address,
-            stake = %Tokens(self.stake_per_round),
+            address = %Redacted(&self.address),
+            stake = %self.stake_per_round,
             stake_factor = ?self.stake_factor,
             "election as single"
         );
 
-        let wallet = Wallet::new(-1, keypair, ctx.subscription.clone());
-        anyhow::ensure!(
-            wallet.address() == &self.address,
-            "validator wallet address mismatch"
-        );
-
-        if let Some(stake) = ctx.elector_data.has_unfrozen_stake(wallet.address()) {
-            wallet.wait_for_balance(2 * ONE_EVER).await?;
-
-            // Prevent shutdown during stake recovery
-            let _guard = ctx.guard.lock().await;
+        let wallet = Wallet::new(-1, signer, ctx.subscription.clone(), ctx.max_attached_value);
+        if wallet.address() != &self.address {
+            return Err(ValidationError::KeyMismatch
+                .with_reason(wallet_mismatch_reason(&self.address, wallet.address())));
+        }
 
-            // Send recover stake message
-            tracing::info!(stake = %Tokens(stake.0), "recovering stake");
-            wallet
-                .call(ctx.elector.recover_stake()?)
-                .await
-                .context("failed to recover stake")?;
+        // NOTE: independent reads are done concurrently to save time near the deadline
+        let (wallet_balance, signature_id) = futures_util::future::join(
+            wallet.get_balance_with_status(),
+            ctx.subscription.get_signature_id(),
+        )
+        .await;
+        let wallet_balance = wallet_balance.context("failed to get validator wallet status")?;
+        wallet.check_not_frozen(wallet_balance.status)?;
+        let signature_id = signature_id?;
+
+        // The balance is reused only if it is not changed by the stake recovery
+        let prefetched_balance = ctx
+            .elector_data
+            .has_unfrozen_stake(wallet.address())
+            .is_none()
+            .then_some(wallet_balance);
+
+        ctx.set_stage("recovering stake");
+        let recovered = recover_stake(
+            &wallet,
+            &ctx.elector,
+            &ctx.elector_data,
+            ctx.one_coin,
+            &ctx.balance_wait,
+            ctx.guard,
+            ctx.dirs,
+        )
+        .await?;
+        if let Some(stake) = recovered {
+            run_hook(
+                ctx.hook,
+                HookEvent::Recovered {
+                    wallet: wallet.address().to_string(),
+                    stake,
+                },
+            );
         }
 
         if ctx.elector_data.elected(wallet.address()) {
             // Do nothing if elected
             tracing::info!("validator already elected");
-            return Ok(());
+            return Ok(ElectOutcome::Done);
         }
 
-        // Wait until validator wallet balance is enough
-        let target_balance = self.stake_per_round as u128 + 2 * ONE_EVER;
-        wallet.wait_for_balance(target_balance).await?;
+        // Resolve the stake for these elections
+        let balance = if SingleStake::requires_balance(&self) {
+            match prefetched_balance {
+                Some(balance) => balance,
+                None => wallet.get_balance_with_status().await?,
+            }
+            .spendable()
+        } else {
+            0
+        };
+        let resolved = SingleStake::resolve(
+            &self,
+            balance,
+            latest_unfrozen_reward(ctx.dirs, &ctx.elector_data, wallet.address()),
+            2 * ctx.one_coin + ctx.balance_wait.safety_margin,
+            &ctx.stakes_config,
+        );
+        let stake_per_round = match resolved {
+            SingleStake::Fixed { stake } => stake,
+            SingleStake::Compounded {
+                base,
+                reward,
+                compounded,
+            } => {
+                let stake = base + compounded;
+                tracing::info!(
+                    base = %Tokens(base),
+                    reward = %Tokens(reward),
+                    compounded = %Tokens(compounded),
+                    total = %Tokens(stake),
+                    "compounded the latest reward into the stake"
+                );
+                stake
+            }
+            SingleStake::Percent {
+                percent,
+                balance,
+                stake,
+            } => {
+                tracing::info!(
+                    percent,
+                    balance = %Tokens(balance),
+                    stake = %Tokens(stake),
+                    "resolved stake from the wallet balance"
+                );
+                stake
+            }
+            SingleStake::BelowMinStake {
+                percent,
+                stake,
+                min_stake,
+            } => {
+                tracing::warn!(
+                    stake = %Tokens(stake),
+                    min_stake = %Tokens(min_stake),
+                    "{percent}% of the balance is below the min stake, skipping elections"
+                );
+                return Ok(ElectOutcome::Done);
+            }
+        };
 
-        let signature_id = ctx.subscription.get_signature_id().await?;
+        ctx.check_effective_stake(
+            stake_per_round,
+            self.stake_factor.unwrap_or(DEFAULT_STAKE_FACTOR),
+        );
 
-        // Prevent shutdown while electing
-        let _guard = ctx.guard.lock().await;
+        // Wait until validator wallet balance is enough
+        // and prevent shutdown while electing
+        ctx.set_stage("waiting for wallet balance");
+        let target_balance = stake_per_round + 2 * ctx.one_coin;
+        let guard = wallet
+            .wait_for_balance_locked(target_balance, &ctx.balance_wait, ctx.guard)
+            .await?;
+        if ctx.dirs.is_paused() {
+            tracing::warn!("validation was paused before sending the stake");
+            return Ok(ElectOutcome::Paused);
+        }
 
         // Prepare node for elections
+        ctx.set_stage("preparing election payload");
+        let stake_factor = self.stake_factor.unwrap_or(DEFAULT_STAKE_FACTOR);
         let payload = ctx
             .elector
             .participate_in_elections(
                 ctx.election_id,
                 wallet.address(),
-                self.stake_factor.unwrap_or(DEFAULT_STAKE_FACTOR),
+                stake_factor,
                 &ctx.timings,
                 signature_id,
             )
@@ -367,18 +1787,59 @@ impl AppConfigValidatorSingle {
         tracing::info!("generated election payload");
 
         // Send election message
-        wallet
-            .call(InternalMessage {
-                dst: ctx.elector.address().clone(),
-                amount: self.stake_per_round as u128 + ONE_EVER,
-                payload,
-            })
+        ctx.set_stage("sending stake");
+        let stake = stake_per_round + ctx.one_coin;
+
+        // NOTE: an expired message could still be delivered later, so the
+        // elector state is checked before each retry to not send the stake twice
+        let (elector, deadline_at, address) = (&ctx.elector, ctx.deadline_at, wallet.address());
+        let still_needed = move || async move {
+            if now() >= deadline_at {
+                return Ok(false);
+            }
+            let elector_data = elector.get_data().await?;
+            Ok(!elector_data.elected(address))
+        };
+
+        let elector_tx = wallet
+            .call_if_with_fees(
+                InternalMessage {
+                    dst: ctx.elector.address().clone(),
+                    amount: stake,
+                    payload,
+                },
+                still_needed,
+            )
             .await
             .context("failed to participate in elections")?;
+        drop(guard);
 
-        // Done
+        let Some((elector_tx, fees)) = elector_tx else {
+            ctx.refresh_elector_data().await?;
+            anyhow::ensure!(
+                ctx.elector_data.elected(wallet.address()),
+                "elections deadline reached before the stake was delivered"
+            );
+            tracing::info!("stake from the expired message was accepted by elector");
+            return Ok(ElectOutcome::Done);
+        };
         tracing::info!("sent validator stake");
-        Ok(())
+
+        ctx.dirs
+            .record_validator_history(HistoryEventKind::StakeSent {
+                election_id: ctx.election_id,
+                participant: wallet.address().to_string(),
+                stake,
+                fees,
+                tx_hash: elector_tx.hash.to_hex_string(),
+            });
+
+        // Check that the stake was not returned
+        ctx.set_stage("verifying stake");
+        let response = Elector::parse_stake_response(&elector_tx.data);
+        ctx.verify_stake_accepted(wallet.address(), stake, stake_factor, response)
+            .await?;
+        Ok(ElectOutcome::Done)
     }
 }
 
@@ -395,11 +1856,16 @@ impl AppConfigValidatorDePool {
                 match &mut self.state {
                     Some(wallet) => Ok(wallet),
                     state => {
-                        let keypair = self.ctx.dirs.load_validator_keys()?;
-                        let res = Wallet::new(0, keypair, self.ctx.subscription.clone());
+                        let signer = Arc::new(self.ctx.dirs.load_validator_keys(self.target)?);
+                        let res = Wallet::new(
+                            0,
+                            signer,
+                            self.ctx.subscription.clone(),
+                            self.ctx.max_attached_value,
+                        );
                         anyhow::ensure!(
                             res.address() == self.target,
-                            "validator wallet address mismatch"
+                            wallet_mismatch_reason(self.target, res.address())
                         );
                         Ok(state.get_or_insert(res))
                     }
@@ -413,11 +1879,7 @@ impl AppConfigValidatorDePool {
             ctx,
         };
 
-        let mut depool = DePool::new(
-            self.depool_type,
-            self.depool.clone(),
-            ctx.subscription.clone(),
-        );
+        let mut depool = DePool::from_config(self, ctx.subscription.clone(), ctx.one_coin)?;
 
         // Ensure that depool is deployed
         if depool
@@ -435,8 +1897,8 @@ impl AppConfigValidatorDePool {
                 .as_ref()
                 .context("deployment params not found")?;
 
-            // Load and check depool keypair
-            depool.set_keypair(ctx.dirs.load_depool_keys()?)?;
+            // Load and check depool keys
+            depool.set_signer(Arc::new(ctx.dirs.load_depool_keys()?))?;
 
             // Prepare wallet
             let wallet = wallet.get_or_init()?;
@@ -449,14 +1911,15 @@ impl AppConfigValidatorDePool {
                 .unwrap_or_default();
 
             // Compute remaining depool balance
-            let depool_initial_balance = DePool::INITIAL_BALANCE
+            let depool_initial_balance = DePool::initial_balance(ctx.one_coin)
                 .checked_sub(depool_balance)
-                .and_then(|diff| (diff > 0).then_some(std::cmp::max(diff, ONE_EVER)));
+                .and_then(|diff| (diff > 0).then_some(std::cmp::max(diff, ctx.one_coin)));
 
             // Wait until there are enough funds on the validator wallet
-            let target =
-                Wallet::INITIAL_BALANCE + depool_initial_balance.unwrap_or_default() + ONE_EVER;
-            wallet.wait_for_balance(target).await?;
+            let target = Wallet::initial_balance(ctx.one_coin)
+                + depool_initial_balance.unwrap_or_default()
+                + ctx.one_coin;
+            wallet.wait_for_balance(target, &ctx.balance_wait).await?;
 
             // Transfer initial funds to the depool (if its balance is not enough)
             if let Some(balance) = depool_initial_balance {
@@ -464,8 +1927,12 @@ impl AppConfigValidatorDePool {
                 let _guard = ctx.guard.lock();
 
                 tracing::info!("transferring initial funds to the DePool");
+                let mut message = InternalMessage::empty(depool.address().clone(), balance);
+                if let Some(comment) = &self.transfer_comment {
+                    message = message.with_comment(comment)?;
+                }
                 wallet
-                    .call(InternalMessage::empty(depool.address().clone(), balance))
+                    .call(message)
                     .await
                     .context("failed to transfer funds to the DePool contract")?;
             }
@@ -525,27 +1992,28 @@ impl AppConfigValidatorDePool {
         Ok(())
     }
 
-    async fn elect(self, keypair: ed25519_dalek::Keypair, ctx: ElectionsContext<'_>) -> Result<()> {
+    async fn elect(
+        self,
+        signer: Arc<dyn Signer>,
+        mut ctx: ElectionsContext<'_>,
+    ) -> Result<ElectOutcome> {
         tracing::info!(
             election_id = ctx.election_id,
-            depool = %self.depool,
+            depool = %Redacted(&self.depool),
             depool_type = ?self.depool_type,
-            owner = %self.owner,
+            owner = %Redacted(&self.owner),
             stake_factor = ?self.stake_factor,
             "election as DePool"
         );
 
-        let wallet = Wallet::new(0, keypair, ctx.subscription.clone());
-        anyhow::ensure!(
-            wallet.address() == &self.owner,
-            "validator wallet address mismatch"
-        );
+        let wallet = Wallet::new(0, signer, ctx.subscription.clone(), ctx.max_attached_value);
+        if wallet.address() != &self.owner {
+            return Err(ValidationError::KeyMismatch
+                .with_reason(wallet_mismatch_reason(&self.owner, wallet.address())));
+        }
+        wallet.ensure_not_frozen().await?;
 
-        let depool = DePool::new(
-            self.depool_type,
-            self.depool.clone(),
-            ctx.subscription.clone(),
-        );
+        let depool = DePool::from_config(self, ctx.subscription.clone(), ctx.one_coin)?;
         let depool_state = depool
             .get_state()
             .await
@@ -554,19 +2022,20 @@ impl AppConfigValidatorDePool {
         let depool_info = depool
             .get_info(&depool_state)
             .context("failed to get DePool info")?;
-        anyhow::ensure!(
-            wallet.address() == &depool_info.validator_wallet,
-            "DePool owner mismatch"
-        );
+        if wallet.address() != &depool_info.validator_wallet {
+            return Err(ValidationError::KeyMismatch.with_reason("DePool owner mismatch"));
+        }
         anyhow::ensure!(depool_info.proxies.len() == 2, "invalid DePool proxies");
 
         // Ensure that depool and proxy balances are enough
-        self.maintain_balances(&wallet, &depool, &ctx)
+        ctx.set_stage("maintaining DePool balances");
+        self.maintain_balances(&wallet, &depool, &depool_state, &ctx)
             .await
             .context("failed to maintain balances")?;
 
         // Update depool
-        let (round_id, step) = match self
+        ctx.set_stage("updating DePool");
+        let round = match self
             .update_depool(&wallet, &depool, &depool_info, depool_state, &ctx)
             .await
             .context("failed to update depool")?
@@ -574,36 +2043,67 @@ impl AppConfigValidatorDePool {
             Some(round) => round,
             None => {
                 tracing::info!("skipping round");
-                return Ok(());
+                return Ok(ElectOutcome::Done);
             }
         };
 
-        if step != depool::RoundStep::WaitingValidatorRequest {
+        if round.step != depool::RoundStep::WaitingValidatorRequest {
             tracing::info!("depool is not waiting for the validator request");
-            return Ok(());
+            return Ok(ElectOutcome::Done);
+        }
+
+        // NOTE: DePool update could take several minutes, so the snapshot might be outdated
+        ctx.refresh_elector_data().await?;
+        if ctx.elector_data.election_id() != Some(ctx.election_id) {
+            return Err(ValidationError::ElectionsClosed.with_reason(format!(
+                "elections {} finished while updating DePool",
+                ctx.election_id
+            )));
         }
 
-        let proxy = &depool_info.proxies[round_id as usize % 2];
+        let route = depool
+            .election_route(&depool_info, &round)
+            .await
+            .context("invalid election route")?;
+        tracing::info!(
+            depool = %Redacted(&route.depool),
+            proxy = %Redacted(&route.proxy),
+            "election route"
+        );
+
+        let proxy = &route.proxy;
         if ctx.elector_data.elected(proxy) {
-            tracing::info!(%proxy, "proxy already elected");
-            return Ok(());
+            tracing::info!(proxy = %Redacted(proxy), "proxy already elected");
+            return Ok(ElectOutcome::Done);
         }
 
-        // Wait until validator wallet balance is enough
-        wallet.wait_for_balance(2 * ONE_EVER).await?;
+        ctx.check_effective_stake(
+            round.stake as u128,
+            self.stake_factor.unwrap_or(DEFAULT_STAKE_FACTOR),
+        );
 
         let signature_id = ctx.subscription.get_signature_id().await?;
 
-        // Prevent shutdown while electing
-        let _guard = ctx.guard.lock().await;
+        // Wait until validator wallet balance is enough
+        // and prevent shutdown while electing
+        ctx.set_stage("waiting for wallet balance");
+        let guard = wallet
+            .wait_for_balance_locked(2 * ctx.one_coin, &ctx.balance_wait, ctx.guard)
+            .await?;
+        if ctx.dirs.is_paused() {
+            tracing::warn!("validation was paused before sending the stake");
+            return Ok(ElectOutcome::Paused);
+        }
 
         // Prepare node for elections
+        ctx.set_stage("preparing election payload");
+        let stake_factor = self.stake_factor.unwrap_or(DEFAULT_STAKE_FACTOR);
         let payload = ctx
             .elector
             .participate_in_elections(
                 ctx.election_id,
                 proxy,
-                self.stake_factor.unwrap_or(DEFAULT_STAKE_FACTOR),
+                stake_factor,
                 &ctx.timings,
                 signature_id,
             )
@@ -612,39 +2112,63 @@ impl AppConfigValidatorDePool {
         tracing::info!("generated election payload");
 
         // Send election message
-        wallet
-            .call(InternalMessage {
-                dst: depool.address().clone(),
-                amount: ONE_EVER,
+        ctx.set_stage("sending stake");
+        let (tx, fees) = wallet
+            .call_with_fees(InternalMessage {
+                dst: route.depool.clone(),
+                amount: ctx.one_coin,
                 payload,
             })
             .await
             .context("failed to participate in elections")?;
-
-        // Done
         tracing::info!("sent validator stake");
-        Ok(())
+        drop(guard);
+
+        ctx.dirs
+            .record_validator_history(HistoryEventKind::StakeSent {
+                election_id: ctx.election_id,
+                participant: proxy.to_string(),
+                stake: round.stake as u128,
+                fees,
+                tx_hash: tx.hash.to_hex_string(),
+            });
+
+        // Check that the stake was not returned
+        ctx.set_stage("verifying stake");
+        // NOTE: the elector response goes to the proxy, so only its state is checked
+        ctx.verify_stake_accepted(proxy, round.stake as u128, stake_factor, None)
+            .await?;
+        Ok(ElectOutcome::Done)
     }
 
     async fn maintain_balances(
         &self,
         wallet: &Wallet,
         depool: &DePool,
+        depool_state: &depool::DePoolState,
         ctx: &ElectionsContext<'_>,
     ) -> Result<()> {
         // Check and refill depool and proxy balances
-        let refill_messages = depool.maintain_balances().await?;
-        for message in refill_messages {
+        let refill_messages = depool.maintain_balances(depool_state).await?;
+        for mut message in refill_messages {
+            if let Some(comment) = &self.transfer_comment {
+                message = message.with_comment(comment)?;
+            }
+
             tracing::info!(
-                target = %message.dst,
+                target = %Redacted(&message.dst),
                 amount = %message.amount,
                 "replenishing depool contracts"
             );
 
-            wallet.wait_for_balance(message.amount + ONE_EVER).await?;
-
             // Prevent shutdown during operation
-            let _guard = ctx.guard.lock().await;
+            let _guard = wallet
+                .wait_for_balance_locked(
+                    message.amount + ctx.one_coin,
+                    &ctx.balance_wait,
+                    ctx.guard,
+                )
+                .await?;
 
             // Send some funds to depool contracts
             wallet
@@ -661,22 +2185,26 @@ impl AppConfigValidatorDePool {
         wallet: &Wallet,
         depool: &DePool,
         depool_info: &depool::DePoolInfo,
-        mut depool_state: ton_block::AccountStuff,
+        mut depool_state: depool::DePoolState,
         ctx: &ElectionsContext<'_>,
-    ) -> Result<Option<(u64, depool::RoundStep)>> {
-        const TICKTOCK_INTERVAL: Duration = Duration::from_secs(60);
-
-        let mut attempts = 4;
+    ) -> Result<Option<depool::Round>> {
+        let mut backoff = self
+            .depool_update_retries(&ctx.depool_update_retries)
+            .backoff();
         let mut sent_ticktock = false;
         loop {
+            // NOTE: Participant info is computed on the state snapshot, while rounds are
+            // computed at the snapshot time, so that the round steps are consistent with it
+
             // Get validator stakes info
             let participant_info = depool
                 .get_participant_info(&depool_state, wallet.address())
                 .context("failed to get participant info")?;
 
             // Get all depool rounds
-            let rounds = depool
-                .get_rounds(&depool_state)
+            let mut rounds = depool
+                .get_rounds(depool_state.now)
+                .await
                 .context("failed to get depool rounds")?
                 .into_values()
                 .collect::<Vec<_>>();
@@ -705,48 +2233,100 @@ impl AppConfigValidatorDePool {
                 .validator_assurance
                 .checked_sub(pooling_round_stake)
             {
+                // NOTE: the stake sent before the restart could still be on its way
+                let mut state = ctx.dirs.load_validator_state()?;
+                if let Some(top_up) = &state.depool_top_up {
+                    let in_flight = top_up.in_flight(pooling_round.id, pooling_round_stake, now());
+                    if in_flight > 0 {
+                        tracing::warn!(
+                            round_id = top_up.round_id,
+                            in_flight = %Tokens(in_flight),
+                            "previous ordinary stake was not observed yet"
+                        );
+                        remaining_stake = remaining_stake.saturating_sub(in_flight);
+                    }
+                }
+
                 if remaining_stake > 0 {
                     remaining_stake = std::cmp::max(remaining_stake, depool_info.min_stake);
-                    wallet
-                        .wait_for_balance(remaining_stake as u128 + ONE_EVER)
+                    // Prevent shutdown during sending stake
+                    let _guard = wallet
+                        .wait_for_balance_locked(
+                            remaining_stake as u128 + ctx.one_coin,
+                            &ctx.balance_wait,
+                            ctx.guard,
+                        )
                         .await?;
 
-                    // Prevent shutdown during sending stake
-                    let _guard = ctx.guard.lock().await;
+                    // Remember the intent before sending anything
+                    state.depool_top_up = Some(DePoolTopUp {
+                        round_id: pooling_round.id,
+                        amount: remaining_stake,
+                        expected_stake: pooling_round_stake + remaining_stake,
+                        created_at: now(),
+                        tx_hash: None,
+                    });
+                    ctx.dirs.store_validator_state(&state)?;
 
                     // Send recover stake message
                     tracing::info!(stake = %Tokens(remaining_stake), "adding ordinary stake");
-                    wallet
-                        .call(depool.add_ordinary_stake(remaining_stake)?)
+                    let (tx, fees) = wallet
+                        .call_with_fees(depool.add_ordinary_stake(remaining_stake)?)
                         .await
                         .context("failed to add ordinary stake")?;
+
+                    if let Some(top_up) = &mut state.depool_top_up {
+                        top_up.tx_hash = Some(tx.hash.to_hex_string());
+                    }
+                    ctx.dirs.store_validator_state(&state)?;
+
+                    ctx.dirs
+                        .record_validator_history(HistoryEventKind::DePoolStakeAdded {
+                            round_id: pooling_round.id,
+                            amount: remaining_stake as u128,
+                            fees,
+                            tx_hash: tx.hash.to_hex_string(),
+                        });
                 }
             }
 
             if target_round.supposed_elected_at == ctx.election_id {
                 // Return target round if it is configured
-                break Ok(Some((target_round.id, target_round.step)));
+                break Ok(Some(rounds.swap_remove(1)));
             } else if sent_ticktock
                 && target_round.completion_reason == depool::CompletionReason::FakeRound
             {
                 // Skip initial fake round
                 break Ok(None);
-            } else {
-                // Reduce attempts otherwise
-                attempts -= 1;
-                anyhow::ensure!(attempts > 0, "failed to update rounds");
             }
 
-            // Update rounds
-            wallet.wait_for_balance(2 * ONE_EVER).await?;
+            // Reduce attempts otherwise
+            let Some(ticktock_interval) = backoff.next_delay() else {
+                return Err(ValidationError::DePoolPhaseSkew.with_reason(format!(
+                    "failed to update rounds (target round is for elections {})",
+                    target_round.supposed_elected_at
+                )));
+            };
 
-            tracing::info!("sending ticktock");
+            // Update rounds
             wallet
-                .call(depool.ticktock()?)
+                .wait_for_balance(depool.ticktock_value() + ctx.one_coin, &ctx.balance_wait)
+                .await?;
+
+            tracing::info!(value = %Tokens(depool.ticktock_value()), "sending ticktock");
+            let (tx, fees) = wallet
+                .call_with_fees(depool.ticktock()?)
                 .await
                 .context("failed to send ticktock")?;
             sent_ticktock = true;
-            tokio::time::sleep(TICKTOCK_INTERVAL).await;
+
+            ctx.dirs
+                .record_validator_history(HistoryEventKind::TicktockSent {
+                    depool: depool.address().to_string(),
+                    fees,
+                    tx_hash: tx.hash.to_hex_string(),
+                });
+            tokio::time::sleep(ticktock_interval).await;
 
             // Update depool state
             depool_state = depool
@@ -758,7 +2338,7 @@ impl AppConfigValidatorDePool {
 }
 
 #[derive(Debug, Clone, Copy)]
-enum Timeline {
+pub enum Timeline {
     BeforeElections {
         until_elections_start: u32,
     },
@@ -795,7 +2375,7 @@ impl std::fmt::Display for Timeline {
 }
 
 impl Timeline {
-    fn compute(
+    pub fn compute(
         timings: &ton_block::ConfigParam15,
         current_vset: &ton_block::ValidatorSet,
         now: u32,
@@ -825,11 +2405,40 @@ impl Timeline {
 }
 
 impl Wallet {
-    async fn wait_for_balance(&self, target: u128) -> Result<u128> {
-        let interval = Duration::from_secs(1);
+    async fn ensure_not_frozen(&self) -> Result<()> {
+        let status = self
+            .get_status()
+            .await
+            .context("failed to get validator wallet status")?;
+        self.check_not_frozen(status)
+    }
+
+    fn check_not_frozen(&self, status: AccountStatus) -> Result<()> {
+        if status == AccountStatus::Frozen {
+            return Err(ValidationError::Underfunded.with_reason(format!(
+                "validator wallet {} is frozen, top it up to unfreeze",
+                Redacted(self.address())
+            )));
+        }
+        Ok(())
+    }
+
+    /// Waits until the wallet balance reaches the target with the safety margin.
+    ///
+    /// Balance is checked on each incoming transaction and periodically as a fallback
+    /// (with the fast poll interval only while transactions are not tracked).
+    /// Only the spendable balance is counted, so a frozen wallet is waited until unfrozen.
+    async fn wait_for_balance(&self, target: u128, params: &BalanceWait) -> Result<u128> {
+        let target = target.saturating_add(params.safety_margin);
+        let deadline = params
+            .timeout
+            .map(|timeout| tokio::time::Instant::now() + timeout);
+
+        let mut transactions = Some(self.subscribe());
         let mut last_balance = None;
         loop {
-            match self.get_balance().await?.unwrap_or_default() {
+            let wallet_balance = self.get_balance_with_status().await?;
+            match wallet_balance.spendable() {
                 balance if balance >= target => {
                     if last_balance.is_some() {
                         tracing::info!(balance = %Tokens(balance), "fetched wallet balance");
@@ -839,7 +2448,8 @@ impl Wallet {
                 balance => {
                     if !matches!(last_balance, Some(last_balance) if last_balance == balance) {
                         tracing::info!(
-                            address = %self.address(),
+                            address = %Redacted(self.address()),
+                            status = %wallet_balance.status,
                             current_balance = %Tokens(balance),
                             target_balance = %Tokens(target),
                             "waiting until validator wallet balance is enough",
@@ -848,11 +2458,552 @@ impl Wallet {
                     last_balance = Some(balance);
                 }
             }
-            tokio::time::sleep(interval).await;
+
+            let mut interval = if transactions.is_some() && self.transactions_tracked() {
+                std::cmp::max(params.fallback_poll_interval, params.poll_interval)
+            } else {
+                params.poll_interval
+            };
+            if let Some(deadline) = deadline {
+                let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+                if remaining.is_zero() {
+                    let balance = last_balance.unwrap_or_default();
+                    return Err(ValidationError::Underfunded.with_reason(format!(
+                        "balance of {} never reached the target {} (last observed {}, \
+                        shortfall {})",
+                        Redacted(self.address()),
+                        Tokens(target),
+                        Tokens(balance),
+                        Tokens(target - balance),
+                    )));
+                }
+                interval = std::cmp::min(interval, remaining);
+            }
+
+            let next_transaction = async {
+                match &mut transactions {
+                    Some(transactions) => transactions.recv().await,
+                    None => futures_util::future::pending().await,
+                }
+            };
+
+            let closed = tokio::select! {
+                transaction = next_transaction => match transaction {
+                    Some(tx) => {
+                        tracing::debug!(tx_hash = ?tx.hash, "checking balance after transaction");
+                        false
+                    }
+                    None => true,
+                },
+                _ = tokio::time::sleep(interval) => false,
+            };
+            if closed {
+                // Fallback to polling only
+                transactions = None;
+            }
+        }
+    }
+
+    /// Waits until the wallet balance is enough and takes the guard.
+    ///
+    /// NOTE: storage fees could be charged between the check and the send,
+    /// so the balance is checked again under the guard right before sending.
+    async fn wait_for_balance_locked<'a>(
+        &self,
+        target: u128,
+        params: &BalanceWait,
+        guard: &'a Mutex<()>,
+    ) -> Result<MutexGuard<'a, ()>> {
+        loop {
+            self.wait_for_balance(target, params).await?;
+
+            let guard = guard.lock().await;
+            let balance = self.get_balance_with_status().await?.spendable();
+            if balance >= target {
+                return Ok(guard);
+            }
+
+            tracing::warn!(
+                address = %Redacted(self.address()),
+                current_balance = %Tokens(balance),
+                target_balance = %Tokens(target),
+                "validator wallet balance dropped below the target, waiting again",
+            );
         }
     }
 }
 
+/// Liveness of the validation loop, exported as metrics
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct LoopHeartbeat {
+    /// When the validator process was started
+    pub started_at: u32,
+    /// When the latest loop iteration was started
+    pub last_iteration_at: u32,
+    /// When the next loop iteration is planned (after the latest one)
+    pub next_iteration_at: Option<u32>,
+    /// Number of elections attempts skipped due to insufficient time since the start
+    #[serde(default)]
+    pub elections_skipped: u32,
+}
+
+/// Periodically stores the pending messages of the subscription for the exporter
+struct PendingMessagesSampler {
+    dirs: ProjectDirs,
+    handle: tokio::task::JoinHandle<()>,
+}
+
+impl PendingMessagesSampler {
+    const INTERVAL: Duration = Duration::from_secs(10);
+
+    fn spawn(dirs: &ProjectDirs, subscription: &Arc<Subscription>) -> Self {
+        let handle = tokio::spawn({
+            let dirs = dirs.clone();
+            let subscription = Arc::downgrade(subscription);
+            async move {
+                let mut interval = tokio::time::interval(Self::INTERVAL);
+                loop {
+                    interval.tick().await;
+                    let Some(subscription) = subscription.upgrade() else { break };
+                    let pending = subscription.pending_messages();
+                    drop(subscription);
+
+                    if let Err(e) = dirs.store_pending_messages(&pending) {
+                        tracing::warn!("failed to store pending messages: {e:?}");
+                    }
+                }
+            }
+        });
+
+        Self {
+            dirs: dirs.clone(),
+            handle,
+        }
+    }
+}
+
+impl Drop for PendingMessagesSampler {
+    fn drop(&mut self) {
+        self.handle.abort();
+
+        // NOTE: nothing can be pending without the subscription
+        let pending = PendingMessages {
+            sampled_at: now(),
+            ..Default::default()
+        };
+        if let Err(e) = self.dirs.store_pending_messages(&pending) {
+            tracing::warn!("failed to store pending messages: {e:?}");
+        }
+    }
+}
+
+/// Retries the node query with a short backoff, so that transient RPC failures
+/// don't postpone the whole validation loop iteration.
+async fn retry_node_query<T, F, R>(retries: &RetryPolicy, name: &str, mut f: F) -> Result<T>
+where
+    F: FnMut() -> R,
+    R: std::future::Future<Output = Result<T>>,
+{
+    let mut backoff = retries.backoff();
+    loop {
+        match f().await {
+            Ok(res) => break Ok(res),
+            Err(e) => match backoff.next_delay() {
+                Some(delay) => {
+                    tracing::warn!(attempt = backoff.attempts() - 1, "failed to {name}: {e:?}");
+                    tokio::time::sleep(delay).await;
+                }
+                None => break Err(e),
+            },
+        }
+    }
+}
+
+/// Sends the recover stake message if the elector has an unfrozen stake for the wallet.
+///
+/// Returns the recovered stake
+async fn recover_stake(
+    wallet: &Wallet,
+    elector: &Elector,
+    elector_data: &elector::ElectorData,
+    one_coin: u128,
+    balance_wait: &BalanceWait,
+    guard: &Mutex<()>,
+    dirs: &ProjectDirs,
+) -> Result<Option<u128>> {
+    if let Some(stake) = elector_data.has_unfrozen_stake(wallet.address()) {
+        // Prevent shutdown during stake recovery
+        let _guard = wallet
+            .wait_for_balance_locked(2 * one_coin, balance_wait, guard)
+            .await?;
+
+        // Send recover stake message
+        tracing::info!(stake = %Tokens(stake.0), "recovering stake");
+        let (tx, fees) = wallet
+            .call_with_fees(elector.recover_stake(one_coin)?)
+            .await
+            .context("failed to recover stake")?;
+
+        dirs.record_validator_history(HistoryEventKind::StakeRecovered {
+            wallet: wallet.address().to_string(),
+            stake: stake.0,
+            fees,
+            tx_hash: tx.hash.to_hex_string(),
+        });
+        return Ok(Some(stake.0));
+    }
+    Ok(None)
+}
+
+/// Sends the wallet balance above `max_hot_balance` to the sweep destination.
+///
+/// NOTE: the stake for the next elections (with the fees reserve) is always kept
+async fn sweep_excess_balance(
+    dirs: &ProjectDirs,
+    config: &AppConfig,
+    single: &AppConfigValidatorSingle,
+    subscription: Arc<Subscription>,
+    elector_data: &elector::ElectorData,
+    limits: &WalletLimits,
+    guard: &Mutex<()>,
+) -> Result<()> {
+    let (Some(_), Some(destination)) = (single.max_hot_balance, &single.sweep_destination) else {
+        return Ok(());
+    };
+
+    let signer: Arc<dyn Signer> = Arc::new(dirs.load_validator_keys(&single.address)?);
+    let wallet = Wallet::new(-1, signer, subscription, limits.max_attached_value);
+    anyhow::ensure!(
+        wallet.address() == &single.address,
+        wallet_mismatch_reason(&single.address, wallet.address())
+    );
+
+    // Prevent shutdown during the transfer
+    let _guard = guard.lock().await;
+
+    let balance = wallet.get_balance_with_status().await?.spendable();
+    let excess = sweep_excess(
+        single,
+        balance,
+        latest_unfrozen_reward(dirs, elector_data, wallet.address()),
+        config.one_coin(),
+        limits.balance_wait.safety_margin,
+        limits.max_attached_value,
+    );
+    let Some(SweepExcess { keep, amount }) = excess else {
+        return Ok(());
+    };
+
+    tracing::info!(
+        destination = %Redacted(destination),
+        balance = %Tokens(balance),
+        keep = %Tokens(keep),
+        amount = %Tokens(amount),
+        "sweeping excess balance"
+    );
+    let (tx, fees) = wallet
+        .call_with_fees(InternalMessage::empty(destination.clone(), amount))
+        .await
+        .context("failed to send excess balance")?;
+
+    dirs.record_validator_history(HistoryEventKind::BalanceSwept {
+        destination: destination.to_string(),
+        amount,
+        fees,
+        tx_hash: tx.hash.to_hex_string(),
+    });
+    Ok(())
+}
+
+/// Reward of the latest round which stake was already unfrozen or recovered.
+///
+/// NOTE: rewards of the frozen rounds are not on the wallet yet, and the elector
+/// forgets the rounds after unfreezing them, so the recovered rounds are taken
+/// from the round reports (with the reward estimated at the end of the round).
+fn latest_unfrozen_reward(
+    dirs: &ProjectDirs,
+    elector_data: &elector::ElectorData,
+    address: &ton_block::MsgAddressInt,
+) -> u128 {
+    let now = now();
+    let frozen = elector_data.frozen_elections(address, now);
+    let unfrozen = elector_data.latest_unfrozen_reward(address, now);
+
+    let history = dirs.load_validator_history().unwrap_or_else(|e| {
+        tracing::warn!("failed to load validator history: {e:?}");
+        Vec::new()
+    });
+    let recovered = history
+        .into_iter()
+        .filter_map(|event| match event.kind {
+            HistoryEventKind::RoundFinished {
+                round_id,
+                elected: true,
+                reward,
+                ..
+            } if !frozen.contains(&round_id) => Some((round_id, reward)),
+            _ => None,
+        })
+        .max_by_key(|(round_id, _)| *round_id);
+
+    // NOTE: the unfrozen round in the elector has the exact reward
+    std::cmp::max(unfrozen, recovered)
+        .map(|(_, reward)| reward)
+        .unwrap_or_default()
+}
+
+/// Explains the difference between the configured wallet address
+/// and the one derived from the validator keys.
+fn wallet_mismatch_reason(
+    configured: &ton_block::MsgAddressInt,
+    derived: &ton_block::MsgAddressInt,
+) -> String {
+    let expected_workchain = derived.workchain_id();
+    let other_workchain = if expected_workchain == ton_block::MASTERCHAIN_ID {
+        ton_block::BASE_WORKCHAIN_ID
+    } else {
+        ton_block::MASTERCHAIN_ID
+    };
+
+    // NOTE: wallet address doesn't depend on the workchain
+    let derived_other = match ton_block::MsgAddressInt::with_standart(
+        None,
+        other_workchain as i8,
+        derived.address(),
+    ) {
+        Ok(address) => address.to_string(),
+        Err(_) => "<invalid>".to_owned(),
+    };
+
+    if configured.workchain_id() != expected_workchain {
+        format!(
+            "validator wallet {} is in workchain {}, but workchain {expected_workchain} \
+            is expected (active keys derive {} in workchain {expected_workchain} \
+            and {} in workchain {other_workchain})",
+            Redacted(configured),
+            configured.workchain_id(),
+            Redacted(derived),
+            Redacted(&derived_other),
+        )
+    } else {
+        format!(
+            "validator wallet address mismatch (active keys derive {} \
+            in workchain {expected_workchain} and {} in workchain {other_workchain})",
+            Redacted(derived),
+            Redacted(&derived_other),
+        )
+    }
+}
+
+/// Recovers unfrozen stakes once and marks the nearest elections as skipped,
+/// so that the node can be safely stopped between rounds.
+///
+/// NOTE: the running validator must be stopped first (see [`InstanceLock::stop_running`]).
+///
+/// Returns the id of the skipped elections.
+pub async fn drain(
+    dirs: &ProjectDirs,
+    max_attached_value: Option<u128>,
+    balance_safety_margin: Option<u128>,
+) -> Result<u32> {
+    let config = dirs.load_app_config()?;
+    let validator = config
+        .validator
+        .as_ref()
+        .context("validation is not configured")?;
+
+    let one_coin = config.one_coin();
+    let mut limits = WalletLimits {
+        max_attached_value: max_attached_value
+            .unwrap_or_else(|| Wallet::default_max_attached_value(one_coin)),
+        balance_wait: BalanceWait::new(one_coin),
+    };
+    if let Some(safety_margin) = balance_safety_margin {
+        limits.balance_wait.safety_margin = safety_margin;
+    }
+
+    // Create subscription
+    let node_tcp_rpc = NodeTcpRpc::new(config.control()?).await?;
+    let node_udp_rpc = NodeUdpRpc::new(config.adnl()?).await?;
+    let subscription = Subscription::new(node_tcp_rpc, node_udp_rpc, config.retries.clone());
+    subscription.ensure_ready().await?;
+
+    // Get current network config params
+    let ElectionsConfig {
+        elector_address,
+        timings,
+        current_vset,
+        ..
+    } = subscription.tcp_rpc().get_elections_config().await?;
+
+    let elector = Elector::new(elector_address, subscription.clone());
+    let elector_data = elector
+        .get_data()
+        .await
+        .context("failed to get elector data")?;
+
+    // Mark the nearest elections as skipped before sending anything
+    let election_id = match elector_data.election_id() {
+        Some(election_id) => election_id,
+        None => match Timeline::compute(&timings, &current_vset, now()) {
+            Timeline::AfterElections { .. } => {
+                current_vset.utime_until() + timings.validators_elected_for
+            }
+            _ => current_vset.utime_until(),
+        },
+    };
+    dirs.store_skipped_election(election_id)?;
+    tracing::info!(election_id, "marked elections as skipped");
+
+    // Recover unfrozen stakes
+    recover_unfrozen_stakes(
+        &ValidationUnit::new(dirs, DEFAULT_UNIT, validator),
+        &config,
+        subscription,
+        &elector,
+        &elector_data,
+        &limits,
+        &Mutex::new(()),
+    )
+    .await?;
+
+    Ok(election_id)
+}
+
+/// Recovers unfrozen stakes of the main validator once.
+///
+/// NOTE: `guard` is held while the recover message is being sent
+async fn recover_stakes(
+    dirs: &ProjectDirs,
+    params: &ValidationParams,
+    guard: &Mutex<()>,
+) -> Result<()> {
+    let config = dirs.load_app_config()?;
+    let validator = config
+        .validator
+        .as_ref()
+        .context("validation is not configured")?;
+
+    // Create subscription
+    let node_tcp_rpc = NodeTcpRpc::new(config.control()?).await?;
+    let node_udp_rpc = NodeUdpRpc::new(config.adnl()?).await?;
+    let subscription = Subscription::new(node_tcp_rpc, node_udp_rpc, config.retries.clone());
+    subscription.ensure_ready().await?;
+
+    // Get current elector state
+    let ConfigWithId {
+        config: blockchain_config,
+        ..
+    } = subscription.tcp_rpc().get_config_all().await?;
+    let elector_address = blockchain_config
+        .elector_address()
+        .context("invalid elector address")?;
+
+    let elector = Elector::new(elector_address, subscription.clone());
+    let elector_data = elector
+        .get_data()
+        .await
+        .context("failed to get elector data")?;
+
+    recover_unfrozen_stakes(
+        &ValidationUnit::new(dirs, DEFAULT_UNIT, validator),
+        &config,
+        subscription,
+        &elector,
+        &elector_data,
+        &params.wallet_limits(config.one_coin()),
+        guard,
+    )
+    .await
+}
+
+async fn recover_unfrozen_stakes(
+    unit: &ValidationUnit,
+    config: &AppConfig,
+    subscription: Arc<Subscription>,
+    elector: &Elector,
+    elector_data: &elector::ElectorData,
+    limits: &WalletLimits,
+    guard: &Mutex<()>,
+) -> Result<()> {
+    let dirs = &unit.dirs;
+    let signer: Arc<dyn Signer> =
+        Arc::new(dirs.load_validator_keys(unit.validator.wallet_address())?);
+    match &unit.validator {
+        AppConfigValidator::Single(single) => {
+            let wallet = Wallet::new(-1, signer, subscription, limits.max_attached_value);
+            anyhow::ensure!(
+                wallet.address() == &single.address,
+                wallet_mismatch_reason(&single.address, wallet.address())
+            );
+            let recovered = recover_stake(
+                &wallet,
+                elector,
+                elector_data,
+                config.one_coin(),
+                &limits.balance_wait,
+                guard,
+                dirs,
+            )
+            .await?;
+            if let Some(stake) = recovered {
+                run_hook(
+                    config.hook.as_ref(),
+                    HookEvent::Recovered {
+                        wallet: wallet.address().to_string(),
+                        stake,
+                    },
+                );
+            }
+        }
+        AppConfigValidator::DePool(depool_config) => {
+            let wallet = Wallet::new(0, signer, subscription.clone(), limits.max_attached_value);
+            anyhow::ensure!(
+                wallet.address() == &depool_config.owner,
+                wallet_mismatch_reason(&depool_config.owner, wallet.address())
+            );
+
+            let depool = DePool::from_config(depool_config, subscription, config.one_coin())?;
+            let depool_state = depool
+                .get_state()
+                .await
+                .context("failed to get DePool state")?;
+            let depool_info = depool
+                .get_info(&depool_state)
+                .context("failed to get DePool info")?;
+
+            // NOTE: DePool recovers proxy stakes on ticktock
+            let has_unfrozen_stake = depool_info
+                .proxies
+                .iter()
+                .any(|proxy| elector_data.has_unfrozen_stake(proxy).is_some());
+            if has_unfrozen_stake {
+                wallet
+                    .wait_for_balance(
+                        depool.ticktock_value() + config.one_coin(),
+                        &limits.balance_wait,
+                    )
+                    .await?;
+
+                tracing::info!("sending ticktock to recover stakes");
+                let _guard = guard.lock().await;
+                let (tx, fees) = wallet
+                    .call_with_fees(depool.ticktock()?)
+                    .await
+                    .context("failed to send ticktock")?;
+
+                dirs.record_validator_history(HistoryEventKind::TicktockSent {
+                    depool: depool.address().to_string(),
+                    fees,
+                    tx_hash: tx.hash.to_hex_string(),
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
 impl Cluster {
     async fn wait_for_depool_strategy(
         &self,
@@ -868,8 +3019,8 @@ impl Cluster {
 
             if std::mem::take(&mut first) {
                 tracing::info!(
-                    cluster = %self.address,
-                    depool = %depool,
+                    cluster = %Redacted(&self.address),
+                    depool = %Redacted(depool),
                     "waiting for the strategy to be added to the cluster for the DePool",
                 );
             }
@@ -880,16 +3031,212 @@ impl Cluster {
 }
 
 impl ProjectDirs {
-    fn load_validator_keys(&self) -> Result<ed25519_dalek::Keypair> {
+    fn load_validator_keys(
+        &self,
+        wallet: &ton_block::MsgAddressInt,
+    ) -> Result<ed25519_dalek::Keypair> {
+        const EXPIRATION_WARNING_OFFSET: u32 = 86400;
+
         let keys = StoredKeys::load(&self.validator_keys)
             .context("failed to load validator wallet keys")?;
-        Ok(keys.as_keypair())
+
+        let now = now();
+        let active = keys
+            .active_keys_for_wallet(now, wallet)
+            .context("active validator keys don't match the configured wallet")?;
+        if let Some(valid_until) = active.valid_until {
+            let expires_in = valid_until.saturating_sub(now);
+            if expires_in < EXPIRATION_WARNING_OFFSET {
+                tracing::warn!(
+                    public = %hex::encode(active.keypair.public.as_bytes()),
+                    expires_in,
+                    "active validator keys will expire soon"
+                );
+            }
+        }
+
+        Ok(active.keypair)
     }
 
     fn load_depool_keys(&self) -> Result<ed25519_dalek::Keypair> {
         let keys = StoredKeys::load(&self.depool_keys).context("failed to load DePool keys")?;
-        Ok(keys.as_keypair())
+        keys.as_keypair()
+    }
+
+    fn load_skipped_election(&self) -> Result<Option<u32>> {
+        if !self.skipped_election.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(&self.skipped_election)
+            .context("failed to read skipped election id")?;
+        let election_id = content
+            .trim()
+            .parse()
+            .context("invalid skipped election id")?;
+        Ok(Some(election_id))
+    }
+
+    fn store_skipped_election(&self, election_id: u32) -> Result<()> {
+        std::fs::write(&self.skipped_election, election_id.to_string())
+            .context("failed to store skipped election id")
+    }
+
+    /// Whether the elections are paused by the operator
+    pub fn is_paused(&self) -> bool {
+        self.pause_file.exists()
+    }
+
+    /// Pauses or resumes participation in elections
+    pub fn set_paused(&self, paused: bool) -> Result<()> {
+        if paused {
+            std::fs::write(&self.pause_file, "").context("failed to create pause file")
+        } else if self.pause_file.exists() {
+            std::fs::remove_file(&self.pause_file).context("failed to remove pause file")
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns since when the node is stuck in the not ready state (if it is)
+    pub fn load_node_not_ready(&self) -> Result<Option<u32>> {
+        if !self.node_not_ready.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(&self.node_not_ready)
+            .context("failed to read node not ready state")?;
+        let since = content
+            .trim()
+            .parse()
+            .context("invalid node not ready state")?;
+        Ok(Some(since))
+    }
+
+    fn set_node_not_ready(&self, since: Option<u32>) -> Result<()> {
+        match since {
+            Some(since) => std::fs::write(&self.node_not_ready, since.to_string())
+                .context("failed to store node not ready state"),
+            None if self.node_not_ready.exists() => std::fs::remove_file(&self.node_not_ready)
+                .context("failed to remove node not ready state"),
+            None => Ok(()),
+        }
+    }
+
+    /// Loads the deadline of the last elections attempt
+    pub fn load_elections_deadline(&self) -> Result<Option<u32>> {
+        if !self.elections_deadline.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(&self.elections_deadline)
+            .context("failed to read elections deadline")?;
+        let deadline = content
+            .trim()
+            .parse()
+            .context("invalid elections deadline")?;
+        Ok(Some(deadline))
+    }
+
+    fn store_elections_deadline(&self, deadline: u32) -> Result<()> {
+        std::fs::write(&self.elections_deadline, deadline.to_string())
+            .context("failed to store elections deadline")
+    }
+
+    pub fn load_validator_state(&self) -> Result<ValidatorState> {
+        if !self.validator_state.exists() {
+            return Ok(Default::default());
+        }
+
+        let content = std::fs::read_to_string(&self.validator_state)
+            .context("failed to read validator state")?;
+        serde_json::from_str(&content).context("invalid validator state")
+    }
+
+    /// Loads the latest snapshot of messages which are waiting for delivery
+    pub fn load_pending_messages(&self) -> Result<Option<PendingMessages>> {
+        if !self.pending_messages.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(&self.pending_messages)
+            .context("failed to read pending messages")?;
+        let pending = serde_json::from_str(&content).context("invalid pending messages")?;
+        Ok(Some(pending))
+    }
+
+    /// Loads the start time of the validator and its latest loop iteration
+    pub fn load_validator_heartbeat(&self) -> Result<Option<LoopHeartbeat>> {
+        if !self.validator_heartbeat.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(&self.validator_heartbeat)
+            .context("failed to read validator heartbeat")?;
+        let heartbeat = serde_json::from_str(&content).context("invalid validator heartbeat")?;
+        Ok(Some(heartbeat))
+    }
+
+    fn store_validator_heartbeat(&self, heartbeat: &LoopHeartbeat) -> Result<()> {
+        let content = serde_json::to_string(heartbeat)?;
+        std::fs::write(&self.validator_heartbeat, content)
+            .context("failed to store validator heartbeat")
+    }
+
+    fn store_pending_messages(&self, pending: &PendingMessages) -> Result<()> {
+        let content = serde_json::to_string(pending)?;
+        std::fs::write(&self.pending_messages, content).context("failed to store pending messages")
+    }
+
+    fn store_validator_state(&self, state: &ValidatorState) -> Result<()> {
+        let content = serde_json::to_string_pretty(state)?;
+        std::fs::write(&self.validator_state, content).context("failed to store validator state")
+    }
+
+    /// Loads all recorded validator actions in the order they were made
+    pub fn load_validator_history(&self) -> Result<Vec<HistoryEvent>> {
+        if !self.validator_history.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = std::fs::read_to_string(&self.validator_history)
+            .context("failed to read validator history")?;
+        content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str(line)
+                    .with_context(|| format!("invalid validator history entry at line {}", i + 1))
+            })
+            .collect()
+    }
+
+    /// Appends the action to the validator history.
+    ///
+    /// NOTE: history is only informational, so errors are just logged
+    fn record_validator_history(&self, kind: HistoryEventKind) {
+        use std::io::Write;
+
+        let event = HistoryEvent {
+            timestamp: now(),
+            kind,
+        };
+
+        let res = serde_json::to_string(&event)
+            .map_err(anyhow::Error::from)
+            .and_then(|line| {
+                let mut file = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.validator_history)?;
+                writeln!(file, "{line}")?;
+                Ok(())
+            });
+        if let Err(e) = res {
+            tracing::warn!("failed to record validator history: {e:?}");
+        }
     }
 }
 
-const DEFAULT_STAKE_FACTOR: u32 = 196608;
+pub const DEFAULT_STAKE_FACTOR: u32 = 196608;
@@ -0,0 +1,385 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use broxus_util::now;
+
+use super::decision::{sweep_excess, ElectionsDecision, ElectionsInput, SingleStake};
+use super::{
+    latest_unfrozen_reward, wallet_mismatch_reason, Timeline, ValidationParams, ValidationUnit,
+    DEFAULT_STAKE_FACTOR,
+};
+use crate::config::*;
+use crate::contracts::*;
+use crate::crypto::Signer;
+use crate::dirs::ProjectDirs;
+use crate::network::{AccountStatus, ElectionsConfig, NodeTcpRpc, NodeUdpRpc, Subscription};
+use crate::util::{human_duration, Tokens};
+
+/// Explains what the validation loop is going to do next, without sending anything.
+///
+/// Uses the same inputs as [`ValidationManager::try_validate`]: the elections timeline,
+/// the elector state, wallet balances and DePool states.
+///
+/// [`ValidationManager::try_validate`]: super::ValidationManager::try_validate
+pub async fn plan(dirs: &ProjectDirs, params: &ValidationParams) -> Result<Vec<String>> {
+    let config = dirs.load_app_config()?;
+    let units = config
+        .validation_units()
+        .map(|(name, validator)| ValidationUnit::new(dirs, name, validator))
+        .collect::<Vec<_>>();
+    anyhow::ensure!(!units.is_empty(), "validation is not configured");
+
+    // Create subscription
+    let node_tcp_rpc = NodeTcpRpc::new(config.control()?).await?;
+    let node_udp_rpc = NodeUdpRpc::new(config.adnl()?).await?;
+    let subscription = Subscription::new(node_tcp_rpc, node_udp_rpc, config.retries.clone());
+    subscription.ensure_ready().await?;
+
+    // Get current network config params
+    let ElectionsConfig {
+        elector_address,
+        timings,
+        stakes_config,
+        current_vset,
+        ..
+    } = subscription.tcp_rpc().get_elections_config().await?;
+
+    let elector = Elector::new(elector_address, subscription.clone());
+    let elector_data = elector
+        .get_data()
+        .await
+        .context("failed to get elector data")?;
+
+    let mut ctx = PlanContext {
+        subscription,
+        elector_data,
+        stakes_config,
+        currency: config.currency().to_owned(),
+        one_coin: config.one_coin(),
+        max_attached_value: params.max_attached_value(config.one_coin()),
+        safety_margin: params.balance_wait(config.one_coin()).safety_margin,
+        participate: true,
+    };
+
+    let mut plan = Vec::new();
+    if dirs.is_paused() {
+        plan.push("Validation is PAUSED; no messages will be sent until it is resumed".to_owned());
+    }
+    if let Some(issue) = params.elections_offsets_issue(&timings) {
+        plan.push(format!("Stakes will NEVER be sent: {issue}"));
+    }
+
+    // Explain when stakes are going to be sent
+    let now = now();
+    let election_id = ctx.elector_data.election_id();
+    match Timeline::compute(&timings, &current_vset, now) {
+        Timeline::BeforeElections {
+            until_elections_start,
+        } => plan.push(format!(
+            "Elections open in {}; stakes will be sent at least {} after that",
+            human_duration(until_elections_start),
+            human_duration(params.elections_start_offset),
+        )),
+        Timeline::Elections {
+            until_elections_end,
+            ..
+        } if until_elections_end <= params.elections_end_offset => plan.push(format!(
+            "Elections close in {}, which is within the end offset; \
+            no stakes will be sent until the next elections",
+            human_duration(until_elections_end),
+        )),
+        Timeline::Elections { elections_end, .. } => match election_id {
+            Some(election_id) => {
+                // NOTE: the same decision is made by the validation loop
+                let unfreeze_at = ctx
+                    .elector_data
+                    .nearest_unfreeze_at(election_id)
+                    .map(|unfreeze_at| unfreeze_at + params.stake_unfreeze_offset);
+                let deadline_at = elections_end.saturating_sub(params.elections_end_offset);
+                let start_at = ElectionsInput::attempt_start_at(now, unfreeze_at, deadline_at);
+                let min_time_remaining =
+                    params.required_time_remaining(&config.retries.send_message);
+                let decision = ElectionsInput {
+                    election_id,
+                    skipped_election: dirs.load_skipped_election()?,
+                    paused: dirs.is_paused(),
+                    round_parity: config.round_parity,
+                    validators_elected_for: timings.validators_elected_for,
+                    deadline_at,
+                    start_at,
+                    min_time_remaining,
+                }
+                .decide();
+                ctx.participate = decision == ElectionsDecision::Participate;
+
+                plan.push(match decision {
+                    ElectionsDecision::Participate => format!(
+                        "Elections {election_id} are open; stakes will be sent within {}",
+                        human_duration(deadline_at.saturating_sub(now)),
+                    ),
+                    ElectionsDecision::Skipped => format!(
+                        "Elections {election_id} are marked as skipped; no stakes will be sent"
+                    ),
+                    ElectionsDecision::Paused => format!(
+                        "Elections {election_id} are open, but no stakes will be sent \
+                        until the validation is resumed"
+                    ),
+                    ElectionsDecision::OtherParity(parity) => format!(
+                        "Elections {election_id} don't match the `{parity}` round parity; \
+                        no stakes will be sent, only unfrozen stakes will be recovered"
+                    ),
+                    ElectionsDecision::InsufficientTime { time_remaining } => format!(
+                        "Elections {election_id} leave only {} for the attempt \
+                        (at least {} is required); no stakes will be sent",
+                        human_duration(time_remaining),
+                        human_duration(min_time_remaining),
+                    ),
+                });
+
+                if decision == ElectionsDecision::Participate && start_at > now {
+                    plan.push(format!(
+                        "Stakes will be sent after the previous stakes unfreeze in {}",
+                        human_duration(start_at - now),
+                    ));
+                }
+            }
+            None => plan.push("Elections are not announced by the elector yet".to_owned()),
+        },
+        Timeline::AfterElections { until_round_end } => plan.push(format!(
+            "Elections are finished; the new round starts in {}",
+            human_duration(until_round_end),
+        )),
+    }
+
+    // Explain the actions of each unit
+    for unit in &units {
+        let steps = match &unit.validator {
+            AppConfigValidator::Single(single) => ctx.plan_single(unit, single).await,
+            AppConfigValidator::DePool(depool) => ctx.plan_depool(unit, depool).await,
+        };
+        let steps = steps.unwrap_or_else(|e| vec![format!("FAILED to check the unit: {e:?}")]);
+        plan.extend(
+            steps
+                .into_iter()
+                .map(|step| format!("[{}] {step}", unit.name)),
+        );
+    }
+
+    Ok(plan)
+}
+
+struct PlanContext {
+    subscription: Arc<Subscription>,
+    elector_data: elector::ElectorData,
+    stakes_config: ton_block::ConfigParam17,
+    currency: String,
+    one_coin: u128,
+    max_attached_value: u128,
+    safety_margin: u128,
+    /// Whether stakes will be sent in the current elections
+    participate: bool,
+}
+
+impl PlanContext {
+    async fn plan_single(
+        &self,
+        unit: &ValidationUnit,
+        config: &AppConfigValidatorSingle,
+    ) -> Result<Vec<String>> {
+        let signer: Arc<dyn Signer> = Arc::new(unit.dirs.load_validator_keys(&config.address)?);
+        let wallet = Wallet::new(
+            -1,
+            signer,
+            self.subscription.clone(),
+            self.max_attached_value,
+        );
+        if wallet.address() != &config.address {
+            return Ok(vec![format!(
+                "Nothing can be sent: {}",
+                wallet_mismatch_reason(&config.address, wallet.address())
+            )]);
+        }
+
+        let balance = wallet.get_balance_with_status().await?;
+        let reward = latest_unfrozen_reward(&unit.dirs, &self.elector_data, wallet.address());
+
+        let mut steps = Vec::new();
+        let mut required = 0;
+
+        if let Some(stake) = self.elector_data.has_unfrozen_stake(wallet.address()) {
+            required = 2 * self.one_coin;
+            steps.push(format!(
+                "Will recover the unfrozen stake of {} {} first",
+                Tokens(stake.0),
+                self.currency,
+            ));
+        }
+
+        if !self.participate {
+            steps.push("Will not stake in these elections".to_owned());
+        } else if self.elector_data.elected(wallet.address()) {
+            steps.push("Will do nothing, the wallet is already elected".to_owned());
+        } else {
+            // NOTE: the stake is resolved in the same way as in the validation loop
+            let resolved = SingleStake::resolve(
+                config,
+                balance.spendable(),
+                reward,
+                2 * self.one_coin + self.safety_margin,
+                &self.stakes_config,
+            );
+            match resolved {
+                SingleStake::BelowMinStake {
+                    percent,
+                    stake,
+                    min_stake,
+                } => steps.push(format!(
+                    "Will skip the elections: {percent}% of the balance ({} {}) \
+                    is below the min stake ({} {})",
+                    Tokens(stake),
+                    self.currency,
+                    Tokens(min_stake),
+                    self.currency,
+                )),
+                resolved => {
+                    let stake = resolved.stake().unwrap_or_default();
+                    required = stake + 2 * self.one_coin;
+                    steps.push(format!(
+                        "Will stake {} {} from wallet {} (stake factor {})",
+                        Tokens(stake),
+                        self.currency,
+                        wallet.address(),
+                        config.stake_factor.unwrap_or(DEFAULT_STAKE_FACTOR),
+                    ));
+                }
+            }
+            if let SingleStake::Compounded { compounded, .. } = resolved {
+                steps.push(format!(
+                    "The stake includes {} {} of the latest reward",
+                    Tokens(compounded),
+                    self.currency,
+                ));
+            }
+        }
+
+        let sweep = sweep_excess(
+            config,
+            balance.spendable(),
+            reward,
+            self.one_coin,
+            self.safety_margin,
+            self.max_attached_value,
+        );
+        if let (Some(sweep), Some(destination)) = (sweep, &config.sweep_destination) {
+            steps.push(format!(
+                "Will sweep {} {} to {} between the elections (keeping {} {})",
+                Tokens(sweep.amount),
+                self.currency,
+                destination,
+                Tokens(sweep.keep),
+                self.currency,
+            ));
+        }
+
+        steps.push(self.describe_balance(&wallet, &balance, required));
+        Ok(steps)
+    }
+
+    async fn plan_depool(
+        &self,
+        unit: &ValidationUnit,
+        config: &AppConfigValidatorDePool,
+    ) -> Result<Vec<String>> {
+        let signer: Arc<dyn Signer> = Arc::new(unit.dirs.load_validator_keys(&config.owner)?);
+        let wallet = Wallet::new(
+            0,
+            signer,
+            self.subscription.clone(),
+            self.max_attached_value,
+        );
+        if wallet.address() != &config.owner {
+            return Ok(vec![format!(
+                "Nothing can be sent: {}",
+                wallet_mismatch_reason(&config.owner, wallet.address())
+            )]);
+        }
+
+        let depool = DePool::from_config(config, self.subscription.clone(), self.one_coin)?;
+        let depool_state = depool
+            .get_state()
+            .await
+            .context("failed to get DePool state")?;
+        let depool_info = depool
+            .get_info(&depool_state)
+            .context("failed to get DePool info")?;
+        if wallet.address() != &depool_info.validator_wallet {
+            return Ok(vec!["Nothing can be sent: DePool owner mismatch".to_owned()]);
+        }
+
+        let mut steps = Vec::new();
+
+        // NOTE: refill messages are computed on the same state as in the validation loop
+        let refill = depool
+            .maintain_balances(&depool_state)
+            .await
+            .context("failed to compute DePool refills")?
+            .iter()
+            .map(|message| message.amount)
+            .sum::<u128>();
+        if refill > 0 {
+            steps.push(format!(
+                "Will top up the DePool and its proxies with {} {}",
+                Tokens(refill),
+                self.currency,
+            ));
+        }
+
+        if !self.participate {
+            steps.push("Will not stake in these elections".to_owned());
+        } else if let Some(proxy) = depool_info
+            .proxies
+            .iter()
+            .find(|proxy| self.elector_data.elected(proxy))
+        {
+            steps.push(format!("Will do nothing, proxy {proxy} is already elected"));
+        } else {
+            steps.push(format!(
+                "Will update DePool {} and send the validator request \
+                for its pooling round (stake factor {})",
+                config.depool,
+                config.stake_factor.unwrap_or(DEFAULT_STAKE_FACTOR),
+            ));
+        }
+
+        let balance = wallet.get_balance_with_status().await?;
+        steps.push(self.describe_balance(&wallet, &balance, refill + 2 * self.one_coin));
+        Ok(steps)
+    }
+
+    fn describe_balance(&self, wallet: &Wallet, balance: &WalletBalance, required: u128) -> String {
+        if balance.status == AccountStatus::Frozen {
+            return format!(
+                "Wallet {} is FROZEN, top it up to unfreeze",
+                wallet.address()
+            );
+        }
+        let balance = balance.spendable();
+
+        match required.checked_sub(balance) {
+            Some(missing) if missing > 0 => format!(
+                "Current balance {} {} is INSUFFICIENT by {} {}",
+                Tokens(balance),
+                self.currency,
+                Tokens(missing),
+                self.currency,
+            ),
+            _ => format!(
+                "Current balance {} {} is SUFFICIENT (at least {} {} is required)",
+                Tokens(balance),
+                self.currency,
+                Tokens(required),
+                self.currency,
+            ),
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+
+/// Validator state which must survive restarts
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ValidatorState {
+    /// The last ordinary stake sent to the DePool
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub depool_top_up: Option<DePoolTopUp>,
+    /// Elected validator keys which were not found in the node
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub missing_keys: Option<MissingKeys>,
+    /// The last round which summary was reported
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_round_report: Option<u32>,
+}
+
+/// Intent of adding an ordinary stake to the DePool round
+#[derive(Serialize, Deserialize)]
+pub struct DePoolTopUp {
+    /// Pooling round id
+    pub round_id: u64,
+    /// Sent stake
+    pub amount: u64,
+    /// Observed participant stake with this top-up applied
+    pub expected_stake: u64,
+    /// Timestamp when the stake was sent
+    pub created_at: u32,
+    /// Hash of the DePool transaction (if it was observed)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx_hash: Option<String>,
+}
+
+/// Validator keys of the current round which the node doesn't have
+#[derive(Serialize, Deserialize)]
+pub struct MissingKeys {
+    /// Validation round id (`utime_since` of the validator set)
+    pub round_id: u32,
+    /// Hex encoded public keys
+    pub public_keys: Vec<String>,
+}
+
+impl DePoolTopUp {
+    /// How long an unconfirmed top-up is considered to be on its way
+    const PENDING_TTL: u32 = 600;
+
+    /// Returns the part of the stake which could still be on its way to the DePool
+    pub fn in_flight(&self, round_id: u64, observed_stake: u64, now: u32) -> u64 {
+        if self.round_id != round_id
+            || self.tx_hash.is_some()
+            || now >= self.created_at.saturating_add(Self::PENDING_TTL)
+        {
+            return 0;
+        }
+
+        std::cmp::min(
+            self.expected_stake.saturating_sub(observed_stake),
+            self.amount,
+        )
+    }
+}
@@ -0,0 +1,48 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use broxus_util::now;
+
+/// Inputs and decisions of the latest validation loop iteration.
+///
+/// The file is rewritten on each record, so it always contains a self-contained
+/// JSON object, even if the iteration was interrupted.
+pub struct DecisionTrace {
+    path: PathBuf,
+    entries: serde_json::Map<String, serde_json::Value>,
+}
+
+impl DecisionTrace {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            entries: Default::default(),
+        }
+    }
+
+    /// Starts the trace of a new iteration
+    pub fn reset(&mut self) {
+        self.entries.clear();
+        self.record("started_at", now().into());
+    }
+
+    pub fn started_at(&self) -> u32 {
+        self.entries
+            .get("started_at")
+            .and_then(|value| value.as_u64())
+            .unwrap_or_default() as u32
+    }
+
+    pub fn record(&mut self, key: &str, value: serde_json::Value) {
+        self.entries.insert(key.to_owned(), value);
+        self.entries.insert("updated_at".to_owned(), now().into());
+        if let Err(e) = self.write() {
+            tracing::warn!("failed to write decision trace: {e:?}");
+        }
+    }
+
+    fn write(&self) -> Result<()> {
+        let data = serde_json::to_string_pretty(&self.entries)?;
+        std::fs::write(&self.path, data).context("failed to write file")
+    }
+}